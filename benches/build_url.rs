@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::hint::black_box;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use prometheus_push::prometheus_client_crate::PrometheusClientMetricsConverter;
+use prometheus_push::ConvertMetrics;
+use url::Url;
+
+const GROUPING_SIZES: [usize; 4] = [0, 4, 32, 256];
+
+fn create_grouping(size: usize) -> Vec<(String, String)> {
+    (0..size)
+        .map(|i| (format!("label_name_{i}"), format!("label_value_{i}")))
+        .collect()
+}
+
+fn bench_build_url(c: &mut Criterion) {
+    let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+    let converter = PrometheusClientMetricsConverter::new();
+    let mut group = c.benchmark_group("build_url");
+
+    for size in GROUPING_SIZES {
+        let labels = create_grouping(size);
+        let grouping: HashMap<&str, &str> = labels
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &grouping,
+            |b, grouping| {
+                b.iter(|| {
+                    converter
+                        .create_push_details(black_box("bench_job"), &url, grouping, String::new())
+                        .unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_build_url);
+criterion_main!(benches);
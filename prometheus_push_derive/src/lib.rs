@@ -0,0 +1,212 @@
+//! Derive and attribute macros for the [`prometheus_push`](https://docs.rs/prometheus_push)
+//! crate, enabled with its `derive` feature.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::parse::Parser;
+use syn::parse_macro_input;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Expr;
+use syn::Fields;
+use syn::ItemFn;
+use syn::LitStr;
+use syn::ReturnType;
+use syn::Type;
+
+/// Derives `prometheus_push::grouping::GroupingLabels` for a struct with named fields that
+/// implement `AsRef<str>`, so every field becomes a grouping label named after the field.
+/// A label is renamed with `#[grouping(rename = "<label name>")]`.
+///
+/// ```ignore
+/// #[derive(Grouping)]
+/// struct BatchGrouping {
+///     instance: String,
+///     #[grouping(rename = "env")]
+///     environment: &'static str,
+/// }
+/// ```
+#[proc_macro_derive(Grouping, attributes(grouping))]
+pub fn derive_grouping(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Grouping can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Grouping can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut labels = Vec::with_capacity(fields.len());
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named fields have an ident");
+        let mut name = LitStr::new(&ident.to_string(), ident.span());
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("grouping"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"<label name>\"`"))
+                }
+            })?;
+        }
+
+        labels.push(quote! {
+            (#name, ::core::convert::AsRef::<str>::as_ref(&self.#ident))
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::prometheus_push::grouping::GroupingLabels for #ident #type_generics
+            #where_clause
+        {
+            fn labels(&self) -> ::std::collections::HashMap<&str, &str> {
+                ::std::collections::HashMap::from([#(#labels),*])
+            }
+        }
+    })
+}
+
+/// Instruments a function: every invocation pushes its duration, whether it succeeded and the
+/// number of invocations since the start of the process with `push_add` logic, as the gauges
+/// `<function>_duration_seconds` and `<function>_last_success` and the counter
+/// `<function>_invocations_total`. A function returning a `Result` succeeded if it returned
+/// `Ok`, any other function always succeeds. Failed pushes are logged with the `log` feature
+/// and never fail the function.
+///
+/// The arguments are:
+/// - `pusher`: an expression of the `MetricsPusher` that accepts the metrics as `String` in the
+///   text format, e.g. a global one in a static or one passed in as argument. The pusher is
+///   blocking for a synchronous and non-blocking for an `async` function.
+/// - `job`: the job name, defaults to the name of the function.
+/// - `grouping`: an expression of the grouping labels as `HashMap<&str, &str>`, defaults to none.
+///
+/// ```ignore
+/// #[push_metrics(pusher = &*PUSHER, job = "nightly_batch")]
+/// fn import_orders() -> Result<(), ImportError> {
+///     ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn push_metrics(args: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+
+    expand_push_metrics(args.into(), function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_push_metrics(
+    args: proc_macro2::TokenStream,
+    function: ItemFn,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut pusher: Option<Expr> = None;
+    let mut job: Option<LitStr> = None;
+    let mut grouping: Option<Expr> = None;
+
+    syn::meta::parser(|meta| {
+        if meta.path.is_ident("pusher") {
+            pusher = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("job") {
+            job = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("grouping") {
+            grouping = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `pusher`, `job` or `grouping`"));
+        }
+        Ok(())
+    })
+    .parse2(args)?;
+
+    let pusher = pusher.ok_or_else(|| {
+        syn::Error::new_spanned(&function.sig.ident, "missing `pusher = <expression>`")
+    })?;
+    let name = function.sig.ident.unraw().to_string();
+    let job = job.map_or_else(|| quote!(#name), |job| quote!(#job));
+    let grouping = grouping.map_or_else(
+        || quote!(::std::collections::HashMap::<&str, &str>::new()),
+        |grouping| quote!(#grouping),
+    );
+
+    let ItemFn { attrs, vis, sig, block } = function;
+    let output = match &sig.output {
+        ReturnType::Type(_, ty) if !matches!(**ty, Type::ImplTrait(_)) => quote!(: #ty),
+        _ => quote!(),
+    };
+    let success = match returns_result(&sig.output) {
+        true => quote!(__push_metrics_result.is_ok()),
+        false => quote!(true),
+    };
+    let (result, push) = match sig.asyncness {
+        Some(_) => (
+            quote!(async #block.await),
+            quote!((#pusher).push_add(#job, &#grouping, __push_metrics_payload).await),
+        ),
+        None => (
+            quote!((|| #block)()),
+            quote!((#pusher).push_add(#job, &#grouping, __push_metrics_payload)),
+        ),
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            static __PUSH_METRICS_INVOCATIONS: ::std::sync::atomic::AtomicU64 =
+                ::std::sync::atomic::AtomicU64::new(0);
+
+            let __push_metrics_started = ::std::time::Instant::now();
+            let __push_metrics_result #output = #result;
+            let __push_metrics_payload = ::prometheus_push::instrument::payload(
+                #name,
+                __push_metrics_started.elapsed(),
+                #success,
+                __PUSH_METRICS_INVOCATIONS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1,
+            );
+            ::prometheus_push::instrument::report(#name, #push);
+
+            __push_metrics_result
+        }
+    })
+}
+
+/// Checks whether the function returns a `Result`, e.g. `std::io::Result<()>`.
+fn returns_result(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Result"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use url::Url;
+
+use crate::error::Result;
+use crate::text_format;
+use crate::text_format::MetricFamily;
+use crate::utils::text_payload;
+use crate::ConvertMetrics;
+
+const STATE_EXTENSION: &str = "prom";
+
+/// `AccumulatingConverter` wraps a [`ConvertMetrics`] implementation and merges the payload of
+/// every push with the metric families previously pushed to the same group. Families of the
+/// current payload replace the ones with the same name, all others are pushed again, so a
+/// `push_all` of a restarted worker does not wipe the families it contributed before.
+///
+/// The accumulated families are kept in memory, or with [`AccumulatingConverter::with_directory`]
+/// in a file per group that survives restarts. The wrapped converter has to encode in the text
+/// format.
+#[derive(Debug)]
+pub struct AccumulatingConverter<CM> {
+    converter: CM,
+    accumulated: Mutex<HashMap<Url, Vec<MetricFamily>>>,
+    directory: Option<PathBuf>,
+}
+
+impl<CM> AccumulatingConverter<CM> {
+    /// Creates a new [`AccumulatingConverter`] that accumulates the payloads of the given
+    /// converter in memory.
+    pub fn new(converter: CM) -> Self {
+        Self { converter, accumulated: Mutex::new(HashMap::new()), directory: None }
+    }
+
+    /// Persists the accumulated families of every group in a file in the given directory,
+    /// which is read on the first push of the group after a restart. The directory has to
+    /// exist and should only be used for the pushes to one pushgateway.
+    pub fn with_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    /// Merges the given families into the accumulated families of the group and returns the
+    /// result, which is persisted if a directory is configured.
+    fn accumulate(
+        &self,
+        group: &Url,
+        families: Vec<MetricFamily>,
+        content_type: &str,
+    ) -> Result<Vec<MetricFamily>> {
+        let mut accumulated = self
+            .accumulated
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut merged = match accumulated.get(group) {
+            Some(previous) => previous.clone(),
+            None => self.load(group)?,
+        };
+        text_format::replace(&mut merged, families);
+
+        if let Some(file) = self.state_file(group) {
+            fs::write(file, text_format::encode_as(&merged, content_type))?;
+        }
+        accumulated.insert(group.clone(), merged.clone());
+
+        Ok(merged)
+    }
+
+    /// Reads the persisted families of the group, if any.
+    fn load(&self, group: &Url) -> Result<Vec<MetricFamily>> {
+        let Some(file) = self.state_file(group) else {
+            return Ok(Vec::new());
+        };
+
+        match fs::read_to_string(file) {
+            Ok(persisted) => text_format::parse(&persisted),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Names the file of a group after the path of its url. The segments of the path are
+    /// percent-encoded, so replacing the slashes keeps the names of different groups apart.
+    fn state_file(&self, group: &Url) -> Option<PathBuf> {
+        self.directory.as_ref().map(|directory| {
+            let name = group.path().trim_matches('/').replace('/', "%2F");
+            directory.join(format!("{name}.{STATE_EXTENSION}"))
+        })
+    }
+}
+
+impl<CM, MF, C, B> ConvertMetrics<MF, C, B> for AccumulatingConverter<CM>
+where
+    CM: ConvertMetrics<MF, C, B>,
+    B: AsRef<[u8]> + From<String>,
+{
+    fn metrics_from(&self, collectors: C) -> Result<MF> {
+        self.converter.metrics_from(collectors)
+    }
+
+    fn create_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<(Url, B, String)> {
+        let (url, body, content_type) =
+            self.converter
+                .create_push_details(job, url, grouping, metric_families)?;
+        let families = text_format::parse(&text_payload(body.as_ref(), &content_type)?)?;
+
+        let merged = self.accumulate(&url, families, &content_type)?;
+
+        let body = B::from(text_format::encode_as(&merged, &content_type));
+        Ok((url, body, content_type))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use url::Url;
+
+    use crate::accumulate::AccumulatingConverter;
+    use crate::raw::RawMetricsConverter;
+    use crate::ConvertMetrics;
+
+    fn convert(converter: &AccumulatingConverter<RawMetricsConverter>, payload: &str) -> String {
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let (_, body, _) = converter
+            .create_push_details("worker", &url, &HashMap::new(), payload.to_owned())
+            .unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn test_previously_pushed_families_are_pushed_again() {
+        // Given I have an accumulating converter
+        let converter = AccumulatingConverter::new(RawMetricsConverter::text());
+
+        // When I convert two payloads with different families of the same group
+        convert(&converter, "# TYPE a gauge\na 1\n# TYPE b gauge\nb 1\n");
+        let second = convert(&converter, "# TYPE b gauge\nb 2\n");
+
+        // Then the second payload keeps the first family and replaces the second
+        assert_eq!(second, "# TYPE a gauge\na 1\n# TYPE b gauge\nb 2\n");
+    }
+
+    #[test]
+    fn test_accumulated_families_survive_a_restart() {
+        // Given I have a directory for the accumulated families
+        let directory = tempfile::tempdir().unwrap();
+
+        // When I convert a payload, restart and convert another one
+        let converter = AccumulatingConverter::new(RawMetricsConverter::text())
+            .with_directory(directory.path());
+        convert(&converter, "# TYPE a gauge\na 1\n");
+        let restarted = AccumulatingConverter::new(RawMetricsConverter::text())
+            .with_directory(directory.path());
+        let payload = convert(&restarted, "# TYPE b gauge\nb 1\n");
+
+        // Then the payload after the restart contains the family pushed before
+        assert_eq!(payload, "# TYPE a gauge\na 1\n# TYPE b gauge\nb 1\n");
+    }
+}
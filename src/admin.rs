@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+
+/// `PushgatewayStatus` is the answer of the `/api/v1/status` endpoint of the pushgateway, see
+/// `status` of [`non_blocking::MetricsPusher`](crate::non_blocking::MetricsPusher) and
+/// [`blocking::MetricsPusher`](crate::blocking::MetricsPusher).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PushgatewayStatus {
+    pub build_information: BuildInformation,
+    /// The command line flags the pushgateway was started with, e.g. `web.enable-admin-api`.
+    pub flags: HashMap<String, String>,
+    /// The time the pushgateway was started as RFC 3339 timestamp.
+    pub start_time: String,
+}
+
+/// `BuildInformation` describes the build of the pushgateway.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BuildInformation {
+    pub version: String,
+    pub revision: String,
+    pub branch: String,
+    pub build_user: String,
+    pub build_date: String,
+    pub go_version: String,
+}
+
+/// `MetricGroup` is a group of the `/api/v1/metrics` endpoint of the pushgateway, see
+/// `metric_groups` of [`non_blocking::MetricsPusher`](crate::non_blocking::MetricsPusher) and
+/// [`blocking::MetricsPusher`](crate::blocking::MetricsPusher).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct MetricGroup {
+    /// The job and grouping labels of the group.
+    pub labels: HashMap<String, String>,
+    pub last_push_successful: bool,
+    /// The metric families of the group by name, including the `push_time_seconds` and
+    /// `push_failure_time_seconds` the pushgateway adds.
+    #[serde(flatten)]
+    pub metric_families: HashMap<String, GroupMetricFamily>,
+}
+
+impl MetricGroup {
+    /// Returns the job of the group.
+    pub fn job(&self) -> Option<&str> {
+        self.labels.get("job").map(String::as_str)
+    }
+
+    /// Returns the time of the last successful push to the group in seconds since the epoch.
+    pub fn push_time_seconds(&self) -> Option<f64> {
+        self.metric_families
+            .get("push_time_seconds")?
+            .metrics
+            .first()?
+            .value
+            .as_deref()?
+            .parse()
+            .ok()
+    }
+}
+
+/// `GroupMetricFamily` is a metric family of a [`MetricGroup`]. Values are kept as the
+/// pushgateway sends them, i.e. as text like `1`, `NaN` or `+Inf`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GroupMetricFamily {
+    /// The time of the push that last changed the family as RFC 3339 timestamp.
+    pub time_stamp: String,
+    /// The type of the family, e.g. `COUNTER` or `HISTOGRAM`.
+    #[serde(rename = "type")]
+    pub metric_type: String,
+    pub help: String,
+    pub metrics: Vec<GroupMetric>,
+}
+
+/// `GroupMetric` is a metric of a [`GroupMetricFamily`]: a `value` for counters, gauges and
+/// untyped metrics, `buckets` or `quantiles` with `count` and `sum` for histograms and
+/// summaries.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GroupMetric {
+    pub labels: HashMap<String, String>,
+    pub value: Option<String>,
+    pub buckets: Option<HashMap<String, String>>,
+    pub quantiles: Option<HashMap<String, String>>,
+    pub count: Option<String>,
+    pub sum: Option<String>,
+}
+
+/// The envelope of every answer of the pushgateway api.
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    status: String,
+    data: Option<T>,
+    error: Option<String>,
+}
+
+/// Parses the data of the given answer of the pushgateway api.
+pub(crate) fn parse<T: DeserializeOwned>(body: &str) -> Result<T> {
+    let response: ApiResponse<T> = serde_json::from_str(body)
+        .map_err(|error| PushMetricsError::InvalidApiResponse(error.to_string()))?;
+
+    match response {
+        ApiResponse { status, data: Some(data), .. } if status == "success" => Ok(data),
+        ApiResponse { status, error, .. } => Err(PushMetricsError::InvalidApiResponse(
+            error.unwrap_or(status),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::admin::parse;
+    use crate::admin::MetricGroup;
+    use crate::admin::PushgatewayStatus;
+    use crate::error::PushMetricsError;
+
+    #[test]
+    fn test_parse_status_and_metric_groups() {
+        // Given I have the answers of the status and metrics endpoints of a pushgateway
+        let status = r#"{"status":"success","data":{
+            "build_information":{"branch":"HEAD","buildDate":"20240101","buildUser":"root",
+                "goVersion":"go1.21","revision":"abc","version":"1.7.0"},
+            "flags":{"web.enable-admin-api":"true"},
+            "start_time":"2024-01-01T00:00:00Z"}}"#;
+        let metrics = r#"{"status":"success","data":[{
+            "labels":{"job":"batch","env":"prod"},
+            "last_push_successful":true,
+            "push_time_seconds":{"time_stamp":"2024-01-01T00:00:00Z","type":"GAUGE",
+                "help":"Last time a group was successfully pushed.",
+                "metrics":[{"labels":{"job":"batch","env":"prod"},"value":"1.7040672e+09"}]},
+            "latency":{"time_stamp":"2024-01-01T00:00:00Z","type":"HISTOGRAM","help":"help",
+                "metrics":[{"labels":{"job":"batch","env":"prod"},"buckets":{"1":"2"},
+                    "count":"2","sum":"0.5"}]}}]}"#;
+
+        // When I parse them
+        let status: PushgatewayStatus = parse(status).unwrap();
+        let groups: Vec<MetricGroup> = parse(metrics).unwrap();
+
+        // Then they are typed
+        assert_eq!(status.build_information.version, "1.7.0");
+        assert_eq!(status.build_information.go_version, "go1.21");
+        assert_eq!(status.flags["web.enable-admin-api"], "true");
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].job(), Some("batch"));
+        assert!(groups[0].last_push_successful);
+        assert_eq!(groups[0].push_time_seconds(), Some(1_704_067_200.0));
+        let latency = &groups[0].metric_families["latency"];
+        assert_eq!(latency.metric_type, "HISTOGRAM");
+        assert_eq!(latency.metrics[0].count.as_deref(), Some("2"));
+    }
+
+    #[cfg(feature = "with_reqwest")]
+    #[tokio::test]
+    async fn test_wipe_and_metric_groups() {
+        use mockito::Server;
+        use url::Url;
+
+        use crate::non_blocking::MetricsPusher;
+        use crate::raw::RawMetricsConverter;
+        use crate::with_reqwest::PushClient;
+
+        // Given I have a pushgateway with the admin api enabled and a pusher behind a prefix
+        let mut server = Server::new_async().await;
+        let wipe_mock = server
+            .mock("PUT", "/gateway/api/v1/admin/wipe")
+            .with_status(202)
+            .expect(1)
+            .create();
+        let metrics_mock = server
+            .mock("GET", "/gateway/api/v1/metrics")
+            .with_body(r#"{"status":"success","data":[]}"#)
+            .expect(1)
+            .create();
+        let url = Url::parse(&format!("{}/gateway", server.url())).unwrap();
+        let metrics_pusher: MetricsPusher<_, _, String, String, _> = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+
+        // When I wipe it and read its groups
+        metrics_pusher.wipe().await.expect("Failed to wipe");
+        let groups = metrics_pusher
+            .metric_groups()
+            .await
+            .expect("Failed to read groups");
+
+        // Then the admin and query endpoints below the prefix are used
+        assert!(groups.is_empty());
+        wipe_mock.assert();
+        metrics_mock.assert();
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        // Given I have an error answer of the pushgateway api
+        let body = r#"{"status":"error","errorType":"bad_data","error":"admin api disabled"}"#;
+
+        // When I parse it
+        let result = parse::<PushgatewayStatus>(body);
+
+        // Then the error of the pushgateway is returned
+        assert!(matches!(
+            result,
+            Err(PushMetricsError::InvalidApiResponse(error)) if error == "admin api disabled"
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use url::Url;
+
+#[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
+use crate::error::PushMetricsError;
+use crate::grouping::Grouping;
+use crate::Auth;
+#[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
+use crate::ConvertMetrics;
+
+/// `PusherArgs` holds the command line flags to configure a pushgateway. Flatten them into the
+/// arguments of your own CLI with `#[command(flatten)]` and create a `MetricsPusher` from them
+/// with [`TryFrom`]:
+///
+/// ```ignore
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[command(flatten)]
+///     pusher: PusherArgs,
+/// }
+///
+/// let cli = Cli::parse();
+/// let metrics_pusher = PrometheusMetricsPusher::try_from(&cli.pusher)?;
+/// metrics_pusher.push_all(&cli.pusher.job, &cli.pusher.grouping().labels(), metrics).await?;
+/// ```
+#[derive(Clone, Debug, clap::Args)]
+pub struct PusherArgs {
+    /// Address of the pushgateway instance
+    #[arg(long = "pushgateway-url")]
+    pub gateway: Url,
+
+    /// Name of the job the metrics are pushed for
+    #[arg(long = "pushgateway-job")]
+    pub job: String,
+
+    /// Grouping label in the form `name=value`, can be repeated
+    #[arg(long = "pushgateway-label", value_parser = parse_label)]
+    pub labels: Vec<(String, String)>,
+
+    /// Interval between two pushes, e.g. `500ms`, `15s`, `1m` or `1h`
+    #[arg(long = "push-interval", default_value = "15s", value_parser = parse_duration)]
+    pub interval: Duration,
+
+    /// Username for basic authentication with the pushgateway
+    #[arg(long = "pushgateway-username")]
+    pub username: Option<String>,
+
+    /// Password for basic authentication with the pushgateway
+    #[arg(long = "pushgateway-password", requires = "username")]
+    pub password: Option<String>,
+
+    /// Bearer token for authentication with the pushgateway
+    #[arg(long = "pushgateway-bearer-token", conflicts_with = "username")]
+    pub bearer_token: Option<String>,
+}
+
+impl PusherArgs {
+    /// Returns the grouping labels given with `--pushgateway-label`.
+    pub fn grouping(&self) -> Grouping {
+        self.labels
+            .iter()
+            .fold(Grouping::new(), |grouping, (name, value)| {
+                grouping.with_label(name, value)
+            })
+    }
+
+    /// Returns the credentials given with `--pushgateway-username` and `--pushgateway-password`
+    /// or `--pushgateway-bearer-token`.
+    pub fn auth(&self) -> Option<Auth> {
+        match (&self.username, &self.bearer_token) {
+            (Some(username), _) => {
+                Some(Auth::Basic { username: username.clone(), password: self.password.clone() })
+            }
+            (None, Some(token)) => Some(Auth::Bearer(token.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+fn parse_label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_owned(), value.to_owned())),
+        _ => Err(format!("label must be in the form `name=value`: '{label}'")),
+    }
+}
+
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (value, unit) = duration.split_at(split);
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| format!("duration must start with a number: '{duration}'"))?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 60 * 60)),
+        _ => Err(format!(
+            "duration unit must be one of ms, s, m or h: '{duration}'"
+        )),
+    }
+}
+
+#[cfg(feature = "with_reqwest")]
+impl<CM, MF, C, B> TryFrom<&PusherArgs>
+    for crate::non_blocking::MetricsPusher<crate::with_reqwest::PushClient, CM, MF, C, B>
+where
+    CM: ConvertMetrics<MF, C, B> + Default,
+    B: Into<reqwest::Body> + Send + Sync + 'static,
+{
+    type Error = PushMetricsError;
+
+    fn try_from(args: &PusherArgs) -> Result<Self, Self::Error> {
+        let push_client = crate::with_reqwest::PushClient::builder();
+        let push_client = match args.auth() {
+            Some(auth) => push_client.auth(auth),
+            None => push_client,
+        };
+
+        Self::new(push_client.build()?, CM::default(), &args.gateway)
+    }
+}
+
+#[cfg(feature = "with_reqwest_blocking")]
+impl<CM, MF, C, B> TryFrom<&PusherArgs>
+    for crate::blocking::MetricsPusher<crate::blocking::with_reqwest::PushClient, CM, MF, C, B>
+where
+    CM: ConvertMetrics<MF, C, B> + Default,
+    B: Into<reqwest::blocking::Body>,
+{
+    type Error = PushMetricsError;
+
+    fn try_from(args: &PusherArgs) -> Result<Self, Self::Error> {
+        let push_client = crate::blocking::with_reqwest::PushClient::builder();
+        let push_client = match args.auth() {
+            Some(auth) => push_client.auth(auth),
+            None => push_client,
+        };
+
+        Self::new(push_client.build()?, CM::default(), &args.gateway)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use clap::Parser;
+
+    use crate::args::PusherArgs;
+    use crate::grouping::Grouping;
+    #[cfg(feature = "with_reqwest")]
+    use crate::non_blocking::MetricsPusher;
+    #[cfg(feature = "with_reqwest")]
+    use crate::raw::RawMetricsConverter;
+    #[cfg(feature = "with_reqwest")]
+    use crate::with_reqwest::PushClient;
+    use crate::Auth;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        pusher: PusherArgs,
+    }
+
+    #[test]
+    fn test_parse_pusher_args() {
+        // Given I have the command line flags of a pushgateway
+        let flags = [
+            "test",
+            "--pushgateway-url",
+            "http://pushgateway:9091",
+            "--pushgateway-job",
+            "backup",
+            "--pushgateway-label",
+            "env=prod",
+            "--push-interval",
+            "500ms",
+            "--pushgateway-username",
+            "user",
+            "--pushgateway-password",
+            "secret",
+        ];
+
+        // When I parse them
+        let cli = Cli::try_parse_from(flags).unwrap();
+
+        // Then the pusher args are configured accordingly
+        assert_eq!(cli.pusher.gateway.as_str(), "http://pushgateway:9091/");
+        assert_eq!(cli.pusher.job, "backup");
+        assert_eq!(
+            cli.pusher.grouping(),
+            Grouping::new().with_label("env", "prod")
+        );
+        assert_eq!(cli.pusher.interval, Duration::from_millis(500));
+        assert_eq!(
+            cli.pusher.auth(),
+            Some(Auth::Basic {
+                username: String::from("user"),
+                password: Some(String::from("secret")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_reject_basic_auth_and_bearer_token() {
+        // Given I have flags for basic authentication and a bearer token
+        let flags = [
+            "test",
+            "--pushgateway-url",
+            "http://pushgateway:9091",
+            "--pushgateway-job",
+            "backup",
+            "--pushgateway-username",
+            "user",
+            "--pushgateway-bearer-token",
+            "token",
+        ];
+
+        // When I parse them
+        let result = Cli::try_parse_from(flags);
+
+        // Then parsing fails as only one of them can be used
+        assert!(result.is_err());
+    }
+
+    #[cfg(feature = "with_reqwest")]
+    #[tokio::test]
+    async fn test_metrics_pusher_from_pusher_args() {
+        // Given I have a push gateway that requires a bearer token
+        let mut server = mockito::Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/backup/env/prod")
+            .match_header("authorization", "Bearer token")
+            .match_body("backup_size_bytes 42\n")
+            .create();
+
+        // And pusher args for it
+        let gateway = server.url();
+        let flags = [
+            "test",
+            "--pushgateway-url",
+            &gateway,
+            "--pushgateway-job",
+            "backup",
+            "--pushgateway-label",
+            "env=prod",
+            "--pushgateway-bearer-token",
+            "token",
+        ];
+        let args = Cli::try_parse_from(flags).unwrap().pusher;
+
+        // When I create a metrics pusher from them and push
+        let metrics_pusher: MetricsPusher<PushClient, RawMetricsConverter, _, _, _> =
+            MetricsPusher::try_from(&args).unwrap();
+        metrics_pusher
+            .push_all(
+                &args.job,
+                &args.grouping().labels(),
+                "backup_size_bytes 42\n",
+            )
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the metrics are pushed with the token to the configured group
+        pushgateway_mock.expect(1).assert();
+    }
+}
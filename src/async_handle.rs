@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::blocking::MetricsPusher;
+use crate::blocking::Push;
+use crate::error::Result;
+use crate::ConvertMetrics;
+use crate::PushReceipt;
+
+/// `AsyncHandle` exposes a blocking [`MetricsPusher`] to asynchronous code by running each of
+/// its pushes on [`tokio::task::spawn_blocking`], e.g. for a custom blocking-only [`Push`]
+/// implementation that has to be called from an async call site without blocking the runtime.
+///
+/// Job names and grouping labels are copied for every call, as the pushes outlive the borrows
+/// of the caller on the blocking thread pool.
+#[derive(Debug)]
+pub struct AsyncHandle<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    metrics_pusher: Arc<MetricsPusher<P, CM, MF, C, B>>,
+}
+
+impl<P, CM, MF, C, B> Clone for AsyncHandle<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    fn clone(&self) -> Self {
+        Self { metrics_pusher: Arc::clone(&self.metrics_pusher) }
+    }
+}
+
+impl<P, CM, MF, C, B> AsyncHandle<P, CM, MF, C, B>
+where
+    P: Push<B> + Send + Sync + 'static,
+    CM: ConvertMetrics<MF, C, B> + Send + Sync + 'static,
+    MF: Send + Sync + 'static,
+    C: Send + Sync + 'static,
+    B: Send + Sync + 'static,
+{
+    /// Creates a new [`AsyncHandle`] that pushes with the given [`MetricsPusher`].
+    pub fn new(metrics_pusher: MetricsPusher<P, CM, MF, C, B>) -> Self {
+        Self { metrics_pusher: Arc::new(metrics_pusher) }
+    }
+
+    /// Pushes all metrics on the blocking thread pool, see [`MetricsPusher::push_all`].
+    pub async fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        self.spawn(job, grouping, move |pusher, job, grouping| {
+            pusher.push_all(job, grouping, metric_families)
+        })
+        .await
+    }
+
+    /// Pushes all metrics with add logic on the blocking thread pool, see
+    /// [`MetricsPusher::push_add`].
+    pub async fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        self.spawn(job, grouping, move |pusher, job, grouping| {
+            pusher.push_add(job, grouping, metric_families)
+        })
+        .await
+    }
+
+    /// Deletes the group on the blocking thread pool, see [`MetricsPusher::delete`].
+    pub async fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        self.spawn(job, grouping, |pusher, job, grouping| {
+            pusher.delete(job, grouping)
+        })
+        .await
+    }
+
+    /// Returns the wrapped [`MetricsPusher`], e.g. to push from synchronous code as well.
+    pub fn metrics_pusher(&self) -> &MetricsPusher<P, CM, MF, C, B> {
+        &self.metrics_pusher
+    }
+
+    /// Runs the given push with owned copies of the job and grouping labels on the blocking
+    /// thread pool. A panic of the push is resumed on the caller.
+    async fn spawn(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        push: impl FnOnce(
+                &MetricsPusher<P, CM, MF, C, B>,
+                &str,
+                &HashMap<&str, &str>,
+            ) -> Result<PushReceipt>
+            + Send
+            + 'static,
+    ) -> Result<PushReceipt> {
+        let metrics_pusher = Arc::clone(&self.metrics_pusher);
+        let job = job.to_owned();
+        let grouping: HashMap<String, String> = grouping
+            .iter()
+            .map(|(label_name, label_value)| (label_name.to_string(), label_value.to_string()))
+            .collect();
+
+        let task = tokio::task::spawn_blocking(move || {
+            let grouping = grouping
+                .iter()
+                .map(|(label_name, label_value)| (label_name.as_str(), label_value.as_str()))
+                .collect();
+            push(&metrics_pusher, &job, &grouping)
+        });
+
+        match task.await {
+            Ok(result) => result,
+            Err(error) => match error.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(error) => panic!("push on the blocking thread pool was cancelled: {error}"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use url::Url;
+
+    use crate::async_handle::AsyncHandle;
+    use crate::blocking::MetricsPusher;
+    use crate::blocking::Push;
+    use crate::error::Result;
+    use crate::raw::RawMetricsConverter;
+    use crate::Method;
+
+    type Requests = Arc<Mutex<Vec<(Method, String, Option<Vec<u8>>)>>>;
+
+    struct RecordingPushClient {
+        requests: Requests,
+    }
+
+    impl Push<Vec<u8>> for RecordingPushClient {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(Vec<u8>, &str)>,
+            _: &[(&str, &str)],
+        ) -> Result<()> {
+            self.requests.lock().unwrap().push((
+                method,
+                url.path().to_owned(),
+                body.map(|(body, _)| body),
+            ));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_from_asynchronous_code() {
+        // Given I have an async handle of a blocking metrics pusher with a blocking-only client
+        let requests = Requests::default();
+        let url = Url::parse("http://pushgateway:9091").unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            RecordingPushClient { requests: Arc::clone(&requests) },
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let async_handle = AsyncHandle::new(metrics_pusher);
+
+        // When I push and delete from asynchronous code
+        async_handle
+            .push_all("async_job", &HashMap::from([("env", "test")]), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        async_handle
+            .delete("async_job", &HashMap::from([("env", "test")]))
+            .await
+            .expect("Failed to delete metrics");
+
+        // Then the client sent both requests with the owned job and grouping labels
+        let requests = requests.lock().unwrap();
+        assert_eq!(
+            *requests,
+            vec![
+                (
+                    Method::Put,
+                    "/metrics/job/async_job/env/test".to_owned(),
+                    Some(b"up 1\n".to_vec())
+                ),
+                (
+                    Method::Delete,
+                    "/metrics/job/async_job/env/test".to_owned(),
+                    None
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::PathBuf;
+
+use clap::ArgGroup;
+use clap::Parser;
+use prometheus_push::blocking::with_reqwest::PushClient;
+use prometheus_push::blocking::MetricsPusher;
+use prometheus_push::raw::RawMetricsConverter;
+use url::Url;
+
+/// Pushes metrics in the Prometheus text format from stdin or a file to a pushgateway.
+#[derive(Debug, Parser)]
+#[command(name = "push-metrics", version)]
+#[command(group(ArgGroup::new("mode").args(["add", "replace", "delete"])))]
+struct Args {
+    /// Address of the pushgateway instance
+    #[arg(long)]
+    gateway: Url,
+
+    /// Name of the job the metrics are pushed for
+    #[arg(long)]
+    job: String,
+
+    /// Grouping label in the form `name=value`, can be repeated
+    #[arg(long = "label", value_parser = parse_label)]
+    labels: Vec<(String, String)>,
+
+    /// Only replace metrics with the same name within the group (POST)
+    #[arg(long)]
+    add: bool,
+
+    /// Replace all metrics within the group (PUT), this is the default
+    #[arg(long)]
+    replace: bool,
+
+    /// Delete all metrics of the group (DELETE)
+    #[arg(long)]
+    delete: bool,
+
+    /// File to read the metrics from, reads from stdin if omitted or `-`
+    file: Option<PathBuf>,
+}
+
+fn parse_label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_owned(), value.to_owned())),
+        _ => Err(format!("label must be in the form `name=value`: '{label}'")),
+    }
+}
+
+fn read_metrics(file: Option<&PathBuf>) -> io::Result<String> {
+    match file {
+        Some(path) if path.as_os_str() != "-" => fs::read_to_string(path),
+        _ => {
+            let mut metrics = String::new();
+            io::stdin().read_to_string(&mut metrics)?;
+            Ok(metrics)
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let push_client = PushClient::builder().build()?;
+    let metrics_pusher =
+        MetricsPusher::new(push_client, RawMetricsConverter::text(), &args.gateway)?;
+    let grouping: HashMap<&str, &str> = args
+        .labels
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+
+    if args.delete {
+        metrics_pusher.delete(&args.job, &grouping)?;
+        return Ok(());
+    }
+
+    let metrics = read_metrics(args.file.as_ref())?;
+    if args.add {
+        metrics_pusher.push_add(&args.job, &grouping, metrics)?;
+    } else {
+        metrics_pusher.push_all(&args.job, &grouping, metrics)?;
+    }
+
+    Ok(())
+}
@@ -1,14 +1,78 @@
 #[cfg(feature = "with_reqwest_blocking")]
 pub mod with_reqwest;
+#[cfg(feature = "with_ureq")]
+pub mod with_ureq;
 
 use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
 
 use url::Url;
 
+#[cfg(feature = "admin")]
+use crate::admin;
+#[cfg(feature = "admin")]
+use crate::admin::MetricGroup;
+#[cfg(feature = "admin")]
+use crate::admin::PushgatewayStatus;
+use crate::error::PushMetricsError;
 use crate::error::Result;
+use crate::grouping::JobName;
+#[cfg(doc)]
+use crate::raw::RawMetricsConverter;
+use crate::tenant::with_tenant_header;
+use crate::tenant::Tenant;
+use crate::tenant::TenantResolver;
+use crate::tenant::TenantRouter;
+use crate::text_format;
+use crate::utils::borrow_grouping;
+use crate::utils::build_url;
 use crate::utils::create_metrics_job_url;
+use crate::utils::into_receipt;
+use crate::utils::merge_grouping;
+use crate::utils::negotiate;
+use crate::utils::rejects_format;
+use crate::utils::sanitize_grouping;
+use crate::utils::text_payload;
+#[cfg(any(
+    feature = "body_digest",
+    feature = "compress_gzip",
+    feature = "compress_zstd"
+))]
+use crate::utils::with_header;
+#[cfg(feature = "body_digest")]
+use crate::utils::BodyDigester;
+#[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+use crate::utils::Compressor;
+use crate::utils::Deferred;
+use crate::utils::Format;
+use crate::utils::FormatCache;
+use crate::utils::NonFiniteFilter;
+use crate::utils::PayloadSizeLimit;
+use crate::utils::PayloadSizeWarning;
+use crate::utils::PushObserver;
 use crate::utils::PushType;
+use crate::utils::PushedFamilies;
+use crate::utils::Throttle;
+use crate::utils::CONTENT_TYPE_TEXT;
+#[cfg(feature = "body_digest")]
+use crate::BodyDigest;
+#[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+use crate::Compression;
+use crate::ContentEncoding;
 use crate::ConvertMetrics;
+use crate::Method;
+use crate::NonFinitePolicy;
+use crate::NoopPushClient;
+use crate::NoopPusher;
+use crate::PushEvent;
+use crate::PushReceipt;
+use crate::PushTimes;
+use crate::RequestId;
+use crate::UrlJoin;
+use crate::ValidationMode;
 
 /// `MetricsPusher` is a prometheus pushgateway client that holds information about the
 /// address of your pushgateway instance and the [`Push`] client that is used to push
@@ -22,17 +86,139 @@ where
 {
     push_client: P,
     metrics_converter: CM,
+    gateway_url: Url,
     url: Url,
+    url_join: UrlJoin,
+    validation_mode: ValidationMode,
+    default_job: Option<JobName>,
+    default_grouping: HashMap<String, String>,
+    enabled: AtomicBool,
+    request_id: Option<RequestId>,
+    payload_size_warning: Option<PayloadSizeWarning<B>>,
+    payload_size_limit: Option<PayloadSizeLimit<B>>,
+    non_finite: Option<NonFiniteFilter<B>>,
+    #[cfg(feature = "body_digest")]
+    body_digest: Option<BodyDigester<B>>,
+    #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+    compression: Option<Compressor<B>>,
+    observer: Option<PushObserver<B>>,
+    formats: FormatCache,
+    pushed_families: PushedFamilies,
+    throttle: Option<Throttle<B>>,
+    tenants: Option<TenantRouter>,
+    parallelism: usize,
     mf: std::marker::PhantomData<MF>,
     c: std::marker::PhantomData<C>,
     b: std::marker::PhantomData<B>,
 }
 
 /// `Push` is a trait that defines the interface for the implementation of your own http
-/// client of choice.
+/// client of choice. Only [`Push::send`] has to be implemented, the other methods send the
+/// requests of the pushgateway api with it.
 pub trait Push<B> {
-    fn push_all(&self, url: &Url, body: B, content_type: &str) -> Result<()>;
-    fn push_add(&self, url: &Url, body: B, content_type: &str) -> Result<()>;
+    /// Sends a request with the given method, body with its content type and headers to the
+    /// given url. Fails if the request fails or the pushgateway does not answer with a success
+    /// status code. The body is `None` for [`Method::Delete`].
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(B, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<()>;
+
+    /// Pushes the body with a `PUT` request.
+    fn push_all(
+        &self,
+        url: &Url,
+        body: B,
+        content_type: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        self.send(Method::Put, url, Some((body, content_type)), headers)
+    }
+
+    /// Pushes the body with a `POST` request.
+    fn push_add(
+        &self,
+        url: &Url,
+        body: B,
+        content_type: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        self.send(Method::Post, url, Some((body, content_type)), headers)
+    }
+
+    /// Deletes the group with a `DELETE` request.
+    fn delete(&self, url: &Url, headers: &[(&str, &str)]) -> Result<()> {
+        self.send(Method::Delete, url, None, headers)
+    }
+
+    /// Pushes like [`Push::push_all`] but fails with [`PushMetricsError::Timeout`] if the
+    /// request does not finish within the given timeout. The default implementation ignores the
+    /// timeout, override it if your http client supports timeouts per request.
+    fn push_all_with_timeout(
+        &self,
+        url: &Url,
+        body: B,
+        content_type: &str,
+        headers: &[(&str, &str)],
+        _timeout: Duration,
+    ) -> Result<()> {
+        self.push_all(url, body, content_type, headers)
+    }
+}
+
+/// `Fetch` is a trait that defines the interface for reading from an http endpoint with
+/// your own http client of choice, e.g. to read back the metrics of your pushgateway.
+pub trait Fetch {
+    fn fetch(&self, url: &Url) -> Result<String>;
+}
+
+const DEFAULT_PARALLELISM: usize = 4;
+
+/// Runs `push` for all given items concurrently on at most `parallelism` scoped threads, e.g. to
+/// push to several pushgateways without an async runtime. The results are returned in the order
+/// of the items.
+pub fn fan_out<T, R>(items: Vec<T>, parallelism: usize, push: impl Fn(T) -> R + Sync) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+{
+    let workers = parallelism.clamp(1, items.len().max(1));
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let next = || {
+        queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .next()
+    };
+
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    while let Some((index, item)) = next() {
+                        results.push((index, push(item)));
+                    }
+                    results
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
 }
 
 impl<P, CM, MF, C, B> MetricsPusher<P, CM, MF, C, B>
@@ -41,26 +227,236 @@ where
     CM: ConvertMetrics<MF, C, B>,
 {
     /// Creates a new [`MetricsPusher`] with the given [`Push`] client, [`ConvertMetrics`]
-    /// implementation and the url of your pushgateway instance.
+    /// implementation and the url of your pushgateway instance. The path of the url is kept,
+    /// see [`UrlJoin`].
     pub fn new(
         push_client: P,
         metrics_converter: CM,
         url: &Url,
     ) -> Result<MetricsPusher<P, CM, MF, C, B>> {
-        let url = create_metrics_job_url(url)?;
+        let gateway_url = url.clone();
+        let url = create_metrics_job_url(url, UrlJoin::default())?;
         Ok(Self {
             push_client,
             metrics_converter,
+            gateway_url,
             url,
+            url_join: UrlJoin::default(),
+            validation_mode: ValidationMode::default(),
+            default_job: None,
+            default_grouping: HashMap::new(),
+            enabled: AtomicBool::new(true),
+            request_id: None,
+            payload_size_warning: None,
+            payload_size_limit: None,
+            non_finite: None,
+            #[cfg(feature = "body_digest")]
+            body_digest: None,
+            #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+            compression: None,
+            observer: None,
+            formats: FormatCache::default(),
+            pushed_families: PushedFamilies::default(),
+            throttle: None,
+            tenants: None,
+            parallelism: DEFAULT_PARALLELISM,
             mf: std::marker::PhantomData,
             c: std::marker::PhantomData,
             b: std::marker::PhantomData,
         })
     }
 
+    /// Sets how the url of your pushgateway instance is joined with the pushgateway api path.
+    /// Defaults to [`UrlJoin::Directory`] which keeps the complete path of the url.
+    pub fn with_url_join(mut self, url_join: UrlJoin) -> Result<Self> {
+        self.url = create_metrics_job_url(&self.gateway_url, url_join)?;
+        self.url_join = url_join;
+        Ok(self)
+    }
+
+    /// Sets the [`ValidationMode`] that is applied to the job name and grouping labels of
+    /// every push. Defaults to [`ValidationMode::Strict`].
+    pub fn with_validation_mode(mut self, validation_mode: ValidationMode) -> Self {
+        self.validation_mode = validation_mode;
+        self
+    }
+
+    /// Sets the job name that is used by [`MetricsPusher::push_all_default`],
+    /// [`MetricsPusher::push_add_default`] and [`MetricsPusher::delete_default`], so services
+    /// that push with exactly one job do not have to pass it through to every call site.
+    pub fn with_default_job(mut self, job: JobName) -> Self {
+        self.default_job = Some(job);
+        self
+    }
+
+    /// Sets grouping labels that are merged into the grouping labels of every push and delete,
+    /// e.g. `instance` or `env`. Grouping labels given to a single call win over default
+    /// grouping labels with the same name.
+    pub fn with_default_grouping(mut self, grouping: &HashMap<&str, &str>) -> Self {
+        self.default_grouping = grouping
+            .iter()
+            .map(|(label_name, label_value)| (label_name.to_string(), label_value.to_string()))
+            .collect();
+        self
+    }
+
+    /// Disables the pusher at runtime, e.g. in local development or during an incident of the
+    /// pushgateway: until [`MetricsPusher::enable`] is called nothing is sent and every push and
+    /// delete returns a [`PushReceipt`] that [`is_skipped`](PushReceipt::is_skipped). Job
+    /// names, grouping labels and metrics are still validated and encoded.
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    /// Enables the pusher again after [`MetricsPusher::disable`].
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the pusher sends pushes, see [`MetricsPusher::disable`].
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Sends a request id with every push as configured by the given [`RequestId`]. The id is
+    /// logged and returned in the [`PushReceipt`], so failed pushes can be correlated with the
+    /// logs of the pushgateway.
+    pub fn with_request_id(mut self, request_id: RequestId) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    /// Measures every encoded payload, returns its size in the [`PushReceipt`] and logs a
+    /// warning if it exceeds the given threshold in bytes, so a cardinality explosion is
+    /// noticed before it overloads the pushgateway.
+    pub fn with_payload_size_warning(mut self, threshold: usize) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        self.payload_size_warning = Some(PayloadSizeWarning::new(threshold));
+        self
+    }
+
+    /// Rejects every encoded payload above the given size in bytes with
+    /// [`PushMetricsError::PayloadTooLarge`] before it is sent, e.g. to fail fast instead of
+    /// running into the request size limit of the pushgateway.
+    pub fn with_max_payload_size(mut self, limit: usize) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        self.payload_size_limit = Some(PayloadSizeLimit::new(limit));
+        self
+    }
+
+    /// Applies the given [`NonFinitePolicy`] to the samples with a `NaN` or infinite value of
+    /// every pushed payload, as pushgateways and their consumers may choke on them. The number
+    /// of affected samples is returned in the [`PushReceipt`]. Requires the metrics to be
+    /// encoded in the text format.
+    pub fn with_non_finite_policy(mut self, policy: NonFinitePolicy) -> Self
+    where
+        B: AsRef<[u8]> + From<String>,
+    {
+        self.non_finite = Some(NonFiniteFilter::new(policy));
+        self
+    }
+
+    /// Attaches the given [`BodyDigest`] of the encoded body to every push of metrics, so a
+    /// proxy in front of the pushgateway can detect truncated uploads.
+    #[cfg(feature = "body_digest")]
+    pub fn with_body_digest(mut self, digest: BodyDigest) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        self.body_digest = Some(BodyDigester::new(digest));
+        self
+    }
+
+    /// Compresses the payloads according to the given [`Compression`] and sends them with the
+    /// matching `Content-Encoding` header, which is returned in the [`PushReceipt`]. Payloads
+    /// below its thresholds are sent uncompressed.
+    #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+    pub fn with_compression(mut self, compression: Compression) -> Self
+    where
+        B: AsRef<[u8]> + From<Vec<u8>>,
+    {
+        self.compression = Some(Compressor::new(compression));
+        self
+    }
+
+    /// Passes a [`PushEvent`] with the job, grouping, endpoint, outcome, duration and size of
+    /// every sent request to the given observer, e.g. to log it in a structured way. Skipped
+    /// requests of a disabled pusher are not observed.
+    pub fn with_observer(mut self, observer: impl Fn(&PushEvent) + Send + Sync + 'static) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        self.observer = Some(PushObserver::new(observer));
+        self
+    }
+
+    /// Resolves the [`Tenant`] of every push and delete with the given [`TenantResolver`] when
+    /// it is sent and routes it with the header of the tenant to its endpoint, so one pusher
+    /// can push the metrics of several customers with the right isolation. The tenant is
+    /// returned in the [`PushReceipt`].
+    pub fn with_tenant_resolver(mut self, resolver: impl TenantResolver + 'static) -> Self {
+        self.tenants = Some(TenantRouter::new(resolver));
+        self
+    }
+
+    /// Pushes each group at most once per given interval, e.g. when pushes are triggered from a
+    /// hot path, to protect the pushgateway. A push that comes in too early is deferred and its
+    /// receipt `is_skipped`, a newer push of the group replaces it. Deferred payloads are pushed
+    /// with the next push of their group after the interval or by [`MetricsPusher::flush`],
+    /// unless the group is deleted or cleared in the meantime.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.throttle = Some(Throttle::new(interval));
+        self
+    }
+
+    /// Pushes the payloads that were deferred by [`MetricsPusher::with_min_interval`] right
+    /// away, e.g. before shutdown, and returns their results.
+    pub fn flush(&self) -> Vec<Result<PushReceipt>> {
+        let Some(throttle) = &self.throttle else {
+            return Vec::new();
+        };
+
+        let mut receipts = Vec::new();
+        for deferred in throttle.take_deferred() {
+            let grouping = deferred
+                .grouping
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            let context = self.context(&deferred.job, &grouping);
+            let receipt = self
+                .transmit(
+                    &deferred.job,
+                    &grouping,
+                    deferred.url,
+                    deferred.body,
+                    &deferred.content_type,
+                    deferred.push_type,
+                )
+                .map_err(context);
+            receipts.push(receipt);
+        }
+
+        receipts
+    }
+
+    /// Sets how many pushes of [`MetricsPusher::push_all_concurrently`] run at the same time.
+    /// Defaults to 4.
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
     /// Pushes all metrics to your pushgateway instance.
     ///
-    /// Job name and grouping labels must not contain the character '/'.
+    /// Grouping label names must follow the Prometheus naming rules `[a-zA-Z_][a-zA-Z0-9_]*`.
+    /// Job names and label values with the character '/' as well as empty label values are
+    /// sent as base64 encoded path segments, other reserved url characters like '?', '#', '%'
+    /// or spaces are percent-encoded.
     ///
     /// As this method pushes all metrics to the pushgateway it replaces all previously
     /// pushed metrics with the same job and grouping labels.
@@ -69,39 +465,461 @@ where
         job: &str,
         grouping: &HashMap<&str, &str>,
         metric_families: MF,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         self.push(job, grouping, metric_families, PushType::All)
     }
 
     /// Pushes all metrics to your pushgateway instance with add logic. It will only replace
     /// recently pushed metrics with the same name and grouping labels.
     ///
-    /// Job name and grouping labels must not contain the character '/'.
+    /// Grouping label names must follow the Prometheus naming rules `[a-zA-Z_][a-zA-Z0-9_]*`.
+    /// Job names and label values with the character '/' as well as empty label values are
+    /// sent as base64 encoded path segments, other reserved url characters like '?', '#', '%'
+    /// or spaces are percent-encoded.
     pub fn push_add(
         &self,
         job: &str,
         grouping: &HashMap<&str, &str>,
         metric_families: MF,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         self.push(job, grouping, metric_families, PushType::Add)
     }
 
+    /// Pushes all metrics like [`MetricsPusher::push_all`] but fails with
+    /// [`PushMetricsError::Timeout`] if this single push does not finish within the given
+    /// timeout, regardless of the timeout the http client is configured with.
+    pub fn push_all_with_timeout(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+        timeout: Duration,
+    ) -> Result<PushReceipt> {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let (url, encoded_metrics, content_type) =
+            self.push_details(job, grouping, |converter, url, grouping| {
+                converter.create_push_details(job, url, grouping, metric_families)
+            })?;
+        if !self.is_enabled() {
+            return Ok(PushReceipt::skipped(url));
+        }
+
+        let payload_size = self.payload_size(&encoded_metrics, &url);
+        let (url, tenant) = self
+            .route(job, grouping, &url)
+            .map_err(self.context(job, grouping))?;
+        let request_id = self.request_id.as_ref().map(RequestId::generate);
+        let header = request_id.as_ref().map(|(name, id)| (*name, id.as_str()));
+        let headers = with_tenant_header(header.as_slice(), tenant.as_ref());
+        let mut observation =
+            PushObserver::start(self.observer.as_ref(), Method::Put, job, grouping, &url);
+        observation.measure(&encoded_metrics);
+        let result = self.push_client.push_all_with_timeout(
+            &url,
+            encoded_metrics,
+            &content_type,
+            &headers,
+            timeout,
+        );
+        let result = result.map_err(self.context(job, grouping));
+        observation.finish(&result);
+
+        into_receipt(result, url, request_id).map(|receipt| {
+            receipt
+                .with_payload_size(payload_size)
+                .with_tenant(tenant.as_ref())
+        })
+    }
+
+    /// Pushes the metrics of several jobs or groups like [`MetricsPusher::push_all`], but
+    /// concurrently on at most the configured parallelism of scoped threads, see
+    /// [`MetricsPusher::with_parallelism`]. The results are returned in the order of the pushes.
+    pub fn push_all_concurrently(
+        &self,
+        pushes: Vec<(&str, &HashMap<&str, &str>, MF)>,
+    ) -> Vec<Result<PushReceipt>>
+    where
+        Self: Sync,
+        MF: Send,
+    {
+        fan_out(
+            pushes,
+            self.parallelism,
+            |(job, grouping, metric_families)| self.push_all(job, grouping, metric_families),
+        )
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_all`] with the default job, see
+    /// [`MetricsPusher::with_default_job`]. Fails with [`PushMetricsError::MissingJob`] if no
+    /// default job is configured.
+    pub fn push_all_default(
+        &self,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        let job = self.default_job()?;
+        self.push(job, grouping, metric_families, PushType::All)
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_add`] with the default job, see
+    /// [`MetricsPusher::push_all_default`].
+    pub fn push_add_default(
+        &self,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        let job = self.default_job()?;
+        self.push(job, grouping, metric_families, PushType::Add)
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_all`] but gathers them with the given
+    /// function only right before they are pushed, so no stale pre-gathered metrics are pushed.
+    pub fn push_all_with(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        gather: impl FnOnce() -> MF,
+    ) -> Result<PushReceipt> {
+        self.push(job, grouping, gather(), PushType::All)
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_add`] but gathers them with the given
+    /// function only right before they are pushed, see [`MetricsPusher::push_all_with`].
+    pub fn push_add_with(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        gather: impl FnOnce() -> MF,
+    ) -> Result<PushReceipt> {
+        self.push(job, grouping, gather(), PushType::Add)
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_all`] and afterwards reads the group back
+    /// from the `/metrics` endpoint of your pushgateway instance to verify that every pushed
+    /// sample is exposed with the pushed value. Otherwise
+    /// [`PushMetricsError::Verification`](crate::error::PushMetricsError::Verification)
+    /// lists every mismatch.
+    ///
+    /// Verification requires the metrics to be encoded in the text format, e.g. by the
+    /// prometheus-client converter or
+    /// [`RawMetricsConverter::text`](crate::raw::RawMetricsConverter::text).
+    pub fn push_all_verified(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt>
+    where
+        P: Fetch,
+        B: AsRef<[u8]>,
+    {
+        self.push_verified(job, grouping, metric_families, PushType::All)
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_add`] and verifies them like
+    /// [`MetricsPusher::push_all_verified`].
+    pub fn push_add_verified(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt>
+    where
+        P: Fetch,
+        B: AsRef<[u8]>,
+    {
+        self.push_verified(job, grouping, metric_families, PushType::Add)
+    }
+
+    /// Pushes only the metric families that changed since the last [`MetricsPusher::push_changed`]
+    /// to the group with add logic like [`MetricsPusher::push_add`], which keeps the payloads of
+    /// mostly static registries small. If nothing changed nothing is pushed and the receipt
+    /// `is_skipped`.
+    ///
+    /// Families that are no longer gathered are kept at the pushgateway, so use it for groups
+    /// with a stable set of families and push them only with this method. A delete or clear of
+    /// the group pushes all families again with the next push. Like
+    /// [`MetricsPusher::push_all_verified`] it requires the metrics to be encoded in the text
+    /// format.
+    pub fn push_changed(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt>
+    where
+        B: AsRef<[u8]> + From<String>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let sanitized_grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let (url, encoded_metrics, content_type) = self
+            .metrics_converter
+            .create_push_details(job, &self.url, sanitized_grouping, metric_families)
+            .map_err(&context)?;
+        let families = text_payload(encoded_metrics.as_ref(), &content_type)
+            .and_then(|text| text_format::parse(&text))
+            .map_err(&context)?;
+
+        let changed = self.pushed_families.changed(&url, &families);
+        if changed.is_empty() {
+            return Ok(PushReceipt::skipped(url));
+        }
+
+        let body = B::from(text_format::encode_as(&changed, &content_type));
+        let receipt = self
+            .send(
+                job,
+                grouping,
+                url.clone(),
+                body,
+                &content_type,
+                PushType::Add,
+            )
+            .map_err(context)?;
+        if !receipt.is_skipped() {
+            self.pushed_families.remember(&url, families);
+        }
+
+        Ok(receipt)
+    }
+
+    /// Splits the metrics by the value of the given label, e.g. `tenant`, and pushes each part
+    /// like [`MetricsPusher::push_all`] to its own group with the label added to the grouping,
+    /// e.g. `tenant=a`, and removed from the series. Series without the label are pushed to the
+    /// group of the given grouping. The receipts are returned in the order of the label values,
+    /// the first failed push stops the remaining ones.
+    ///
+    /// Like [`MetricsPusher::push_all_verified`] it requires the metrics to be encoded in the
+    /// text format.
+    pub fn push_all_split(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        label: &str,
+        metric_families: MF,
+    ) -> Result<Vec<PushReceipt>>
+    where
+        B: AsRef<[u8]> + From<String>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let sanitized_grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let (_, encoded_metrics, content_type) = self
+            .metrics_converter
+            .create_push_details(job, &self.url, sanitized_grouping, metric_families)
+            .map_err(&context)?;
+        let families = text_payload(encoded_metrics.as_ref(), &content_type)
+            .and_then(|text| text_format::parse(&text))
+            .map_err(&context)?;
+
+        let mut receipts = Vec::new();
+        for (value, families) in text_format::split_by_label(families, label) {
+            let mut grouping = grouping.clone();
+            if let Some(value) = &value {
+                grouping.insert(label, value);
+            }
+
+            let context = self.context(job, &grouping);
+            let url = self.group_url(job, &grouping).map_err(&context)?;
+            let body = B::from(text_format::encode_as(&families, &content_type));
+            let receipt = self
+                .send(job, &grouping, url, body, &content_type, PushType::All)
+                .map_err(context)?;
+            receipts.push(receipt);
+        }
+
+        Ok(receipts)
+    }
+
+    /// Deletes all metrics of the group identified by the given job and grouping labels
+    /// from your pushgateway instance.
+    ///
+    /// Grouping label names must follow the Prometheus naming rules `[a-zA-Z_][a-zA-Z0-9_]*`.
+    /// Job names and label values with the character '/' as well as empty label values are
+    /// sent as base64 encoded path segments, other reserved url characters like '?', '#', '%'
+    /// or spaces are percent-encoded.
+    pub fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let url = self.group_url(job, grouping)?;
+        if !self.is_enabled() {
+            return Ok(PushReceipt::skipped(url));
+        }
+        let request_id = self.request_id.as_ref().map(RequestId::generate);
+        let header = request_id.as_ref().map(|(name, id)| (*name, id.as_str()));
+        let (routed, tenant) = self
+            .route(job, grouping, &url)
+            .map_err(self.context(job, grouping))?;
+        let headers = with_tenant_header(header.as_slice(), tenant.as_ref());
+        let observation = PushObserver::start(
+            self.observer.as_ref(),
+            Method::Delete,
+            job,
+            grouping,
+            &routed,
+        );
+        let result = self
+            .push_client
+            .delete(&routed, &headers)
+            .map_err(self.context(job, grouping));
+
+        observation.finish(&result);
+        if result.is_ok() {
+            self.forget_group(&url);
+        }
+        into_receipt(result, routed, request_id).map(|receipt| receipt.with_tenant(tenant.as_ref()))
+    }
+
+    /// Deletes the group of the default job like [`MetricsPusher::delete`], see
+    /// [`MetricsPusher::push_all_default`].
+    pub fn delete_default(&self, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        self.delete(self.default_job()?, grouping)
+    }
+
+    /// Clears the group identified by the given job and grouping labels: all its metrics are
+    /// deleted from your pushgateway instance but, unlike [`MetricsPusher::delete`], the group
+    /// itself is kept together with its `push_time_seconds`.
+    ///
+    /// Job name and grouping labels follow the same rules as for [`MetricsPusher::delete`].
+    pub fn clear(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt>
+    where
+        B: Default,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let url = self.group_url(job, grouping)?;
+        if !self.is_enabled() {
+            return Ok(PushReceipt::skipped(url));
+        }
+        let request_id = self.request_id.as_ref().map(RequestId::generate);
+        let header = request_id.as_ref().map(|(name, id)| (*name, id.as_str()));
+        let (routed, tenant) = self
+            .route(job, grouping, &url)
+            .map_err(self.context(job, grouping))?;
+        let headers = with_tenant_header(header.as_slice(), tenant.as_ref());
+        let mut observation =
+            PushObserver::start(self.observer.as_ref(), Method::Put, job, grouping, &routed);
+        let body = B::default();
+        observation.measure(&body);
+        let result = self
+            .push_client
+            .push_all(&routed, body, CONTENT_TYPE_TEXT, &headers)
+            .map_err(self.context(job, grouping));
+
+        observation.finish(&result);
+        if result.is_ok() {
+            self.forget_group(&url);
+        }
+        into_receipt(result, routed, request_id).map(|receipt| receipt.with_tenant(tenant.as_ref()))
+    }
+
     pub fn push_all_collectors(
         &self,
         job: &str,
         grouping: &HashMap<&str, &str>,
         collectors: C,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         self.push_collectors(job, grouping, collectors, PushType::All)
     }
 
+    /// Pushes a single gauge without labels, e.g. the size of a backup reported by a script,
+    /// without setting up a registry. Like [`MetricsPusher::push_add`] only metrics with the
+    /// same name are replaced within the group.
+    pub fn push_gauge(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        name: &str,
+        value: f64,
+        help: &str,
+    ) -> Result<PushReceipt>
+    where
+        B: From<String>,
+    {
+        self.push_single_sample(job, grouping, name, "gauge", value, help)
+    }
+
+    /// Pushes a single counter without labels, see [`MetricsPusher::push_gauge`].
+    pub fn push_counter(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        name: &str,
+        value: f64,
+        help: &str,
+    ) -> Result<PushReceipt>
+    where
+        B: From<String>,
+    {
+        self.push_single_sample(job, grouping, name, "counter", value, help)
+    }
+
+    /// Reads back the [`PushTimes`] of the group identified by the given job and grouping labels
+    /// from the `/metrics` endpoint of your pushgateway instance, e.g. to verify that the last
+    /// push is recent enough. Returns `None` if the pushgateway does not know the group.
+    pub fn push_times(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<Option<PushTimes>>
+    where
+        P: Fetch,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let metrics = self.push_client.fetch(&self.metrics_url()?)?;
+
+        text_format::push_times(&metrics, job, grouping)
+    }
+
+    /// Deletes all metrics of all groups from your pushgateway instance with its admin api,
+    /// which has to be enabled with `--web.enable-admin-api`. Nothing is deleted while pushing
+    /// is turned off.
+    #[cfg(feature = "admin")]
+    pub fn wipe(&self) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        self.push_client
+            .send(Method::Put, &self.api_url("admin/wipe")?, None, &[])
+    }
+
+    /// Reads the build information, flags and start time of your pushgateway instance.
+    #[cfg(feature = "admin")]
+    pub fn status(&self) -> Result<PushgatewayStatus>
+    where
+        P: Fetch,
+    {
+        admin::parse(&self.push_client.fetch(&self.api_url("status")?)?)
+    }
+
+    /// Reads all groups with their metrics from your pushgateway instance.
+    #[cfg(feature = "admin")]
+    pub fn metric_groups(&self) -> Result<Vec<MetricGroup>>
+    where
+        P: Fetch,
+    {
+        admin::parse(&self.push_client.fetch(&self.api_url("metrics")?)?)
+    }
+
     /// Pushes all metrics from collectors to the pushgateway.
     pub fn push_add_collectors(
         &self,
         job: &str,
         grouping: &HashMap<&str, &str>,
         collectors: C,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         self.push_collectors(job, grouping, collectors, PushType::Add)
     }
 
@@ -113,7 +931,7 @@ where
         grouping: &HashMap<&str, &str>,
         collectors: C,
         push_type: PushType,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         let metric_families = self.metrics_converter.metrics_from(collectors)?;
         self.push(job, grouping, metric_families, push_type)
     }
@@ -124,17 +942,533 @@ where
         grouping: &HashMap<&str, &str>,
         metric_families: MF,
         push_type: PushType,
-    ) -> Result<()> {
-        let (url, encoded_metrics, encoder) = self.metrics_converter.create_push_details(
+    ) -> Result<PushReceipt> {
+        self.push_encoded(job, grouping, push_type, |converter, url, grouping| {
+            converter.create_push_details(job, url, grouping, metric_families)
+        })
+    }
+
+    /// Pushes the metrics the given function encodes with the converter for the base url and
+    /// the sanitized grouping.
+    pub(crate) fn push_encoded(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        push_type: PushType,
+        encode: impl FnOnce(&CM, &Url, &HashMap<&str, &str>) -> Result<(Url, B, String)>,
+    ) -> Result<PushReceipt> {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let (url, encoded_metrics, content_type) = self.push_details(job, grouping, encode)?;
+
+        self.send(
             job,
-            &self.url,
             grouping,
-            metric_families,
+            url,
+            encoded_metrics,
+            &content_type,
+            push_type,
+        )
+        .map_err(context)
+    }
+
+    fn push_details(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        encode: impl FnOnce(&CM, &Url, &HashMap<&str, &str>) -> Result<(Url, B, String)>,
+    ) -> Result<(Url, B, String)> {
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        encode(&self.metrics_converter, &self.url, grouping).map_err(context)
+    }
+
+    fn default_job(&self) -> Result<&str> {
+        self.default_job
+            .as_deref()
+            .ok_or(PushMetricsError::MissingJob)
+    }
+
+    /// Adds the job, grouping and pushgateway of a push to the errors of encoding or sending it.
+    fn context<'a>(
+        &'a self,
+        job: &'a str,
+        grouping: &'a HashMap<&str, &str>,
+    ) -> impl Fn(PushMetricsError) -> PushMetricsError + 'a {
+        move |error| error.with_context(job, grouping, &self.gateway_url)
+    }
+
+    /// Applies the [`NonFinitePolicy`] of the pusher to the payload and sends it.
+    fn send(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: Url,
+        encoded_metrics: B,
+        content_type: &str,
+        push_type: PushType,
+    ) -> Result<PushReceipt> {
+        let (encoded_metrics, non_finite_samples) =
+            NonFiniteFilter::apply(self.non_finite.as_ref(), encoded_metrics, content_type)?;
+
+        self.dispatch(job, grouping, url, encoded_metrics, content_type, push_type)
+            .map(|receipt| receipt.with_non_finite_samples(non_finite_samples))
+    }
+
+    /// Transmits the payload unless the throttle of the pusher defers it, see
+    /// [`MetricsPusher::with_min_interval`].
+    fn dispatch(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: Url,
+        encoded_metrics: B,
+        content_type: &str,
+        push_type: PushType,
+    ) -> Result<PushReceipt> {
+        PayloadSizeLimit::check(self.payload_size_limit.as_ref(), &encoded_metrics)?;
+
+        let Some(throttle) = self.throttle.as_ref().filter(|_| self.is_enabled()) else {
+            return self.transmit(job, grouping, url, encoded_metrics, content_type, push_type);
+        };
+
+        let payload = Deferred::new(job, grouping, url, encoded_metrics, content_type, push_type);
+        let group = payload.url.clone();
+        match throttle.admit(payload) {
+            Some(payload) => self.transmit(
+                job,
+                grouping,
+                payload.url,
+                payload.body,
+                content_type,
+                push_type,
+            ),
+            None => Ok(PushReceipt::skipped(group)),
+        }
+    }
+
+    fn transmit(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: Url,
+        encoded_metrics: B,
+        content_type: &str,
+        push_type: PushType,
+    ) -> Result<PushReceipt> {
+        if !self.is_enabled() {
+            return Ok(PushReceipt::skipped(url));
+        }
+
+        let format = self.formats.get(&self.gateway_url);
+        let negotiated = negotiate(
+            &self.metrics_converter,
+            format,
+            encoded_metrics,
+            content_type,
         )?;
+        let (url, tenant) = self.route(job, grouping, &url)?;
+        let request_id = self.request_id.as_ref().map(RequestId::generate);
+        let header = request_id.as_ref().map(|(name, id)| (*name, id.as_str()));
+        let headers = with_tenant_header(header.as_slice(), tenant.as_ref());
+
+        let mut observation = PushObserver::start(
+            self.observer.as_ref(),
+            push_type.method(),
+            job,
+            grouping,
+            &url,
+        );
+        observation.measure(&negotiated.body);
+        let mut payload_size = self.payload_size(&negotiated.body, &url);
+        let mut result = self.push_body(
+            &url,
+            negotiated.body,
+            &negotiated.content_type,
+            push_type,
+            &headers,
+        );
+
+        if let Some((body, fallback_content_type)) = negotiated.fallback {
+            match &result {
+                Ok(_) => self.formats.set(&self.gateway_url, Format::Native),
+                Err(error) if rejects_format(error) => {
+                    #[cfg(feature = "log")]
+                    log::warn!(
+                        "Pushgateway {} rejected '{}', retrying with '{fallback_content_type}'",
+                        self.gateway_url,
+                        negotiated.content_type
+                    );
+                    observation.retry();
+                    observation.measure(&body);
+                    payload_size = self.payload_size(&body, &url);
+                    result =
+                        self.push_body(&url, body, &fallback_content_type, push_type, &headers);
+                    if result.is_ok() {
+                        self.formats.set(&self.gateway_url, Format::Fallback);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+
+        let content_encoding = result.as_ref().copied().unwrap_or_default();
+        let result = result.map(|_| ());
+        observation.finish(&result);
+        into_receipt(result, url, request_id).map(|receipt| {
+            receipt
+                .with_payload_size(payload_size)
+                .with_content_encoding(content_encoding)
+                .with_tenant(tenant.as_ref())
+        })
+    }
+
+    fn push_body(
+        &self,
+        url: &Url,
+        body: B,
+        content_type: &str,
+        push_type: PushType,
+        headers: &[(&str, &str)],
+    ) -> Result<ContentEncoding> {
+        #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+        let (body, content_encoding) = Compressor::compress(self.compression.as_ref(), body)?;
+        #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+        let encoding = (content_encoding != ContentEncoding::Identity)
+            .then(|| ("Content-Encoding", content_encoding.as_str().to_string()));
+        #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+        let headers = with_header(headers, encoding.as_ref());
+        #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+        let headers = headers.as_slice();
+        #[cfg(not(any(feature = "compress_gzip", feature = "compress_zstd")))]
+        let content_encoding = ContentEncoding::Identity;
+
+        #[cfg(feature = "body_digest")]
+        let digest = self
+            .body_digest
+            .as_ref()
+            .map(|digester| digester.header(&body));
+        #[cfg(feature = "body_digest")]
+        let headers = with_header(headers, digest.as_ref());
+        #[cfg(feature = "body_digest")]
+        let headers = headers.as_slice();
+
+        let result = match push_type {
+            PushType::Add => self.push_client.push_add(url, body, content_type, headers),
+            PushType::All => self.push_client.push_all(url, body, content_type, headers),
+        };
+
+        result.map(|()| content_encoding)
+    }
+
+    fn payload_size(&self, encoded_metrics: &B, url: &Url) -> Option<usize> {
+        self.payload_size_warning
+            .as_ref()
+            .map(|warning| warning.check(encoded_metrics, url))
+    }
+
+    fn push_verified(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+        push_type: PushType,
+    ) -> Result<PushReceipt>
+    where
+        P: Fetch,
+        B: AsRef<[u8]>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let sanitized_grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let (url, encoded_metrics, content_type) = self
+            .metrics_converter
+            .create_push_details(job, &self.url, sanitized_grouping, metric_families)
+            .map_err(&context)?;
+        let (encoded_metrics, non_finite_samples) =
+            NonFiniteFilter::apply(self.non_finite.as_ref(), encoded_metrics, &content_type)
+                .map_err(&context)?;
+        let pushed = text_payload(encoded_metrics.as_ref(), &content_type).map_err(&context)?;
+
+        let receipt = self
+            .dispatch(
+                job,
+                grouping,
+                url,
+                encoded_metrics,
+                &content_type,
+                push_type,
+            )
+            .map_err(&context)?
+            .with_non_finite_samples(non_finite_samples);
+        if receipt.is_skipped() {
+            return Ok(receipt);
+        }
+
+        let exposed = self
+            .push_client
+            .fetch(&self.metrics_url()?)
+            .map_err(&context)?;
+        text_format::verify(&pushed, &exposed, job, sanitized_grouping).map_err(&context)?;
+
+        Ok(receipt)
+    }
+
+    fn metrics_url(&self) -> Result<Url> {
+        Ok(self.url.join("../../metrics")?)
+    }
+
+    #[cfg(feature = "admin")]
+    fn api_url(&self, path: &str) -> Result<Url> {
+        Ok(self.url.join(&format!("../../api/v1/{path}"))?)
+    }
+
+    fn push_single_sample(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        name: &str,
+        kind: &str,
+        value: f64,
+        help: &str,
+    ) -> Result<PushReceipt>
+    where
+        B: From<String>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let url = self.group_url(job, grouping)?;
+        let metrics = text_format::single_sample(name, kind, value, help).map_err(&context)?;
+
+        self.send(
+            job,
+            grouping,
+            url,
+            B::from(metrics),
+            CONTENT_TYPE_TEXT,
+            PushType::Add,
+        )
+        .map_err(context)
+    }
+
+    /// Routes the request of the given group to its tenant, see
+    /// [`MetricsPusher::with_tenant_resolver`].
+    fn route(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: &Url,
+    ) -> Result<(Url, Option<Tenant>)> {
+        TenantRouter::route(
+            self.tenants.as_ref(),
+            job,
+            grouping,
+            &self.url,
+            self.url_join,
+            url.clone(),
+        )
+    }
 
-        match push_type {
-            PushType::Add => self.push_client.push_add(&url, encoded_metrics, &encoder),
-            PushType::All => self.push_client.push_all(&url, encoded_metrics, &encoder),
+    /// Forgets what the pusher keeps about a group after it was deleted or cleared, so neither
+    /// its pushed families nor a deferred payload outlive it.
+    fn forget_group(&self, group: &Url) {
+        self.pushed_families.forget(group);
+        if let Some(throttle) = &self.throttle {
+            throttle.forget(group);
         }
     }
+
+    fn group_url(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<Url> {
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        build_url(&self.url, job, grouping)
+    }
+}
+
+/// `PushMetrics` abstracts over a whole pusher, so libraries can accept anything that pushes
+/// metrics of type `MF` without exposing the generic parameters of [`MetricsPusher`]. It is
+/// implemented by [`MetricsPusher`] and by [`NoopPusher`] for tests.
+pub trait PushMetrics<MF> {
+    /// Pushes all metrics, see [`MetricsPusher::push_all`].
+    fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt>;
+
+    /// Pushes metrics that replace the metrics with the same name, see
+    /// [`MetricsPusher::push_add`].
+    fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt>;
+
+    /// Deletes the group, see [`MetricsPusher::delete`].
+    fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt>;
+}
+
+impl<P, CM, MF, C, B> PushMetrics<MF> for MetricsPusher<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        MetricsPusher::push_all(self, job, grouping, metric_families)
+    }
+
+    fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        MetricsPusher::push_add(self, job, grouping, metric_families)
+    }
+
+    fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        MetricsPusher::delete(self, job, grouping)
+    }
+}
+
+impl<MF> PushMetrics<MF> for NoopPusher {
+    fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        _metric_families: MF,
+    ) -> Result<PushReceipt> {
+        self.receipt(job, grouping)
+    }
+
+    fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        _metric_families: MF,
+    ) -> Result<PushReceipt> {
+        self.receipt(job, grouping)
+    }
+
+    fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        self.receipt(job, grouping)
+    }
+}
+
+impl<B> Push<B> for NoopPushClient {
+    fn send(&self, _: Method, _: &Url, _: Option<(B, &str)>, _: &[(&str, &str)]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use url::Url;
+
+    use crate::blocking::fan_out;
+    use crate::blocking::MetricsPusher;
+    use crate::blocking::PushMetrics;
+    use crate::error::Result;
+    use crate::raw::RawMetricsConverter;
+    use crate::NoopPushClient;
+    use crate::NoopPusher;
+    use crate::PushReceipt;
+
+    fn push_twice(pusher: &impl PushMetrics<String>) -> Result<Vec<PushReceipt>> {
+        let grouping = HashMap::from([("env", "test")]);
+        Ok(vec![
+            pusher.push_all("abstract", &grouping, String::from("up 1\n"))?,
+            pusher.push_add("abstract", &grouping, String::from("up 1\n"))?,
+        ])
+    }
+
+    #[test]
+    fn test_noop_pusher_validates_and_returns_receipts() {
+        // Given I have a no-op pusher
+        let url = Url::parse("http://pushgateway:9091").unwrap();
+        let pusher = NoopPusher::new(&url).unwrap();
+
+        // When I pass it to code that accepts any pusher
+        let receipts = push_twice(&pusher).expect("Failed to push");
+        let invalid =
+            PushMetrics::<String>::delete(&pusher, "abstract", &HashMap::from([("a/b", "c")]));
+
+        // Then the receipts point to the group and invalid label names are rejected
+        assert_eq!(
+            receipts[0].url().as_str(),
+            "http://pushgateway:9091/metrics/job/abstract/env/test"
+        );
+        assert_eq!(receipts[0], receipts[1]);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_disabled_pusher_skips_pushes() {
+        // Given I have a metrics pusher with a no-op push client
+        let url = Url::parse("http://pushgateway:9091").unwrap();
+        let pusher = MetricsPusher::new(NoopPushClient, RawMetricsConverter::text(), &url).unwrap();
+
+        // When I push while it is enabled, disabled and enabled again
+        let enabled = pusher
+            .push_all("switch", &HashMap::new(), "up 1\n")
+            .unwrap();
+        pusher.disable();
+        let disabled = pusher
+            .push_all("switch", &HashMap::new(), "up 1\n")
+            .unwrap();
+        let deleted = pusher.delete("switch", &HashMap::new()).unwrap();
+        pusher.enable();
+        let reenabled = pusher
+            .push_all("switch", &HashMap::new(), "up 1\n")
+            .unwrap();
+
+        // Then only the pushes while it is disabled are skipped
+        assert!(!enabled.is_skipped());
+        assert!(disabled.is_skipped() && deleted.is_skipped());
+        assert!(!reenabled.is_skipped());
+        assert_eq!(disabled.url(), enabled.url());
+    }
+
+    #[test]
+    fn test_fan_out_caps_parallelism_and_keeps_order() {
+        // Given I have more pushes than threads
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+
+        // When I fan them out on two threads
+        let results = fan_out((0..8).collect(), 2, |push: u32| {
+            let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now_running, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+            push * 2
+        });
+
+        // Then they ran concurrently without exceeding the cap and the results keep their order
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        assert_eq!(results, [0, 2, 4, 6, 8, 10, 12, 14]);
+    }
 }
@@ -1,49 +1,353 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
 use reqwest::blocking::Body;
 use reqwest::blocking::Client;
+use reqwest::blocking::ClientBuilder;
+use reqwest::blocking::RequestBuilder;
 use reqwest::blocking::Response;
+use reqwest::dns::Resolve;
 use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+#[cfg(feature = "socks")]
+use reqwest::Proxy;
 use reqwest::StatusCode;
 use url::Url;
 
+use crate::blocking::Fetch;
+use crate::blocking::MetricsPusher;
 use crate::blocking::Push;
+use crate::error::PushMetricsError;
 use crate::error::Result;
-use crate::utils::handle_response;
-use crate::utils::Respond;
+use crate::response::check_status;
+use crate::response::BufferedResponse;
+use crate::response::Respond;
+use crate::response::ResponseHandler;
+use crate::response::StatusCodeHandler;
+#[cfg(feature = "retry")]
+use crate::retry::Retry;
+#[cfg(feature = "rustls_tls")]
+use crate::tls::TlsConfig;
+use crate::utils::DEFAULT_CONNECT_TIMEOUT;
+use crate::utils::DEFAULT_POOL_MAX_IDLE;
+use crate::utils::DEFAULT_TIMEOUT;
+use crate::utils::DEFAULT_USER_AGENT;
+use crate::Auth;
+use crate::ConvertMetrics;
+use crate::Method;
 
 /// `PushClient` is a wrapper for a blocking `reqwest` http [`Client`] that implements
-/// the [`Push`] trait.
+/// the [`Push`] and [`Fetch`] traits.
 #[derive(Debug)]
 pub struct PushClient {
     client: Client,
+    auth: Option<Auth>,
+    headers: Vec<(String, String)>,
+    response_handler: Arc<dyn ResponseHandler>,
 }
 
 impl PushClient {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            auth: None,
+            headers: Vec::new(),
+            response_handler: Arc::new(StatusCodeHandler),
+        }
+    }
+
+    /// Creates a [`PushClientBuilder`] to configure the underlying [`Client`].
+    pub fn builder() -> PushClientBuilder {
+        PushClientBuilder::default()
+    }
+}
+
+/// `PushClientBuilder` creates a [`PushClient`] with a configured [`Client`], so you do not have
+/// to rebuild the whole client with the right features yourself.
+///
+/// Unlike a plain [`Client`] it comes with defaults suited for pushing: a connect timeout of
+/// 5 seconds, a timeout of 30 seconds per push, no redirects and at most 2 idle connections.
+/// Pushes are identified by the `User-Agent` `prometheus-push/<version>`.
+#[derive(Debug)]
+pub struct PushClientBuilder {
+    client_builder: ClientBuilder,
+    auth: Option<Auth>,
+    headers: Vec<(String, String)>,
+    response_handler: Arc<dyn ResponseHandler>,
+    #[cfg(feature = "rustls_tls")]
+    tls_config: TlsConfig,
+}
+
+impl Default for PushClientBuilder {
+    fn default() -> Self {
+        Self {
+            client_builder: ClientBuilder::new()
+                .connect_timeout(DEFAULT_CONNECT_TIMEOUT)
+                .timeout(DEFAULT_TIMEOUT)
+                .redirect(Policy::none())
+                .pool_max_idle_per_host(DEFAULT_POOL_MAX_IDLE)
+                .user_agent(DEFAULT_USER_AGENT),
+            auth: None,
+            headers: Vec::new(),
+            response_handler: Arc::new(StatusCodeHandler),
+            #[cfg(feature = "rustls_tls")]
+            tls_config: TlsConfig::default(),
+        }
+    }
+}
+
+impl PushClientBuilder {
+    /// Sets the timeout for establishing the connection to the pushgateway.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.client_builder = self.client_builder.connect_timeout(timeout);
+        self
+    }
+
+    /// Sets the timeout for a whole push, from connecting until the response is received.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client_builder = self.client_builder.timeout(timeout);
+        self
+    }
+
+    /// Sets the redirect [`Policy`]. Redirects are not followed by default, as a redirected
+    /// push usually points to a misconfigured pushgateway url.
+    pub fn redirect(mut self, policy: Policy) -> Self {
+        self.client_builder = self.client_builder.redirect(policy);
+        self
+    }
+
+    /// Sets the `User-Agent` pushes are sent with, so the pushgateway's operators can identify
+    /// your application.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.client_builder = self.client_builder.user_agent(user_agent);
+        self
+    }
+
+    /// Sets the maximum number of idle connections that are kept open to the pushgateway.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.client_builder = self.client_builder.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// Sends the given [`Auth`] credentials with every request.
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sends the given header with every request, e.g. a header a proxy in front of the
+    /// pushgateway routes by. Invalid headers fail the requests when they are sent.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Decides with the given [`ResponseHandler`] whether a push was accepted, instead of only
+    /// checking the status code of the response.
+    pub fn response_handler(mut self, handler: impl ResponseHandler + 'static) -> Self {
+        self.response_handler = Arc::new(handler);
+        self
+    }
+
+    /// Resolves `host` to the given address instead of using DNS. The url of the pushgateway
+    /// is kept as is, so the `Host` header and TLS server name still match the hostname.
+    pub fn resolve(mut self, host: &str, addr: SocketAddr) -> Self {
+        self.client_builder = self.client_builder.resolve(host, addr);
+        self
+    }
+
+    /// Resolves `host` to the given addresses instead of using DNS, see
+    /// [`PushClientBuilder::resolve`].
+    pub fn resolve_to_addrs(mut self, host: &str, addrs: &[SocketAddr]) -> Self {
+        self.client_builder = self.client_builder.resolve_to_addrs(host, addrs);
+        self
+    }
+
+    /// Uses the given [`Resolve`] implementation for all hosts that are not overridden by
+    /// [`PushClientBuilder::resolve`], e.g. to resolve the pushgateway in a split-horizon DNS.
+    pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> Self {
+        self.client_builder = self.client_builder.dns_resolver(resolver);
+        self
+    }
+
+    /// Adds all certificates of the given PEM, e.g. of your private CA, to the trusted root
+    /// certificates.
+    #[cfg(feature = "rustls_tls")]
+    pub fn add_root_certificates_pem(mut self, pem: &[u8]) -> Result<Self> {
+        self.tls_config.add_root_certificates_pem(pem)?;
+        Ok(self)
+    }
+
+    /// Pins the certificate of the pushgateway: the connection is only established if the
+    /// SHA-256 hash of the DER encoded server certificate matches one of the pinned hashes.
+    /// Call it more than once to pin several certificates, e.g. during a rotation.
+    #[cfg(feature = "rustls_tls")]
+    pub fn pin_certificate_sha256(mut self, hash: [u8; 32]) -> Self {
+        self.tls_config.pin_certificate_sha256(hash);
+        self
+    }
+
+    /// Pushes through the SOCKS5 proxy at the given url, e.g. `socks5://jump-host:1080`. With
+    /// the `socks5h` scheme the proxy resolves the hostname of the pushgateway. Optional
+    /// credentials are used for username/password authentication with the proxy.
+    #[cfg(feature = "socks")]
+    pub fn socks5_proxy(mut self, proxy: &Url, credentials: Option<(&str, &str)>) -> Result<Self> {
+        let proxy = Proxy::all(proxy.as_str())?;
+        let proxy = match credentials {
+            Some((username, password)) => proxy.basic_auth(username, password),
+            None => proxy,
+        };
+
+        self.client_builder = self.client_builder.proxy(proxy);
+        Ok(self)
+    }
+
+    /// Builds the [`PushClient`].
+    pub fn build(self) -> Result<PushClient> {
+        let client_builder = self.client_builder;
+
+        // the selected backend wins over the one other crates enable on the shared reqwest
+        #[cfg(any(
+            feature = "with_reqwest_rustls",
+            feature = "with_reqwest_rustls_native_roots"
+        ))]
+        let client_builder = client_builder.use_rustls_tls();
+        #[cfg(all(
+            feature = "with_reqwest_native_tls",
+            not(any(
+                feature = "with_reqwest_rustls",
+                feature = "with_reqwest_rustls_native_roots"
+            ))
+        ))]
+        let client_builder = client_builder.use_native_tls();
+
+        #[cfg(feature = "rustls_tls")]
+        let client_builder = match self.tls_config.is_empty() {
+            true => client_builder,
+            false => client_builder.use_preconfigured_tls(self.tls_config.client_config()?),
+        };
+
+        Ok(PushClient {
+            client: client_builder.build()?,
+            auth: self.auth,
+            headers: self.headers,
+            response_handler: self.response_handler,
+        })
     }
 }
 
+crate::utils::metrics_pusher_builder!();
+
 impl<B: Into<Body>> Push<B> for PushClient {
-    fn push_all(&self, url: &Url, body: B, content_type: &str) -> Result<()> {
-        let response = &self
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(B, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        let request = self.client.request(method.into(), url.as_str());
+        let request = match body {
+            Some((body, content_type)) => request.header(CONTENT_TYPE, content_type).body(body),
+            None => request,
+        };
+        let response = request
+            .with_headers(headers)
+            .with_auth(self.auth.as_ref())
+            .with_default_headers(&self.headers)
+            .send()?;
+
+        self.handle(response)
+    }
+
+    fn push_all_with_timeout(
+        &self,
+        url: &Url,
+        body: B,
+        content_type: &str,
+        headers: &[(&str, &str)],
+        timeout: Duration,
+    ) -> Result<()> {
+        let response = self
             .client
             .put(url.as_str())
             .header(CONTENT_TYPE, content_type)
             .body(body)
-            .send()?;
+            .with_headers(headers)
+            .with_auth(self.auth.as_ref())
+            .with_default_headers(&self.headers)
+            .timeout(timeout)
+            .send()
+            .map_err(|error| match error.is_timeout() {
+                true => PushMetricsError::Timeout(timeout),
+                false => PushMetricsError::from(error),
+            })?;
+
+        self.handle(response)
+    }
+}
+
+impl PushClient {
+    /// Reads the whole response, so the [`ResponseHandler`] can inspect its body as well.
+    fn handle(&self, response: Response) -> Result<()> {
+        let status = response.status();
+        let url = response.url().clone();
+        let headers = response.headers().clone();
+        let body = response.text()?;
 
-        handle_response(response)
+        self.response_handler
+            .handle(&BufferedResponse { status, url, headers, body })
     }
+}
 
-    fn push_add(&self, url: &Url, body: B, content_type: &str) -> Result<()> {
-        let response = &self
+impl Fetch for PushClient {
+    fn fetch(&self, url: &Url) -> Result<String> {
+        let response = self
             .client
-            .post(url.as_str())
-            .header(CONTENT_TYPE, content_type)
-            .body(body)
+            .get(url.as_str())
+            .with_auth(self.auth.as_ref())
+            .with_default_headers(&self.headers)
             .send()?;
+        check_status(&response)?;
 
-        handle_response(response)
+        Ok(response.text()?)
+    }
+}
+
+trait WithHeaders {
+    fn with_headers(self, headers: &[(&str, &str)]) -> Self;
+    fn with_auth(self, auth: Option<&Auth>) -> Self;
+    fn with_default_headers(self, headers: &[(String, String)]) -> Self;
+}
+
+impl WithHeaders for RequestBuilder {
+    /// Adds the given headers to the request. Invalid headers fail the request when it is sent.
+    fn with_headers(self, headers: &[(&str, &str)]) -> Self {
+        headers
+            .iter()
+            .fold(self, |request, (name, value)| request.header(*name, *value))
+    }
+
+    /// Adds the `Authorization` or custom header for the given credentials to the request.
+    fn with_auth(self, auth: Option<&Auth>) -> Self {
+        match auth {
+            Some(Auth::Basic { username, password }) => {
+                self.basic_auth(username, password.as_ref())
+            }
+            Some(Auth::Bearer(token)) => self.bearer_auth(token),
+            Some(Auth::Header { name, value }) => self.header(name.as_str(), value.as_str()),
+            None => self,
+        }
+    }
+
+    /// Adds the headers the client sends with every request to the request.
+    fn with_default_headers(self, headers: &[(String, String)]) -> Self {
+        headers
+            .iter()
+            .fold(self, |request, (name, value)| request.header(name, value))
     }
 }
 
@@ -55,4 +359,47 @@ impl Respond for Response {
     fn get_url(&self) -> &Url {
         self.url()
     }
+
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use url::Url;
+
+    use crate::blocking::with_reqwest::PushClient;
+    use crate::blocking::MetricsPusher;
+    use crate::error::PushMetricsError;
+    use crate::raw::RawMetricsConverter;
+
+    #[test]
+    fn test_push_all_with_timeout() {
+        // Given I have a push gateway that accepts connections but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+        // And a metrics pusher with a push client with the default timeout
+        let push_client = PushClient::builder().build().unwrap();
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &url).unwrap();
+
+        // When I push with a short timeout for this single push
+        let timeout = Duration::from_millis(100);
+        let result =
+            metrics_pusher.push_all_with_timeout("timeout", &HashMap::new(), "up 1\n", timeout);
+
+        // Then the push fails with the timeout error
+        match result.map_err(PushMetricsError::without_context) {
+            Err(PushMetricsError::Timeout(elapsed)) => assert_eq!(elapsed, timeout),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
 }
@@ -0,0 +1,310 @@
+use std::io;
+
+use ureq::http::header::CONTENT_TYPE;
+use ureq::http::header::RETRY_AFTER;
+use ureq::http::header::USER_AGENT;
+use ureq::http::request::Builder;
+use ureq::http::Request;
+use ureq::http::Response;
+use ureq::Agent;
+use ureq::AsSendBody;
+use ureq::Body;
+use ureq::Timeout;
+use url::Url;
+
+use crate::blocking::Fetch;
+use crate::blocking::Push;
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::utils::DEFAULT_CONNECT_TIMEOUT;
+use crate::utils::DEFAULT_TIMEOUT;
+use crate::utils::DEFAULT_USER_AGENT;
+use crate::Method;
+
+/// `UreqPushClient` is a [`Push`] and [`Fetch`] implementation on top of a `ureq` [`Agent`],
+/// for blocking applications that want a far lighter dependency tree than `reqwest::blocking`,
+/// which pulls in tokio.
+///
+/// Pushes are identified by the `User-Agent` `prometheus-push/<version>` and fail with the
+/// variant of their failure class if the pushgateway does not answer with a success status
+/// code, e.g. [`PushMetricsError::ServerError`].
+#[derive(Clone, Debug)]
+pub struct UreqPushClient {
+    agent: Agent,
+}
+
+impl UreqPushClient {
+    /// Creates a new [`UreqPushClient`] that sends with the given [`Agent`]. Error status codes
+    /// are read as responses even if the agent treats them as errors, so failed pushes keep the
+    /// body and `Retry-After` of the pushgateway.
+    pub fn new(agent: Agent) -> Self {
+        Self { agent }
+    }
+
+    /// Sends the request and returns the body of a successful response.
+    fn request(
+        &self,
+        request: Builder,
+        url: &Url,
+        body: Option<(Vec<u8>, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<String> {
+        let request = headers
+            .iter()
+            .fold(request, |request, (name, value)| {
+                request.header(*name, *value)
+            })
+            .header(USER_AGENT, DEFAULT_USER_AGENT);
+        let response = match body {
+            Some((body, content_type)) => {
+                let request = request.header(CONTENT_TYPE, content_type).body(body);
+                self.run(request.map_err(io::Error::other)?)
+            }
+            None => self.run(request.body(()).map_err(io::Error::other)?),
+        };
+
+        match response {
+            Ok(response) => self.read_response(response, url),
+            Err(error) => Err(self.transport_error(error)),
+        }
+    }
+
+    /// Runs the request with error status codes as responses, whatever the agent is
+    /// configured with.
+    fn run<S: AsSendBody>(
+        &self,
+        request: Request<S>,
+    ) -> std::result::Result<Response<Body>, ureq::Error> {
+        let request = self
+            .agent
+            .configure_request(request)
+            .http_status_as_error(false)
+            .build();
+
+        self.agent.run(request)
+    }
+
+    fn read_response(&self, mut response: Response<Body>, url: &Url) -> Result<String> {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let body = response
+            .body_mut()
+            .read_to_string()
+            .map_err(|error| self.transport_error(error))?;
+
+        if !status.is_success() {
+            return Err(PushMetricsError::response(
+                &status,
+                url,
+                Some(&body),
+                retry_after.as_deref(),
+            ));
+        }
+
+        Ok(body)
+    }
+
+    /// Maps a failed request to an io error of the kind that tells whether it is worth
+    /// retrying, or to [`PushMetricsError::Timeout`] if the push took longer than the global
+    /// timeout of the agent.
+    fn transport_error(&self, error: ureq::Error) -> PushMetricsError {
+        let kind = match &error {
+            ureq::Error::Io(_) => return error.into_io().into(),
+            ureq::Error::Timeout(Timeout::Global) => match self.agent.config().timeouts().global {
+                Some(timeout) => return PushMetricsError::Timeout(timeout),
+                None => io::ErrorKind::TimedOut,
+            },
+            ureq::Error::Timeout(_) => io::ErrorKind::TimedOut,
+            ureq::Error::ConnectionFailed | ureq::Error::HostNotFound => {
+                io::ErrorKind::NotConnected
+            }
+            _ => io::ErrorKind::Other,
+        };
+
+        io::Error::new(kind, error).into()
+    }
+}
+
+/// Unlike a plain [`Agent`] the default client comes with defaults suited for pushing: a
+/// connect timeout of 5 seconds, a timeout of 30 seconds per push and no redirects.
+impl Default for UreqPushClient {
+    fn default() -> Self {
+        let config = Agent::config_builder()
+            .timeout_connect(Some(DEFAULT_CONNECT_TIMEOUT))
+            .timeout_global(Some(DEFAULT_TIMEOUT))
+            .max_redirects(0)
+            .build();
+
+        Self::new(config.into())
+    }
+}
+
+impl Push<Vec<u8>> for UreqPushClient {
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(Vec<u8>, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        let request = Request::builder().method(method.as_str()).uri(url.as_str());
+
+        self.request(request, url, body, headers).map(drop)
+    }
+}
+
+impl Fetch for UreqPushClient {
+    fn fetch(&self, url: &Url) -> Result<String> {
+        self.request(Request::get(url.as_str()), url, None, &[])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use mockito::Server;
+    use ureq::Agent;
+    use url::Url;
+
+    use crate::blocking::with_ureq::UreqPushClient;
+    use crate::blocking::MetricsPusher;
+    use crate::error::PushMetricsError;
+    use crate::raw::RawMetricsConverter;
+
+    #[test]
+    fn test_push_with_ureq() {
+        // Given I have a push gateway that accepts one group and fails for another
+        let mut server = Server::new();
+        let accepted_mock = server
+            .mock("PUT", "/metrics/job/ureq/env/test")
+            .match_header("content-type", "text/plain; version=0.0.4")
+            .match_header(
+                "user-agent",
+                concat!("prometheus-push/", env!("CARGO_PKG_VERSION")),
+            )
+            .match_body("up 1\n")
+            .expect(1)
+            .create();
+        let failing_mock = server
+            .mock("DELETE", "/metrics/job/failing")
+            .with_status(500)
+            .with_body("storage full\n")
+            .expect(1)
+            .create();
+
+        // And a metrics pusher with a ureq client
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher: MetricsPusher<_, _, Vec<u8>, Vec<u8>, _> =
+            MetricsPusher::new(UreqPushClient::default(), RawMetricsConverter::text(), &url)
+                .unwrap();
+
+        // When I push to the one and delete the other
+        let pushed = metrics_pusher.push_all(
+            "ureq",
+            &HashMap::from([("env", "test")]),
+            b"up 1\n".to_vec(),
+        );
+        let deleted = metrics_pusher.delete("failing", &HashMap::new());
+
+        // Then the push succeeds and the delete fails with an error that is worth retrying
+        pushed.expect("Failed to push metrics");
+        let error = deleted.unwrap_err();
+        assert!(error.is_retryable());
+        assert!(matches!(
+            error.without_context(),
+            PushMetricsError::ServerError(response) if response.body() == Some("storage full\n")
+        ));
+        accepted_mock.assert();
+        failing_mock.assert();
+    }
+
+    #[test]
+    fn test_agent_with_defaults_keeps_the_rejection() {
+        // Given I have a push gateway that asks to slow down
+        let mut server = Server::new();
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/ureq")
+            .with_status(429)
+            .with_header("retry-after", "120")
+            .with_body("slow down\n")
+            .expect(1)
+            .create();
+
+        // And a metrics pusher with a ureq agent that treats error status codes as errors
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::<_, _, Vec<u8>, Vec<u8>, _>::new(
+            UreqPushClient::new(Agent::new_with_defaults()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+
+        // When I push
+        let error = metrics_pusher
+            .push_all("ureq", &HashMap::new(), b"up 1\n".to_vec())
+            .unwrap_err();
+
+        // Then the push fails with the body and retry after of the pushgateway
+        match error.without_context() {
+            PushMetricsError::TooManyRequests { response, retry_after } => {
+                assert_eq!(response.body(), Some("slow down\n"));
+                assert_eq!(retry_after, Some(Duration::from_secs(120)));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+        pushgateway_mock.assert();
+    }
+
+    #[test]
+    fn test_unreachable_and_slow_pushgateways_are_retryable() {
+        // Given I have an address nobody listens on and a push gateway that answers too late
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable =
+            Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+        let mut server = Server::new();
+        server
+            .mock("PUT", "/metrics/job/ureq")
+            .with_chunked_body(|_| {
+                thread::sleep(Duration::from_millis(500));
+                Ok(())
+            })
+            .create();
+
+        // And metrics pushers with a ureq client with a short timeout
+        let metrics_pusher = |url: &Url| {
+            let agent = Agent::config_builder()
+                .timeout_global(Some(Duration::from_millis(100)))
+                .build();
+            MetricsPusher::<_, _, Vec<u8>, Vec<u8>, _>::new(
+                UreqPushClient::new(agent.into()),
+                RawMetricsConverter::text(),
+                url,
+            )
+            .unwrap()
+        };
+
+        // When I push to both
+        let refused = metrics_pusher(&unreachable)
+            .push_all("ureq", &HashMap::new(), b"up 1\n".to_vec())
+            .unwrap_err();
+        let timed_out = metrics_pusher(&Url::parse(&server.url()).unwrap())
+            .push_all("ureq", &HashMap::new(), b"up 1\n".to_vec())
+            .unwrap_err();
+
+        // Then both fail with an error that is worth retrying
+        assert!(refused.is_retryable());
+        assert!(matches!(
+            timed_out.without_context(),
+            PushMetricsError::Timeout(timeout) if timeout == Duration::from_millis(100)
+        ));
+    }
+}
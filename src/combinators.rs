@@ -0,0 +1,332 @@
+/// `Tee` sends every request to both clients and fails if either of them fails, with the error
+/// of the first one if both fail. The non-blocking implementation sends both concurrently.
+///
+/// Like [`Fallback`] and [`Race`] it wraps [`Push`](crate::non_blocking::Push) or
+/// [`blocking::Push`](crate::blocking::Push) implementations and implements them itself, so
+/// they nest, e.g. `Fallback(Tee(primary, mirror), backup)`. The body is sent more than once,
+/// so it has to be [`Clone`].
+#[derive(Clone, Debug)]
+pub struct Tee<A, B>(pub A, pub B);
+
+/// `Fallback` sends every request to the primary client and only to the secondary one if the
+/// primary failed.
+#[derive(Clone, Debug)]
+pub struct Fallback<A, B>(pub A, pub B);
+
+/// `Race` sends every request to both clients and succeeds with the first success, it only
+/// fails if both fail, with the error of the second one. The non-blocking implementation
+/// returns as soon as one succeeded and drops the other request, the blocking one sends both
+/// on their own threads and waits for both.
+#[derive(Clone, Debug)]
+pub struct Race<A, B>(pub A, pub B);
+
+#[cfg(feature = "blocking")]
+mod blocking_impl {
+    use url::Url;
+
+    use super::Fallback;
+    use super::Race;
+    use super::Tee;
+    use crate::blocking::Push;
+    use crate::error::Result;
+    use crate::Method;
+
+    impl<A, B, T> Push<T> for Tee<A, B>
+    where
+        A: Push<T>,
+        B: Push<T>,
+        T: Clone,
+    {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(T, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            let first = self.0.send(method, url, body.clone(), headers);
+            let second = self.1.send(method, url, body, headers);
+            first.and(second)
+        }
+    }
+
+    impl<A, B, T> Push<T> for Fallback<A, B>
+    where
+        A: Push<T>,
+        B: Push<T>,
+        T: Clone,
+    {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(T, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            self.0
+                .send(method, url, body.clone(), headers)
+                .or_else(|_| self.1.send(method, url, body, headers))
+        }
+    }
+
+    impl<A, B, T> Push<T> for Race<A, B>
+    where
+        A: Push<T> + Sync,
+        B: Push<T> + Sync,
+        T: Clone + Send,
+    {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(T, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            let (first, second) = std::thread::scope(|scope| {
+                let first_body = body.clone();
+                let first = scope.spawn(move || self.0.send(method, url, first_body, headers));
+                let second = self.1.send(method, url, body, headers);
+                (first.join(), second)
+            });
+
+            match first {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(_)) => second,
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "non_blocking")]
+mod non_blocking_impl {
+    use std::future::poll_fn;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::Poll;
+
+    use url::Url;
+
+    use super::Fallback;
+    use super::Race;
+    use super::Tee;
+    use crate::error::Result;
+    use crate::non_blocking::Push;
+    use crate::Method;
+
+    impl<A, B, T> Push<T> for Tee<A, B>
+    where
+        A: Push<T> + Sync,
+        B: Push<T> + Sync,
+        T: Clone + Send,
+    {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(T, &str)>,
+            headers: &[(&str, &str)],
+        ) -> impl Future<Output = Result<()>> + Send {
+            let first = self.0.send(method, url, body.clone(), headers);
+            let second = self.1.send(method, url, body, headers);
+
+            async move {
+                let (first, second) = join(first, second).await;
+                first.and(second)
+            }
+        }
+    }
+
+    impl<A, B, T> Push<T> for Fallback<A, B>
+    where
+        A: Push<T> + Sync,
+        B: Push<T> + Sync,
+        T: Clone + Send,
+    {
+        async fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(T, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            match self.0.send(method, url, body.clone(), headers).await {
+                Ok(()) => Ok(()),
+                Err(_) => self.1.send(method, url, body, headers).await,
+            }
+        }
+    }
+
+    impl<A, B, T> Push<T> for Race<A, B>
+    where
+        A: Push<T> + Sync,
+        B: Push<T> + Sync,
+        T: Clone + Send,
+    {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(T, &str)>,
+            headers: &[(&str, &str)],
+        ) -> impl Future<Output = Result<()>> + Send {
+            let first = self.0.send(method, url, body.clone(), headers);
+            let second = self.1.send(method, url, body, headers);
+
+            first_success(first, second)
+        }
+    }
+
+    /// Polls both futures until both finished and returns their results.
+    async fn join<F, S>(first: F, second: S) -> (Result<()>, Result<()>)
+    where
+        F: Future<Output = Result<()>>,
+        S: Future<Output = Result<()>>,
+    {
+        let mut first = pin!(first);
+        let mut second = pin!(second);
+        let (mut first_result, mut second_result) = (None, None);
+
+        poll_fn(|cx| {
+            if first_result.is_none() {
+                if let Poll::Ready(result) = first.as_mut().poll(cx) {
+                    first_result = Some(result);
+                }
+            }
+            if second_result.is_none() {
+                if let Poll::Ready(result) = second.as_mut().poll(cx) {
+                    second_result = Some(result);
+                }
+            }
+
+            match (first_result.take(), second_result.take()) {
+                (Some(first), Some(second)) => Poll::Ready((first, second)),
+                (first, second) => {
+                    (first_result, second_result) = (first, second);
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Polls both futures until one of them succeeded or both failed.
+    async fn first_success<F, S>(first: F, second: S) -> Result<()>
+    where
+        F: Future<Output = Result<()>>,
+        S: Future<Output = Result<()>>,
+    {
+        let mut first = pin!(first);
+        let mut second = pin!(second);
+        let (mut first_failed, mut second_error) = (false, None);
+
+        poll_fn(|cx| {
+            if !first_failed {
+                match first.as_mut().poll(cx) {
+                    Poll::Ready(Ok(())) => return Poll::Ready(Ok(())),
+                    Poll::Ready(Err(_)) => first_failed = true,
+                    Poll::Pending => {}
+                }
+            }
+            if second_error.is_none() {
+                match second.as_mut().poll(cx) {
+                    Poll::Ready(Ok(())) => return Poll::Ready(Ok(())),
+                    Poll::Ready(Err(error)) => second_error = Some(error),
+                    Poll::Pending => {}
+                }
+            }
+
+            match second_error.take() {
+                Some(error) if first_failed => Poll::Ready(Err(error)),
+                error => {
+                    second_error = error;
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "non_blocking"))]
+mod test {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use url::Url;
+
+    use crate::combinators::Fallback;
+    use crate::combinators::Race;
+    use crate::combinators::Tee;
+    use crate::error::PushMetricsError;
+    use crate::error::Result;
+    use crate::non_blocking::Push;
+    use crate::Method;
+
+    type Sent = Arc<Mutex<Vec<&'static str>>>;
+
+    struct TestClient {
+        name: &'static str,
+        delay: Duration,
+        fails: bool,
+        sent: Sent,
+    }
+
+    impl TestClient {
+        fn new(name: &'static str, fails: bool, sent: &Sent) -> Self {
+            Self { name, delay: Duration::ZERO, fails, sent: Arc::clone(sent) }
+        }
+    }
+
+    impl Push<Vec<u8>> for TestClient {
+        async fn send(
+            &self,
+            _: Method,
+            _: &Url,
+            _: Option<(Vec<u8>, &str)>,
+            _: &[(&str, &str)],
+        ) -> Result<()> {
+            tokio::time::sleep(self.delay).await;
+            self.sent.lock().unwrap().push(self.name);
+            match self.fails {
+                true => Err(PushMetricsError::Transcode(self.name.to_owned())),
+                false => Ok(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_combinators_send_to_their_clients() {
+        // Given I have clients that succeed or fail
+        let sent = Sent::default();
+        let url = Url::parse("http://pushgateway:9091/metrics/job/batch").unwrap();
+        let body = Some((b"up 1\n".to_vec(), "text/plain"));
+        let ok = |name| TestClient::new(name, false, &sent);
+        let failing = |name| TestClient::new(name, true, &sent);
+
+        // When I send with a tee, a fallback and a race of a failing and a slow client
+        let tee = Tee(ok("tee_a"), failing("tee_b"))
+            .send(Method::Put, &url, body.clone(), &[])
+            .await;
+        let fallback = Fallback(failing("primary"), ok("secondary"))
+            .send(Method::Put, &url, body.clone(), &[])
+            .await;
+        let mut slow = ok("slow");
+        slow.delay = Duration::from_millis(20);
+        let race = Race(failing("fast"), slow)
+            .send(Method::Put, &url, body, &[])
+            .await;
+
+        // Then the tee sent to both and failed with the failing one
+        assert!(matches!(tee, Err(PushMetricsError::Transcode(name)) if name == "tee_b"));
+
+        // And the fallback and the race succeeded with their other client
+        assert!(fallback.is_ok());
+        assert!(race.is_ok());
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec!["tee_a", "tee_b", "primary", "secondary", "fast", "slow"]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use prometheus::proto::MetricFamily;
+use url::Url;
+
+use crate::blocking::with_reqwest::PushClient;
+use crate::blocking::MetricsPusher;
+use crate::error::Result;
+use crate::prometheus_crate::PrometheusMetricsConverter;
+use crate::prometheus_crate::PrometheusMetricsPusherBlocking;
+use crate::utils::PushType;
+use crate::Auth;
+
+/// `BasicAuthentication` mirrors the basic authentication of the push functions of the
+/// `prometheus` crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicAuthentication {
+    /// The basic authentication username, possibly an empty string.
+    pub username: String,
+    /// The basic authentication password, possibly an empty string.
+    pub password: String,
+}
+
+/// Pushes all metrics like `prometheus::push_metrics` with the signature of the `prometheus`
+/// crate, so a project can switch to this crate by changing its imports. A url without scheme
+/// is pushed to with `http://`. Errors are returned as [`prometheus::Error::Msg`].
+///
+/// Every call creates a new blocking client, use a [`MetricsPusher`] to push repeatedly.
+pub fn push_metrics<S: BuildHasher>(
+    job: &str,
+    grouping: HashMap<String, String, S>,
+    url: &str,
+    mfs: Vec<MetricFamily>,
+    basic_auth: Option<BasicAuthentication>,
+) -> prometheus::Result<()> {
+    push(job, grouping, url, mfs, PushType::All, basic_auth)
+}
+
+/// Pushes metrics with add logic like `prometheus::push_add_metrics`, see [`push_metrics`].
+pub fn push_add_metrics<S: BuildHasher>(
+    job: &str,
+    grouping: HashMap<String, String, S>,
+    url: &str,
+    mfs: Vec<MetricFamily>,
+    basic_auth: Option<BasicAuthentication>,
+) -> prometheus::Result<()> {
+    push(job, grouping, url, mfs, PushType::Add, basic_auth)
+}
+
+fn push<S: BuildHasher>(
+    job: &str,
+    grouping: HashMap<String, String, S>,
+    url: &str,
+    mfs: Vec<MetricFamily>,
+    push_type: PushType,
+    basic_auth: Option<BasicAuthentication>,
+) -> prometheus::Result<()> {
+    let grouping = grouping
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+
+    let result = metrics_pusher(url, basic_auth).and_then(|metrics_pusher| match push_type {
+        PushType::All => metrics_pusher.push_all(job, &grouping, mfs),
+        PushType::Add => metrics_pusher.push_add(job, &grouping, mfs),
+    });
+
+    result
+        .map(|_| ())
+        .map_err(|error| prometheus::Error::Msg(error.to_string()))
+}
+
+fn metrics_pusher(
+    url: &str,
+    basic_auth: Option<BasicAuthentication>,
+) -> Result<PrometheusMetricsPusherBlocking> {
+    let url = match url.contains("://") {
+        true => Cow::Borrowed(url),
+        false => Cow::Owned(format!("http://{url}")),
+    };
+
+    let mut push_client = PushClient::builder();
+    if let Some(BasicAuthentication { username, password }) = basic_auth {
+        push_client = push_client.auth(Auth::Basic { username, password: Some(password) });
+    }
+
+    MetricsPusher::new(
+        push_client.build()?,
+        PrometheusMetricsConverter,
+        &Url::parse(&url)?,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use mockito::Server;
+    use prometheus::Counter;
+    use prometheus::Opts;
+    use prometheus::Registry;
+
+    use crate::compat::push_metrics;
+    use crate::compat::BasicAuthentication;
+
+    #[test]
+    fn test_push_metrics_like_the_prometheus_crate() {
+        // Given I have a counter metric
+        let registry = Registry::new();
+        let counter = Counter::with_opts(Opts::new("compat_counter", "help")).unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+
+        // And a push gateway that expects basic authentication
+        let mut server = Server::new();
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/compat_job/instance/a")
+            .match_header("authorization", "Basic dXNlcjpzZWNyZXQ=")
+            .create();
+
+        // When I push with the signature of the prometheus crate to the address without scheme
+        push_metrics(
+            "compat_job",
+            HashMap::from([("instance".to_owned(), "a".to_owned())]),
+            &server.host_with_port(),
+            registry.gather(),
+            Some(BasicAuthentication {
+                username: "user".to_owned(),
+                password: "secret".to_owned(),
+            }),
+        )
+        .expect("Failed to push metrics");
+
+        // Then the metrics are received by the push gateway
+        pushgateway_mock.expect(1).assert();
+    }
+}
@@ -0,0 +1,203 @@
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::Method;
+
+/// `DeadLetter` is a request that failed for good, i.e. after every retry of the wrapped
+/// client, and is handed to a [`DeadLetterSink`] instead of being dropped.
+#[derive(Debug)]
+pub struct DeadLetter<'a, B> {
+    pub method: Method,
+    pub url: &'a Url,
+    /// The body with its content type, `None` for [`Method::Delete`].
+    pub body: Option<(B, &'a str)>,
+    pub error: &'a PushMetricsError,
+}
+
+/// `DeadLetterSink` receives the [`DeadLetter`]s of [`DeadLetters`], e.g. to write them to a
+/// file, send them to a channel or page someone. It is implemented for closures.
+pub trait DeadLetterSink<B> {
+    fn dead_letter(&self, letter: DeadLetter<'_, B>);
+}
+
+impl<B, F> DeadLetterSink<B> for F
+where
+    F: Fn(DeadLetter<'_, B>),
+{
+    fn dead_letter(&self, letter: DeadLetter<'_, B>) {
+        self(letter)
+    }
+}
+
+/// `DeadLetters` wraps a push client and hands every request that fails to its
+/// [`DeadLetterSink`] together with the error before the error is returned, so no payload is
+/// silently lost. Wrap it around a [`Retry`](crate::retry::Retry) client to only receive the
+/// requests that failed after all retries.
+///
+/// Like the [`combinators`](crate::combinators) it wraps [`Push`](crate::non_blocking::Push)
+/// or [`blocking::Push`](crate::blocking::Push) implementations and implements them itself. The
+/// body is kept for the sink, so it has to be [`Clone`].
+#[derive(Clone, Debug)]
+pub struct DeadLetters<P, S> {
+    client: P,
+    sink: S,
+}
+
+impl<P, S> DeadLetters<P, S> {
+    /// Creates a new [`DeadLetters`] that hands the failed requests of the given client to the
+    /// given sink.
+    pub fn new(client: P, sink: S) -> Self {
+        Self { client, sink }
+    }
+
+    fn bury<B>(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(B, &str)>,
+        error: PushMetricsError,
+    ) -> PushMetricsError
+    where
+        S: DeadLetterSink<B>,
+    {
+        #[cfg(feature = "log")]
+        log::warn!("Handing failed {method:?} to {url} to the dead letter sink: {error}");
+
+        self.sink
+            .dead_letter(DeadLetter { method, url, body, error: &error });
+        error
+    }
+}
+
+#[cfg(feature = "blocking")]
+mod blocking_impl {
+    use url::Url;
+
+    use super::DeadLetterSink;
+    use super::DeadLetters;
+    use crate::blocking::Push;
+    use crate::error::Result;
+    use crate::Method;
+
+    impl<P, S, B> Push<B> for DeadLetters<P, S>
+    where
+        P: Push<B>,
+        S: DeadLetterSink<B>,
+        B: Clone,
+    {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            self.client
+                .send(method, url, body.clone(), headers)
+                .map_err(|error| self.bury(method, url, body, error))
+        }
+    }
+}
+
+#[cfg(feature = "non_blocking")]
+mod non_blocking_impl {
+    use url::Url;
+
+    use super::DeadLetterSink;
+    use super::DeadLetters;
+    use crate::error::Result;
+    use crate::non_blocking::Push;
+    use crate::Method;
+
+    impl<P, S, B> Push<B> for DeadLetters<P, S>
+    where
+        P: Push<B> + Sync,
+        S: DeadLetterSink<B> + Sync,
+        B: Clone + Send + Sync,
+    {
+        async fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            self.client
+                .send(method, url, body.clone(), headers)
+                .await
+                .map_err(|error| self.bury(method, url, body, error))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "non_blocking"))]
+mod test {
+    use std::io::ErrorKind;
+    use std::sync::Mutex;
+
+    use url::Url;
+
+    use crate::dead_letter::DeadLetter;
+    use crate::dead_letter::DeadLetters;
+    use crate::error::PushMetricsError;
+    use crate::error::Result;
+    use crate::non_blocking::Push;
+    use crate::Method;
+
+    struct DownClient;
+
+    impl Push<Vec<u8>> for DownClient {
+        async fn send(
+            &self,
+            _: Method,
+            url: &Url,
+            _: Option<(Vec<u8>, &str)>,
+            _: &[(&str, &str)],
+        ) -> Result<()> {
+            if url.path().ends_with("/up") {
+                Ok(())
+            } else {
+                Err(PushMetricsError::Io(ErrorKind::ConnectionRefused.into()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_requests_are_handed_to_the_sink() {
+        // Given I have a client of a pushgateway that is down and a sink that keeps its letters
+        let letters = Mutex::new(Vec::new());
+        let client = DeadLetters::new(DownClient, |letter: DeadLetter<'_, Vec<u8>>| {
+            letters.lock().unwrap().push((
+                letter.method,
+                letter.url.to_string(),
+                letter
+                    .body
+                    .map(|(body, content_type)| (body, content_type.to_string())),
+                letter.error.to_string(),
+            ))
+        });
+
+        // When I push to it and to a group that succeeds
+        let down = Url::parse("http://pushgateway:9091/metrics/job/down").unwrap();
+        let up = Url::parse("http://pushgateway:9091/metrics/job/up").unwrap();
+        let failed = client
+            .push_all(&down, b"up 1\n".to_vec(), "text/plain", &[])
+            .await;
+        let succeeded = client
+            .push_all(&up, b"up 1\n".to_vec(), "text/plain", &[])
+            .await;
+
+        // Then the failed push still fails and only its payload and error reach the sink
+        assert!(matches!(failed, Err(PushMetricsError::Io(_))));
+        assert!(succeeded.is_ok());
+        assert_eq!(
+            *letters.lock().unwrap(),
+            [(
+                Method::Put,
+                down.to_string(),
+                Some((b"up 1\n".to_vec(), "text/plain".to_string())),
+                PushMetricsError::Io(ErrorKind::ConnectionRefused.into()).to_string(),
+            )]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use url::Url;
+
+use crate::error::Result;
+use crate::text_format;
+use crate::text_format::Sample;
+use crate::utils::text_payload;
+use crate::ConvertMetrics;
+
+/// `DeltaConverter` wraps a [`ConvertMetrics`] implementation and replaces the values of
+/// counters with their increase since the previous conversion for the same group, for
+/// aggregation gateways that sum up pushed values, e.g. of ephemeral workers. A counter that
+/// decreased since then was reset and is pushed with its current value, the first push of a
+/// group pushes all counters as they are.
+///
+/// The wrapped converter has to encode in the text format. The snapshot of a group is taken
+/// when its push is converted, so the increase of a push that failed afterwards is lost.
+#[derive(Debug)]
+pub struct DeltaConverter<CM> {
+    converter: CM,
+    snapshots: Mutex<HashMap<Url, HashMap<String, f64>>>,
+}
+
+impl<CM> DeltaConverter<CM> {
+    /// Creates a new [`DeltaConverter`] that computes the deltas of the payloads of the given
+    /// converter.
+    pub fn new(converter: CM) -> Self {
+        Self { converter, snapshots: Mutex::new(HashMap::new()) }
+    }
+
+    /// Replaces the counter values of the given samples with their increase since the previous
+    /// snapshot of the group and remembers the current values as new snapshot.
+    fn deltas<'a>(&self, group: &Url, samples: impl Iterator<Item = &'a mut Sample>) {
+        let mut snapshots = self
+            .snapshots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = snapshots.remove(group).unwrap_or_default();
+        let mut current = HashMap::new();
+
+        for sample in samples {
+            let key = series_key(sample);
+            let value = sample.value;
+            if let Some(previous) = previous.get(&key).filter(|previous| **previous <= value) {
+                sample.value = value - previous;
+            }
+            current.insert(key, value);
+        }
+
+        snapshots.insert(group.clone(), current);
+    }
+}
+
+impl<CM, MF, C, B> ConvertMetrics<MF, C, B> for DeltaConverter<CM>
+where
+    CM: ConvertMetrics<MF, C, B>,
+    B: AsRef<[u8]> + From<String>,
+{
+    fn metrics_from(&self, collectors: C) -> Result<MF> {
+        self.converter.metrics_from(collectors)
+    }
+
+    fn create_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<(Url, B, String)> {
+        let (url, body, content_type) =
+            self.converter
+                .create_push_details(job, url, grouping, metric_families)?;
+        let mut families = text_format::parse(&text_payload(body.as_ref(), &content_type)?)?;
+
+        let counters = families
+            .iter_mut()
+            .filter(|family| family.kind.as_deref() == Some("counter"))
+            .flat_map(|family| family.samples.iter_mut())
+            .filter(|sample| !sample.name.ends_with("_created"));
+        self.deltas(&url, counters);
+
+        let body = B::from(text_format::encode_as(&families, &content_type));
+        Ok((url, body, content_type))
+    }
+}
+
+/// Identifies the series of a sample by its name and its labels sorted by name.
+fn series_key(sample: &Sample) -> String {
+    let mut labels: Vec<_> = sample.labels.iter().collect();
+    labels.sort();
+
+    let labels: Vec<_> = labels
+        .into_iter()
+        .map(|(name, value)| format!("{name}=\"{value}\""))
+        .collect();
+    format!("{}{{{}}}", sample.name, labels.join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use url::Url;
+
+    use crate::delta::DeltaConverter;
+    use crate::raw::RawMetricsConverter;
+    use crate::ConvertMetrics;
+
+    #[test]
+    fn test_counters_are_pushed_as_deltas() {
+        // Given I have a delta converter
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let converter = DeltaConverter::new(RawMetricsConverter::text());
+        let convert = |payload: &str| {
+            let (_, body, _) = converter
+                .create_push_details("worker", &url, &HashMap::new(), payload.to_owned())
+                .unwrap();
+            String::from_utf8(body).unwrap()
+        };
+
+        // When I convert a counter and a gauge three times with a reset of the counter
+        let first = convert(
+            "# TYPE jobs_total counter\njobs_total{kind=\"a\"} 5\n# TYPE busy gauge\nbusy 3\n",
+        );
+        let second = convert(
+            "# TYPE jobs_total counter\njobs_total{kind=\"a\"} 8\n# TYPE busy gauge\nbusy 4\n",
+        );
+        let third = convert(
+            "# TYPE jobs_total counter\njobs_total{kind=\"a\"} 2\n# TYPE busy gauge\nbusy 4\n",
+        );
+
+        // Then the counter is pushed as it is first, then as delta and after the reset as it is
+        assert!(first.contains("jobs_total{kind=\"a\"} 5\n"));
+        assert!(second.contains("jobs_total{kind=\"a\"} 3\n"));
+        assert!(third.contains("jobs_total{kind=\"a\"} 2\n"));
+
+        // And the gauge is pushed as it is
+        assert!(second.contains("busy 4\n"));
+    }
+}
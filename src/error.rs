@@ -11,24 +11,106 @@ pub enum PushMetricsError {
     Url(#[from] url::ParseError),
     #[error("pushed metric already contains label '{0}'")]
     AlreadyContainsLabel(String),
-    #[error("labels and job name must not contain '/': '{0}'")]
+    #[error("label names must not contain '/': '{0}'")]
     SlashInName(String),
-    #[cfg(feature = "prometheus_crate")]
+    #[error("invalid grouping label name '{0}', must match [a-zA-Z_][a-zA-Z0-9_]*")]
+    InvalidLabelName(String),
+    #[error("invalid job name '{0}', must not be empty or contain control characters")]
+    InvalidJobName(String),
+    #[cfg(feature = "prometheus_crate_text")]
     #[error("prometheus error: {0}")]
     Prometheus(#[from] prometheus::Error),
     #[cfg(feature = "prometheus_client_crate")]
     #[error("prometheus client error: {0}")]
     PrometheusClient(#[from] std::fmt::Error),
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("invalid text format: {0}")]
+    TextFormat(String),
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("sample '{0}' has a non-finite value")]
+    NonFiniteValue(String),
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("payload of {size} bytes exceeds the limit of {limit} bytes")]
+    PayloadTooLarge { size: usize, limit: usize },
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("metric '{metric}' carries the forbidden label '{label}'")]
+    ForbiddenLabel { metric: String, label: String },
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("metric '{metric}' does not follow the OpenMetrics naming conventions: it {reason}")]
+    NonConformingName { metric: String, reason: String },
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("pushed metrics not found on the pushgateway: {}", display_mismatches(.0))]
+    Verification(Vec<Mismatch>),
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("no job name given and no default job configured")]
+    MissingJob,
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("cannot re-encode payload: {0}")]
+    Transcode(String),
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error("push did not finish within {0:?}")]
+    Timeout(std::time::Duration),
+    #[cfg(feature = "http")]
+    #[error("{0}")]
+    BadRequest(Box<ResponseError>),
+    #[cfg(feature = "http")]
+    #[error("{0}")]
+    Unauthorized(Box<ResponseError>),
+    #[cfg(feature = "http")]
+    #[error("{response}{}", display_retry_after(.retry_after))]
+    TooManyRequests {
+        response: Box<ResponseError>,
+        retry_after: Option<std::time::Duration>,
+    },
+    #[cfg(feature = "http")]
+    #[error("{0}")]
+    ServerError(Box<ResponseError>),
+    #[cfg(feature = "http")]
+    #[error("{0}")]
+    Response(Box<ResponseError>),
     #[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
-    #[error("unexpected status code while pushing to url")]
-    Response(String),
+    #[error("push rejected: {0}")]
+    Rejected(String),
     #[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
     #[error("reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
+    #[cfg(feature = "rustls_tls")]
+    #[error("tls error: {0}")]
+    Tls(String),
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    #[error(transparent)]
+    Context(Box<ErrorContext>),
+    #[cfg(feature = "blocking")]
+    #[error("no global pusher installed")]
+    NoGlobalPusher,
+    #[cfg(feature = "blocking")]
+    #[error("a global pusher is already installed")]
+    GlobalPusherAlreadySet,
+    #[cfg(feature = "redact_regex")]
+    #[error("invalid redaction pattern: {0}")]
+    Regex(#[from] regex::Error),
+    #[cfg(feature = "retry")]
+    #[error("retry budget exhausted: {0}")]
+    RetryBudgetExhausted(Box<PushMetricsError>),
+    #[cfg(feature = "queue")]
+    #[error("the push queue is closed")]
+    QueueClosed,
+    #[cfg(feature = "srv")]
+    #[error("discovery error: {0}")]
+    Discovery(String),
+    #[cfg(feature = "remote_write")]
+    #[error("remote write does not support deleting series")]
+    RemoteWriteDelete,
+    #[cfg(feature = "admin")]
+    #[error("invalid pushgateway api response: {0}")]
+    InvalidApiResponse(String),
 }
 
 impl PushMetricsError {
-    #[cfg(feature = "prometheus_crate")]
+    #[cfg(feature = "prometheus_crate_text")]
     pub(crate) fn contains_label(metric: &str, label_type: LabelType<'_>) -> Self {
         let message = format!(
             "pushed metric {metric} already contains {label}",
@@ -38,28 +120,367 @@ impl PushMetricsError {
         PushMetricsError::AlreadyContainsLabel(message)
     }
 
-    #[cfg(any(feature = "prometheus_crate", feature = "prometheus_client_crate"))]
+    #[cfg(any(
+        feature = "blocking",
+        feature = "non_blocking",
+        feature = "prometheus_crate_text",
+        feature = "prometheus_client_crate"
+    ))]
     pub(crate) fn slash_in_name(value: &str) -> Self {
-        let message = format!("labels and job name must not contain '/': '{value}'");
+        let message = format!("label names must not contain '/': '{value}'");
         PushMetricsError::SlashInName(message)
     }
 
-    #[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
-    pub(crate) fn response(status_code: &reqwest::StatusCode, url: &url::Url) -> Self {
-        PushMetricsError::Response(format!(
-            "unexpected status code {status_code} while pushing to {url}",
-        ))
+    /// Maps an unexpected status code of the pushgateway to the variant of its failure class.
+    /// `Retry-After` is only understood in seconds, not as a date.
+    #[cfg(feature = "http")]
+    pub(crate) fn response(
+        status_code: &http::StatusCode,
+        url: &url::Url,
+        body: Option<&str>,
+        retry_after: Option<&str>,
+    ) -> Self {
+        let response = Box::new(ResponseError {
+            status: *status_code,
+            url: url.clone(),
+            body: body.filter(|body| !body.is_empty()).map(str::to_owned),
+        });
+
+        match *status_code {
+            http::StatusCode::BAD_REQUEST => PushMetricsError::BadRequest(response),
+            http::StatusCode::UNAUTHORIZED => PushMetricsError::Unauthorized(response),
+            http::StatusCode::TOO_MANY_REQUESTS => PushMetricsError::TooManyRequests {
+                response,
+                retry_after: retry_after
+                    .and_then(|seconds| seconds.trim().parse().ok())
+                    .map(std::time::Duration::from_secs),
+            },
+            status if status.is_server_error() => PushMetricsError::ServerError(response),
+            _ => PushMetricsError::Response(response),
+        }
+    }
+
+    /// Returns the status code of the pushgateway's response if the push failed because of an
+    /// unexpected status code.
+    #[cfg(feature = "http")]
+    pub fn status(&self) -> Option<http::StatusCode> {
+        self.response_error().map(|response| response.status())
+    }
+
+    /// Returns why the pushgateway rejected the pushed payload if it answered with
+    /// `400 Bad Request`, see [`GatewayRejected`].
+    #[cfg(feature = "http")]
+    pub fn rejection(&self) -> Option<GatewayRejected> {
+        match self.response_error() {
+            Some(response) if response.status() == http::StatusCode::BAD_REQUEST => {
+                response.body().map(GatewayRejected::parse)
+            }
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "http")]
+    fn response_error(&self) -> Option<&ResponseError> {
+        match self {
+            PushMetricsError::BadRequest(response)
+            | PushMetricsError::Unauthorized(response)
+            | PushMetricsError::TooManyRequests { response, .. }
+            | PushMetricsError::ServerError(response)
+            | PushMetricsError::Response(response) => Some(response),
+            #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+            PushMetricsError::Context(context) => context.error().response_error(),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the failure is transient, so sending the same request again later may
+    /// succeed: the pushgateway could not be reached, the request timed out or the pushgateway
+    /// answered with `429 Too Many Requests` or a server error. Rejected payloads, missing
+    /// credentials and invalid metrics fail again.
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            PushMetricsError::Io(error) => matches!(
+                error.kind(),
+                std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::NotConnected
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+            ),
+            PushMetricsError::Timeout(_) => true,
+            #[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
+            PushMetricsError::Reqwest(error) => error.is_connect() || error.is_timeout(),
+            #[cfg(feature = "http")]
+            PushMetricsError::TooManyRequests { .. } | PushMetricsError::ServerError(_) => true,
+            PushMetricsError::Context(context) => context.error().is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// Returns the job, grouping and pushgateway the failed push was meant for, if the error
+    /// occurred while encoding or sending the push.
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            PushMetricsError::Context(context) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without the context of the push, e.g. to match on its variant.
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub fn without_context(self) -> Self {
+        match self {
+            PushMetricsError::Context(context) => context.error,
+            error => error,
+        }
+    }
+
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub(crate) fn with_context(
+        self,
+        job: &str,
+        grouping: &std::collections::HashMap<&str, &str>,
+        gateway: &url::Url,
+    ) -> Self {
+        if let PushMetricsError::Context(_) = self {
+            return self;
+        }
+
+        let mut grouping: Vec<_> = grouping
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        grouping.sort();
+
+        PushMetricsError::Context(Box::new(ErrorContext {
+            job: job.to_owned(),
+            grouping,
+            gateway: gateway.clone(),
+            error: self,
+        }))
+    }
+}
+
+/// `ErrorContext` tells which push failed: it holds the job, the grouping labels and the
+/// pushgateway of the push together with the error that made it fail.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Debug)]
+pub struct ErrorContext {
+    job: String,
+    grouping: Vec<(String, String)>,
+    gateway: url::Url,
+    error: PushMetricsError,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl ErrorContext {
+    /// Returns the job of the failed push.
+    pub fn job(&self) -> &str {
+        &self.job
+    }
+
+    /// Returns the grouping labels of the failed push sorted by name.
+    pub fn grouping(&self) -> &[(String, String)] {
+        &self.grouping
+    }
+
+    /// Returns the url of the pushgateway the push was meant for.
+    pub fn gateway(&self) -> &url::Url {
+        &self.gateway
+    }
+
+    /// Returns the error that made the push fail.
+    pub fn error(&self) -> &PushMetricsError {
+        &self.error
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "push of job '{}'", self.job)?;
+        if !self.grouping.is_empty() {
+            let grouping: Vec<_> = self
+                .grouping
+                .iter()
+                .map(|(name, value)| format!("{name}=\"{value}\""))
+                .collect();
+            write!(f, " with grouping {{{}}}", grouping.join(", "))?;
+        }
+
+        write!(f, " to {} failed: {}", self.gateway, self.error)
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl std::error::Error for ErrorContext {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// `ResponseError` describes an unexpected response of the pushgateway that is none of the
+/// failure classes with their own variant, e.g. `403 Forbidden` or `404 Not Found`. The
+/// variants of the failure classes hold it as well.
+#[cfg(feature = "http")]
+#[derive(Debug)]
+pub struct ResponseError {
+    status: http::StatusCode,
+    url: url::Url,
+    body: Option<String>,
+}
+
+#[cfg(feature = "http")]
+impl ResponseError {
+    /// Returns the status code of the response.
+    pub fn status(&self) -> http::StatusCode {
+        self.status
+    }
+
+    /// Returns the final url of the request.
+    pub fn url(&self) -> &url::Url {
+        &self.url
+    }
+
+    /// Returns the body of the response if there was one.
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+}
+
+#[cfg(feature = "http")]
+impl std::fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unexpected status code {} while pushing to {}",
+            self.status, self.url
+        )?;
+        match &self.body {
+            Some(body) => write!(f, ": {}", body.trim_end()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `GatewayRejected` tells from the well-known messages of the pushgateway why it rejected a
+/// payload with `400 Bad Request`, so callers can react to it without parsing the message
+/// themselves. Every variant holds the message of the pushgateway.
+#[cfg(feature = "http")]
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum GatewayRejected {
+    /// The payload could not be parsed, e.g. a text format parsing error.
+    #[error("invalid payload: {0}")]
+    InvalidPayload(String),
+    /// A metric has label dimensions inconsistent with the metrics of its family or group.
+    #[error("inconsistent labels: {0}")]
+    InconsistentLabels(String),
+    /// A metric was pushed more than once with the same name and label values.
+    #[error("duplicate labels: {0}")]
+    DuplicateLabels(String),
+    /// The pushed metrics carry timestamps, which the pushgateway does not accept.
+    #[error("timestamps not accepted: {0}")]
+    Timestamps(String),
+    /// Any other reason of the pushgateway.
+    #[error("{0}")]
+    Other(String),
+}
+
+#[cfg(feature = "http")]
+impl GatewayRejected {
+    /// Maps the body of a `400 Bad Request` of the pushgateway to the reason of the rejection.
+    pub fn parse(body: &str) -> Self {
+        let message = body.trim().to_owned();
+        let lowercase = message.to_lowercase();
+
+        if lowercase.contains("timestamp") {
+            GatewayRejected::Timestamps(message)
+        } else if lowercase.contains("with the same name and label values")
+            || lowercase.contains("duplicate")
+        {
+            GatewayRejected::DuplicateLabels(message)
+        } else if lowercase.contains("inconsistent") && lowercase.contains("label") {
+            GatewayRejected::InconsistentLabels(message)
+        } else if lowercase.contains("parsing error")
+            || lowercase.contains("error parsing")
+            || lowercase.contains("proto")
+        {
+            GatewayRejected::InvalidPayload(message)
+        } else {
+            GatewayRejected::Other(message)
+        }
+    }
+}
+
+/// `Mismatch` describes a pushed sample that the pushgateway does not expose as pushed.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    pub(crate) sample: String,
+    pub(crate) expected: f64,
+    pub(crate) found: Option<f64>,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl Mismatch {
+    /// Returns the sample as name and labels, e.g. `jobs_total{kind="a"}`.
+    pub fn sample(&self) -> &str {
+        &self.sample
+    }
+
+    /// Returns the pushed value of the sample.
+    pub fn expected(&self) -> f64 {
+        self.expected
+    }
+
+    /// Returns the value the pushgateway exposes for the sample or `None` if it is missing.
+    pub fn found(&self) -> Option<f64> {
+        self.found
     }
 }
 
-#[cfg(feature = "prometheus_crate")]
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.found {
+            Some(found) => write!(
+                f,
+                "{} expected {} but found {found}",
+                self.sample, self.expected
+            ),
+            None => write!(f, "{} is missing", self.sample),
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+fn display_retry_after(retry_after: &Option<std::time::Duration>) -> String {
+    match retry_after {
+        Some(retry_after) => format!(", retry after {retry_after:?}"),
+        None => String::new(),
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+fn display_mismatches(mismatches: &[Mismatch]) -> String {
+    mismatches
+        .iter()
+        .map(Mismatch::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(feature = "prometheus_crate_text")]
 #[derive(Debug)]
 pub(crate) enum LabelType<'a> {
     Job,
     Grouping(&'a str),
 }
 
-#[cfg(feature = "prometheus_crate")]
+#[cfg(feature = "prometheus_crate_text")]
 impl<'a> LabelType<'a> {
     fn message(&self) -> String {
         match self {
@@ -68,3 +489,100 @@ impl<'a> LabelType<'a> {
         }
     }
 }
+
+#[cfg(all(test, feature = "http"))]
+mod test {
+    use std::time::Duration;
+
+    use http::StatusCode;
+    use url::Url;
+
+    use crate::error::GatewayRejected;
+    use crate::error::PushMetricsError;
+
+    #[test]
+    fn test_status_codes_are_mapped_to_failure_classes() {
+        // Given I have a url of a push
+        let url = Url::parse("http://pushgateway/metrics/job/classes").unwrap();
+        let response = |status, body, retry_after| {
+            PushMetricsError::response(&status, &url, body, retry_after)
+        };
+
+        // When the pushgateway answers with unexpected status codes
+        let bad_request = response(StatusCode::BAD_REQUEST, Some("invalid\n"), None);
+        let unauthorized = response(StatusCode::UNAUTHORIZED, Some(""), None);
+        let too_many_requests = response(StatusCode::TOO_MANY_REQUESTS, None, Some("2"));
+        let server_error = response(StatusCode::SERVICE_UNAVAILABLE, None, None);
+        let not_found = response(StatusCode::NOT_FOUND, Some("not found"), None);
+
+        // Then each is mapped to its class with the url and body of the response
+        assert!(matches!(
+            &bad_request,
+            PushMetricsError::BadRequest(response)
+                if response.body() == Some("invalid\n") && response.url() == &url
+        ));
+        assert!(matches!(
+            &unauthorized,
+            PushMetricsError::Unauthorized(response) if response.body().is_none()
+        ));
+        assert!(matches!(
+            &too_many_requests,
+            PushMetricsError::TooManyRequests { retry_after: Some(retry_after), .. }
+                if *retry_after == Duration::from_secs(2)
+        ));
+        assert!(matches!(
+            &server_error,
+            PushMetricsError::ServerError(response)
+                if response.status() == StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert!(matches!(
+            &not_found,
+            PushMetricsError::Response(response) if response.body() == Some("not found")
+        ));
+
+        // And only the transient ones are retryable
+        assert_eq!(
+            [
+                &bad_request,
+                &unauthorized,
+                &too_many_requests,
+                &server_error,
+                &not_found
+            ]
+            .map(PushMetricsError::is_retryable),
+            [false, false, true, true, false]
+        );
+        assert_eq!(
+            bad_request.to_string(),
+            "unexpected status code 400 Bad Request while pushing to http://pushgateway/metrics/job/classes: invalid"
+        );
+    }
+
+    #[test]
+    fn test_well_known_rejections_are_parsed() {
+        // Given I have the bodies of well-known rejections of the pushgateway
+        let bodies = [
+            "text format parsing error in line 1: expected float as value, got \"one\"\n",
+            "pushed metrics are invalid or inconsistent with existing metrics: collected metric \"up\" { gauge:<value:1 > } was collected before with the same name and label values\n",
+            "pushed metrics are invalid or inconsistent with existing metrics: collected metric \"up\" { label:<name:\"a\" value:\"b\" > gauge:<value:1 > } has label dimensions inconsistent with previously collected metrics in the same metric family\n",
+            "pushed metrics must not have timestamps\n",
+            "something else\n",
+        ];
+
+        // When I parse them
+        let rejections: Vec<_> = bodies.into_iter().map(GatewayRejected::parse).collect();
+
+        // Then each is mapped to its reason with the trimmed message
+        assert!(matches!(rejections[0], GatewayRejected::InvalidPayload(_)));
+        assert!(matches!(rejections[1], GatewayRejected::DuplicateLabels(_)));
+        assert!(matches!(
+            rejections[2],
+            GatewayRejected::InconsistentLabels(_)
+        ));
+        assert!(matches!(rejections[3], GatewayRejected::Timestamps(_)));
+        assert_eq!(
+            rejections[4],
+            GatewayRejected::Other(String::from("something else"))
+        );
+    }
+}
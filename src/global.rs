@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::blocking::MetricsPusher;
+use crate::blocking::Push;
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::ConvertMetrics;
+use crate::PushReceipt;
+
+static GLOBAL_PUSHER: OnceLock<Box<dyn GlobalPusher>> = OnceLock::new();
+
+/// `GlobalPusher` is implemented by the pushers that can be installed with
+/// [`init_global_pusher`]: every blocking [`MetricsPusher`] that accepts the metrics as
+/// [`String`] in the text format, e.g. with a
+/// [`RawMetricsConverter::text`](crate::raw::RawMetricsConverter::text).
+pub trait GlobalPusher: Send + Sync {
+    /// Pushes all metrics, see [`MetricsPusher::push_all`].
+    fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metrics: String,
+    ) -> Result<PushReceipt>;
+
+    /// Pushes all metrics with add logic, see [`MetricsPusher::push_add`].
+    fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metrics: String,
+    ) -> Result<PushReceipt>;
+
+    /// Deletes the group, see [`MetricsPusher::delete`].
+    fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt>;
+}
+
+impl<P, CM, C, B> GlobalPusher for MetricsPusher<P, CM, String, C, B>
+where
+    P: Push<B> + Send + Sync,
+    CM: ConvertMetrics<String, C, B> + Send + Sync,
+    C: Send + Sync,
+    B: Send + Sync,
+{
+    fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metrics: String,
+    ) -> Result<PushReceipt> {
+        MetricsPusher::push_all(self, job, grouping, metrics)
+    }
+
+    fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metrics: String,
+    ) -> Result<PushReceipt> {
+        MetricsPusher::push_add(self, job, grouping, metrics)
+    }
+
+    fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        MetricsPusher::delete(self, job, grouping)
+    }
+}
+
+/// Installs the global pusher that [`push_all`], [`push_add`] and [`delete`] push with, so
+/// libraries and deeply nested code can push without the pusher being passed through every
+/// layer. Like the global logger of the `log` crate it is installed once by the application,
+/// a second call fails with [`PushMetricsError::GlobalPusherAlreadySet`].
+pub fn init_global_pusher(pusher: impl GlobalPusher + 'static) -> Result<()> {
+    GLOBAL_PUSHER
+        .set(Box::new(pusher))
+        .map_err(|_| PushMetricsError::GlobalPusherAlreadySet)
+}
+
+/// Returns whether a global pusher is installed.
+pub fn is_initialized() -> bool {
+    GLOBAL_PUSHER.get().is_some()
+}
+
+/// Pushes all metrics with the global pusher. Fails with [`PushMetricsError::NoGlobalPusher`]
+/// if the application did not install one, which libraries may ignore.
+pub fn push_all(job: &str, grouping: &HashMap<&str, &str>, metrics: String) -> Result<PushReceipt> {
+    global_pusher()?.push_all(job, grouping, metrics)
+}
+
+/// Pushes all metrics with add logic with the global pusher, see [`push_all`].
+pub fn push_add(job: &str, grouping: &HashMap<&str, &str>, metrics: String) -> Result<PushReceipt> {
+    global_pusher()?.push_add(job, grouping, metrics)
+}
+
+/// Deletes the group with the global pusher, see [`push_all`].
+pub fn delete(job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+    global_pusher()?.delete(job, grouping)
+}
+
+fn global_pusher() -> Result<&'static dyn GlobalPusher> {
+    GLOBAL_PUSHER
+        .get()
+        .map(Box::as_ref)
+        .ok_or(PushMetricsError::NoGlobalPusher)
+}
+
+#[cfg(all(test, feature = "with_reqwest_blocking"))]
+mod test {
+    use std::collections::HashMap;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::blocking::with_reqwest::PushClient;
+    use crate::blocking::MetricsPusher;
+    use crate::error::PushMetricsError;
+    use crate::global;
+    use crate::raw::RawMetricsConverter;
+
+    #[test]
+    fn test_push_with_global_pusher() {
+        // Given I have a push gateway and no global pusher
+        let mut server = Server::new();
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/library_job")
+            .match_body("up 1\n")
+            .create();
+        let result = global::push_all("library_job", &HashMap::new(), "up 1\n".to_owned());
+        assert!(matches!(result, Err(PushMetricsError::NoGlobalPusher)));
+
+        // When I install a global pusher and push from anywhere
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::blocking::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        global::init_global_pusher(metrics_pusher).expect("Failed to install global pusher");
+        global::push_all("library_job", &HashMap::new(), "up 1\n".to_owned())
+            .expect("Failed to push metrics");
+
+        // Then the metrics are received by the push gateway
+        pushgateway_mock.expect(1).assert();
+
+        // And another global pusher cannot be installed
+        let other = MetricsPusher::new(
+            PushClient::new(reqwest::blocking::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        assert!(matches!(
+            global::init_global_pusher(other),
+            Err(PushMetricsError::GlobalPusherAlreadySet)
+        ));
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+
+#[cfg(feature = "derive")]
+pub use prometheus_push_derive::Grouping;
+
+const ENV_PREFIX: &str = "PUSH_LABEL_";
+
+const INSTANCE_LABEL: &str = "instance";
+
+#[cfg(feature = "kubernetes")]
+const SERVICE_ACCOUNT_NAMESPACE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+/// `Grouping` holds owned grouping labels, e.g. built once at startup from the environment,
+/// that are passed to the push methods with [`Grouping::labels`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Grouping {
+    labels: HashMap<String, String>,
+}
+
+/// `GroupingLabels` is implemented by types that provide grouping labels in the form the push
+/// methods accept. With the `derive` feature it is derived for structs of string fields with
+/// `#[derive(Grouping)]`, so the label names are checked at compile time.
+pub trait GroupingLabels {
+    /// Returns the grouping labels in the form the push methods accept.
+    fn labels(&self) -> HashMap<&str, &str>;
+}
+
+impl GroupingLabels for Grouping {
+    fn labels(&self) -> HashMap<&str, &str> {
+        Grouping::labels(self)
+    }
+}
+
+impl Grouping {
+    /// Creates an empty [`Grouping`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given label, replacing a label with the same name.
+    pub fn with_label(mut self, name: &str, value: &str) -> Self {
+        self.labels.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Adds the given [`InstanceId`] as `instance` label, so a restarted process pushes to its
+    /// previous group instead of a new one.
+    pub fn with_instance(self, instance_id: &InstanceId) -> Self {
+        self.with_label(INSTANCE_LABEL, instance_id)
+    }
+
+    /// Returns the grouping labels in the form the push methods accept.
+    pub fn labels(&self) -> HashMap<&str, &str> {
+        self.labels
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// Creates a [`Grouping`] from the environment variables prefixed with `PUSH_LABEL_`, see
+    /// [`Grouping::from_env_prefix`].
+    pub fn from_env() -> Self {
+        Self::from_env_prefix(ENV_PREFIX)
+    }
+
+    /// Creates a [`Grouping`] from the environment variables with the given prefix, so
+    /// deployment manifests control the grouping without code changes. The label name is the
+    /// rest of the variable name, e.g. `PUSH_LABEL_env=prod` with the prefix `PUSH_LABEL_`
+    /// becomes the label `env="prod"`. Variables that are not valid unicode are ignored.
+    pub fn from_env_prefix(prefix: &str) -> Self {
+        let vars = std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        });
+        Self::from_vars(prefix, vars)
+    }
+
+    fn from_vars(prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let labels = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let name = name.strip_prefix(prefix)?;
+                (!name.is_empty()).then(|| (name.to_owned(), value))
+            })
+            .collect();
+
+        Self { labels }
+    }
+
+    /// Creates a [`Grouping`] with the `pod`, `namespace` and `node` labels of the pod this
+    /// process runs in, so pushes from pods are labeled consistently.
+    ///
+    /// The values are read from the environment variables `POD_NAME`, `POD_NAMESPACE` and
+    /// `NODE_NAME` as usually exposed via the Downward API. If they are not set the pod name
+    /// falls back to `HOSTNAME` and the namespace to the one of the service account. Labels
+    /// without a value are omitted.
+    #[cfg(feature = "kubernetes")]
+    pub fn from_kubernetes() -> Self {
+        Self::from_kubernetes_with(
+            |name| std::env::var(name).ok(),
+            |path| std::fs::read_to_string(path).ok(),
+        )
+    }
+
+    #[cfg(feature = "kubernetes")]
+    fn from_kubernetes_with(
+        env: impl Fn(&str) -> Option<String>,
+        read_file: impl Fn(&Path) -> Option<String>,
+    ) -> Self {
+        let pod = env("POD_NAME").or_else(|| env("HOSTNAME"));
+        let namespace =
+            env("POD_NAMESPACE").or_else(|| read_file(Path::new(SERVICE_ACCOUNT_NAMESPACE)));
+        let node = env("NODE_NAME");
+
+        let labels = [("pod", pod), ("namespace", namespace), ("node", node)]
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let value = value?.trim().to_owned();
+                (!value.is_empty()).then(|| (name.to_owned(), value))
+            })
+            .collect();
+
+        Self { labels }
+    }
+}
+
+/// `JobName` is a job name that is validated once when it is created, e.g. from the
+/// configuration at startup, instead of failing with the first push. It dereferences to
+/// [`str`], so it is accepted by all push methods, e.g. as `&job_name`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct JobName(String);
+
+impl JobName {
+    /// Creates a [`JobName`] if the given name is not empty and contains no control
+    /// characters. A name with '/' is pushed as base64 encoded path segment.
+    pub fn new(name: &str) -> Result<Self> {
+        if name.is_empty() || name.chars().any(char::is_control) {
+            return Err(PushMetricsError::InvalidJobName(
+                name.escape_debug().to_string(),
+            ));
+        }
+
+        Ok(Self(name.to_owned()))
+    }
+
+    /// Returns the job name.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for JobName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for JobName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for JobName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for JobName {
+    type Err = PushMetricsError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::new(name)
+    }
+}
+
+impl TryFrom<String> for JobName {
+    type Error = PushMetricsError;
+
+    fn try_from(name: String) -> Result<Self> {
+        Self::new(&name)
+    }
+}
+
+/// `InstanceId` identifies a process for the `instance` grouping label, see
+/// [`Grouping::with_instance`]. An id that survives restarts lets the restarted process
+/// overwrite the group of its previous run instead of leaving it behind on the pushgateway.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceId(String);
+
+impl InstanceId {
+    /// Reads the id from the given state file or, if there is none yet, generates a random
+    /// UUID and persists it there, creating missing directories. The id is stable as long as
+    /// the file is kept, e.g. on a persistent volume.
+    pub fn persisted(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(id) if !id.trim().is_empty() => return Ok(Self(id.trim().to_owned())),
+            Ok(_) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+
+        if let Some(directory) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(directory)?;
+        }
+        let id = random_uuid();
+        std::fs::write(path, format!("{id}\n"))?;
+        Ok(Self(id))
+    }
+
+    /// Derives the id from the hostname and the time this process first asked for it, e.g.
+    /// `worker-1-65f1c2a0`, for processes without a place to persist state. It is stable
+    /// within the process but changes with every restart.
+    pub fn from_host() -> Self {
+        static STARTED: OnceLock<u64> = OnceLock::new();
+
+        let started = STARTED.get_or_init(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default()
+        });
+        Self(format!("{}-{started:x}", hostname()))
+    }
+
+    /// Returns the id.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for InstanceId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for InstanceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Returns the hostname from the environment or `/etc/hostname`, `localhost` if it is unknown.
+fn hostname() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .into_iter()
+        .find_map(|name| std::env::var(name).ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_owned())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| "localhost".to_owned())
+}
+
+/// Generates a random version 4 UUID from the randomly seeded hasher of the standard library.
+fn random_uuid() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    let high = RandomState::new().hash_one((nanos, std::process::id()));
+    let low = RandomState::new().hash_one((high, nanos));
+    let bits = (u128::from(high) << 64 | u128::from(low)) & !(0xf << 76) & !(0b11 << 62)
+        | 0x4 << 76
+        | 0b10 << 62;
+
+    let hex = format!("{bits:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::error::PushMetricsError;
+    use crate::grouping::Grouping;
+    use crate::grouping::InstanceId;
+    use crate::grouping::JobName;
+
+    #[test]
+    fn test_persisted_instance_id_survives_restarts() {
+        // Given I have no state file yet
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("instance-id");
+
+        // When I create the instance id twice like two runs of the same worker
+        let first = InstanceId::persisted(&path).unwrap();
+        let second = InstanceId::persisted(&path).unwrap();
+
+        // Then the second run reads the uuid the first one generated
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 36);
+        assert_eq!(first.as_bytes()[14], b'4');
+        assert_eq!(
+            Grouping::new().with_instance(&first).labels(),
+            HashMap::from([("instance", first.as_str())])
+        );
+    }
+
+    #[test]
+    fn test_job_name_is_validated_at_construction() {
+        // Given I have valid and invalid job names
+        let valid = JobName::new("nightly backup");
+        let slash = JobName::new("nightly/backup");
+        let empty = JobName::new("");
+        let control = "nightly\nbackup".parse::<JobName>();
+
+        // Then only the valid ones can be created and are usable as a str
+        assert_eq!(&*valid.unwrap(), "nightly backup");
+        assert_eq!(&*slash.unwrap(), "nightly/backup");
+        assert!(matches!(empty, Err(PushMetricsError::InvalidJobName(_))));
+        assert!(matches!(control, Err(PushMetricsError::InvalidJobName(_))));
+    }
+
+    #[test]
+    fn test_from_env_prefix() {
+        // Given I have environment variables with and without the prefix
+        let vars = [
+            ("PUSH_LABEL_env", "prod"),
+            ("PUSH_LABEL_region", "eu-west-1"),
+            ("PUSH_LABEL_", "no name"),
+            ("HOME", "/root"),
+        ]
+        .map(|(name, value)| (name.to_owned(), value.to_owned()));
+
+        // When I create the grouping from them
+        let grouping = Grouping::from_vars("PUSH_LABEL_", vars);
+
+        // Then only the prefixed variables with a label name are used
+        let expected = HashMap::from([("env", "prod"), ("region", "eu-west-1")]);
+        assert_eq!(grouping.labels(), expected);
+    }
+
+    #[cfg(feature = "kubernetes")]
+    #[test]
+    fn test_from_kubernetes() {
+        // Given I have a pod with the Downward API env vars for pod name and node
+        let env = HashMap::from([
+            ("POD_NAME", "billing-7d9f"),
+            ("HOSTNAME", "ignored"),
+            ("NODE_NAME", "node-1"),
+        ]);
+
+        // When I create the grouping from the environment and the service account namespace
+        let grouping = Grouping::from_kubernetes_with(
+            |name| env.get(name).map(|value| value.to_string()),
+            |_| Some(String::from("payments\n")),
+        );
+
+        // Then all labels are present
+        let expected = HashMap::from([
+            ("pod", "billing-7d9f"),
+            ("namespace", "payments"),
+            ("node", "node-1"),
+        ]);
+        assert_eq!(grouping.labels(), expected);
+    }
+
+    #[cfg(feature = "kubernetes")]
+    #[test]
+    fn test_from_kubernetes_outside_of_a_pod() {
+        // Given I have no Kubernetes environment
+        let grouping = Grouping::from_kubernetes_with(|_| None, |_| None);
+
+        // Then the grouping is empty
+        assert_eq!(grouping, Grouping::new());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_grouping_is_derived_from_struct() {
+        use crate::grouping::GroupingLabels;
+
+        // Given I have a struct that derives its grouping labels
+        #[derive(crate::grouping::Grouping)]
+        struct BatchGrouping<'a> {
+            instance: String,
+            #[grouping(rename = "env")]
+            environment: &'a str,
+        }
+        let grouping = BatchGrouping { instance: "worker-1".to_owned(), environment: "prod" };
+
+        // When I get its labels
+        let labels = grouping.labels();
+
+        // Then every field is a label named after the field or its rename
+        assert_eq!(
+            labels,
+            HashMap::from([("instance", "worker-1"), ("env", "prod")])
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+pub use prometheus_push_derive::push_metrics;
+
+use crate::error::Result;
+
+/// Encodes the metrics of an invocation of the function instrumented with
+/// [`push_metrics`](macro@push_metrics) in the Prometheus text format.
+pub fn payload(function: &str, duration: Duration, success: bool, invocations: u64) -> String {
+    format!(
+        "# HELP {function}_duration_seconds Duration of the last invocation of {function}.\n\
+         # TYPE {function}_duration_seconds gauge\n\
+         {function}_duration_seconds {duration}\n\
+         # HELP {function}_last_success Whether the last invocation of {function} succeeded.\n\
+         # TYPE {function}_last_success gauge\n\
+         {function}_last_success {success}\n\
+         # HELP {function}_invocations_total Invocations of {function} since the process started.\n\
+         # TYPE {function}_invocations_total counter\n\
+         {function}_invocations_total {invocations}\n",
+        duration = duration.as_secs_f64(),
+        success = u8::from(success),
+    )
+}
+
+/// Logs the failed push of the metrics of the instrumented function with the `log` feature,
+/// as the push must not fail the function itself.
+pub fn report<T>(function: &str, result: Result<T>) {
+    #[cfg(feature = "log")]
+    if let Err(error) = result {
+        log::error!("Failed to push the metrics of {function}: {error}");
+    }
+    #[cfg(not(feature = "log"))]
+    let _ = (function, result);
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use mockito::Matcher;
+    use mockito::Server;
+    use url::Url;
+
+    use crate::instrument::push_metrics;
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::with_reqwest::PushClient;
+
+    #[tokio::test]
+    async fn test_instrumented_function_pushes_its_metrics() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let failed_mock = server
+            .mock("POST", "/metrics/job/import/step/orders")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex("import_orders_last_success 0\n".to_owned()),
+                Matcher::Regex("import_orders_invocations_total 1\n".to_owned()),
+            ]))
+            .create();
+        let succeeded_mock = server
+            .mock("POST", "/metrics/job/import/step/orders")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex("import_orders_last_success 1\n".to_owned()),
+                Matcher::Regex("import_orders_invocations_total 2\n".to_owned()),
+            ]))
+            .create();
+
+        // And a function instrumented with a metrics pusher that is passed in
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+
+        #[push_metrics(
+            pusher = metrics_pusher,
+            job = "import",
+            grouping = std::collections::HashMap::from([("step", "orders")])
+        )]
+        async fn import_orders(
+            metrics_pusher: &MetricsPusher<
+                PushClient,
+                RawMetricsConverter,
+                String,
+                String,
+                Vec<u8>,
+            >,
+            fail: bool,
+        ) -> Result<usize, String> {
+            if fail {
+                return Err("no orders".to_owned());
+            }
+            Ok(3)
+        }
+
+        // When I call it once failing and once succeeding
+        let failed = import_orders(&metrics_pusher, true).await;
+        let succeeded = import_orders(&metrics_pusher, false).await;
+
+        // Then it returns its results and pushes the outcome and count of every invocation
+        assert_eq!(failed, Err("no orders".to_owned()));
+        assert_eq!(succeeded, Ok(3));
+        failed_mock.expect(1).assert();
+        succeeded_mock.expect(1).assert();
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::non_blocking::MetricsPusher;
+use crate::non_blocking::Push;
+use crate::ConvertMetrics;
+use crate::PushReceipt;
+
+/// A group on the pushgateway identified by its job and grouping labels.
+type Group = (String, BTreeMap<String, String>);
+
+/// `Janitor` pushes with the given [`MetricsPusher`] and remembers when it pushed each group
+/// last. Groups that were not pushed again within the staleness window are deleted from the
+/// pushgateway by [`Janitor::sweep`], e.g. the groups of dynamic jobs that disappeared, so
+/// their series do not linger as zombies.
+///
+/// Only groups pushed through the janitor by this process are deleted, the janitor does not
+/// know about groups pushed before a restart.
+#[derive(Debug)]
+pub struct Janitor<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    metrics_pusher: MetricsPusher<P, CM, MF, C, B>,
+    staleness: Duration,
+    groups: Mutex<HashMap<Group, Instant>>,
+}
+
+impl<P, CM, MF, C, B> Janitor<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    /// Creates a new [`Janitor`] that deletes the groups it pushed with the given
+    /// [`MetricsPusher`] once they were not pushed for longer than `staleness`.
+    pub fn new(metrics_pusher: MetricsPusher<P, CM, MF, C, B>, staleness: Duration) -> Self {
+        Self { metrics_pusher, staleness, groups: Mutex::new(HashMap::new()) }
+    }
+
+    /// Pushes all metrics and refreshes the group, see [`MetricsPusher::push_all`].
+    pub async fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        let receipt = self
+            .metrics_pusher
+            .push_all(job, grouping, metric_families)
+            .await?;
+        self.refresh(job, grouping);
+        Ok(receipt)
+    }
+
+    /// Pushes all metrics with add logic and refreshes the group, see
+    /// [`MetricsPusher::push_add`].
+    pub async fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        let receipt = self
+            .metrics_pusher
+            .push_add(job, grouping, metric_families)
+            .await?;
+        self.refresh(job, grouping);
+        Ok(receipt)
+    }
+
+    /// Deletes every group that was not pushed within the staleness window. A group whose
+    /// delete failed is tried again with the next sweep.
+    pub async fn sweep(&self) -> Vec<Result<PushReceipt>> {
+        let stale = {
+            let mut groups = self.lock_groups();
+            let stale: Vec<(Group, Instant)> = groups
+                .iter()
+                .filter(|(_, pushed_at)| pushed_at.elapsed() > self.staleness)
+                .map(|(group, pushed_at)| (group.clone(), *pushed_at))
+                .collect();
+            for (group, _) in &stale {
+                groups.remove(group);
+            }
+            stale
+        };
+
+        let mut results = Vec::with_capacity(stale.len());
+        for ((job, grouping), pushed_at) in stale {
+            let labels = grouping
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            let result = self.metrics_pusher.delete(&job, &labels).await;
+
+            if result.is_err() {
+                self.lock_groups()
+                    .entry((job, grouping))
+                    .or_insert(pushed_at);
+            }
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Sweeps every interval until the returned future is dropped. Failed deletes are logged.
+    pub async fn run(&self, interval: Duration) {
+        self.run_until(interval, std::future::pending()).await
+    }
+
+    /// Sweeps every interval until `shutdown` completes. Failed deletes are logged.
+    pub async fn run_until(&self, interval: Duration, shutdown: impl Future<Output = ()>) {
+        let mut interval = tokio::time::interval(interval);
+        tokio::pin!(shutdown);
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = interval.tick() => {
+                    for result in self.sweep().await {
+                        if let Err(error) = result {
+                            log::error!("Failed to delete stale group: {error}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the wrapped [`MetricsPusher`], e.g. to push groups that are never deleted.
+    pub fn metrics_pusher(&self) -> &MetricsPusher<P, CM, MF, C, B> {
+        &self.metrics_pusher
+    }
+
+    fn refresh(&self, job: &str, grouping: &HashMap<&str, &str>) {
+        let grouping = grouping
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.lock_groups()
+            .insert((job.to_owned(), grouping), Instant::now());
+    }
+
+    fn lock_groups(&self) -> std::sync::MutexGuard<'_, HashMap<Group, Instant>> {
+        self.groups
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::janitor::Janitor;
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::with_reqwest::PushClient;
+
+    #[tokio::test]
+    async fn test_stale_groups_are_deleted() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        server.mock("PUT", mockito::Matcher::Any).create();
+        let stale_mock = server
+            .mock("DELETE", "/metrics/job/worker/instance/a")
+            .create();
+        let fresh_mock = server
+            .mock("DELETE", "/metrics/job/worker/instance/b")
+            .create();
+
+        // And a janitor with a short staleness window
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let janitor = Janitor::new(metrics_pusher, Duration::from_millis(50));
+
+        // When I push two groups, only refresh one of them and sweep after the window
+        let instance_a = HashMap::from([("instance", "a")]);
+        let instance_b = HashMap::from([("instance", "b")]);
+        janitor
+            .push_all("worker", &instance_a, "up 1\n")
+            .await
+            .unwrap();
+        janitor
+            .push_all("worker", &instance_b, "up 1\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        janitor
+            .push_all("worker", &instance_b, "up 1\n")
+            .await
+            .unwrap();
+        let results = janitor.sweep().await;
+
+        // Then only the stale group is deleted, and only once
+        assert_eq!(results.len(), 1);
+        assert!(janitor.sweep().await.is_empty());
+        stale_mock.expect(1).assert();
+        fresh_mock.expect(0).assert();
+    }
+}
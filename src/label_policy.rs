@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::text_format;
+use crate::text_format::Sample;
+use crate::utils::text_payload;
+use crate::ConvertMetrics;
+
+/// `LabelViolation` defines what [`LabelPolicyConverter`] does with samples that carry a label
+/// name its policy forbids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelViolation {
+    /// Drops the affected samples and pushes the rest of their metric family.
+    DropSample,
+    /// Drops the whole metric family of an affected sample.
+    DropFamily,
+    /// Rejects the push with [`PushMetricsError::ForbiddenLabel`].
+    Error,
+}
+
+#[derive(Debug)]
+enum LabelPolicy {
+    Allow(HashSet<String>),
+    Deny(HashSet<String>),
+}
+
+/// `LabelPolicyConverter` wraps a [`ConvertMetrics`] implementation and enforces which label
+/// names may be pushed, so teams sharing a pushgateway can keep its label set tidy on the client
+/// side.
+///
+/// Label names are either checked against an allowlist or a denylist. Samples that violate the
+/// policy are dropped or reject the push, see [`LabelPolicyConverter::with_violation`]. The
+/// wrapped converter has to encode in the text format.
+#[derive(Debug)]
+pub struct LabelPolicyConverter<CM> {
+    converter: CM,
+    policy: LabelPolicy,
+    violation: LabelViolation,
+}
+
+impl<CM> LabelPolicyConverter<CM> {
+    /// Creates a new [`LabelPolicyConverter`] that only lets the given label names pass.
+    pub fn allowing(converter: CM, labels: &[&str]) -> Self {
+        Self::with_policy(converter, LabelPolicy::Allow(owned(labels)))
+    }
+
+    /// Creates a new [`LabelPolicyConverter`] that lets all but the given label names pass.
+    pub fn denying(converter: CM, labels: &[&str]) -> Self {
+        Self::with_policy(converter, LabelPolicy::Deny(owned(labels)))
+    }
+
+    fn with_policy(converter: CM, policy: LabelPolicy) -> Self {
+        Self { converter, policy, violation: LabelViolation::DropSample }
+    }
+
+    /// Sets what happens to samples with a forbidden label name, defaults to
+    /// [`LabelViolation::DropSample`].
+    pub fn with_violation(mut self, violation: LabelViolation) -> Self {
+        self.violation = violation;
+        self
+    }
+
+    fn forbidden<'a>(&self, sample: &'a Sample) -> Option<&'a str> {
+        sample
+            .labels
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .find(|name| match &self.policy {
+                LabelPolicy::Allow(allowed) => !allowed.contains(*name),
+                LabelPolicy::Deny(denied) => denied.contains(*name),
+            })
+    }
+}
+
+fn owned(labels: &[&str]) -> HashSet<String> {
+    labels.iter().map(|label| label.to_string()).collect()
+}
+
+impl<CM, MF, C, B> ConvertMetrics<MF, C, B> for LabelPolicyConverter<CM>
+where
+    CM: ConvertMetrics<MF, C, B>,
+    B: AsRef<[u8]> + From<String>,
+{
+    fn metrics_from(&self, collectors: C) -> Result<MF> {
+        self.converter.metrics_from(collectors)
+    }
+
+    fn create_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<(Url, B, String)> {
+        let (url, body, content_type) =
+            self.converter
+                .create_push_details(job, url, grouping, metric_families)?;
+        let mut families = text_format::parse(&text_payload(body.as_ref(), &content_type)?)?;
+
+        if self.violation == LabelViolation::Error {
+            if let Some((sample, label)) = families
+                .iter()
+                .flat_map(|family| &family.samples)
+                .find_map(|sample| Some((sample, self.forbidden(sample)?)))
+            {
+                return Err(PushMetricsError::ForbiddenLabel {
+                    metric: sample.name.clone(),
+                    label: label.to_owned(),
+                });
+            }
+        }
+
+        families.retain_mut(|family| match self.violation {
+            LabelViolation::DropFamily => family
+                .samples
+                .iter()
+                .all(|sample| self.forbidden(sample).is_none()),
+            _ => {
+                family
+                    .samples
+                    .retain(|sample| self.forbidden(sample).is_none());
+                !family.samples.is_empty()
+            }
+        });
+
+        let body = B::from(text_format::encode_as(&families, &content_type));
+        Ok((url, body, content_type))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use url::Url;
+
+    use crate::error::PushMetricsError;
+    use crate::label_policy::LabelPolicyConverter;
+    use crate::label_policy::LabelViolation;
+    use crate::raw::RawMetricsConverter;
+    use crate::ConvertMetrics;
+
+    const PAYLOAD: &str = "# TYPE requests counter\n\
+        requests{method=\"get\"} 1\n\
+        requests{method=\"get\",user_id=\"42\"} 1\n\
+        # TYPE errors counter\n\
+        errors{method=\"get\"} 1\n";
+
+    fn convert(
+        converter: &LabelPolicyConverter<RawMetricsConverter>,
+    ) -> Result<String, PushMetricsError> {
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let (_, body, _) =
+            converter.create_push_details("worker", &url, &HashMap::new(), PAYLOAD.to_owned())?;
+        Ok(String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn test_samples_outside_the_allowlist_are_dropped() {
+        // Given I have a converter that only allows the method label
+        let converter = LabelPolicyConverter::allowing(RawMetricsConverter::text(), &["method"]);
+
+        // When I convert a payload with a sample carrying a user_id label
+        let payload = convert(&converter).unwrap();
+
+        // Then only that sample is dropped
+        assert_eq!(
+            payload,
+            "# TYPE requests counter\nrequests{method=\"get\"} 1\n# TYPE errors counter\nerrors{method=\"get\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn test_families_with_denied_labels_are_dropped() {
+        // Given I have a converter that denies the user_id label and drops whole families
+        let converter = LabelPolicyConverter::denying(RawMetricsConverter::text(), &["user_id"])
+            .with_violation(LabelViolation::DropFamily);
+
+        // When I convert a payload with a sample carrying a user_id label
+        let payload = convert(&converter).unwrap();
+
+        // Then the family of that sample is dropped
+        assert_eq!(payload, "# TYPE errors counter\nerrors{method=\"get\"} 1\n");
+    }
+
+    #[test]
+    fn test_denied_labels_reject_the_push() {
+        // Given I have a converter that rejects pushes with a user_id label
+        let converter = LabelPolicyConverter::denying(RawMetricsConverter::text(), &["user_id"])
+            .with_violation(LabelViolation::Error);
+
+        // When I convert a payload with a sample carrying a user_id label
+        let result = convert(&converter);
+
+        // Then the push is rejected naming the metric and the label
+        assert!(matches!(
+            result,
+            Err(PushMetricsError::ForbiddenLabel { metric, label }) if metric == "requests" && label == "user_id"
+        ));
+    }
+}
@@ -55,6 +55,13 @@
 //! }
 //! ```
 //!
+//! The `prometheus` crate pushes protobuf. If a pushgateway clone rejects it with `400` or `415`, the push is
+//! retried once in the text format and the pushgateway is remembered to only accept text. Your own `ConvertMetrics`
+//! can offer such a fallback with `fallback_push_details`.
+//!
+//! If you build the `prometheus` crate without its `protobuf` feature, enable `prometheus_crate_text` instead of
+//! `prometheus_crate` and the metrics are pushed in the text format.
+//!
 //!### 2. I use `reqwest` and `prometheus` crates in a **blocking** fashion
 //!
 //!In your `Cargo.toml`:
@@ -85,6 +92,17 @@
 //! }
 //! ```
 //!
+//! To push several jobs or groups at once without an async runtime, `push_all_concurrently` fans the pushes out on a small
+//! pool of scoped threads (4 by default, see `with_parallelism`). `blocking::fan_out` does the same for pushes to several
+//! pushgateways.
+//!
+//! If you still push with `prometheus::push_metrics` or `push_add_metrics`, the `compat` module mirrors their signatures,
+//! so only the import changes:
+//!
+//! ```ignore
+//! use prometheus_push::compat::{push_add_metrics, push_metrics, BasicAuthentication};
+//! ```
+//!
 //! ### 3. I use `reqwest` and `prometheus-client` crates in a **non-blocking** fashion
 //!
 //! In your `Cargo.toml`:
@@ -121,6 +139,15 @@
 //! }
 //! ```
 //!
+//! To push several registries at once, encode each of them and join the payloads with `concat_openmetrics`, which keeps
+//! exactly one `# EOF` marker at the end.
+//!
+//! When you push collectors, `PrometheusClientMetricsConverter::new().with_prefix(..).with_labels(..)` encodes them like a
+//! registry created with `Registry::with_prefix_and_labels`.
+//!
+//! `with_eof(false)` removes the `# EOF` marker and `with_unit_lines(false)` the `# UNIT` lines for pushgateways that do not
+//! expect the OpenMetrics extras, `with_eof(true)` adds the marker to payloads you encoded without it.
+//!
 //! ### 4. I use `reqwest` and `prometheus-client` crates in a **blocking** fashion
 //!
 //! In your `Cargo.toml`:
@@ -161,22 +188,28 @@
 //!
 //! #### Implement `Push` yourself
 //!
-//! If you are not using reqwest as an http client you are free to implement the `Push` traits two methods yourself. As a guide you can use the
+//! If you are not using reqwest as an http client you are free to implement the `send` method of the `Push` trait yourself. As a guide you can use the
 //! implementation of the `with_reqwest` feature (see [here](https://github.com/maoertel/prometheus-push/blob/7fe1946dd143f4870beb80e642b0acb7854a3cb8/src/with_reqwest.rs)).
-//! Basically it is as simple as that.
+//! Basically it is as simple as that. If the `with_reqwest` or `with_reqwest_blocking` feature is enabled anyway, implement
+//! `Respond` for the response of your client and reuse `handle_response` to check it the same way the provided `PushClient`s do.
 //!
 //! ```ignore
+//! use prometheus_push::Method;
 //! use prometheus_push::Push;
 //!
 //! pub struct YourPushClient;
 //!
 //! impl Push<Vec<u8>> for YourPushClient {
-//!     async fn push_all(&self, url: &Url, body: Vec<u8>, content_type: &str) -> Result<()> {
-//!         // implement a PUT request with your client with this body and `content_type` in header
-//!     }
-//!
-//!     async fn push_add(&self, url: &Url, body: Vec<u8>, content_type: &str) -> Result<()> {
-//!         // implement a POST request with your client with this body and `content_type` in header
+//!     async fn send(
+//!         &self,
+//!         method: Method,
+//!         url: &Url,
+//!         body: Option<(Vec<u8>, &str)>,
+//!         headers: &[(&str, &str)],
+//!     ) -> Result<()> {
+//!         // implement a request with your client with this `method` and `headers` and, if
+//!         // there is a body, with the body and its content type. `push_all`, `push_add` and
+//!         // `delete` of the trait send the requests of the pushgateway api with it.
 //!     }
 //! }
 //! ```
@@ -208,6 +241,564 @@
 //! }
 //! ```
 //!
+//! #### Push already encoded payloads
+//!
+//! If your metrics are already encoded, e.g. by another library or a bridge to another language, you do not need to implement
+//! `ConvertMetrics` at all. The `RawMetricsConverter` pushes a `String` or `Vec<u8>` payload as is with the configured content type.
+//!
+//! ```ignore
+//! use prometheus_push::non_blocking::MetricsPusher;
+//! use prometheus_push::raw::RawMetricsConverter;
+//! use prometheus_push::with_reqwest::PushClient;
+//!
+//! let metrics_pusher = MetricsPusher::new(PushClient::new(client), RawMetricsConverter::text(), &push_gateway)?;
+//! metrics_pusher.push_all("<your push jobs name>", &grouping, String::from("backup_size_bytes 1234\n")).await?;
+//! ```
+//!
+//! #### Push a single value
+//!
+//! Scripts that only report a single value do not need a registry at all: `push_gauge` and `push_counter` build the metric
+//! in the text format and push it, replacing only the metric with the same name within the group.
+//!
+//! ```ignore
+//! metrics_pusher.push_gauge("backup", &grouping, "backup_size_bytes", 123.4, "Size of the last backup.").await?;
+//! ```
+//!
+//! #### Delete a group
+//!
+//! `delete` removes the whole group of a job and grouping labels from the pushgateway with a `DELETE` request, e.g. when a
+//! batch job is retired. `clear` only removes its metrics and keeps the group with its `push_time_seconds`:
+//!
+//! ```ignore
+//! metrics_pusher.delete("<your push jobs name>", &grouping).await?;
+//! ```
+//!
+//! #### Query and wipe your pushgateway
+//!
+//! With the `admin` feature the `MetricsPusher` reads the `status` and all `metric_groups` of your pushgateway as typed structs and
+//! `wipe`s all groups with the admin api, which has to be enabled with `--web.enable-admin-api`.
+//!
+//! ```ignore
+//! let status = metrics_pusher.status().await?;
+//! println!("pushgateway {}", status.build_information.version);
+//!
+//! for group in metrics_pusher.metric_groups().await? {
+//!     println!("{:?} last pushed at {:?}", group.labels, group.push_time_seconds());
+//! }
+//!
+//! metrics_pusher.wipe().await?;
+//! ```
+//!
+//! #### Correlate pushes with the logs of your pushgateway
+//!
+//! Every push returns a `PushReceipt`. With a `RequestId` configured a request id is sent as header (`X-Request-Id` by default) with
+//! every push, logged and returned in the receipt, so failed pushes can be found in the logs of your pushgateway or its proxy.
+//!
+//! ```ignore
+//! use prometheus_push::RequestId;
+//!
+//! let metrics_pusher = metrics_pusher.with_request_id(RequestId::default());
+//! let receipt = metrics_pusher.push_all("<your push jobs name>", &grouping, metrics).await?;
+//! println!("pushed with request id {:?}", receipt.request_id());
+//! ```
+//!
+//! #### Push only what changed
+//!
+//! For mostly static registries `push_changed` remembers the metric families of its last push per group and pushes only the
+//! changed ones with `push_add` logic. If nothing changed nothing is sent and the receipt `is_skipped()`. It requires the text
+//! format and keeps families at the pushgateway that are no longer gathered.
+//!
+//! #### Split a payload into groups per label
+//!
+//! Instead of one registry per tenant, `push_all_split` splits the gathered metrics by the value of a label, e.g. `tenant`,
+//! and pushes each part to its own group, e.g. `tenant=a`, with the label removed from the series. It requires the text format.
+//!
+//! #### Instrument functions
+//!
+//! With the `derive` feature, `#[push_metrics]` pushes the duration, the outcome and the invocation count of a function
+//! every time it is called, e.g. for the steps of a batch job. The pusher is any expression, like a global one in a static:
+//!
+//! ```ignore
+//! use prometheus_push::instrument::push_metrics;
+//!
+//! #[push_metrics(pusher = &*PUSHER, job = "nightly_batch")]
+//! fn import_orders() -> Result<(), ImportError> {
+//!     // ...
+//! }
+//! ```
+//!
+//! #### Push a stream of metric families
+//!
+//! With the `stream` feature `push_all_stream` takes a `Stream` of metric families and sends every item as the next chunk
+//! of the body as soon as it arrives, so producers that generate metrics from e.g. a database scan do not have to hold all
+//! of them in memory. The client has to implement `PushStream`, as the `reqwest` based `PushClient` does:
+//!
+//! ```ignore
+//! let metric_families = rows.map(|row| family_of(row));
+//! metrics_pusher.push_all_stream("scan_job", &grouping, metric_families).await?;
+//! ```
+//!
+//! #### Push counter deltas
+//!
+//! For aggregation gateways that sum up pushed values, wrap your converter in a `DeltaConverter`. It pushes the increase of every
+//! counter since the previous push of the group instead of its total and detects resets, e.g. of ephemeral workers.
+//!
+//! ```ignore
+//! use prometheus_push::delta::DeltaConverter;
+//!
+//! let metrics_pusher = MetricsPusher::new(push_client, DeltaConverter::new(RawMetricsConverter::text()), &push_gateway)?;
+//! ```
+//!
+//! #### Accumulate pushes across restarts
+//!
+//! Wrap your converter in an `AccumulatingConverter` to push the families of previous pushes to the same group again, so a
+//! `push_all` of a restarted worker does not wipe what it contributed before. `with_directory` keeps them in a file per group.
+//!
+//! ```ignore
+//! use prometheus_push::accumulate::AccumulatingConverter;
+//!
+//! let converter = AccumulatingConverter::new(RawMetricsConverter::text()).with_directory("/var/lib/worker/pushed");
+//! ```
+//!
+//! #### Redact label values
+//!
+//! Wrap your converter in a `RedactingConverter` to mask label values before they leave the process, e.g. personal
+//! data that ended up in labels by accident. Rules are closures over the label name and value or, with the `redact_regex`
+//! feature, regular expressions:
+//!
+//! ```ignore
+//! use prometheus_push::redact::RedactingConverter;
+//!
+//! let converter = RedactingConverter::new(RawMetricsConverter::text())
+//!     .with_rule(|name, _| (name == "user_id").then(|| "redacted".to_owned()))
+//!     .with_regex(r"[^@\s]+@([^@\s]+)", "***@$1")?;
+//! ```
+//!
+//! #### Enforce label names
+//!
+//! To enforce which label names may reach a shared pushgateway, wrap your converter in a `LabelPolicyConverter` with
+//! an allowlist or a denylist. Samples with other labels are dropped, or drop their whole family or reject the push:
+//!
+//! ```ignore
+//! use prometheus_push::label_policy::{LabelPolicyConverter, LabelViolation};
+//!
+//! let converter = LabelPolicyConverter::denying(RawMetricsConverter::text(), &["user_id", "email"])
+//!     .with_violation(LabelViolation::Error);
+//! ```
+//!
+//! #### Normalize metric names
+//!
+//! Wrap your converter in a `NamingConverter` to push names that follow the OpenMetrics conventions: counter samples end
+//! with `_total` and nothing else does, and names use base units like `_seconds` and `_bytes`. By default violations are fixed,
+//! values in e.g. milliseconds are converted to seconds. `NamingMode::Error` rejects them with the metric and the reason instead:
+//!
+//! ```ignore
+//! use prometheus_push::naming::{NamingConverter, NamingMode};
+//!
+//! let converter = NamingConverter::new(RawMetricsConverter::text()).with_mode(NamingMode::Error);
+//! ```
+//!
+//! #### Reduce histogram buckets
+//!
+//! If your pushgateway or Prometheus cannot handle the full resolution of your histograms, wrap your converter in a
+//! `RebucketingConverter` to push only the buckets of the given upper bounds, with `with_family_bounds` for single histograms.
+//!
+//! ```ignore
+//! use prometheus_push::rebucket::RebucketingConverter;
+//!
+//! let converter = RebucketingConverter::new(RawMetricsConverter::text(), &[0.1, 1.0, 10.0])
+//!     .with_family_bounds("request_size_bytes", &[1024.0, 1048576.0]);
+//! ```
+//!
+//! #### Throttle pushes from hot paths
+//!
+//! `with_min_interval` pushes each group at most once per interval. Pushes that come in too early are deferred and return a
+//! receipt that `is_skipped()`, the newest one of a group is pushed with the next push after the interval or by `flush()`, e.g.
+//! before shutdown.
+//!
+//! #### Handle NaN and infinite values
+//!
+//! `with_non_finite_policy` drops samples or whole families with `NaN` or infinite values, replaces their values with `0` or
+//! rejects the push, as pushgateways and their consumers may choke on them. The receipt counts the affected samples in
+//! `non_finite_samples()`.
+//!
+//! #### Limit the payload size
+//!
+//! `with_max_payload_size` rejects payloads above the given size in bytes with `PushMetricsError::PayloadTooLarge`
+//! before anything is sent, e.g. to fail fast instead of running into the request size limit of your pushgateway.
+//!
+//! #### Compress large payloads
+//!
+//! With the `compress_gzip` and `compress_zstd` features `with_compression` compresses payloads above a size threshold and
+//! sends them with a `Content-Encoding` header, small payloads are not worth the cpu time and stay uncompressed. Above
+//! several thresholds the encoding of the highest one is chosen and a payload that does not get smaller is sent uncompressed.
+//! The pushgateway only accepts gzip, zstd needs a proxy in front of it. The chosen encoding is returned in the
+//! `PushReceipt`. It requires a byte payload like `Vec<u8>`:
+//!
+//! ```ignore
+//! use prometheus_push::Compression;
+//!
+//! let metrics_pusher = metrics_pusher.with_compression(Compression::new().with_gzip_above(8 * 1024));
+//! let receipt = metrics_pusher.push_all("my_job", &grouping, metrics).await?;
+//! log::debug!("pushed with {}", receipt.content_encoding().as_str());
+//! ```
+//!
+//! #### Log every push
+//!
+//! An observer set with `with_observer` receives a `PushEvent` with job, grouping, endpoint, method, outcome, duration, size and
+//! retries of every request. With the `json_events` feature it is serializable with `serde`, `to_json()` gives a single line for
+//! your log pipeline.
+//!
+//! ```ignore
+//! let metrics_pusher = metrics_pusher.with_observer(|event| log::info!("{}", event.to_json()));
+//! ```
+//!
+//! #### Turn pushing off
+//!
+//! `NoopPushClient` pushes nowhere, e.g. for local development. A pusher with a real client is switched off at runtime with
+//! `disable()` and on again with `enable()`, e.g. during an incident of your pushgateway. While it is disabled every push returns
+//! a receipt that `is_skipped()`.
+//!
+//! #### Route pushes per tenant
+//!
+//! A `TenantResolver` set with `with_tenant_resolver` maps job and grouping labels of every push and delete to a `Tenant`
+//! when it is sent. Its header is sent with the request and its endpoint replaces the url of the pusher, so multi-tenant
+//! agents push the metrics of all customers through one pusher with the right isolation. The receipt tells the tenant:
+//!
+//! ```ignore
+//! use prometheus_push::tenant::Tenant;
+//!
+//! let metrics_pusher = metrics_pusher.with_tenant_resolver(|_: &str, grouping: &HashMap<&str, &str>| {
+//!     let customer = grouping["customer"];
+//!     Ok(Tenant::new(customer).with_header("X-Scope-OrgID", customer))
+//! });
+//! ```
+//!
+//! #### Keep the instance label across restarts
+//!
+//! An `InstanceId` added with `Grouping::with_instance` as `instance` grouping label lets a restarted worker overwrite the
+//! group of its previous run instead of leaving it behind on the pushgateway. `InstanceId::persisted` reads a UUID from a
+//! state file or generates and stores one, `InstanceId::from_host` derives the id from the hostname and the start time:
+//!
+//! ```ignore
+//! use prometheus_push::grouping::{Grouping, InstanceId};
+//!
+//! let instance_id = InstanceId::persisted("/var/lib/worker/instance-id")?;
+//! let grouping = Grouping::from_env().with_instance(&instance_id);
+//! metrics_pusher.push_all("worker", &grouping.labels(), metrics).await?;
+//! ```
+//!
+//! #### Push many jobs from one process
+//!
+//! A `MetricsPusherPool` hands out a `ScopedPusher` per job and grouping that pushes to its group with one shared
+//! `MetricsPusher`. The scoped pushers are cached and the least recently used one is evicted above `with_max_pushers`.
+//!
+//! ```ignore
+//! use prometheus_push::scoped::MetricsPusherPool;
+//!
+//! let pool = MetricsPusherPool::new(metrics_pusher);
+//! pool.get("<your push jobs name>", &grouping).push_all(metrics).await?;
+//! ```
+//!
+//! #### Combine push clients
+//!
+//! The `combinators` wrap push clients: `Tee(a, b)` sends to both, `Fallback(primary, secondary)` tries them in order and
+//! `Race(a, b)` succeeds with the first success. They are push clients themselves, so they nest:
+//!
+//! ```ignore
+//! use prometheus_push::combinators::{Fallback, Tee};
+//!
+//! let push_client = Fallback(Tee(primary, mirror), backup);
+//! ```
+//!
+//! #### Retry failed pushes
+//!
+//! With the `retry` feature `Retry` wraps a push client and sends failed requests again with an exponential backoff.
+//! `push_all` (PUT) and `delete` are idempotent and retried on connect errors, timeouts, `429` and server errors. `push_add`
+//! (POST) is only retried if it could not connect, as a request that timed out may have been applied and a retry would add
+//! its metrics twice. `with_retry_on` overrides this per method:
+//!
+//! ```ignore
+//! use prometheus_push::retry::{Retry, RetryOn};
+//!
+//! let push_client = Retry::new(PushClient::new(reqwest::Client::new()), 3)
+//!     .with_backoff(Duration::from_millis(200))
+//!     .with_retry_on(Method::Post, RetryOn::Never);
+//! ```
+//!
+//! A retry after `429 Too Many Requests` waits at least as long as its `Retry-After` header asks for. The pushgateway's
+//! answers map to their own errors: `BadRequest`, `Unauthorized`, `TooManyRequests` with the `Retry-After`, `ServerError`
+//! and `Response` for any other status. Each of them holds the `ResponseError` with the status, url and body of the
+//! answer. `is_retryable()` tells whether sending the push again may succeed, e.g. to retry or alert yourself:
+//!
+//! ```ignore
+//! match metrics_pusher.push_all("<your push jobs name>", &grouping, registry.gather()).await {
+//!     Err(error) if error.is_retryable() => log::warn!("Push failed, trying again later: {error}"),
+//!     Err(error) => return Err(error.into()),
+//!     Ok(_) => {}
+//! }
+//! ```
+//!
+//! A `RetryBudget` shared between the clients of many groups caps their retries together, so an outage of the
+//! pushgateway does not multiply them. Every retry takes a token from the budget, which refills at a fixed rate. A retry
+//! without a token fails with `PushMetricsError::RetryBudgetExhausted` and `collector()` exposes the budget as metrics:
+//!
+//! ```ignore
+//! use prometheus_push::retry::RetryBudget;
+//!
+//! let budget = RetryBudget::new(100, 10.0);
+//! let push_client = Retry::new(PushClient::new(reqwest::Client::new()), 3).with_budget(budget.clone());
+//! registry.register(Box::new(budget.collector()))?;
+//! ```
+//!
+//! #### Keep failed pushes in a dead letter sink
+//!
+//! `DeadLetters` wraps a push client and hands every request that failed, with its body and error, to a `DeadLetterSink`
+//! before the error is returned, e.g. to write it to a file, send it to a channel or page someone. Wrapped around `Retry`
+//! it only receives the requests that failed after all retries:
+//!
+//! ```ignore
+//! use prometheus_push::dead_letter::{DeadLetter, DeadLetters};
+//!
+//! let push_client = DeadLetters::new(
+//!     Retry::new(PushClient::new(reqwest::Client::new()), 3),
+//!     move |letter: DeadLetter<'_, Vec<u8>>| {
+//!         let _ = sender.send((letter.url.clone(), letter.body.map(|(body, _)| body)));
+//!     },
+//! );
+//! ```
+//!
+//! #### Authenticate at a reverse proxy
+//!
+//! The `PushClient` builder of `with_reqwest` and `with_reqwest_blocking` sends `Auth` credentials with every push and delete: basic
+//! authentication, a bearer token or a custom header.
+//!
+//! ```ignore
+//! use prometheus_push::with_reqwest::PushClient;
+//! use prometheus_push::Auth;
+//!
+//! let push_client = PushClient::builder().auth(Auth::Bearer("<your token>".to_owned())).build()?;
+//! let push_client = PushClient::builder()
+//!     .auth(Auth::Header { name: "X-Api-Key".to_owned(), value: "<your key>".to_owned() })
+//!     .build()?;
+//! ```
+//!
+//! #### Configure a pusher in one place
+//!
+//! `MetricsPusherBuilder` of `with_reqwest` and `with_reqwest_blocking` builds the `PushClient` and the `MetricsPusher`
+//! together: timeout, user agent, credentials, extra headers, default grouping labels and, with the `retry` feature,
+//! retries. `wrap` adds any other wrapper of the push client.
+//!
+//! ```ignore
+//! use prometheus_push::prometheus_crate::PrometheusMetricsConverter;
+//! use prometheus_push::with_reqwest::MetricsPusherBuilder;
+//!
+//! let metrics_pusher = MetricsPusherBuilder::new(PrometheusMetricsConverter, &push_gateway)
+//!     .timeout(Duration::from_secs(10))
+//!     .user_agent("<your application>")
+//!     .auth(Auth::Bearer("<your token>".to_owned()))
+//!     .header("X-Scope-OrgID", "<your tenant>")
+//!     .default_grouping(&labels! { "instance" => "<your instance>" })
+//!     .retry(3, Duration::from_millis(200))
+//!     .build()?;
+//! ```
+//!
+//! #### Push to a remote write receiver
+//!
+//! With the `remote_write` feature the same `MetricsPusher` pushes directly to Prometheus, Mimir, Thanos Receive or
+//! VictoriaMetrics: the `RemoteWriteConverter` encodes the metric families as a snappy compressed remote write request with the
+//! job and grouping as labels of every series, the `RemoteWriteClient` sends it with the headers of the protocol.
+//!
+//! ```ignore
+//! use prometheus_push::non_blocking::MetricsPusher;
+//! use prometheus_push::remote_write::RemoteWriteClient;
+//! use prometheus_push::remote_write::RemoteWriteConverter;
+//! use prometheus_push::with_reqwest::PushClient;
+//!
+//! let metrics_pusher = MetricsPusher::new(
+//!     RemoteWriteClient::new(PushClient::new(reqwest::Client::new())),
+//!     RemoteWriteConverter::new().with_path("api/v1/push"),
+//!     &Url::parse("https://mimir:8080")?,
+//! )?;
+//!
+//! metrics_pusher.push_all("<your push jobs name>", &grouping, registry.gather()).await?;
+//! ```
+//!
+//! Remote write cannot delete series, so `delete` fails.
+//!
+//! #### Push with hyper instead of reqwest
+//!
+//! If your application already depends on hyper 1.x, the `with_hyper` feature gives you the `HyperPushClient` on top of the legacy
+//! client of `hyper-util`, so `reqwest` stays out of your dependency tree. Its plain HTTP client connects within 5 seconds and
+//! finishes a push within 30 seconds. Pass your own client to choose the connector, e.g. the one of `hyper-rustls` for HTTPS,
+//! and set its timeout with `with_timeout`.
+//!
+//! ```ignore
+//! use prometheus_push::non_blocking::MetricsPusher;
+//! use prometheus_push::with_hyper::HyperPushClient;
+//!
+//! let metrics_pusher = MetricsPusher::new(HyperPushClient::http(), PrometheusMetricsConverter, &url)?;
+//! ```
+//!
+//! #### Push with ureq instead of reqwest
+//!
+//! For blocking applications the `with_ureq` feature gives you the `UreqPushClient` on top of `ureq`, which is far lighter than
+//! `reqwest::blocking` and does not pull in tokio. Its default agent connects within 5 seconds, finishes a push within 30 seconds
+//! and does not follow redirects. Pass your own `ureq::Agent` to configure it differently.
+//!
+//! ```ignore
+//! use prometheus_push::blocking::with_ureq::UreqPushClient;
+//! use prometheus_push::blocking::MetricsPusher;
+//!
+//! let metrics_pusher = MetricsPusher::new(UreqPushClient::default(), PrometheusMetricsConverter, &url)?;
+//! ```
+//!
+//! #### Push over a Windows named pipe
+//!
+//! With the `named_pipe` feature `PipePushClient` sends the requests as HTTP/1.1 over a local stream instead of TCP, e.g.
+//! to a collector that listens on a Windows named pipe. On other platforms pass a closure that opens the stream:
+//!
+//! ```ignore
+//! use prometheus_push::pipe::PipePushClient;
+//!
+//! let push_client = PipePushClient::named_pipe(r"\\.\pipe\collector");
+//! let metrics_pusher = MetricsPusher::new(push_client, RawMetricsConverter::text(), &Url::parse("http://collector")?)?;
+//! ```
+//!
+//! #### Push tracing event counts
+//!
+//! If your application is only instrumented with `tracing`, the `tracing_layer` feature gives you error rates anyway:
+//! the layer of a `TracingPusher` counts events and spans by level and target and the pusher pushes them as
+//! `tracing_events_total` and `tracing_spans_total`:
+//!
+//! ```ignore
+//! use prometheus_push::tracing_layer::TracingPusher;
+//! use tracing_subscriber::layer::SubscriberExt;
+//!
+//! let tracing_pusher = TracingPusher::new(metrics_pusher, "my_app");
+//! tracing::subscriber::set_global_default(tracing_subscriber::registry().with(tracing_pusher.layer()))?;
+//! tracing_pusher.run(Duration::from_secs(30)).await;
+//! ```
+//!
+//! #### Push with a global pusher
+//!
+//! Like the global logger of the `log` crate, the application installs a blocking pusher once and libraries push with
+//! it without the pusher being passed through every layer. Without a global pusher the pushes fail with `NoGlobalPusher`.
+//!
+//! ```ignore
+//! use prometheus_push::global;
+//!
+//! global::init_global_pusher(metrics_pusher)?;
+//!
+//! // anywhere else
+//! global::push_all("<your push jobs name>", &grouping, metrics)?;
+//! ```
+//!
+//! #### Push to several pushgateways
+//!
+//! The `GatewayPool` wraps your push client and spreads the pushes over several pushgateways by weighted round-robin. A gateway
+//! whose last push failed is skipped for a while, so a beefier central gateway can take most of the traffic while a smaller
+//! standby takes the rest.
+//!
+//! ```ignore
+//! use prometheus_push::pool::GatewayPool;
+//!
+//! let push_client = GatewayPool::new(PushClient::new(client), &standby_gateway).with_gateway(&central_gateway, 3);
+//! let metrics_pusher = MetricsPusher::new(push_client, PrometheusMetricsConverter, &central_gateway)?;
+//! ```
+//!
+//! If a gateway only accepts another format, e.g. a hosted one that does not accept protobuf, `with_format(&hosted_gateway,
+//! WireFormat::Text)` re-encodes the pushes to it.
+//!
+//! With the `srv` feature the gateways of the pool can be discovered via DNS SRV records instead, `SrvDiscovery::run` keeps them up
+//! to date with the `GatewaySet` of the pool.
+//!
+//! `GatewaySet::check_health` probes the `/-/ready` endpoint of every gateway and evicts the ones that are not ready until they
+//! recover, the `health_check` feature runs these checks in the background.
+//!
+//! ### 6. I want to push from the command line
+//!
+//! With the `cli` feature enabled you get the `push-metrics` binary that reads metrics in the Prometheus text format from
+//! stdin or a file and pushes them to your pushgateway. This comes in handy in shell scripts and CI pipelines.
+//!
+//! ```sh
+//! cargo install prometheus_push --features cli
+//! echo "backup_size_bytes 1234" | push-metrics --gateway http://localhost:9091 --job backup --label env=prod
+//! push-metrics --gateway http://localhost:9091 --job backup --add metrics.prom
+//! push-metrics --gateway http://localhost:9091 --job backup --label env=prod --delete
+//! ```
+//!
+//! `--replace` (PUT) is the default, `--add` uses POST and `--delete` removes the whole group.
+//!
+//! ### 7. My pushgateway uses a private CA
+//!
+//! `reqwest` is built without a TLS backend, so pick one to push via HTTPS: `with_reqwest_rustls`,
+//! `with_reqwest_rustls_native_roots` or `with_reqwest_native_tls`. The `PushClient` builder uses the selected backend even if
+//! another crate enables a different one on the same `reqwest`:
+//!
+//! ```toml
+//! [dependencies]
+//! prometheus_push = { version = "<version>", default-features = false, features = ["with_reqwest", "with_reqwest_rustls"] }
+//! ```
+//!
+//! The `rustls_tls` feature builds on `with_reqwest_rustls` and lets the `PushClient` builder of `with_reqwest` and
+//! `with_reqwest_blocking` trust additional root certificates and pin the certificate of your pushgateway.
+//!
+//! ```ignore
+//! use prometheus_push::with_reqwest::PushClient;
+//!
+//! let push_client = PushClient::builder()
+//!     .add_root_certificates_pem(&std::fs::read("<path to your CA>.pem")?)?
+//!     .pin_certificate_sha256(<SHA-256 hash of the DER encoded pushgateway certificate>)
+//!     .build()?;
+//! ```
+//!
+//! ### 8. I want to push from my axum or hyper server
+//!
+//! With the `tower` feature the `PushLayer` pushes the metrics of your server periodically while it is serving and a final time
+//! when it shut down, so the last state of your metrics makes it to the pushgateway. Without a server the `periodic` feature gives
+//! you the underlying `PeriodicPusher` that you can run until your own shutdown signal.
+//!
+//! ```ignore
+//! use prometheus_push::periodic::PeriodicPusher;
+//! use prometheus_push::tower::PushLayer;
+//!
+//! let periodic_pusher = PeriodicPusher::new(metrics_pusher, "<your push jobs name>", || registry.gather(), interval);
+//! let (push_layer, final_push) = PushLayer::new(periodic_pusher);
+//!
+//! let app = Router::new().route("/", get(handler)).layer(push_layer);
+//! axum::serve(listener, app).with_graceful_shutdown(shutdown_signal()).await?;
+//! final_push.await?;
+//! ```
+//!
+//! To run it in the background without a server, `spawn` it and shut it down with the returned handle, which waits for
+//! the final push:
+//!
+//! ```ignore
+//! let handle = PeriodicPusher::new(metrics_pusher, "<your push jobs name>", || registry.gather(), interval)
+//!     .with_grouping(Grouping::new().with_label("instance", "<your instance>"))
+//!     .spawn();
+//!
+//! // ... your application runs ...
+//!
+//! handle.shutdown().await?;
+//! ```
+//!
+//! To reload the gateway url, interval and grouping at runtime, e.g. from a config map, run it with `run_with_config` and a
+//! `tokio::sync::watch::Receiver<PusherConfig>`.
+//!
+//! For readiness probes or admin endpoints `last_push` returns a `tokio::sync::watch::Receiver` of the time, payload size
+//! and jobs of the most recent successful push.
+//!
+//! ## Migrating from 0.4
+//!
+//! - `PrometheusClientMetricsConverter` is no longer a unit struct, create it with `PrometheusClientMetricsConverter::new()`
+//!   instead of `PrometheusClientMetricsConverter`.
+//! - Implementations of the `Push` traits only implement `send`, which gets the `Method`, the optional body with its content
+//!   type and extra headers. `push_all`, `push_add` and `delete` are provided on top of it.
+//! - `PushMetricsError::Response` holds the status, url and body of the response instead of a `String`. Failed pushes are
+//!   reported as `BadRequest`, `Unauthorized`, `TooManyRequests` or `ServerError` for these status codes.
+//!
 //! ## Features
 //!
 //! - `default`: by default async functionality and no reqwest is enabled
@@ -216,24 +807,154 @@
 //! - `with_reqwest`: this feature enables the `non_blocking` feature as well as `reqwest` in minimal configuration and enables the alredy implemented `PushClient`
 //! - `with_reqwest_blocking`: like `with_reqwest` but including `blocking` instead of `non_blocking`
 //! - `prometheus_crate`: enables the functionality of the [prometheus](https://crates.io/crates/prometheus) crate
+//! - `prometheus_crate_text`: like `prometheus_crate` but without the `protobuf` feature of the prometheus crate, pushes the text format
 //! - `prometheus_client_crate`: enables the functionality of the [prometheus-client](https://crates.io/crates/prometheus-client) crate
+//! - `utf8_label_names`: accepts grouping label names outside of `[a-zA-Z_][a-zA-Z0-9_]*` for pushgateways running in UTF-8 mode
+//! - `cli`: builds the `push-metrics` binary that pushes metrics in the text format from stdin or a file
+//! - `clap_args`: enables `PusherArgs` with the pushgateway flags (url, job, labels, interval and auth) to flatten into your own `clap` CLI and create a `MetricsPusher` from
+//! - `textfile`: enables the `TextfilePusher` that pushes the `*.prom` files of a node_exporter textfile-collector directory
+//! - `scrape`: enables the `ScrapePusher` that scrapes a `/metrics` endpoint of any exporter and forwards it to the pushgateway
+//! - `rustls_tls`: enables `with_reqwest_rustls` and adds private root certificates and certificate pinning to the `PushClient` builders (see [here](#7-my-pushgateway-uses-a-private-ca))
+//! - `socks`: enables pushing through a SOCKS5 proxy with the `PushClient` builder, includes `rustls_tls` as `reqwest` connects to SOCKS proxies only with a TLS backend
+//! - `with_reqwest_rustls`: lets the `PushClient`s connect via HTTPS with `rustls` and the webpki root certificates
+//! - `with_reqwest_rustls_native_roots`: like `with_reqwest_rustls` but trusts the root certificates of the operating system
+//! - `with_reqwest_native_tls`: lets the `PushClient`s connect via HTTPS with the TLS library of the operating system, e.g. openssl on Linux.
+//!   If a `rustls` feature is enabled as well, `rustls` is used
+//! - `kubernetes`: enables `Grouping::from_kubernetes` that labels pushes with the `pod`, `namespace` and `node` of the pod they are pushed from
+//! - `health_check`: enables `GatewaySet::run_health_checks` that evicts pushgateways of a `GatewayPool` while their `/-/ready` endpoint fails
+//! - `opentelemetry`: enables `otel::job_and_grouping` that maps the identity of an OpenTelemetry `Resource` to job and grouping labels
+//! - `periodic`: enables the `PeriodicPusher` that gathers and pushes metrics on an interval and a final time on shutdown (in the background with `spawn`)
+//! - `queue`: enables the `QueuedPusher` that pushes queued payloads in the background and coalesces consecutive adds of the same group
+//! - `srv`: enables the `SrvDiscovery` that keeps the gateways of a `GatewayPool` up to date with the DNS SRV records of your pushgateway service
+//! - `tower`: enables the `PushLayer` that pushes periodically while a tower based server (e.g. axum or hyper) is serving and a final time when it shut down (see [here](#8-i-want-to-push-from-my-axum-or-hyper-server))
+//! - `sync_handle`: enables the `SyncHandle` that pushes with the async `MetricsPusher` from synchronous code on a tokio runtime, so the blocking `reqwest` client is not needed
+//! - `async_handle`: enables the `AsyncHandle` that pushes with a blocking `MetricsPusher` from async code on tokio's blocking thread pool
+//! - `json_events`: makes the `PushEvent` of the observer serializable with `serde` and adds `PushEvent::to_json`
+//! - `janitor`: adds the `Janitor` that deletes the groups it pushed once they were not refreshed within a staleness window
+//! - `body_digest`: adds `with_body_digest` to attach a `Content-MD5` or `Digest` header with the digest of every pushed body
+//! - `derive`: adds `#[derive(Grouping)]` to turn a struct of string fields into grouping labels with `GroupingLabels::labels`
+//!   and the `#[push_metrics]` attribute that pushes the duration, outcome and invocation count of a function
+//! - `debug_sinks`: adds the `FilePushClient` and `StdoutPushClient` that write the pushes to files or stdout instead of sending them
+//! - `redact_regex`: adds `RedactingConverter::with_regex` to mask label values matching a regular expression
+//! - `retry`: adds the `Retry` push client wrapper that sends failed requests again with an exponential backoff, optionally limited by a shared `RetryBudget`
+//! - `named_pipe`: adds the `PipePushClient` that pushes over a Windows named pipe or any other local stream
+//! - `tracing_layer`: adds the `TracingPusher` with a `tracing` layer that pushes the number of events and spans by level and target
+//! - `compress_gzip`: adds `Compression::with_gzip_above` to compress payloads above a size threshold with gzip
+//! - `compress_zstd`: adds `Compression::with_zstd_above` to compress payloads above a size threshold with zstd
+//! - `stream`: adds `push_all_stream` that pushes a `Stream` of metric families while they arrive
+//! - `remote_write`: enables the `RemoteWriteConverter` and `RemoteWriteClient` that push to Prometheus remote write receivers instead of a pushgateway (see [here](#push-to-a-remote-write-receiver))
+//! - `admin`: adds `status`, `metric_groups` and `wipe` for the query and admin api of the pushgateway (see [here](#query-and-wipe-your-pushgateway))
+//! - `with_hyper`: enables the `HyperPushClient` that pushes with the legacy client of `hyper-util` instead of `reqwest` (see [here](#push-with-hyper-instead-of-reqwest))
+//! - `with_ureq`: enables the blocking `UreqPushClient` that pushes with `ureq` instead of `reqwest::blocking` (see [here](#push-with-ureq-instead-of-reqwest))
 //!
 
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod accumulate;
+#[cfg(all(feature = "admin", any(feature = "blocking", feature = "non_blocking")))]
+pub mod admin;
+#[cfg(feature = "clap_args")]
+pub mod args;
+#[cfg(feature = "async_handle")]
+pub mod async_handle;
 #[cfg(feature = "blocking")]
 pub mod blocking;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod combinators;
+#[cfg(all(feature = "prometheus_crate_text", feature = "with_reqwest_blocking"))]
+pub mod compat;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod dead_letter;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod delta;
+#[cfg(feature = "blocking")]
+pub mod global;
+pub mod grouping;
+#[cfg(feature = "derive")]
+pub mod instrument;
+#[cfg(feature = "janitor")]
+pub mod janitor;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod label_policy;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod naming;
 #[cfg(feature = "non_blocking")]
 pub mod non_blocking;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+#[cfg(feature = "periodic")]
+pub mod periodic;
+#[cfg(all(
+    feature = "named_pipe",
+    any(feature = "blocking", feature = "non_blocking")
+))]
+pub mod pipe;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod pool;
 #[cfg(feature = "prometheus_client_crate")]
 pub mod prometheus_client_crate;
-#[cfg(feature = "prometheus_crate")]
+#[cfg(feature = "prometheus_crate_text")]
 pub mod prometheus_crate;
+#[cfg(feature = "queue")]
+pub mod queue;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod raw;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod rebucket;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod redact;
+#[cfg(feature = "remote_write")]
+pub mod remote_write;
+#[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
+pub mod response;
+#[cfg(all(feature = "retry", any(feature = "blocking", feature = "non_blocking")))]
+pub mod retry;
+#[cfg(feature = "non_blocking")]
+pub mod scoped;
+#[cfg(feature = "scrape")]
+pub mod scrape;
+#[cfg(all(
+    feature = "debug_sinks",
+    any(feature = "blocking", feature = "non_blocking")
+))]
+pub mod sink;
+#[cfg(feature = "srv")]
+pub mod srv;
+#[cfg(feature = "sync_handle")]
+pub mod sync_handle;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub mod tenant;
+#[cfg(feature = "textfile")]
+pub mod textfile;
+#[cfg(all(
+    feature = "rustls_tls",
+    any(feature = "with_reqwest", feature = "with_reqwest_blocking")
+))]
+mod tls;
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(feature = "tracing_layer")]
+pub mod tracing_layer;
+#[cfg(feature = "with_hyper")]
+pub mod with_hyper;
 #[cfg(feature = "with_reqwest")]
 pub mod with_reqwest;
 
 pub mod error;
+
+#[cfg(all(test, feature = "derive"))]
+extern crate self as prometheus_push;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+mod text_format;
 mod utils;
 
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use std::time::Duration;
+use std::time::SystemTime;
 
 use url::Url;
 
@@ -253,4 +974,479 @@ pub trait ConvertMetrics<MF, C, B> {
         grouping: &HashMap<&str, &str>,
         metrics: MF,
     ) -> Result<(Url, B, String)>;
+
+    /// fallback_push_details re-encodes a payload created by
+    /// [`ConvertMetrics::create_push_details`] in a format that more pushgateway clones accept,
+    /// e.g. the text format instead of protobuf. If the pushgateway rejects a push with
+    /// `400 Bad Request` or `415 Unsupported Media Type` it is retried once with this payload
+    /// and the pushgateway is remembered to only accept the fallback format. Returns `None` if
+    /// there is no fallback for the given content type, which is the default.
+    fn fallback_push_details(&self, _body: &B, _content_type: &str) -> Option<Result<(B, String)>> {
+        None
+    }
+}
+
+/// `ValidationMode` defines how [`non_blocking::MetricsPusher`] and [`blocking::MetricsPusher`]
+/// treat job names and grouping labels that do not follow the pushgateway's rules.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Rejects everything outside of the pushgateway's rules with an error.
+    #[default]
+    Strict,
+    /// Sanitizes where possible: characters of grouping label names outside of
+    /// `[a-zA-Z0-9_]` are replaced by '_' and a leading digit is prefixed with '_'.
+    Lenient,
+}
+
+/// `NonFinitePolicy` defines how [`non_blocking::MetricsPusher`] and
+/// [`blocking::MetricsPusher`] treat samples with a `NaN` or infinite value, see their
+/// `with_non_finite_policy`. The number of affected samples is returned in the [`PushReceipt`].
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Drops the affected samples and pushes the rest of their metric family.
+    DropSample,
+    /// Drops the whole metric family of an affected sample.
+    DropFamily,
+    /// Pushes `0` instead of the value of the affected samples.
+    ReplaceWithZero,
+    /// Rejects the push with an error.
+    Error,
+}
+
+/// `BodyDigest` is the digest of the encoded body that [`non_blocking::MetricsPusher`] and
+/// [`blocking::MetricsPusher`] attach to every push with their `with_body_digest`, e.g. for a
+/// proxy in front of the pushgateway that detects truncated uploads.
+#[cfg(all(
+    feature = "body_digest",
+    any(feature = "blocking", feature = "non_blocking")
+))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyDigest {
+    /// The base64 encoded MD5 digest in the `Content-MD5` header of RFC 1864.
+    ContentMd5,
+    /// The base64 encoded SHA-256 digest in the `Digest` header of RFC 3230, e.g.
+    /// `Digest: sha-256=<digest>`.
+    Sha256,
+}
+
+/// `ContentEncoding` is the `Content-Encoding` of a pushed payload, see [`Compression`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ContentEncoding {
+    /// The payload is sent uncompressed.
+    #[default]
+    Identity,
+    /// The payload is compressed with gzip, which the pushgateway accepts.
+    Gzip,
+    /// The payload is compressed with zstd, e.g. for a proxy in front of the pushgateway that
+    /// decompresses it.
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Returns the value of the `Content-Encoding` header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Identity => "identity",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+}
+
+/// `Compression` selects the [`ContentEncoding`] of the payloads of
+/// [`non_blocking::MetricsPusher`] and [`blocking::MetricsPusher`] by their size, see their
+/// `with_compression`. Small payloads are not worth the cpu time and stay uncompressed, larger
+/// ones are compressed with the encoding of the highest threshold they exceed. A compressed
+/// payload that is not smaller than the original is sent uncompressed.
+#[cfg(all(
+    any(feature = "compress_gzip", feature = "compress_zstd"),
+    any(feature = "blocking", feature = "non_blocking")
+))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Compression {
+    thresholds: Vec<(usize, ContentEncoding)>,
+}
+
+#[cfg(all(
+    any(feature = "compress_gzip", feature = "compress_zstd"),
+    any(feature = "blocking", feature = "non_blocking")
+))]
+impl Compression {
+    /// Creates a new [`Compression`] that sends every payload uncompressed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compresses payloads above the given size in bytes with gzip.
+    #[cfg(feature = "compress_gzip")]
+    pub fn with_gzip_above(self, threshold: usize) -> Self {
+        self.with_encoding_above(threshold, ContentEncoding::Gzip)
+    }
+
+    /// Compresses payloads above the given size in bytes with zstd, which compresses large
+    /// payloads faster and smaller than gzip. The pushgateway itself only accepts gzip, so it
+    /// needs a proxy in front of it that decompresses zstd.
+    #[cfg(feature = "compress_zstd")]
+    pub fn with_zstd_above(self, threshold: usize) -> Self {
+        self.with_encoding_above(threshold, ContentEncoding::Zstd)
+    }
+
+    /// Returns the encoding for a payload of the given size.
+    pub(crate) fn select(&self, size: usize) -> ContentEncoding {
+        self.thresholds
+            .iter()
+            .filter(|(threshold, _)| size > *threshold)
+            .max_by_key(|(threshold, _)| *threshold)
+            .map_or(ContentEncoding::Identity, |(_, encoding)| *encoding)
+    }
+
+    fn with_encoding_above(mut self, threshold: usize, encoding: ContentEncoding) -> Self {
+        self.thresholds
+            .retain(|(_, configured)| *configured != encoding);
+        self.thresholds.push((threshold, encoding));
+        self
+    }
+}
+
+/// `UrlJoin` defines how the path of your pushgateway's url is combined with the
+/// `metrics/job/` path of the pushgateway api.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UrlJoin {
+    /// Treats the url as a directory, so `https://host/push` becomes
+    /// `https://host/push/metrics/job/`.
+    #[default]
+    Directory,
+    /// Resolves the path relative to the url as described in RFC 3986, which drops the last
+    /// path segment if the url lacks a trailing slash, so `https://host/push` becomes
+    /// `https://host/metrics/job/`. This was the behavior before [`UrlJoin`] was introduced.
+    Relative,
+}
+
+/// `Method` is the http method of a request to the pushgateway, see `send` of
+/// [`non_blocking::Push`] and [`blocking::Push`].
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "json_events",
+    derive(serde::Serialize),
+    serde(rename_all = "UPPERCASE")
+)]
+pub enum Method {
+    /// Replaces all metrics of the group, sent by `push_all`.
+    Put,
+    /// Replaces only the metrics with the same name within the group, sent by `push_add`.
+    Post,
+    /// Deletes the group, sent by `delete`.
+    Delete,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl Method {
+    /// Returns the name of the method, e.g. `PUT`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Put => "PUT",
+            Method::Post => "POST",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+#[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
+impl From<Method> for reqwest::Method {
+    fn from(method: Method) -> Self {
+        match method {
+            Method::Put => reqwest::Method::PUT,
+            Method::Post => reqwest::Method::POST,
+            Method::Delete => reqwest::Method::DELETE,
+        }
+    }
+}
+
+/// `PushReceipt` is returned by every successful push or delete of
+/// [`non_blocking::MetricsPusher`] and [`blocking::MetricsPusher`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PushReceipt {
+    url: Url,
+    request_id: Option<String>,
+    payload_size: Option<usize>,
+    non_finite_samples: usize,
+    content_encoding: ContentEncoding,
+    tenant: Option<String>,
+    skipped: bool,
+}
+
+impl PushReceipt {
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub(crate) fn new(url: Url, request_id: Option<String>) -> Self {
+        Self {
+            url,
+            request_id,
+            payload_size: None,
+            non_finite_samples: 0,
+            content_encoding: ContentEncoding::Identity,
+            tenant: None,
+            skipped: false,
+        }
+    }
+
+    /// Creates the receipt of a push or delete that was skipped because the pusher is
+    /// disabled, nothing changed or the push was deferred.
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub(crate) fn skipped(url: Url) -> Self {
+        Self { skipped: true, ..Self::new(url, None) }
+    }
+
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub(crate) fn with_payload_size(mut self, payload_size: Option<usize>) -> Self {
+        self.payload_size = payload_size;
+        self
+    }
+
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub(crate) fn with_non_finite_samples(mut self, non_finite_samples: usize) -> Self {
+        self.non_finite_samples = non_finite_samples;
+        self
+    }
+
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub(crate) fn with_content_encoding(mut self, content_encoding: ContentEncoding) -> Self {
+        self.content_encoding = content_encoding;
+        self
+    }
+
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub(crate) fn with_tenant(mut self, tenant: Option<&tenant::Tenant>) -> Self {
+        self.tenant = tenant.map(|tenant| tenant.id().to_owned());
+        self
+    }
+
+    /// Returns the url the metrics were pushed to.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the request id that was sent with the push if a [`RequestId`] is configured.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Returns the size of the pushed payload in bytes if the pusher measures it, see
+    /// `with_payload_size_warning` of [`non_blocking::MetricsPusher`] and
+    /// [`blocking::MetricsPusher`].
+    pub fn payload_size(&self) -> Option<usize> {
+        self.payload_size
+    }
+
+    /// Returns the number of samples with a `NaN` or infinite value that were dropped or
+    /// replaced according to the [`NonFinitePolicy`] of the pusher.
+    pub fn non_finite_samples(&self) -> usize {
+        self.non_finite_samples
+    }
+
+    /// Returns the `Content-Encoding` the payload was sent with, see the [`Compression`] set
+    /// with `with_compression` of [`non_blocking::MetricsPusher`] and
+    /// [`blocking::MetricsPusher`].
+    pub fn content_encoding(&self) -> ContentEncoding {
+        self.content_encoding
+    }
+
+    /// Returns the identifier of the tenant the push or delete was routed to, see
+    /// `with_tenant_resolver` of [`non_blocking::MetricsPusher`] and
+    /// [`blocking::MetricsPusher`].
+    pub fn tenant(&self) -> Option<&str> {
+        self.tenant.as_deref()
+    }
+
+    /// Returns whether nothing was sent because the pusher is disabled, nothing changed or the
+    /// push was deferred, see `disable`, `push_changed` and `with_min_interval` of
+    /// [`non_blocking::MetricsPusher`] and [`blocking::MetricsPusher`].
+    pub fn is_skipped(&self) -> bool {
+        self.skipped
+    }
+}
+
+/// `PushEvent` describes a finished request of a [`non_blocking::MetricsPusher`] or
+/// [`blocking::MetricsPusher`] for the observer set with their `with_observer`, e.g. to feed a
+/// log pipeline. With the `json_events` feature it is serializable with `serde`.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json_events", derive(serde::Serialize))]
+pub struct PushEvent {
+    pub job: String,
+    pub grouping: BTreeMap<String, String>,
+    pub endpoint: String,
+    pub method: Method,
+    /// Whether the pushgateway accepted the request.
+    pub success: bool,
+    /// The status code the pushgateway rejected the request with, if it is known.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    #[cfg_attr(
+        feature = "json_events",
+        serde(rename = "duration_ms", serialize_with = "utils::serialize_millis")
+    )]
+    pub duration: Duration,
+    /// The size of the sent body in bytes, `None` for deletes.
+    pub bytes: Option<usize>,
+    /// The number of times the request was sent again, e.g. in the fallback format.
+    pub retries: u32,
+}
+
+#[cfg(all(
+    feature = "json_events",
+    any(feature = "blocking", feature = "non_blocking")
+))]
+impl PushEvent {
+    /// Serializes the event to a single line of JSON, e.g. to log it as is.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("push events serialize to JSON")
+    }
+}
+
+/// `NoopPusher` implements [`non_blocking::PushMetrics`] and [`blocking::PushMetrics`] without
+/// pushing anything, e.g. to test code that accepts any pusher. Job names and grouping labels
+/// are validated like for a real push and the receipt contains the url it would have been
+/// pushed to.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Debug)]
+pub struct NoopPusher {
+    url: Url,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl NoopPusher {
+    /// Creates a new [`NoopPusher`] that pretends to push to the given pushgateway url.
+    pub fn new(url: &Url) -> Result<Self> {
+        Ok(Self { url: utils::create_metrics_job_url(url, UrlJoin::default())? })
+    }
+
+    pub(crate) fn receipt(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        let url = utils::build_url(&self.url, job, grouping)?;
+        Ok(PushReceipt::new(url, None))
+    }
+}
+
+/// `NoopPushClient` implements [`non_blocking::Push`] and [`blocking::Push`] without sending
+/// anything, so a [`non_blocking::MetricsPusher`] or [`blocking::MetricsPusher`] can be wired
+/// up as usual but every push and delete succeeds without a pushgateway, e.g. in local
+/// development or tests.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopPushClient;
+
+/// `PushTimes` holds the times of the last successful and the last failed push of a group as
+/// reported by the pushgateway's `push_time_seconds` and `push_failure_time_seconds` metrics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PushTimes {
+    pub(crate) last_push: Option<SystemTime>,
+    pub(crate) last_failure: Option<SystemTime>,
+}
+
+impl PushTimes {
+    /// Returns the time of the last successful push, if there was one.
+    pub fn last_push(&self) -> Option<SystemTime> {
+        self.last_push
+    }
+
+    /// Returns the time of the last failed push, if there was one.
+    pub fn last_failure(&self) -> Option<SystemTime> {
+        self.last_failure
+    }
+}
+
+/// `RequestId` configures a request id that is sent as header with every push, so failed
+/// pushes can be correlated with the logs of the pushgateway or a proxy in front of it.
+///
+/// By default a unique id is generated per push and sent as `X-Request-Id` header. Use
+/// [`RequestId::with_generator`] to send ids of your own, e.g. the id of the current trace.
+#[derive(Clone)]
+pub struct RequestId {
+    header: String,
+    generator: Arc<dyn Fn() -> String + Send + Sync>,
+}
+
+impl RequestId {
+    /// Creates a new [`RequestId`] that sends a generated id with the given header.
+    pub fn new(header: &str) -> Self {
+        Self {
+            header: header.to_owned(),
+            generator: Arc::new(utils::generate_request_id),
+        }
+    }
+
+    /// Sets the function that provides the request id for every push.
+    pub fn with_generator(
+        mut self,
+        generator: impl Fn() -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.generator = Arc::new(generator);
+        self
+    }
+
+    /// Returns the name of the header the request id is sent with.
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+    pub(crate) fn generate(&self) -> (&str, String) {
+        (&self.header, (self.generator)())
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new("X-Request-Id")
+    }
+}
+
+impl fmt::Debug for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestId")
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+/// `Auth` defines the credentials a `PushClient` of `with_reqwest` or `with_reqwest_blocking`
+/// sends with every request, e.g. to pass a reverse proxy in front of the pushgateway.
+#[cfg(any(
+    feature = "with_reqwest",
+    feature = "with_reqwest_blocking",
+    feature = "clap_args"
+))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum Auth {
+    /// HTTP basic authentication with a username and an optional password.
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    /// A bearer token sent in the `Authorization` header.
+    Bearer(String),
+    /// A custom header, e.g. an API key of a gateway that does not use `Authorization`.
+    Header { name: String, value: String },
+}
+
+#[cfg(any(
+    feature = "with_reqwest",
+    feature = "with_reqwest_blocking",
+    feature = "clap_args"
+))]
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Auth::Bearer(_) => f.debug_tuple("Bearer").finish_non_exhaustive(),
+            Auth::Header { name, .. } => f
+                .debug_struct("Header")
+                .field("name", name)
+                .finish_non_exhaustive(),
+        }
+    }
 }
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::text_format;
+use crate::text_format::MetricFamily;
+use crate::utils::text_payload;
+use crate::ConvertMetrics;
+
+const TOTAL: &str = "_total";
+
+/// Units that are not the base unit of their quantity, with their base unit and the factor
+/// that converts them to it.
+const NON_BASE_UNITS: [(&str, &str, f64); 8] = [
+    ("nanoseconds", "seconds", 1e-9),
+    ("microseconds", "seconds", 1e-6),
+    ("milliseconds", "seconds", 1e-3),
+    ("minutes", "seconds", 60.0),
+    ("hours", "seconds", 3600.0),
+    ("kilobytes", "bytes", 1e3),
+    ("megabytes", "bytes", 1e6),
+    ("gigabytes", "bytes", 1e9),
+];
+
+/// Sample suffixes whose values count observations or are timestamps and keep their value if
+/// the unit changes.
+const COUNT_SUFFIXES: [&str; 5] = ["_bucket", "_gbucket", "_count", "_gcount", "_created"];
+
+/// `NamingMode` defines what [`NamingConverter`] does with metric names that do not follow the
+/// OpenMetrics conventions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NamingMode {
+    /// Renames the metrics and converts their values to base units.
+    Fix,
+    /// Rejects the push with [`PushMetricsError::NonConformingName`].
+    Error,
+}
+
+/// `NamingConverter` wraps a [`ConvertMetrics`] implementation and checks the metric names of
+/// every payload against the OpenMetrics conventions before it is pushed, for pushgateways
+/// that reject non-conforming names with errors that are hard to trace back.
+///
+/// Counter samples have to end with `_total` and no other samples may, names use base units
+/// like `_seconds` and `_bytes` instead of e.g. `_milliseconds` or `_megabytes`, and a family
+/// with a `# UNIT` ends with it. Violations are fixed or reject the push, see
+/// [`NamingConverter::with_mode`]. The wrapped converter has to encode in the text format.
+#[derive(Debug)]
+pub struct NamingConverter<CM> {
+    converter: CM,
+    mode: NamingMode,
+}
+
+impl<CM> NamingConverter<CM> {
+    /// Creates a new [`NamingConverter`] that fixes the names of the given converter's metrics.
+    pub fn new(converter: CM) -> Self {
+        Self { converter, mode: NamingMode::Fix }
+    }
+
+    /// Sets what happens to names that violate the conventions, defaults to
+    /// [`NamingMode::Fix`].
+    pub fn with_mode(mut self, mode: NamingMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Normalizes the names of the given family, where counters are named like their samples
+    /// unless the payload is in the OpenMetrics format.
+    fn normalize(&self, family: &mut MetricFamily, openmetrics: bool) -> Result<()> {
+        let counter = family.kind.as_deref() == Some("counter");
+        let original = family
+            .name
+            .strip_suffix(TOTAL)
+            .unwrap_or(&family.name)
+            .to_owned();
+        let mut violations = Vec::new();
+
+        let mut name = original.clone();
+        if !counter && family.name.ends_with(TOTAL) {
+            violations.push(format!("only counters may end with '{TOTAL}'"));
+        }
+
+        let mut factor = None;
+        if let Some((unit, base_unit, unit_factor)) = non_base_unit(&name) {
+            violations.push(format!("uses the unit '{unit}' instead of '{base_unit}'"));
+            name = format!("{}{base_unit}", &name[..name.len() - unit.len()]);
+            factor = Some(unit_factor);
+        }
+
+        if let Some(unit) = &mut family.unit {
+            if let Some((_, base_unit, unit_factor)) = NON_BASE_UNITS
+                .iter()
+                .find(|(non_base, ..)| non_base == unit)
+            {
+                violations.push(format!(
+                    "declares the unit '{unit}' instead of '{base_unit}'"
+                ));
+                *unit = base_unit.to_string();
+                factor = factor.or(Some(*unit_factor));
+            }
+            if !unit.is_empty() && !name.ends_with(&format!("_{unit}")) {
+                violations.push(format!("does not end with its unit '{unit}'"));
+                name = format!("{name}_{unit}");
+            }
+        }
+
+        for sample in &mut family.samples {
+            let mut suffix = sample.name.strip_prefix(&original).unwrap_or_default();
+            match counter {
+                true if suffix.is_empty() => {
+                    violations.push(format!("counter samples have to end with '{TOTAL}'"));
+                    suffix = TOTAL;
+                }
+                false if suffix == TOTAL => suffix = "",
+                _ => {}
+            }
+
+            if let Some(factor) = factor {
+                if !COUNT_SUFFIXES.contains(&suffix) {
+                    sample.value *= factor;
+                }
+                for (label, value) in &mut sample.labels {
+                    match value.parse::<f64>() {
+                        Ok(bound) if label == "le" && bound.is_finite() => {
+                            *value = (bound * factor).to_string()
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            sample.name = format!("{name}{suffix}");
+        }
+
+        if let Some(violation) = violations.into_iter().next() {
+            if self.mode == NamingMode::Error {
+                return Err(PushMetricsError::NonConformingName {
+                    metric: family.name.clone(),
+                    reason: violation,
+                });
+            }
+            #[cfg(feature = "log")]
+            log::debug!("Renaming metric {} as it {violation}", family.name);
+        }
+
+        family.name = match counter && !openmetrics {
+            true => format!("{name}{TOTAL}"),
+            false => name,
+        };
+        Ok(())
+    }
+}
+
+/// Returns the non-base unit the given name ends with, with its base unit and factor.
+fn non_base_unit(name: &str) -> Option<(&'static str, &'static str, f64)> {
+    NON_BASE_UNITS.iter().copied().find(|(unit, ..)| {
+        name.strip_suffix(unit)
+            .is_some_and(|stem| stem.ends_with('_'))
+    })
+}
+
+impl<CM, MF, C, B> ConvertMetrics<MF, C, B> for NamingConverter<CM>
+where
+    CM: ConvertMetrics<MF, C, B>,
+    B: AsRef<[u8]> + From<String>,
+{
+    fn metrics_from(&self, collectors: C) -> Result<MF> {
+        self.converter.metrics_from(collectors)
+    }
+
+    fn create_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<(Url, B, String)> {
+        let (url, body, content_type) =
+            self.converter
+                .create_push_details(job, url, grouping, metric_families)?;
+        let mut families = text_format::parse(&text_payload(body.as_ref(), &content_type)?)?;
+
+        let openmetrics = content_type.starts_with("application/openmetrics-text");
+        for family in &mut families {
+            self.normalize(family, openmetrics)?;
+        }
+
+        let body = B::from(text_format::encode_as(&families, &content_type));
+        Ok((url, body, content_type))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use url::Url;
+
+    use crate::error::PushMetricsError;
+    use crate::naming::NamingConverter;
+    use crate::naming::NamingMode;
+    use crate::raw::RawMetricsConverter;
+    use crate::ConvertMetrics;
+
+    fn convert(
+        converter: &NamingConverter<RawMetricsConverter>,
+        payload: &str,
+    ) -> Result<String, PushMetricsError> {
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let (_, body, _) =
+            converter.create_push_details("worker", &url, &HashMap::new(), payload.to_owned())?;
+        Ok(String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn test_names_are_fixed() {
+        // Given I have a converter that fixes names
+        let converter = NamingConverter::new(RawMetricsConverter::text());
+
+        // When I convert a counter without _total, a gauge with it and a histogram in milliseconds
+        let payload = convert(
+            &converter,
+            "# TYPE jobs counter\njobs 3\n\
+             # TYPE queue_total gauge\nqueue_total 2\n\
+             # TYPE latency_milliseconds histogram\n\
+             latency_milliseconds_bucket{le=\"500\"} 1\n\
+             latency_milliseconds_bucket{le=\"+Inf\"} 2\n\
+             latency_milliseconds_sum 1500\n\
+             latency_milliseconds_count 2\n",
+        )
+        .unwrap();
+
+        // Then the counter ends with _total, the gauge does not and the histogram is in seconds
+        assert_eq!(
+            payload,
+            "# TYPE jobs_total counter\njobs_total 3\n\
+             # TYPE queue gauge\nqueue 2\n\
+             # TYPE latency_seconds histogram\n\
+             latency_seconds_bucket{le=\"0.5\"} 1\n\
+             latency_seconds_bucket{le=\"+Inf\"} 2\n\
+             latency_seconds_sum 1.5\n\
+             latency_seconds_count 2\n"
+        );
+    }
+
+    #[test]
+    fn test_conforming_names_are_kept() {
+        // Given I have a converter that fixes names
+        let converter = NamingConverter::new(RawMetricsConverter::text());
+        let conforming = "# TYPE jobs_total counter\njobs_total 3\n\
+                          # TYPE size_bytes gauge\nsize_bytes 2\n";
+
+        // When I convert metrics that follow the conventions
+        let payload = convert(&converter, conforming).unwrap();
+
+        // Then they are pushed unchanged
+        assert_eq!(payload, conforming);
+    }
+
+    #[test]
+    fn test_violations_reject_the_push() {
+        // Given I have a converter that rejects non-conforming names
+        let converter =
+            NamingConverter::new(RawMetricsConverter::text()).with_mode(NamingMode::Error);
+
+        // When I convert a gauge in megabytes
+        let result = convert(
+            &converter,
+            "# TYPE heap_megabytes gauge\nheap_megabytes 2\n",
+        );
+
+        // Then the push is rejected with the metric and the reason
+        assert!(matches!(
+            result,
+            Err(PushMetricsError::NonConformingName { metric, reason })
+                if metric == "heap_megabytes" && reason == "uses the unit 'megabytes' instead of 'bytes'"
+        ));
+    }
+}
@@ -1,12 +1,79 @@
 use std::collections::HashMap;
 use std::future::Future;
+#[cfg(feature = "stream")]
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+#[cfg(feature = "stream")]
+use std::task::Context;
+#[cfg(feature = "stream")]
+use std::task::Poll;
+use std::time::Duration;
 
 use url::Url;
 
+#[cfg(feature = "admin")]
+use crate::admin;
+#[cfg(feature = "admin")]
+use crate::admin::MetricGroup;
+#[cfg(feature = "admin")]
+use crate::admin::PushgatewayStatus;
+use crate::error::PushMetricsError;
 use crate::error::Result;
+use crate::grouping::JobName;
+#[cfg(doc)]
+use crate::raw::RawMetricsConverter;
+use crate::tenant::with_tenant_header;
+use crate::tenant::Tenant;
+use crate::tenant::TenantResolver;
+use crate::tenant::TenantRouter;
+use crate::text_format;
+use crate::utils::borrow_grouping;
+use crate::utils::build_url;
 use crate::utils::create_metrics_job_url;
+use crate::utils::into_receipt;
+use crate::utils::merge_grouping;
+use crate::utils::negotiate;
+use crate::utils::rejects_format;
+use crate::utils::sanitize_grouping;
+use crate::utils::text_payload;
+#[cfg(any(
+    feature = "body_digest",
+    feature = "compress_gzip",
+    feature = "compress_zstd"
+))]
+use crate::utils::with_header;
+#[cfg(feature = "body_digest")]
+use crate::utils::BodyDigester;
+#[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+use crate::utils::Compressor;
+use crate::utils::Deferred;
+use crate::utils::Format;
+use crate::utils::FormatCache;
+use crate::utils::NonFiniteFilter;
+use crate::utils::PayloadSizeLimit;
+use crate::utils::PayloadSizeWarning;
+use crate::utils::PushObserver;
 use crate::utils::PushType;
+use crate::utils::PushedFamilies;
+use crate::utils::Throttle;
+use crate::utils::CONTENT_TYPE_TEXT;
+#[cfg(feature = "body_digest")]
+use crate::BodyDigest;
+#[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+use crate::Compression;
+use crate::ContentEncoding;
 use crate::ConvertMetrics;
+use crate::Method;
+use crate::NonFinitePolicy;
+use crate::NoopPushClient;
+use crate::NoopPusher;
+use crate::PushEvent;
+use crate::PushReceipt;
+use crate::PushTimes;
+use crate::RequestId;
+use crate::UrlJoin;
+use crate::ValidationMode;
 
 /// `MetricsPusher` is a prometheus pushgateway client that holds information about the
 /// address of your pushgateway instance and the [`Push`] client that is used to push
@@ -20,22 +87,116 @@ where
 {
     push_client: P,
     metrics_converter: CM,
+    gateway_url: Url,
     url: Url,
+    url_join: UrlJoin,
+    validation_mode: ValidationMode,
+    default_job: Option<JobName>,
+    default_grouping: HashMap<String, String>,
+    enabled: AtomicBool,
+    request_id: Option<RequestId>,
+    payload_size_warning: Option<PayloadSizeWarning<B>>,
+    payload_size_limit: Option<PayloadSizeLimit<B>>,
+    non_finite: Option<NonFiniteFilter<B>>,
+    #[cfg(feature = "body_digest")]
+    body_digest: Option<BodyDigester<B>>,
+    #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+    compression: Option<Compressor<B>>,
+    observer: Option<PushObserver<B>>,
+    formats: FormatCache,
+    pushed_families: PushedFamilies,
+    throttle: Option<Throttle<B>>,
+    tenants: Option<TenantRouter>,
     mf: std::marker::PhantomData<MF>,
     c: std::marker::PhantomData<C>,
     b: std::marker::PhantomData<B>,
 }
 
 /// `Push` is a trait that defines the interface for the implementation of your own http
-/// client of choice.
+/// client of choice. Only [`Push::send`] has to be implemented, the other methods send the
+/// requests of the pushgateway api with it.
 pub trait Push<B> {
+    /// Sends a request with the given method, body with its content type and headers to the
+    /// given url. Fails if the request fails or the pushgateway does not answer with a success
+    /// status code. The body is `None` for [`Method::Delete`].
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(B, &str)>,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Pushes the body with a `PUT` request.
     fn push_all(
         &self,
         url: &Url,
         body: B,
         content_type: &str,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<()>> + Send {
+        self.send(Method::Put, url, Some((body, content_type)), headers)
+    }
+
+    /// Pushes the body with a `POST` request.
+    fn push_add(
+        &self,
+        url: &Url,
+        body: B,
+        content_type: &str,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<()>> + Send {
+        self.send(Method::Post, url, Some((body, content_type)), headers)
+    }
+
+    /// Deletes the group with a `DELETE` request.
+    fn delete(
+        &self,
+        url: &Url,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<()>> + Send {
+        self.send(Method::Delete, url, None, headers)
+    }
+}
+
+/// `Fetch` is a trait that defines the interface for reading from an http endpoint with
+/// your own http client of choice, e.g. to scrape a local `/metrics` endpoint.
+pub trait Fetch {
+    fn fetch(&self, url: &Url) -> impl Future<Output = Result<String>> + Send;
+}
+
+/// `PushStream` is a trait for [`Push`] clients that can send a body while it is still being
+/// produced, see [`MetricsPusher::push_all_stream`].
+#[cfg(feature = "stream")]
+pub trait PushStream {
+    /// Sends a request with the given method, streamed body with its content type and headers
+    /// to the given url. Fails like [`Push::send`] or if the body stream ends with an error.
+    fn send_stream(
+        &self,
+        method: Method,
+        url: &Url,
+        body: BodyStream,
+        content_type: &str,
+        headers: &[(&str, &str)],
     ) -> impl Future<Output = Result<()>> + Send;
-    fn push_add(&self, url: &Url, body: B, content_type: &str) -> impl Future<Output = Result<()>>;
+}
+
+/// `BodyStream` is the [`Stream`](futures_core::Stream) of encoded chunks that
+/// [`MetricsPusher::push_all_stream`] passes to a [`PushStream`] client. If a chunk cannot be
+/// encoded the stream ends with an error, so the request is aborted.
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct BodyStream {
+    chunks: tokio::sync::mpsc::Receiver<Result<Vec<u8>>>,
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for BodyStream {
+    type Item = Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.chunks.poll_recv(cx)
+    }
 }
 
 impl<P, CM, MF, C, B> MetricsPusher<P, CM, MF, C, B>
@@ -44,26 +205,237 @@ where
     CM: ConvertMetrics<MF, C, B>,
 {
     /// Creates a new [`MetricsPusher`] with the given [`Push`] client, [`ConvertMetrics`]
-    /// implementation and the url of your pushgateway instance.
+    /// implementation and the url of your pushgateway instance. The path of the url is kept,
+    /// see [`UrlJoin`].
     pub fn new(
         push_client: P,
         metrics_converter: CM,
         url: &Url,
     ) -> Result<MetricsPusher<P, CM, MF, C, B>> {
-        let url = create_metrics_job_url(url)?;
+        let gateway_url = url.clone();
+        let url = create_metrics_job_url(url, UrlJoin::default())?;
         Ok(Self {
             push_client,
             metrics_converter,
+            gateway_url,
             url,
+            url_join: UrlJoin::default(),
+            validation_mode: ValidationMode::default(),
+            default_job: None,
+            default_grouping: HashMap::new(),
+            enabled: AtomicBool::new(true),
+            request_id: None,
+            payload_size_warning: None,
+            payload_size_limit: None,
+            non_finite: None,
+            #[cfg(feature = "body_digest")]
+            body_digest: None,
+            #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+            compression: None,
+            observer: None,
+            formats: FormatCache::default(),
+            pushed_families: PushedFamilies::default(),
+            throttle: None,
+            tenants: None,
             mf: std::marker::PhantomData,
             c: std::marker::PhantomData,
             b: std::marker::PhantomData,
         })
     }
 
+    /// Sets how the url of your pushgateway instance is joined with the pushgateway api path.
+    /// Defaults to [`UrlJoin::Directory`] which keeps the complete path of the url.
+    pub fn with_url_join(mut self, url_join: UrlJoin) -> Result<Self> {
+        self.url = create_metrics_job_url(&self.gateway_url, url_join)?;
+        self.url_join = url_join;
+        Ok(self)
+    }
+
+    /// Replaces the url of the pushgateway instance, keeping the configured [`UrlJoin`].
+    #[cfg(feature = "periodic")]
+    pub(crate) fn set_gateway_url(&mut self, url: &Url) -> Result<()> {
+        self.url = create_metrics_job_url(url, self.url_join)?;
+        self.gateway_url = url.clone();
+        Ok(())
+    }
+
+    /// Sets the [`ValidationMode`] that is applied to the job name and grouping labels of
+    /// every push. Defaults to [`ValidationMode::Strict`].
+    pub fn with_validation_mode(mut self, validation_mode: ValidationMode) -> Self {
+        self.validation_mode = validation_mode;
+        self
+    }
+
+    /// Sets the job name that is used by [`MetricsPusher::push_all_default`],
+    /// [`MetricsPusher::push_add_default`] and [`MetricsPusher::delete_default`], so services
+    /// that push with exactly one job do not have to pass it through to every call site.
+    pub fn with_default_job(mut self, job: JobName) -> Self {
+        self.default_job = Some(job);
+        self
+    }
+
+    /// Sets grouping labels that are merged into the grouping labels of every push and delete,
+    /// e.g. `instance` or `env`. Grouping labels given to a single call win over default
+    /// grouping labels with the same name.
+    pub fn with_default_grouping(mut self, grouping: &HashMap<&str, &str>) -> Self {
+        self.default_grouping = grouping
+            .iter()
+            .map(|(label_name, label_value)| (label_name.to_string(), label_value.to_string()))
+            .collect();
+        self
+    }
+
+    /// Disables the pusher at runtime, e.g. in local development or during an incident of the
+    /// pushgateway: until [`MetricsPusher::enable`] is called nothing is sent and every push and
+    /// delete returns a [`PushReceipt`] that [`is_skipped`](PushReceipt::is_skipped). Job
+    /// names, grouping labels and metrics are still validated and encoded.
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    /// Enables the pusher again after [`MetricsPusher::disable`].
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the pusher sends pushes, see [`MetricsPusher::disable`].
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Sends a request id with every push as configured by the given [`RequestId`]. The id is
+    /// logged and returned in the [`PushReceipt`], so failed pushes can be correlated with the
+    /// logs of the pushgateway.
+    pub fn with_request_id(mut self, request_id: RequestId) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    /// Measures every encoded payload, returns its size in the [`PushReceipt`] and logs a
+    /// warning if it exceeds the given threshold in bytes, so a cardinality explosion is
+    /// noticed before it overloads the pushgateway.
+    pub fn with_payload_size_warning(mut self, threshold: usize) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        self.payload_size_warning = Some(PayloadSizeWarning::new(threshold));
+        self
+    }
+
+    /// Rejects every encoded payload above the given size in bytes with
+    /// [`PushMetricsError::PayloadTooLarge`] before it is sent, e.g. to fail fast instead of
+    /// running into the request size limit of the pushgateway.
+    pub fn with_max_payload_size(mut self, limit: usize) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        self.payload_size_limit = Some(PayloadSizeLimit::new(limit));
+        self
+    }
+
+    /// Applies the given [`NonFinitePolicy`] to the samples with a `NaN` or infinite value of
+    /// every pushed payload, as pushgateways and their consumers may choke on them. The number
+    /// of affected samples is returned in the [`PushReceipt`]. Requires the metrics to be
+    /// encoded in the text format.
+    pub fn with_non_finite_policy(mut self, policy: NonFinitePolicy) -> Self
+    where
+        B: AsRef<[u8]> + From<String>,
+    {
+        self.non_finite = Some(NonFiniteFilter::new(policy));
+        self
+    }
+
+    /// Attaches the given [`BodyDigest`] of the encoded body to every push of metrics, so a
+    /// proxy in front of the pushgateway can detect truncated uploads.
+    #[cfg(feature = "body_digest")]
+    pub fn with_body_digest(mut self, digest: BodyDigest) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        self.body_digest = Some(BodyDigester::new(digest));
+        self
+    }
+
+    /// Compresses the payloads according to the given [`Compression`] and sends them with the
+    /// matching `Content-Encoding` header, which is returned in the [`PushReceipt`]. Payloads
+    /// below its thresholds are sent uncompressed.
+    #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+    pub fn with_compression(mut self, compression: Compression) -> Self
+    where
+        B: AsRef<[u8]> + From<Vec<u8>>,
+    {
+        self.compression = Some(Compressor::new(compression));
+        self
+    }
+
+    /// Passes a [`PushEvent`] with the job, grouping, endpoint, outcome, duration and size of
+    /// every sent request to the given observer, e.g. to log it in a structured way. Skipped
+    /// requests of a disabled pusher are not observed.
+    pub fn with_observer(mut self, observer: impl Fn(&PushEvent) + Send + Sync + 'static) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        self.observer = Some(PushObserver::new(observer));
+        self
+    }
+
+    /// Resolves the [`Tenant`] of every push and delete with the given [`TenantResolver`] when
+    /// it is sent and routes it with the header of the tenant to its endpoint, so one pusher
+    /// can push the metrics of several customers with the right isolation. The tenant is
+    /// returned in the [`PushReceipt`].
+    pub fn with_tenant_resolver(mut self, resolver: impl TenantResolver + 'static) -> Self {
+        self.tenants = Some(TenantRouter::new(resolver));
+        self
+    }
+
+    /// Pushes each group at most once per given interval, e.g. when pushes are triggered from a
+    /// hot path, to protect the pushgateway. A push that comes in too early is deferred and its
+    /// receipt `is_skipped`, a newer push of the group replaces it. Deferred payloads are pushed
+    /// with the next push of their group after the interval or by [`MetricsPusher::flush`],
+    /// unless the group is deleted or cleared in the meantime.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.throttle = Some(Throttle::new(interval));
+        self
+    }
+
+    /// Pushes the payloads that were deferred by [`MetricsPusher::with_min_interval`] right
+    /// away, e.g. before shutdown, and returns their results.
+    pub async fn flush(&self) -> Vec<Result<PushReceipt>> {
+        let Some(throttle) = &self.throttle else {
+            return Vec::new();
+        };
+
+        let mut receipts = Vec::new();
+        for deferred in throttle.take_deferred() {
+            let grouping = deferred
+                .grouping
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            let context = self.context(&deferred.job, &grouping);
+            let receipt = self
+                .transmit(
+                    &deferred.job,
+                    &grouping,
+                    deferred.url,
+                    deferred.body,
+                    &deferred.content_type,
+                    deferred.push_type,
+                )
+                .await
+                .map_err(context);
+            receipts.push(receipt);
+        }
+
+        receipts
+    }
+
     /// Pushes all metrics to your pushgateway instance.
     ///
-    /// Job name and grouping labels must not contain the character '/'.
+    /// Grouping label names must follow the Prometheus naming rules `[a-zA-Z_][a-zA-Z0-9_]*`.
+    /// Job names and label values with the character '/' as well as empty label values are
+    /// sent as base64 encoded path segments, other reserved url characters like '?', '#', '%'
+    /// or spaces are percent-encoded.
     ///
     /// As this method pushes all metrics to the pushgateway it replaces all previously
     /// pushed metrics with the same job and grouping labels.
@@ -72,7 +444,7 @@ where
         job: &str,
         grouping: &HashMap<&str, &str>,
         metric_families: MF,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         self.push(job, grouping, metric_families, PushType::All)
             .await
     }
@@ -80,24 +452,482 @@ where
     /// Pushes all metrics to your pushgateway instance with add logic. It will only replace
     /// recently pushed metrics with the same name and grouping labels.
     ///
-    /// Job name and grouping labels must not contain the character '/'.
+    /// Grouping label names must follow the Prometheus naming rules `[a-zA-Z_][a-zA-Z0-9_]*`.
+    /// Job names and label values with the character '/' as well as empty label values are
+    /// sent as base64 encoded path segments, other reserved url characters like '?', '#', '%'
+    /// or spaces are percent-encoded.
     pub async fn push_add(
         &self,
         job: &str,
         grouping: &HashMap<&str, &str>,
         metric_families: MF,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
+        self.push(job, grouping, metric_families, PushType::Add)
+            .await
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_all`] with the default job, see
+    /// [`MetricsPusher::with_default_job`]. Fails with [`PushMetricsError::MissingJob`] if no
+    /// default job is configured.
+    pub async fn push_all_default(
+        &self,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        let job = self.default_job()?;
+        self.push(job, grouping, metric_families, PushType::All)
+            .await
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_add`] with the default job, see
+    /// [`MetricsPusher::push_all_default`].
+    pub async fn push_add_default(
+        &self,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        let job = self.default_job()?;
         self.push(job, grouping, metric_families, PushType::Add)
             .await
     }
 
+    /// Pushes all metrics like [`MetricsPusher::push_all`] but gathers them with the given
+    /// function only right before they are pushed, so no stale pre-gathered metrics are pushed.
+    pub async fn push_all_with(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        gather: impl FnOnce() -> MF,
+    ) -> Result<PushReceipt> {
+        self.push(job, grouping, gather(), PushType::All).await
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_add`] but gathers them with the given
+    /// function only right before they are pushed, see [`MetricsPusher::push_all_with`].
+    pub async fn push_add_with(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        gather: impl FnOnce() -> MF,
+    ) -> Result<PushReceipt> {
+        self.push(job, grouping, gather(), PushType::Add).await
+    }
+
+    /// Pushes the metric families of the given stream like [`MetricsPusher::push_all`] while
+    /// they arrive: every item is encoded on its own and sent as the next chunk of the body, so
+    /// producers that generate metrics e.g. from a database scan do not have to hold all of them
+    /// in memory. The converter has to encode to a format that can be concatenated, like the text
+    /// format, delimited protobuf or OpenMetrics without `# EOF`.
+    ///
+    /// As the size of the payload is unknown until it is sent, the payload size limit and
+    /// warning, compression, body digest, non-finite policy, throttle and format fallback of the
+    /// pusher do not apply to streamed pushes.
+    #[cfg(feature = "stream")]
+    pub async fn push_all_stream<S, I>(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: S,
+    ) -> Result<PushReceipt>
+    where
+        P: PushStream,
+        S: futures_core::Stream<Item = I>,
+        MF: FromIterator<I>,
+        B: Into<Vec<u8>>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let sanitized_grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let (url, _, content_type) = self
+            .metrics_converter
+            .create_push_details(job, &self.url, sanitized_grouping, MF::from_iter([]))
+            .map_err(&context)?;
+        if !self.is_enabled() {
+            return Ok(PushReceipt::skipped(url));
+        }
+
+        let (sender, chunks) = tokio::sync::mpsc::channel(1);
+        let encode = async move {
+            let mut metric_families = std::pin::pin!(metric_families);
+            while let Some(item) =
+                std::future::poll_fn(|cx| metric_families.as_mut().poll_next(cx)).await
+            {
+                let encoded = self.metrics_converter.create_push_details(
+                    job,
+                    &self.url,
+                    sanitized_grouping,
+                    MF::from_iter([item]),
+                );
+                let chunk = match encoded {
+                    Ok((_, chunk, _)) => Ok(chunk.into()),
+                    Err(error) => {
+                        let aborted =
+                            std::io::Error::other("failed to encode the streamed metrics");
+                        let _ = sender.send(Err(PushMetricsError::Io(aborted))).await;
+                        return Err(error);
+                    }
+                };
+                if sender.send(chunk).await.is_err() {
+                    // The request ended early, its result tells why.
+                    break;
+                }
+            }
+
+            Ok(())
+        };
+
+        let (url, tenant) = self.route(job, grouping, &url).map_err(&context)?;
+        let request_id = self.request_id.as_ref().map(RequestId::generate);
+        let header = request_id.as_ref().map(|(name, id)| (*name, id.as_str()));
+        let headers = with_tenant_header(header.as_slice(), tenant.as_ref());
+        let send = self.push_client.send_stream(
+            Method::Put,
+            &url,
+            BodyStream { chunks },
+            &content_type,
+            &headers,
+        );
+
+        let (encoded, result) = tokio::join!(encode, send);
+        encoded.map_err(&context)?;
+        into_receipt(result, url, request_id)
+            .map(|receipt| receipt.with_tenant(tenant.as_ref()))
+            .map_err(context)
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_all`] and afterwards reads the group back
+    /// from the `/metrics` endpoint of your pushgateway instance to verify that every pushed
+    /// sample is exposed with the pushed value. Otherwise
+    /// [`PushMetricsError::Verification`](crate::error::PushMetricsError::Verification)
+    /// lists every mismatch.
+    ///
+    /// Verification requires the metrics to be encoded in the text format, e.g. by the
+    /// prometheus-client converter or
+    /// [`RawMetricsConverter::text`](crate::raw::RawMetricsConverter::text).
+    pub async fn push_all_verified(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt>
+    where
+        P: Fetch,
+        B: AsRef<[u8]>,
+    {
+        self.push_verified(job, grouping, metric_families, PushType::All)
+            .await
+    }
+
+    /// Pushes all metrics like [`MetricsPusher::push_add`] and verifies them like
+    /// [`MetricsPusher::push_all_verified`].
+    pub async fn push_add_verified(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt>
+    where
+        P: Fetch,
+        B: AsRef<[u8]>,
+    {
+        self.push_verified(job, grouping, metric_families, PushType::Add)
+            .await
+    }
+
+    /// Pushes only the metric families that changed since the last [`MetricsPusher::push_changed`]
+    /// to the group with add logic like [`MetricsPusher::push_add`], which keeps the payloads of
+    /// mostly static registries small. If nothing changed nothing is pushed and the receipt
+    /// `is_skipped`.
+    ///
+    /// Families that are no longer gathered are kept at the pushgateway, so use it for groups
+    /// with a stable set of families and push them only with this method. A delete or clear of
+    /// the group pushes all families again with the next push. Like
+    /// [`MetricsPusher::push_all_verified`] it requires the metrics to be encoded in the text
+    /// format.
+    pub async fn push_changed(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt>
+    where
+        B: AsRef<[u8]> + From<String>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let sanitized_grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let (url, encoded_metrics, content_type) = self
+            .metrics_converter
+            .create_push_details(job, &self.url, sanitized_grouping, metric_families)
+            .map_err(&context)?;
+        let families = text_payload(encoded_metrics.as_ref(), &content_type)
+            .and_then(|text| text_format::parse(&text))
+            .map_err(&context)?;
+
+        let changed = self.pushed_families.changed(&url, &families);
+        if changed.is_empty() {
+            return Ok(PushReceipt::skipped(url));
+        }
+
+        let body = B::from(text_format::encode_as(&changed, &content_type));
+        let receipt = self
+            .send(
+                job,
+                grouping,
+                url.clone(),
+                body,
+                &content_type,
+                PushType::Add,
+            )
+            .await
+            .map_err(context)?;
+        if !receipt.is_skipped() {
+            self.pushed_families.remember(&url, families);
+        }
+
+        Ok(receipt)
+    }
+
+    /// Splits the metrics by the value of the given label, e.g. `tenant`, and pushes each part
+    /// like [`MetricsPusher::push_all`] to its own group with the label added to the grouping,
+    /// e.g. `tenant=a`, and removed from the series. Series without the label are pushed to the
+    /// group of the given grouping. The receipts are returned in the order of the label values,
+    /// the first failed push stops the remaining ones.
+    ///
+    /// Like [`MetricsPusher::push_all_verified`] it requires the metrics to be encoded in the
+    /// text format.
+    pub async fn push_all_split(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        label: &str,
+        metric_families: MF,
+    ) -> Result<Vec<PushReceipt>>
+    where
+        B: AsRef<[u8]> + From<String>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let sanitized_grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let (_, encoded_metrics, content_type) = self
+            .metrics_converter
+            .create_push_details(job, &self.url, sanitized_grouping, metric_families)
+            .map_err(&context)?;
+        let families = text_payload(encoded_metrics.as_ref(), &content_type)
+            .and_then(|text| text_format::parse(&text))
+            .map_err(&context)?;
+
+        let mut receipts = Vec::new();
+        for (value, families) in text_format::split_by_label(families, label) {
+            let mut grouping = grouping.clone();
+            if let Some(value) = &value {
+                grouping.insert(label, value);
+            }
+
+            let context = self.context(job, &grouping);
+            let url = self.group_url(job, &grouping).map_err(&context)?;
+            let body = B::from(text_format::encode_as(&families, &content_type));
+            let receipt = self
+                .send(job, &grouping, url, body, &content_type, PushType::All)
+                .await
+                .map_err(context)?;
+            receipts.push(receipt);
+        }
+
+        Ok(receipts)
+    }
+
+    /// Deletes all metrics of the group identified by the given job and grouping labels
+    /// from your pushgateway instance.
+    ///
+    /// Grouping label names must follow the Prometheus naming rules `[a-zA-Z_][a-zA-Z0-9_]*`.
+    /// Job names and label values with the character '/' as well as empty label values are
+    /// sent as base64 encoded path segments, other reserved url characters like '?', '#', '%'
+    /// or spaces are percent-encoded.
+    pub async fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let url = self.group_url(job, grouping)?;
+        if !self.is_enabled() {
+            return Ok(PushReceipt::skipped(url));
+        }
+        let request_id = self.request_id.as_ref().map(RequestId::generate);
+        let header = request_id.as_ref().map(|(name, id)| (*name, id.as_str()));
+        let (routed, tenant) = self
+            .route(job, grouping, &url)
+            .map_err(self.context(job, grouping))?;
+        let headers = with_tenant_header(header.as_slice(), tenant.as_ref());
+        let observation = PushObserver::start(
+            self.observer.as_ref(),
+            Method::Delete,
+            job,
+            grouping,
+            &routed,
+        );
+        let result = self
+            .push_client
+            .delete(&routed, &headers)
+            .await
+            .map_err(self.context(job, grouping));
+
+        observation.finish(&result);
+        if result.is_ok() {
+            self.forget_group(&url);
+        }
+        into_receipt(result, routed, request_id).map(|receipt| receipt.with_tenant(tenant.as_ref()))
+    }
+
+    /// Deletes the group of the default job like [`MetricsPusher::delete`], see
+    /// [`MetricsPusher::push_all_default`].
+    pub async fn delete_default(&self, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        self.delete(self.default_job()?, grouping).await
+    }
+
+    /// Clears the group identified by the given job and grouping labels: all its metrics are
+    /// deleted from your pushgateway instance but, unlike [`MetricsPusher::delete`], the group
+    /// itself is kept together with its `push_time_seconds`.
+    ///
+    /// Job name and grouping labels follow the same rules as for [`MetricsPusher::delete`].
+    pub async fn clear(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt>
+    where
+        B: Default,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let url = self.group_url(job, grouping)?;
+        if !self.is_enabled() {
+            return Ok(PushReceipt::skipped(url));
+        }
+        let request_id = self.request_id.as_ref().map(RequestId::generate);
+        let header = request_id.as_ref().map(|(name, id)| (*name, id.as_str()));
+        let (routed, tenant) = self
+            .route(job, grouping, &url)
+            .map_err(self.context(job, grouping))?;
+        let headers = with_tenant_header(header.as_slice(), tenant.as_ref());
+        let mut observation =
+            PushObserver::start(self.observer.as_ref(), Method::Put, job, grouping, &routed);
+        let body = B::default();
+        observation.measure(&body);
+        let result = self
+            .push_client
+            .push_all(&routed, body, CONTENT_TYPE_TEXT, &headers)
+            .await
+            .map_err(self.context(job, grouping));
+
+        observation.finish(&result);
+        if result.is_ok() {
+            self.forget_group(&url);
+        }
+        into_receipt(result, routed, request_id).map(|receipt| receipt.with_tenant(tenant.as_ref()))
+    }
+
+    /// Pushes a single gauge without labels, e.g. the size of a backup reported by a script,
+    /// without setting up a registry. Like [`MetricsPusher::push_add`] only metrics with the
+    /// same name are replaced within the group.
+    pub async fn push_gauge(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        name: &str,
+        value: f64,
+        help: &str,
+    ) -> Result<PushReceipt>
+    where
+        B: From<String>,
+    {
+        self.push_single_sample(job, grouping, name, "gauge", value, help)
+            .await
+    }
+
+    /// Pushes a single counter without labels, see [`MetricsPusher::push_gauge`].
+    pub async fn push_counter(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        name: &str,
+        value: f64,
+        help: &str,
+    ) -> Result<PushReceipt>
+    where
+        B: From<String>,
+    {
+        self.push_single_sample(job, grouping, name, "counter", value, help)
+            .await
+    }
+
+    /// Reads back the [`PushTimes`] of the group identified by the given job and grouping labels
+    /// from the `/metrics` endpoint of your pushgateway instance, e.g. to verify that the last
+    /// push is recent enough. Returns `None` if the pushgateway does not know the group.
+    pub async fn push_times(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+    ) -> Result<Option<PushTimes>>
+    where
+        P: Fetch,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let metrics = self.push_client.fetch(&self.metrics_url()?).await?;
+
+        text_format::push_times(&metrics, job, grouping)
+    }
+
+    /// Deletes all metrics of all groups from your pushgateway instance with its admin api,
+    /// which has to be enabled with `--web.enable-admin-api`. Nothing is deleted while pushing
+    /// is turned off.
+    #[cfg(feature = "admin")]
+    pub async fn wipe(&self) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        self.push_client
+            .send(Method::Put, &self.api_url("admin/wipe")?, None, &[])
+            .await
+    }
+
+    /// Reads the build information, flags and start time of your pushgateway instance.
+    #[cfg(feature = "admin")]
+    pub async fn status(&self) -> Result<PushgatewayStatus>
+    where
+        P: Fetch,
+    {
+        admin::parse(&self.push_client.fetch(&self.api_url("status")?).await?)
+    }
+
+    /// Reads all groups with their metrics from your pushgateway instance.
+    #[cfg(feature = "admin")]
+    pub async fn metric_groups(&self) -> Result<Vec<MetricGroup>>
+    where
+        P: Fetch,
+    {
+        admin::parse(&self.push_client.fetch(&self.api_url("metrics")?).await?)
+    }
+
     /// Pushes all metrics from collectors to the pushgateway.
     pub async fn push_all_collectors(
         &self,
         job: &str,
         grouping: &HashMap<&str, &str>,
         collectors: C,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         self.push_collectors(job, grouping, collectors, PushType::All)
             .await
     }
@@ -109,7 +939,7 @@ where
         job: &str,
         grouping: &HashMap<&str, &str>,
         collectors: C,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         self.push_collectors(job, grouping, collectors, PushType::Add)
             .await
     }
@@ -120,7 +950,7 @@ where
         grouping: &HashMap<&str, &str>,
         collectors: C,
         push_type: PushType,
-    ) -> Result<()> {
+    ) -> Result<PushReceipt> {
         let metric_families = self.metrics_converter.metrics_from(collectors)?;
         self.push(job, grouping, metric_families, push_type).await
     }
@@ -131,26 +961,1290 @@ where
         grouping: &HashMap<&str, &str>,
         metric_families: MF,
         push_type: PushType,
-    ) -> Result<()> {
-        let (url, encoded_metrics, content_type) = self.metrics_converter.create_push_details(
+    ) -> Result<PushReceipt> {
+        self.push_encoded(job, grouping, push_type, |converter, url, grouping| {
+            converter.create_push_details(job, url, grouping, metric_families)
+        })
+        .await
+    }
+
+    /// Pushes the metrics the given function encodes with the converter for the base url and
+    /// the sanitized grouping.
+    pub(crate) async fn push_encoded(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        push_type: PushType,
+        encode: impl FnOnce(&CM, &Url, &HashMap<&str, &str>) -> Result<(Url, B, String)>,
+    ) -> Result<PushReceipt> {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let sanitized_grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let (url, encoded_metrics, content_type) =
+            encode(&self.metrics_converter, &self.url, sanitized_grouping).map_err(&context)?;
+
+        self.send(
             job,
-            &self.url,
             grouping,
-            metric_families,
-        )?;
+            url,
+            encoded_metrics,
+            &content_type,
+            push_type,
+        )
+        .await
+        .map_err(context)
+    }
 
-        match push_type {
-            PushType::Add => {
-                self.push_client
-                    .push_add(&url, encoded_metrics, &content_type)
-                    .await
+    fn default_job(&self) -> Result<&str> {
+        self.default_job
+            .as_deref()
+            .ok_or(PushMetricsError::MissingJob)
+    }
+
+    /// Adds the job, grouping and pushgateway of a push to the errors of encoding or sending it.
+    fn context<'a>(
+        &'a self,
+        job: &'a str,
+        grouping: &'a HashMap<&str, &str>,
+    ) -> impl Fn(PushMetricsError) -> PushMetricsError + 'a {
+        move |error| error.with_context(job, grouping, &self.gateway_url)
+    }
+
+    /// Applies the [`NonFinitePolicy`] of the pusher to the payload and sends it.
+    async fn send(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: Url,
+        encoded_metrics: B,
+        content_type: &str,
+        push_type: PushType,
+    ) -> Result<PushReceipt> {
+        let (encoded_metrics, non_finite_samples) =
+            NonFiniteFilter::apply(self.non_finite.as_ref(), encoded_metrics, content_type)?;
+
+        self.dispatch(job, grouping, url, encoded_metrics, content_type, push_type)
+            .await
+            .map(|receipt| receipt.with_non_finite_samples(non_finite_samples))
+    }
+
+    /// Transmits the payload unless the throttle of the pusher defers it, see
+    /// [`MetricsPusher::with_min_interval`].
+    async fn dispatch(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: Url,
+        encoded_metrics: B,
+        content_type: &str,
+        push_type: PushType,
+    ) -> Result<PushReceipt> {
+        PayloadSizeLimit::check(self.payload_size_limit.as_ref(), &encoded_metrics)?;
+
+        let Some(throttle) = self.throttle.as_ref().filter(|_| self.is_enabled()) else {
+            return self
+                .transmit(job, grouping, url, encoded_metrics, content_type, push_type)
+                .await;
+        };
+
+        let payload = Deferred::new(job, grouping, url, encoded_metrics, content_type, push_type);
+        let group = payload.url.clone();
+        match throttle.admit(payload) {
+            Some(payload) => {
+                self.transmit(
+                    job,
+                    grouping,
+                    payload.url,
+                    payload.body,
+                    content_type,
+                    push_type,
+                )
+                .await
             }
+            None => Ok(PushReceipt::skipped(group)),
+        }
+    }
 
-            PushType::All => {
-                self.push_client
-                    .push_all(&url, encoded_metrics, &content_type)
-                    .await
+    async fn transmit(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: Url,
+        encoded_metrics: B,
+        content_type: &str,
+        push_type: PushType,
+    ) -> Result<PushReceipt> {
+        if !self.is_enabled() {
+            return Ok(PushReceipt::skipped(url));
+        }
+
+        let format = self.formats.get(&self.gateway_url);
+        let negotiated = negotiate(
+            &self.metrics_converter,
+            format,
+            encoded_metrics,
+            content_type,
+        )?;
+        let (url, tenant) = self.route(job, grouping, &url)?;
+        let request_id = self.request_id.as_ref().map(RequestId::generate);
+        let header = request_id.as_ref().map(|(name, id)| (*name, id.as_str()));
+        let headers = with_tenant_header(header.as_slice(), tenant.as_ref());
+
+        let mut observation = PushObserver::start(
+            self.observer.as_ref(),
+            push_type.method(),
+            job,
+            grouping,
+            &url,
+        );
+        observation.measure(&negotiated.body);
+        let mut payload_size = self.payload_size(&negotiated.body, &url);
+        let mut result = self
+            .push_body(
+                &url,
+                negotiated.body,
+                &negotiated.content_type,
+                push_type,
+                &headers,
+            )
+            .await;
+
+        if let Some((body, fallback_content_type)) = negotiated.fallback {
+            match &result {
+                Ok(_) => self.formats.set(&self.gateway_url, Format::Native),
+                Err(error) if rejects_format(error) => {
+                    #[cfg(feature = "log")]
+                    log::warn!(
+                        "Pushgateway {} rejected '{}', retrying with '{fallback_content_type}'",
+                        self.gateway_url,
+                        negotiated.content_type
+                    );
+                    observation.retry();
+                    observation.measure(&body);
+                    payload_size = self.payload_size(&body, &url);
+                    result = self
+                        .push_body(&url, body, &fallback_content_type, push_type, &headers)
+                        .await;
+                    if result.is_ok() {
+                        self.formats.set(&self.gateway_url, Format::Fallback);
+                    }
+                }
+                Err(_) => {}
             }
         }
+
+        let content_encoding = result.as_ref().copied().unwrap_or_default();
+        let result = result.map(|_| ());
+        observation.finish(&result);
+        into_receipt(result, url, request_id).map(|receipt| {
+            receipt
+                .with_payload_size(payload_size)
+                .with_content_encoding(content_encoding)
+                .with_tenant(tenant.as_ref())
+        })
+    }
+
+    async fn push_body(
+        &self,
+        url: &Url,
+        body: B,
+        content_type: &str,
+        push_type: PushType,
+        headers: &[(&str, &str)],
+    ) -> Result<ContentEncoding> {
+        #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+        let (body, content_encoding) = Compressor::compress(self.compression.as_ref(), body)?;
+        #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+        let encoding = (content_encoding != ContentEncoding::Identity)
+            .then(|| ("Content-Encoding", content_encoding.as_str().to_string()));
+        #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+        let headers = with_header(headers, encoding.as_ref());
+        #[cfg(any(feature = "compress_gzip", feature = "compress_zstd"))]
+        let headers = headers.as_slice();
+        #[cfg(not(any(feature = "compress_gzip", feature = "compress_zstd")))]
+        let content_encoding = ContentEncoding::Identity;
+
+        #[cfg(feature = "body_digest")]
+        let digest = self
+            .body_digest
+            .as_ref()
+            .map(|digester| digester.header(&body));
+        #[cfg(feature = "body_digest")]
+        let headers = with_header(headers, digest.as_ref());
+        #[cfg(feature = "body_digest")]
+        let headers = headers.as_slice();
+
+        let result = match push_type {
+            PushType::Add => {
+                self.push_client
+                    .push_add(url, body, content_type, headers)
+                    .await
+            }
+
+            PushType::All => {
+                self.push_client
+                    .push_all(url, body, content_type, headers)
+                    .await
+            }
+        };
+
+        result.map(|()| content_encoding)
+    }
+
+    fn payload_size(&self, encoded_metrics: &B, url: &Url) -> Option<usize> {
+        self.payload_size_warning
+            .as_ref()
+            .map(|warning| warning.check(encoded_metrics, url))
+    }
+
+    async fn push_verified(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+        push_type: PushType,
+    ) -> Result<PushReceipt>
+    where
+        P: Fetch,
+        B: AsRef<[u8]>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let sanitized_grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        let (url, encoded_metrics, content_type) = self
+            .metrics_converter
+            .create_push_details(job, &self.url, sanitized_grouping, metric_families)
+            .map_err(&context)?;
+        let (encoded_metrics, non_finite_samples) =
+            NonFiniteFilter::apply(self.non_finite.as_ref(), encoded_metrics, &content_type)
+                .map_err(&context)?;
+        let pushed = text_payload(encoded_metrics.as_ref(), &content_type).map_err(&context)?;
+
+        let receipt = self
+            .dispatch(
+                job,
+                grouping,
+                url,
+                encoded_metrics,
+                &content_type,
+                push_type,
+            )
+            .await
+            .map_err(&context)?
+            .with_non_finite_samples(non_finite_samples);
+        if receipt.is_skipped() {
+            return Ok(receipt);
+        }
+
+        let exposed = self
+            .push_client
+            .fetch(&self.metrics_url()?)
+            .await
+            .map_err(&context)?;
+        text_format::verify(&pushed, &exposed, job, sanitized_grouping).map_err(&context)?;
+
+        Ok(receipt)
+    }
+
+    fn metrics_url(&self) -> Result<Url> {
+        Ok(self.url.join("../../metrics")?)
+    }
+
+    #[cfg(feature = "admin")]
+    fn api_url(&self, path: &str) -> Result<Url> {
+        Ok(self.url.join(&format!("../../api/v1/{path}"))?)
+    }
+
+    async fn push_single_sample(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        name: &str,
+        kind: &str,
+        value: f64,
+        help: &str,
+    ) -> Result<PushReceipt>
+    where
+        B: From<String>,
+    {
+        let grouping = merge_grouping(&self.default_grouping, grouping);
+        let grouping = &*grouping;
+        let context = self.context(job, grouping);
+        let url = self.group_url(job, grouping)?;
+        let metrics = text_format::single_sample(name, kind, value, help).map_err(&context)?;
+
+        self.send(
+            job,
+            grouping,
+            url,
+            B::from(metrics),
+            CONTENT_TYPE_TEXT,
+            PushType::Add,
+        )
+        .await
+        .map_err(context)
+    }
+
+    /// Routes the request of the given group to its tenant, see
+    /// [`MetricsPusher::with_tenant_resolver`].
+    fn route(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: &Url,
+    ) -> Result<(Url, Option<Tenant>)> {
+        TenantRouter::route(
+            self.tenants.as_ref(),
+            job,
+            grouping,
+            &self.url,
+            self.url_join,
+            url.clone(),
+        )
+    }
+
+    /// Forgets what the pusher keeps about a group after it was deleted or cleared, so neither
+    /// its pushed families nor a deferred payload outlive it.
+    fn forget_group(&self, group: &Url) {
+        self.pushed_families.forget(group);
+        if let Some(throttle) = &self.throttle {
+            throttle.forget(group);
+        }
+    }
+
+    fn group_url(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<Url> {
+        let sanitized = sanitize_grouping(self.validation_mode, grouping)?;
+        let sanitized = sanitized.as_ref().map(borrow_grouping);
+        let grouping = sanitized.as_ref().unwrap_or(grouping);
+
+        build_url(&self.url, job, grouping)
+    }
+}
+
+/// `PushMetrics` abstracts over a whole pusher, so libraries can accept anything that pushes
+/// metrics of type `MF` without exposing the generic parameters of [`MetricsPusher`]. It is
+/// implemented by [`MetricsPusher`] and by [`NoopPusher`] for tests.
+pub trait PushMetrics<MF> {
+    /// Pushes all metrics, see [`MetricsPusher::push_all`].
+    fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> impl Future<Output = Result<PushReceipt>> + Send;
+
+    /// Pushes metrics that replace the metrics with the same name, see
+    /// [`MetricsPusher::push_add`].
+    fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> impl Future<Output = Result<PushReceipt>> + Send;
+
+    /// Deletes the group, see [`MetricsPusher::delete`].
+    fn delete(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+    ) -> impl Future<Output = Result<PushReceipt>> + Send;
+}
+
+impl<P, CM, MF, C, B> PushMetrics<MF> for MetricsPusher<P, CM, MF, C, B>
+where
+    P: Push<B> + Sync,
+    CM: ConvertMetrics<MF, C, B> + Sync,
+    MF: Send + Sync,
+    C: Sync,
+    B: Send + Sync,
+{
+    async fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        MetricsPusher::push_all(self, job, grouping, metric_families).await
+    }
+
+    async fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        MetricsPusher::push_add(self, job, grouping, metric_families).await
+    }
+
+    async fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        MetricsPusher::delete(self, job, grouping).await
+    }
+}
+
+impl<MF: Send> PushMetrics<MF> for NoopPusher {
+    async fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        _metric_families: MF,
+    ) -> Result<PushReceipt> {
+        self.receipt(job, grouping)
+    }
+
+    async fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        _metric_families: MF,
+    ) -> Result<PushReceipt> {
+        self.receipt(job, grouping)
+    }
+
+    async fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        self.receipt(job, grouping)
+    }
+}
+
+impl<B: Send> Push<B> for NoopPushClient {
+    async fn send(
+        &self,
+        _: Method,
+        _: &Url,
+        _: Option<(B, &str)>,
+        _: &[(&str, &str)],
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::collections::HashMap;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::error::PushMetricsError;
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::with_reqwest::PushClient;
+
+    /// Creates a metrics pusher that pushes payloads in the text format to the given url.
+    fn text_pusher<M: Into<Vec<u8>>>(
+        url: &Url,
+    ) -> MetricsPusher<PushClient, RawMetricsConverter, M, M, Vec<u8>> {
+        MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            url,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lenient_validation_sanitizes_label_names() {
+        use crate::ValidationMode;
+
+        // Given I have a push gateway that expects a sanitized label name
+        let mut server = Server::new_async().await;
+        let path = match cfg!(feature = "utf8_label_names") {
+            true => "/metrics/job/raw_job/my-label/test",
+            false => "/metrics/job/raw_job/my_label/test",
+        };
+        let pushgateway_mock = server.mock("PUT", path).create();
+
+        // And a metrics pusher in lenient validation mode
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url).with_validation_mode(ValidationMode::Lenient);
+
+        // When I push with an invalid label name
+        metrics_pusher
+            .push_all("raw_job", &HashMap::from([("my-label", "test")]), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the label name is sanitized instead of rejected
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_default_grouping_is_merged_into_every_push() {
+        use mockito::Matcher;
+
+        // Given I have a push gateway that expects the default and the given grouping labels
+        let mut server = Server::new_async().await;
+        let path = "^/metrics/job/raw_job/(instance/host-1/env/dev|env/dev/instance/host-1)$";
+        let pushgateway_mock = server.mock("PUT", Matcher::Regex(path.into())).create();
+
+        // And a metrics pusher with default grouping labels
+        let url = Url::parse(&server.url()).unwrap();
+        let default_grouping = HashMap::from([("instance", "host-1"), ("env", "prod")]);
+        let metrics_pusher = text_pusher(&url).with_default_grouping(&default_grouping);
+
+        // When I push with a grouping label that is also a default grouping label
+        metrics_pusher
+            .push_all("raw_job", &HashMap::from([("env", "dev")]), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+
+        // Then both are merged and the given grouping label wins
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_push_with_default_job() {
+        use crate::grouping::JobName;
+
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server.mock("PUT", "/metrics/job/default_job").create();
+
+        // And a metrics pusher without and one with a default job
+        let url = Url::parse(&server.url()).unwrap();
+        let without_default_job = text_pusher(&url);
+        let with_default_job =
+            text_pusher(&url).with_default_job(JobName::new("default_job").unwrap());
+
+        // When I push with the default job
+        let missing = without_default_job
+            .push_all_default(&HashMap::new(), "up 1\n")
+            .await;
+        with_default_job
+            .push_all_default(&HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+
+        // Then only the pusher with a default job pushes
+        assert!(matches!(missing, Err(PushMetricsError::MissingJob)));
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_sent_and_returned_in_receipt() {
+        use crate::RequestId;
+
+        // Given I have a push gateway that expects a request id header
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/raw_job")
+            .match_header("x-correlation-id", "trace-42")
+            .create();
+
+        // And a metrics pusher that sends a request id of its own
+        let url = Url::parse(&server.url()).unwrap();
+        let request_id = RequestId::new("X-Correlation-Id").with_generator(|| "trace-42".into());
+        let metrics_pusher = text_pusher(&url).with_request_id(request_id);
+
+        // When I push the payload
+        let receipt = metrics_pusher
+            .push_all("raw_job", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the request id is sent and returned in the receipt
+        pushgateway_mock.expect(1).assert();
+        assert_eq!(receipt.request_id(), Some("trace-42"));
+        assert_eq!(receipt.url().path(), "/metrics/job/raw_job");
+    }
+
+    #[tokio::test]
+    async fn test_payload_size_is_returned_in_receipt() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server.mock("PUT", "/metrics/job/raw_job").create();
+
+        // And a metrics pusher that measures its payloads
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url).with_payload_size_warning(4);
+
+        // When I push a payload above the threshold
+        let receipt = metrics_pusher
+            .push_all("raw_job", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the payload is pushed anyway and its size is returned in the receipt
+        pushgateway_mock.expect(1).assert();
+        assert_eq!(receipt.payload_size(), Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_payload_above_the_limit_is_not_pushed() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server.mock("PUT", "/metrics/job/raw_job").create();
+
+        // And a metrics pusher that limits its payloads to 4 bytes
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url).with_max_payload_size(4);
+
+        // When I push a payload above the limit
+        let result = metrics_pusher
+            .push_all("raw_job", &HashMap::new(), "up 1\n")
+            .await;
+
+        // Then the push fails with its size and the limit without reaching the push gateway
+        pushgateway_mock.expect(0).assert();
+        assert!(matches!(
+            result.map_err(PushMetricsError::without_context),
+            Err(PushMetricsError::PayloadTooLarge { size: 5, limit: 4 })
+        ));
+    }
+
+    #[cfg(feature = "body_digest")]
+    #[tokio::test]
+    async fn test_body_digest_is_sent_with_push() {
+        use crate::BodyDigest;
+
+        // Given I have a push gateway that expects the digest of the body
+        let mut server = Server::new_async().await;
+        let md5_mock = server
+            .mock("PUT", "/metrics/job/md5_job")
+            .match_header("content-md5", "qJYvhlSNbZIu0pncFVHoug==")
+            .create();
+        let sha256_mock = server
+            .mock("PUT", "/metrics/job/sha256_job")
+            .match_header(
+                "digest",
+                "sha-256=OTbL1zCgtY/XAVSHaK4Qq21ecJGUiODFX+qA1hUYrH4=",
+            )
+            .create();
+
+        // And metrics pushers that attach either digest
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = |digest| text_pusher(&url).with_body_digest(digest);
+
+        // When I push with both of them
+        metrics_pusher(BodyDigest::ContentMd5)
+            .push_all("md5_job", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        metrics_pusher(BodyDigest::Sha256)
+            .push_all("sha256_job", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the digests of the body are sent in the headers
+        md5_mock.expect(1).assert();
+        sha256_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_pushes_are_routed_to_their_tenant() {
+        use crate::tenant::Tenant;
+
+        // Given I have a shared push gateway and a dedicated one of a large customer
+        let mut shared = Server::new_async().await;
+        let shared_mock = shared
+            .mock("PUT", "/metrics/job/agent/customer/small")
+            .match_header("x-scope-orgid", "small")
+            .create();
+        let mut dedicated = Server::new_async().await;
+        let dedicated_mock = dedicated
+            .mock("DELETE", "/push/metrics/job/agent/customer/large")
+            .match_header("x-scope-orgid", "large")
+            .create();
+
+        // And a metrics pusher that resolves the tenant from the customer grouping label
+        let dedicated_url = Url::parse(&format!("{}/push", dedicated.url())).unwrap();
+        let metrics_pusher = text_pusher(&Url::parse(&shared.url()).unwrap()).with_tenant_resolver(
+            move |_: &str, grouping: &HashMap<&str, &str>| {
+                let customer = grouping["customer"];
+                let tenant = Tenant::new(customer).with_header("X-Scope-OrgID", customer);
+                Ok(match customer {
+                    "large" => tenant.with_endpoint(&dedicated_url),
+                    _ => tenant,
+                })
+            },
+        );
+
+        // When I push the metrics of the small customer and delete those of the large one
+        let small = metrics_pusher
+            .push_all("agent", &HashMap::from([("customer", "small")]), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        let large = metrics_pusher
+            .delete("agent", &HashMap::from([("customer", "large")]))
+            .await
+            .expect("Failed to delete metrics");
+
+        // Then each request reaches the push gateway of its tenant with its header
+        shared_mock.expect(1).assert();
+        dedicated_mock.expect(1).assert();
+        assert_eq!(small.tenant(), Some("small"));
+        assert_eq!(large.tenant(), Some("large"));
+        assert_eq!(large.url().path(), "/push/metrics/job/agent/customer/large");
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn test_stream_is_pushed_as_it_arrives() {
+        use std::collections::VecDeque;
+        use std::pin::Pin;
+        use std::task::Context;
+        use std::task::Poll;
+
+        struct Scan(VecDeque<&'static str>);
+
+        impl futures_core::Stream for Scan {
+            type Item = String;
+
+            fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<String>> {
+                Poll::Ready(self.0.pop_front().map(String::from))
+            }
+        }
+
+        // Given I have a push gateway that expects all streamed metrics in one body
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/scan_job")
+            .match_header("content-type", "text/plain; version=0.0.4")
+            .match_body("rows{table=\"a\"} 1\nrows{table=\"b\"} 2\n")
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher::<String>(&url);
+
+        // When I push a stream of metrics
+        let scan = Scan(VecDeque::from([
+            "rows{table=\"a\"} 1\n",
+            "rows{table=\"b\"} 2\n",
+        ]));
+        metrics_pusher
+            .push_all_stream("scan_job", &HashMap::new(), scan)
+            .await
+            .expect("Failed to push metrics");
+
+        // Then every item is pushed in order with a single request
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[cfg(feature = "compress_gzip")]
+    #[tokio::test]
+    async fn test_payloads_above_the_threshold_are_compressed() {
+        use crate::Compression;
+        use crate::ContentEncoding;
+        use mockito::Matcher;
+
+        // Given I have a push gateway that expects gzip for the large payload only
+        let mut server = Server::new_async().await;
+        let small_mock = server
+            .mock("PUT", "/metrics/job/small_job")
+            .match_header("content-encoding", Matcher::Missing)
+            .match_body("up 1\n")
+            .create();
+        let large_mock = server
+            .mock("PUT", "/metrics/job/large_job")
+            .match_header("content-encoding", "gzip")
+            .create();
+
+        // And a metrics pusher that compresses payloads above 64 bytes with gzip
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher =
+            text_pusher(&url).with_compression(Compression::new().with_gzip_above(64));
+
+        // When I push a small and a large payload
+        let small = metrics_pusher
+            .push_all("small_job", &HashMap::new(), "up 1\n".to_string())
+            .await
+            .expect("Failed to push metrics");
+        let large = metrics_pusher
+            .push_all("large_job", &HashMap::new(), "up 1\n".repeat(100))
+            .await
+            .expect("Failed to push metrics");
+
+        // Then only the large payload is sent compressed and the receipts tell the encoding
+        small_mock.expect(1).assert();
+        large_mock.expect(1).assert();
+        assert_eq!(small.content_encoding(), ContentEncoding::Identity);
+        assert_eq!(large.content_encoding(), ContentEncoding::Gzip);
+    }
+
+    #[tokio::test]
+    async fn test_non_finite_samples_are_dropped() {
+        use crate::NonFinitePolicy;
+
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/raw_job")
+            .match_body("temperature{room=\"b\"} 21\n")
+            .create();
+
+        // And a metrics pusher that drops samples with non-finite values
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url).with_non_finite_policy(NonFinitePolicy::DropSample);
+
+        // When I push a NaN and a finite sample
+        let receipt = metrics_pusher
+            .push_all(
+                "raw_job",
+                &HashMap::new(),
+                "temperature{room=\"a\"} NaN\ntemperature{room=\"b\"} 21\n",
+            )
+            .await
+            .expect("Failed to push metrics");
+
+        // Then only the finite sample is pushed and the dropped one is counted in the receipt
+        pushgateway_mock.expect(1).assert();
+        assert_eq!(receipt.non_finite_samples(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_non_finite_single_sample_is_replaced() {
+        use mockito::Matcher;
+
+        use crate::NonFinitePolicy;
+
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("POST", "/metrics/job/backup")
+            .match_body(Matcher::Regex("\nbackup_size_bytes 0\n$".into()))
+            .create();
+
+        // And a metrics pusher that replaces non-finite values with zero
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher =
+            text_pusher::<String>(&url).with_non_finite_policy(NonFinitePolicy::ReplaceWithZero);
+
+        // When I push a single NaN gauge
+        let receipt = metrics_pusher
+            .push_gauge(
+                "backup",
+                &HashMap::new(),
+                "backup_size_bytes",
+                f64::NAN,
+                "Size of the last backup.",
+            )
+            .await
+            .expect("Failed to push gauge");
+
+        // Then the gauge is pushed with zero and the replaced sample is counted in the receipt
+        pushgateway_mock.expect(1).assert();
+        assert_eq!(receipt.non_finite_samples(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_observer_receives_push_events() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        use crate::Method;
+
+        // Given I have a push gateway that accepts pushes but rejects deletes
+        let mut server = Server::new_async().await;
+        let push_mock = server.mock("PUT", "/metrics/job/raw_job/env/test").create();
+        let delete_mock = server
+            .mock("DELETE", "/metrics/job/raw_job/env/test")
+            .with_status(503)
+            .create();
+
+        // And a metrics pusher with an observer that collects the events
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let observed = Arc::clone(&events);
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url)
+            .with_observer(move |event| observed.lock().unwrap().push(event.clone()));
+
+        // When I push and delete the group
+        let grouping = HashMap::from([("env", "test")]);
+        metrics_pusher
+            .push_all("raw_job", &grouping, "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        metrics_pusher
+            .delete("raw_job", &grouping)
+            .await
+            .expect_err("Delete was not rejected");
+
+        // Then the observer received an event for each request
+        push_mock.expect(1).assert();
+        delete_mock.expect(1).assert();
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+
+        assert_eq!(events[0].job, "raw_job");
+        assert_eq!(events[0].grouping["env"], "test");
+        assert_eq!(events[0].method, Method::Put);
+        assert!(events[0].success);
+        assert_eq!(events[0].bytes, Some(5));
+        assert_eq!(events[0].retries, 0);
+
+        assert_eq!(events[1].method, Method::Delete);
+        assert!(!events[1].success);
+        assert_eq!(events[1].status, Some(503));
+        assert_eq!(events[1].bytes, None);
+
+        #[cfg(feature = "json_events")]
+        {
+            let json = events[0].to_json();
+            assert!(json.contains(r#""job":"raw_job""#));
+            assert!(json.contains(r#""grouping":{"env":"test"}"#));
+            assert!(json.contains(r#""method":"PUT""#));
+            assert!(json.contains(r#""duration_ms":"#));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_changed_sends_only_changed_families() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let first_mock = server
+            .mock("POST", "/metrics/job/raw_job")
+            .match_body("# TYPE a gauge\na 1\n# TYPE b gauge\nb 1\n")
+            .create();
+        let second_mock = server
+            .mock("POST", "/metrics/job/raw_job")
+            .match_body("# TYPE b gauge\nb 2\n")
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url);
+
+        // When I push, change one family, push and push again without changes
+        let grouping = HashMap::new();
+        let push = |metrics| metrics_pusher.push_changed("raw_job", &grouping, metrics);
+        let first = push("# TYPE a gauge\na 1\n# TYPE b gauge\nb 1\n")
+            .await
+            .unwrap();
+        let second = push("# TYPE a gauge\na 1\n# TYPE b gauge\nb 2\n")
+            .await
+            .unwrap();
+        let third = push("# TYPE a gauge\na 1\n# TYPE b gauge\nb 2\n")
+            .await
+            .unwrap();
+
+        // Then all families are pushed first, afterwards only the changed one and then nothing
+        first_mock.expect(1).assert();
+        second_mock.expect(1).assert();
+        assert!(!first.is_skipped());
+        assert!(!second.is_skipped());
+        assert!(third.is_skipped());
+    }
+
+    #[tokio::test]
+    async fn test_push_all_split_pushes_a_group_per_label_value() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let tenant_a_mock = server
+            .mock("PUT", "/metrics/job/raw_job/tenant/a")
+            .match_body("# TYPE jobs gauge\njobs{kind=\"x\"} 1\n")
+            .create();
+        let tenant_b_mock = server
+            .mock("PUT", "/metrics/job/raw_job/tenant/b")
+            .match_body("# TYPE jobs gauge\njobs 2\n")
+            .create();
+        let untenanted_mock = server
+            .mock("PUT", "/metrics/job/raw_job")
+            .match_body("# TYPE up gauge\nup 1\n")
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url);
+
+        // When I push metrics of two tenants and one without tenant split by the tenant label
+        let receipts = metrics_pusher
+            .push_all_split(
+                "raw_job",
+                &HashMap::new(),
+                "tenant",
+                "# TYPE jobs gauge\njobs{tenant=\"a\",kind=\"x\"} 1\njobs{tenant=\"b\"} 2\n\
+                 # TYPE up gauge\nup 1\n",
+            )
+            .await
+            .expect("Failed to push metrics");
+
+        // Then every part is pushed to its own group without the tenant label
+        assert_eq!(receipts.len(), 3);
+        tenant_a_mock.expect(1).assert();
+        tenant_b_mock.expect(1).assert();
+        untenanted_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_min_interval_coalesces_pushes() {
+        use std::time::Duration;
+
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let first_mock = server
+            .mock("PUT", "/metrics/job/raw_job")
+            .match_body("up 1\n")
+            .create();
+        let coalesced_mock = server
+            .mock("PUT", "/metrics/job/raw_job")
+            .match_body("up 2\n")
+            .create();
+        let newest_mock = server
+            .mock("PUT", "/metrics/job/raw_job")
+            .match_body("up 3\n")
+            .create();
+
+        // And a metrics pusher that pushes each group at most once an hour
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url).with_min_interval(Duration::from_secs(3600));
+
+        // When I push three times in a row and flush afterwards
+        let grouping = HashMap::new();
+        let first = metrics_pusher
+            .push_all("raw_job", &grouping, "up 1\n")
+            .await;
+        let second = metrics_pusher
+            .push_all("raw_job", &grouping, "up 2\n")
+            .await;
+        let third = metrics_pusher
+            .push_all("raw_job", &grouping, "up 3\n")
+            .await;
+        let flushed = metrics_pusher.flush().await;
+
+        // Then the first push is sent, the others are coalesced and the newest one is flushed
+        assert!(!first.unwrap().is_skipped());
+        assert!(second.unwrap().is_skipped());
+        assert!(third.unwrap().is_skipped());
+        assert_eq!(flushed.len(), 1);
+        assert!(flushed[0].is_ok());
+        first_mock.expect(1).assert();
+        coalesced_mock.expect(0).assert();
+        newest_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_delete_drops_the_deferred_push_of_the_group() {
+        use std::time::Duration;
+
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let push_mock = server.mock("PUT", "/metrics/job/raw_job").create();
+        let delete_mock = server.mock("DELETE", "/metrics/job/raw_job").create();
+
+        // And a metrics pusher that pushes each group at most once an hour
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url).with_min_interval(Duration::from_secs(3600));
+
+        // When I push twice, delete the group and flush afterwards
+        let grouping = HashMap::new();
+        let first = metrics_pusher
+            .push_all("raw_job", &grouping, "up 1\n")
+            .await;
+        let deferred = metrics_pusher
+            .push_all("raw_job", &grouping, "up 2\n")
+            .await;
+        let deleted = metrics_pusher.delete("raw_job", &grouping).await;
+        let flushed = metrics_pusher.flush().await;
+
+        // Then the deferred push does not recreate the deleted group
+        assert!(!first.unwrap().is_skipped());
+        assert!(deferred.unwrap().is_skipped());
+        assert!(deleted.is_ok());
+        assert!(flushed.is_empty());
+        push_mock.expect(1).assert();
+        delete_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_clear_pushes_an_empty_body() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/raw_job/kind/test")
+            .match_body("")
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher::<String>(&url);
+
+        // When I clear the group
+        metrics_pusher
+            .clear("raw_job", &HashMap::from([("kind", "test")]))
+            .await
+            .expect("Failed to clear group");
+
+        // Then the push gateway receives a PUT with an empty body
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_push_all_with_gathers_at_push_time() {
+        use std::cell::Cell;
+
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/raw_job")
+            .match_body("jobs_processed 2\n")
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url);
+
+        // And a counter that changes after the push was prepared
+        let processed = Cell::new(1);
+        let gather = || format!("jobs_processed {}\n", processed.get());
+        processed.set(2);
+
+        // When I push with the gather function
+        metrics_pusher
+            .push_all_with("raw_job", &HashMap::new(), gather)
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the metrics are gathered when they are pushed
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_push_gauge_without_registry() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let expected = "# HELP backup_size_bytes Size of the last backup.\n".to_owned()
+            + "# TYPE backup_size_bytes gauge\n"
+            + "backup_size_bytes 123.4\n";
+        let pushgateway_mock = server
+            .mock("POST", "/metrics/job/backup/host/db")
+            .match_header("content-type", "text/plain; version=0.0.4")
+            .match_body(&*expected)
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher::<String>(&url);
+
+        // When I push a single gauge
+        metrics_pusher
+            .push_gauge(
+                "backup",
+                &HashMap::from([("host", "db")]),
+                "backup_size_bytes",
+                123.4,
+                "Size of the last backup.",
+            )
+            .await
+            .expect("Failed to push gauge");
+
+        // Then the push gateway receives the gauge in the text format
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_push_counter_rejects_invalid_metric_name() {
+        // Given I have a metrics pusher
+        let url = Url::parse("http://localhost:9091").unwrap();
+        let metrics_pusher = text_pusher::<String>(&url);
+
+        // When I push a counter with an invalid name
+        let result = metrics_pusher
+            .push_counter("backup", &HashMap::new(), "1runs", 1.0, "Runs.")
+            .await;
+
+        // Then the push is rejected before it is sent
+        assert!(matches!(
+            result.map_err(PushMetricsError::without_context),
+            Err(PushMetricsError::TextFormat(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_push_times_are_read_from_the_gateway() {
+        // Given I have a push gateway behind a path that exposes the push times of a group
+        let mut server = Server::new_async().await;
+        let metrics = "push_time_seconds{instance=\"\",job=\"raw_job\",kind=\"test\"} 1.7e+09\n";
+        let pushgateway_mock = server
+            .mock("GET", "/gateway/metrics")
+            .with_body(metrics)
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap().join("gateway").unwrap();
+        let metrics_pusher = text_pusher::<String>(&url);
+
+        // When I read back the push times of the group
+        let push_times = metrics_pusher
+            .push_times("raw_job", &HashMap::from([("kind", "test")]))
+            .await
+            .expect("Failed to read push times")
+            .expect("Group is unknown");
+
+        // Then the time of the last push is returned
+        pushgateway_mock.expect(1).assert();
+        let last_push = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        assert_eq!(push_times.last_push(), Some(last_push));
+    }
+
+    #[tokio::test]
+    async fn test_push_all_verified() {
+        // Given I have a push gateway that exposes the pushed metrics afterwards
+        let mut server = Server::new_async().await;
+        let push_mock = server
+            .mock("PUT", "/metrics/job/raw_job/kind/test")
+            .create();
+        let metrics_mock = server
+            .mock("GET", "/metrics")
+            .with_body("up{instance=\"\",job=\"raw_job\",kind=\"test\"} 1\n")
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url);
+
+        // When I push and verify the metrics
+        let grouping = HashMap::from([("kind", "test")]);
+        let verified = metrics_pusher
+            .push_all_verified("raw_job", &grouping, "up 1\n")
+            .await;
+        let mismatched = metrics_pusher
+            .push_all_verified("raw_job", &grouping, "up 0\n")
+            .await;
+
+        // Then the matching push is verified and the other one reports the mismatch
+        push_mock.expect(2).assert();
+        metrics_mock.expect(2).assert();
+        assert!(verified.is_ok());
+        let error = mismatched.unwrap_err();
+        assert_eq!(
+            error.context().map(|context| context.job()),
+            Some("raw_job")
+        );
+        assert!(matches!(
+            error.without_context(),
+            PushMetricsError::Verification(mismatches) if mismatches[0].found() == Some(1.0)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_skipped_push_is_not_verified() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let push_mock = server.mock("PUT", "/metrics/job/raw_job").create();
+        let metrics_mock = server.mock("GET", "/metrics").create();
+
+        // And a disabled metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = text_pusher(&url);
+        metrics_pusher.disable();
+
+        // When I push and verify the metrics
+        let receipt = metrics_pusher
+            .push_all_verified("raw_job", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to skip the push");
+
+        // Then neither the push is sent nor the exposed metrics are fetched
+        assert!(receipt.is_skipped());
+        push_mock.expect(0).assert();
+        metrics_mock.expect(0).assert();
     }
 }
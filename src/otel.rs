@@ -0,0 +1,68 @@
+use opentelemetry_sdk::Resource;
+
+use crate::grouping::Grouping;
+
+const SERVICE_NAME: &str = "service.name";
+const SERVICE_NAMESPACE: &str = "service.namespace";
+const SERVICE_INSTANCE_ID: &str = "service.instance.id";
+
+/// Converts an OpenTelemetry [`Resource`] into the job name and [`Grouping`] to push with,
+/// following the conventions Prometheus uses for OTLP metrics:
+///
+/// - `service.name` becomes the job name,
+/// - `service.instance.id` becomes the `instance` label,
+/// - `service.namespace` becomes the `service_namespace` label. Prometheus prefixes the job
+///   with the namespace instead, which would turn every job name into a base64 encoded path
+///   segment at the pushgateway.
+///
+/// Other resource attributes are not used for grouping.
+pub fn job_and_grouping(resource: &Resource) -> (String, Grouping) {
+    let attribute = |key: &str| {
+        resource
+            .iter()
+            .find(|(resource_key, _)| resource_key.as_str() == key)
+            .map(|(_, value)| value.to_string())
+    };
+
+    let job = attribute(SERVICE_NAME).unwrap_or_else(|| String::from("unknown_service"));
+    let mut grouping = Grouping::new();
+    if let Some(instance) = attribute(SERVICE_INSTANCE_ID) {
+        grouping = grouping.with_label("instance", &instance);
+    }
+    if let Some(namespace) = attribute(SERVICE_NAMESPACE) {
+        grouping = grouping.with_label("service_namespace", &namespace);
+    }
+
+    (job, grouping)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::Resource;
+
+    use crate::otel::job_and_grouping;
+
+    #[test]
+    fn test_job_and_grouping_from_resource() {
+        // Given I have a resource of a service
+        let resource = Resource::builder_empty()
+            .with_service_name("checkout")
+            .with_attributes([
+                KeyValue::new("service.namespace", "shop"),
+                KeyValue::new("service.instance.id", "checkout-1"),
+                KeyValue::new("host.arch", "amd64"),
+            ])
+            .build();
+
+        // When I convert it
+        let (job, grouping) = job_and_grouping(&resource);
+
+        // Then the identity attributes are mapped to job and grouping labels
+        assert_eq!(job, "checkout");
+        let expected = HashMap::from([("instance", "checkout-1"), ("service_namespace", "shop")]);
+        assert_eq!(grouping.labels(), expected);
+    }
+}
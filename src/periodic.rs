@@ -0,0 +1,419 @@
+use std::future::Future;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use tokio::sync::oneshot;
+use tokio::sync::watch;
+use tokio::task::JoinError;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use url::Url;
+
+use crate::error::Result;
+use crate::grouping::Grouping;
+use crate::non_blocking::MetricsPusher;
+use crate::non_blocking::Push;
+use crate::ConvertMetrics;
+use crate::PushReceipt;
+
+/// `PusherConfig` holds the parts of a [`PeriodicPusher`] that can be changed while it is
+/// running, see [`PeriodicPusher::run_with_config`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PusherConfig {
+    pub gateway: Url,
+    pub interval: Duration,
+    pub grouping: Grouping,
+}
+
+/// `LastPush` describes the most recent successful push of a [`PeriodicPusher`], see
+/// [`PeriodicPusher::last_push`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LastPush {
+    pub pushed_at: SystemTime,
+    pub payload_size: Option<usize>,
+    pub jobs: Vec<String>,
+}
+
+/// `PeriodicPusher` gathers metrics with the given function and pushes them with the given
+/// [`MetricsPusher`] on an interval, so the schedule owns when metrics are snapshotted.
+#[derive(Debug)]
+pub struct PeriodicPusher<P, CM, MF, C, B, G>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+    G: Fn() -> MF,
+{
+    metrics_pusher: MetricsPusher<P, CM, MF, C, B>,
+    job: String,
+    grouping: Grouping,
+    gather: G,
+    interval: Duration,
+    last_push: watch::Sender<Option<LastPush>>,
+}
+
+impl<P, CM, MF, C, B, G> PeriodicPusher<P, CM, MF, C, B, G>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+    G: Fn() -> MF,
+{
+    /// Creates a new [`PeriodicPusher`] that pushes the metrics returned by `gather` as `job`
+    /// every `interval`.
+    pub fn new(
+        metrics_pusher: MetricsPusher<P, CM, MF, C, B>,
+        job: &str,
+        gather: G,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            metrics_pusher,
+            job: job.to_owned(),
+            grouping: Grouping::new(),
+            gather,
+            interval,
+            last_push: watch::Sender::new(None),
+        }
+    }
+
+    /// Sets the grouping labels that are used for every push.
+    pub fn with_grouping(mut self, grouping: Grouping) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    /// Returns a receiver of the most recent successful push, e.g. for a readiness probe or an
+    /// admin endpoint to report whether metrics still make it to the pushgateway. It holds
+    /// `None` until the first push succeeded.
+    pub fn last_push(&self) -> watch::Receiver<Option<LastPush>> {
+        self.last_push.subscribe()
+    }
+
+    /// Gathers the metrics and pushes them once, replacing the metrics of the group.
+    pub async fn push(&self) -> Result<PushReceipt> {
+        let receipt = self
+            .metrics_pusher
+            .push_all_with(&self.job, &self.grouping.labels(), &self.gather)
+            .await?;
+
+        if !receipt.is_skipped() {
+            self.last_push.send_replace(Some(LastPush {
+                pushed_at: SystemTime::now(),
+                payload_size: receipt.payload_size(),
+                jobs: vec![self.job.clone()],
+            }));
+        }
+
+        Ok(receipt)
+    }
+
+    /// Pushes every interval until the returned future is dropped. Failed pushes are logged
+    /// and retried with the next tick.
+    pub async fn run(&self) {
+        self.run_until(std::future::pending()).await
+    }
+
+    /// Pushes every interval until `shutdown` completes and pushes a final time afterwards, so
+    /// the last state of the metrics is not lost. Failed pushes are logged.
+    pub async fn run_until(&self, shutdown: impl Future<Output = ()>) {
+        let mut interval = tokio::time::interval(self.interval);
+        let mut shutdown = std::pin::pin!(shutdown);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => self.push_logged().await,
+                _ = &mut shutdown => break,
+            }
+        }
+
+        self.push_logged().await
+    }
+
+    /// Like [`PeriodicPusher::run_until`] but picks up every change of the given
+    /// [`PusherConfig`], e.g. after a config map was reloaded, without restarting the pusher.
+    /// Changes are applied between two pushes, so no push is cut off. A config with an invalid
+    /// gateway url is logged and ignored.
+    pub async fn run_with_config(
+        &mut self,
+        mut config: watch::Receiver<PusherConfig>,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let initial = config.borrow_and_update().clone();
+        self.apply(initial);
+
+        let mut interval = tokio::time::interval(self.interval);
+        let mut shutdown = std::pin::pin!(shutdown);
+        let mut reloading = true;
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = &mut shutdown => break,
+                changed = config.changed(), if reloading => match changed {
+                    Ok(()) => {
+                        let changed = config.borrow_and_update().clone();
+                        if self.apply(changed) {
+                            interval = tokio::time::interval_at(
+                                Instant::now() + self.interval,
+                                self.interval,
+                            );
+                        }
+                    }
+                    Err(_) => reloading = false,
+                },
+                _ = interval.tick() => self.push_logged().await,
+            }
+        }
+
+        self.push_logged().await
+    }
+
+    /// Applies the given config and returns whether the interval changed.
+    fn apply(&mut self, config: PusherConfig) -> bool {
+        if config.interval.is_zero() {
+            log::error!("Ignoring config with an interval of zero");
+            return false;
+        }
+
+        if let Err(error) = self.metrics_pusher.set_gateway_url(&config.gateway) {
+            log::error!(
+                "Ignoring config with invalid gateway {}: {error}",
+                config.gateway
+            );
+            return false;
+        }
+
+        self.grouping = config.grouping;
+        let interval_changed = self.interval != config.interval;
+        self.interval = config.interval;
+        interval_changed
+    }
+
+    /// Spawns [`PeriodicPusher::run_until`] on the current tokio runtime, so the metrics are
+    /// pushed in the background until [`PeriodicPusherHandle::shutdown`] is called or the
+    /// returned handle is dropped. Either way a final push follows.
+    pub fn spawn(self) -> PeriodicPusherHandle
+    where
+        P: Send + Sync + 'static,
+        CM: Send + Sync + 'static,
+        MF: Send + Sync + 'static,
+        C: Send + Sync + 'static,
+        B: Send + Sync + 'static,
+        G: Send + Sync + 'static,
+    {
+        let (shutdown, shutdown_requested) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            // a dropped handle completes the receiver with an error, which is a shutdown as well
+            let shutdown = async move {
+                let _ = shutdown_requested.await;
+            };
+            self.run_until(shutdown).await
+        });
+
+        PeriodicPusherHandle { shutdown, task }
+    }
+
+    async fn push_logged(&self) {
+        if let Err(error) = self.push().await {
+            log::error!("Failed to push metrics of job {}: {error}", self.job);
+        }
+    }
+}
+
+/// `PeriodicPusherHandle` controls a [`PeriodicPusher`] that runs in the background, see
+/// [`PeriodicPusher::spawn`].
+#[derive(Debug)]
+pub struct PeriodicPusherHandle {
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl PeriodicPusherHandle {
+    /// Stops the periodic pushes and waits for the final push, so it is not cut off by the
+    /// process exiting. Returns an error if the task panicked or was aborted.
+    pub async fn shutdown(self) -> std::result::Result<(), JoinError> {
+        let _ = self.shutdown.send(());
+        self.task.await
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::time::Duration;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::grouping::Grouping;
+    use crate::non_blocking::MetricsPusher;
+    use crate::periodic::PeriodicPusher;
+    use crate::periodic::PusherConfig;
+    use crate::raw::RawMetricsConverter;
+    use crate::with_reqwest::PushClient;
+
+    #[tokio::test]
+    async fn test_run_until_pushes_a_final_time() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/periodic/env/test")
+            .match_body("up 1\n")
+            .expect(2)
+            .create();
+
+        // And a periodic pusher with an interval longer than the test
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let periodic_pusher = PeriodicPusher::new(
+            metrics_pusher,
+            "periodic",
+            || String::from("up 1\n"),
+            Duration::from_secs(3600),
+        )
+        .with_grouping(Grouping::new().with_label("env", "test"));
+
+        // When I run it until shutdown
+        periodic_pusher
+            .run_until(tokio::time::sleep(Duration::from_millis(50)))
+            .await;
+
+        // Then the metrics are pushed on the first tick and on shutdown
+        pushgateway_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_spawned_pusher_flushes_on_shutdown() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/periodic")
+            .match_body("up 1\n")
+            .expect(2)
+            .create();
+
+        // And a periodic pusher with an interval longer than the test
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let periodic_pusher = PeriodicPusher::new(
+            metrics_pusher,
+            "periodic",
+            || String::from("up 1\n"),
+            Duration::from_secs(3600),
+        );
+
+        // When I spawn it and shut it down after the first tick
+        let handle = periodic_pusher.spawn();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handle.shutdown().await.expect("Periodic pusher panicked");
+
+        // Then the metrics are pushed on the first tick and once more before shutdown returns
+        pushgateway_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_last_push_is_published() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/periodic")
+            .expect(1)
+            .create();
+
+        // And a periodic pusher that measures its payload
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap()
+        .with_payload_size_warning(1024);
+        let periodic_pusher = PeriodicPusher::new(
+            metrics_pusher,
+            "periodic",
+            || String::from("up 1\n"),
+            Duration::from_secs(3600),
+        );
+        let last_push = periodic_pusher.last_push();
+        assert_eq!(*last_push.borrow(), None);
+
+        // When it pushed once
+        periodic_pusher
+            .push()
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the push is published to the subscribers
+        let last_push = last_push.borrow().clone().expect("No push published");
+        assert_eq!(last_push.payload_size, Some(5));
+        assert_eq!(last_push.jobs, vec![String::from("periodic")]);
+        pushgateway_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_run_with_config_picks_up_changes() {
+        // Given I have two push gateways
+        let mut old_server = Server::new_async().await;
+        let old_mock = old_server
+            .mock("PUT", "/metrics/job/periodic/env/old")
+            .expect(1)
+            .create();
+        let mut new_server = Server::new_async().await;
+        let new_mock = new_server
+            .mock("PUT", "/metrics/job/periodic/env/new")
+            .expect(1)
+            .create();
+
+        // And a periodic pusher configured for the old one
+        let old_config = PusherConfig {
+            gateway: Url::parse(&old_server.url()).unwrap(),
+            interval: Duration::from_secs(3600),
+            grouping: Grouping::new().with_label("env", "old"),
+        };
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &old_config.gateway,
+        )
+        .unwrap();
+        let mut periodic_pusher = PeriodicPusher::new(
+            metrics_pusher,
+            "periodic",
+            || String::from("up 1\n"),
+            Duration::from_secs(1),
+        );
+        let (config, config_receiver) = tokio::sync::watch::channel(old_config);
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+
+        // When the config changes to the new push gateway while it is running
+        let reload = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            config
+                .send(PusherConfig {
+                    gateway: Url::parse(&new_server.url()).unwrap(),
+                    interval: Duration::from_secs(3600),
+                    grouping: Grouping::new().with_label("env", "new"),
+                })
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stop.send(()).unwrap();
+        };
+        let run = periodic_pusher.run_with_config(config_receiver, async {
+            stopped.await.unwrap();
+        });
+        tokio::join!(run, reload);
+
+        // Then the first push goes to the old and the final push to the new push gateway
+        old_mock.assert();
+        new_mock.assert();
+    }
+}
@@ -0,0 +1,403 @@
+use std::io;
+use std::io::Read;
+use std::io::Write;
+#[cfg(windows)]
+use std::path::PathBuf;
+
+use http::StatusCode;
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::utils::DEFAULT_USER_AGENT;
+use crate::Method;
+
+/// `Connect` opens the byte stream a [`PipePushClient`] sends a request over. It is
+/// implemented for [`NamedPipe`] on Windows and for closures returning a stream, e.g. to push
+/// over any other local transport.
+pub trait Connect {
+    type Stream: Read + Write;
+
+    /// Opens a new stream for a single request.
+    fn connect(&self) -> io::Result<Self::Stream>;
+}
+
+impl<F, S> Connect for F
+where
+    F: Fn() -> io::Result<S>,
+    S: Read + Write,
+{
+    type Stream = S;
+
+    fn connect(&self) -> io::Result<S> {
+        self()
+    }
+}
+
+/// `NamedPipe` connects to a Windows named pipe like `\\.\pipe\collector`.
+#[cfg(windows)]
+#[derive(Clone, Debug)]
+pub struct NamedPipe {
+    path: PathBuf,
+}
+
+#[cfg(windows)]
+impl Connect for NamedPipe {
+    type Stream = std::fs::File;
+
+    fn connect(&self) -> io::Result<std::fs::File> {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.path)
+    }
+}
+
+/// `PipePushClient` is a [`Push`](crate::non_blocking::Push) and
+/// [`blocking::Push`](crate::blocking::Push) implementation that sends every request as
+/// HTTP/1.1 over a local stream instead of TCP, e.g. to a collector on the same machine that
+/// listens on a Windows named pipe, see [`PipePushClient::named_pipe`].
+///
+/// Every request opens its own stream with `Connection: close`. The host of the url is sent in
+/// the `Host` header, the path and query in the request line. The non-blocking implementation
+/// sends on tokio's blocking thread pool. Error status codes fail with the variant of their
+/// failure class, e.g. [`PushMetricsError::ServerError`], like the other push clients.
+#[derive(Clone, Debug)]
+pub struct PipePushClient<C> {
+    connector: C,
+}
+
+impl<C: Connect> PipePushClient<C> {
+    /// Creates a new [`PipePushClient`] that sends over the streams of the given connector.
+    pub fn new(connector: C) -> Self {
+        Self { connector }
+    }
+}
+
+#[cfg(windows)]
+impl PipePushClient<NamedPipe> {
+    /// Creates a new [`PipePushClient`] that sends over the Windows named pipe with the given
+    /// path, e.g. `\\.\pipe\collector`.
+    pub fn named_pipe(path: impl Into<PathBuf>) -> Self {
+        Self::new(NamedPipe { path: path.into() })
+    }
+}
+
+/// Sends the request over a new stream of the connector and checks the status of the response.
+/// The headers and body are only read if the status is not a success.
+fn exchange<C: Connect>(connector: &C, url: &Url, request: &[u8]) -> Result<()> {
+    let mut stream = connector.connect()?;
+    stream.write_all(request)?;
+    stream.flush()?;
+
+    let status = parse_status(&read_line(&mut stream)?)?;
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let headers = read_headers(&mut stream)?;
+    let body = read_body(&mut stream, &headers)?;
+    Err(PushMetricsError::response(
+        &status,
+        url,
+        Some(&body),
+        header(&headers, "retry-after"),
+    ))
+}
+
+/// Encodes the request as HTTP/1.1 request.
+fn encode_request(
+    method: Method,
+    url: &Url,
+    body: Option<(&[u8], &str)>,
+    headers: &[(&str, &str)],
+) -> Vec<u8> {
+    let target = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_owned(),
+    };
+    let host = url.host_str().unwrap_or("localhost");
+
+    let mut head = format!(
+        "{} {target} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: {DEFAULT_USER_AGENT}\r\nConnection: close\r\n",
+        method.as_str()
+    );
+    let (body, content_type) = body.unwrap_or((&[], ""));
+    head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    if !content_type.is_empty() {
+        head.push_str(&format!("Content-Type: {content_type}\r\n"));
+    }
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+
+    let mut request = head.into_bytes();
+    request.extend_from_slice(body);
+    request
+}
+
+/// Reads the response up to the end of the next line.
+fn read_line(stream: &mut impl Read) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0; 1];
+    while !line.ends_with(b"\r\n") {
+        match stream.read(&mut byte)? {
+            0 => break,
+            _ => line.push(byte[0]),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&line).trim_end().to_owned())
+}
+
+/// Parses the status code of the status line, e.g. `HTTP/1.1 200 OK`.
+fn parse_status(status_line: &str) -> io::Result<StatusCode> {
+    status_line
+        .split(' ')
+        .nth(1)
+        .and_then(|status| StatusCode::from_bytes(status.as_bytes()).ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid http status line '{status_line}'"),
+            )
+        })
+}
+
+/// Reads the headers up to the empty line that ends them, with lowercase names.
+fn read_headers(stream: &mut impl Read) -> io::Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(stream)?;
+        let Some((name, value)) = line.split_once(':') else {
+            return Ok(headers);
+        };
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Reads the body as announced by the headers: chunked, with a content length or up to the
+/// end of the stream, which the `Connection: close` of the request ends.
+fn read_body(stream: &mut impl Read, headers: &[(String, String)]) -> io::Result<String> {
+    let mut body = Vec::new();
+    if header(headers, "transfer-encoding").is_some_and(|encoding| encoding.ends_with("chunked")) {
+        loop {
+            let size = read_line(stream)?;
+            let size = size.split(';').next().unwrap_or_default().trim();
+            let size = u64::from_str_radix(size, 16).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid chunk size: {error}"),
+                )
+            })?;
+            if size == 0 {
+                break;
+            }
+            stream.take(size).read_to_end(&mut body)?;
+            read_line(stream)?;
+        }
+    } else if let Some(length) = header(headers, "content-length").and_then(|l| l.parse().ok()) {
+        stream.take(length).read_to_end(&mut body)?;
+    } else {
+        stream.read_to_end(&mut body)?;
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[cfg(feature = "blocking")]
+mod blocking_impl {
+    use url::Url;
+
+    use super::encode_request;
+    use super::exchange;
+    use super::Connect;
+    use super::PipePushClient;
+    use crate::blocking::Push;
+    use crate::error::Result;
+    use crate::Method;
+
+    impl<C: Connect, B: AsRef<[u8]>> Push<B> for PipePushClient<C> {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            let body = body.as_ref().map(|(body, ct)| (body.as_ref(), *ct));
+            exchange(
+                &self.connector,
+                url,
+                &encode_request(method, url, body, headers),
+            )
+        }
+    }
+}
+
+#[cfg(feature = "non_blocking")]
+mod non_blocking_impl {
+    use std::io;
+
+    use url::Url;
+
+    use super::encode_request;
+    use super::exchange;
+    use super::Connect;
+    use super::PipePushClient;
+    use crate::error::Result;
+    use crate::non_blocking::Push;
+    use crate::Method;
+
+    impl<C, B> Push<B> for PipePushClient<C>
+    where
+        C: Connect + Clone + Send + Sync + 'static,
+        B: AsRef<[u8]> + Send,
+    {
+        async fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            let body = body.as_ref().map(|(body, ct)| (body.as_ref(), *ct));
+            let request = encode_request(method, url, body, headers);
+            let connector = self.connector.clone();
+            let url = url.clone();
+
+            tokio::task::spawn_blocking(move || exchange(&connector, &url, &request))
+                .await
+                .map_err(io::Error::other)?
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod test {
+    use std::io;
+    use std::io::Cursor;
+    use std::io::Read;
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use url::Url;
+
+    use crate::blocking::Push;
+    use crate::error::PushMetricsError;
+    use crate::pipe::PipePushClient;
+
+    type Recorded = Arc<Mutex<Vec<u8>>>;
+
+    /// A stream that records the request and answers with a fixed response.
+    struct Loopback {
+        request: Recorded,
+        response: Cursor<&'static [u8]>,
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.response.read(buf)
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.request.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn client(
+        response: &'static [u8],
+    ) -> (PipePushClient<impl Fn() -> io::Result<Loopback>>, Recorded) {
+        let request = Recorded::default();
+        let recorded = Arc::clone(&request);
+        let connector =
+            move || Ok(Loopback { request: Arc::clone(&request), response: Cursor::new(response) });
+
+        (PipePushClient::new(connector), recorded)
+    }
+
+    #[test]
+    fn test_request_is_sent_as_http() {
+        // Given I have a pipe that answers with 200
+        let (client, request) = client(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let url = Url::parse("http://collector/metrics/job/batch").unwrap();
+
+        // When I push metrics over it
+        client
+            .push_all(
+                &url,
+                b"up 1\n".to_vec(),
+                "text/plain",
+                &[("X-Request-Id", "42")],
+            )
+            .unwrap();
+
+        // Then the request is written as HTTP/1.1
+        let request = String::from_utf8(request.lock().unwrap().clone()).unwrap();
+        assert!(request.starts_with("PUT /metrics/job/batch HTTP/1.1\r\nHost: collector\r\n"));
+        assert!(request
+            .contains("Content-Length: 5\r\nContent-Type: text/plain\r\nX-Request-Id: 42\r\n"));
+        assert!(request.ends_with("\r\n\r\nup 1\n"));
+    }
+
+    #[test]
+    fn test_error_status_fails_the_push() {
+        // Given I have a pipe that answers with 500
+        let (client, _) = client(
+            b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 13\r\n\r\nstorage full\n",
+        );
+        let url = Url::parse("http://collector/metrics/job/batch").unwrap();
+
+        // When I delete a group over it
+        let result = <PipePushClient<_> as Push<Vec<u8>>>::delete(&client, &url, &[]);
+
+        // Then the push fails with the status, url and body of the response
+        let error = result.unwrap_err();
+        assert!(error.is_retryable());
+        match error {
+            PushMetricsError::ServerError(response) => {
+                assert_eq!(response.url(), &url);
+                assert_eq!(response.body(), Some("storage full\n"));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_too_many_requests_keeps_the_retry_after() {
+        // Given I have a pipe that asks to slow down with a chunked body
+        let (client, _) = client(
+            b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 30\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5\r\nslow \r\n5\r\ndown\n\r\n0\r\n\r\n",
+        );
+        let url = Url::parse("http://collector/metrics/job/batch").unwrap();
+
+        // When I push metrics over it
+        let result = client.push_all(&url, b"up 1\n".to_vec(), "text/plain", &[]);
+
+        // Then the push fails with the body and retry after of the response
+        match result {
+            Err(PushMetricsError::TooManyRequests { response, retry_after }) => {
+                assert_eq!(response.body(), Some("slow down\n"));
+                assert_eq!(retry_after, Some(Duration::from_secs(30)));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
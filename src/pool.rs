@@ -0,0 +1,638 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::text_format;
+use crate::utils::CONTENT_TYPE_OPENMETRICS;
+use crate::utils::CONTENT_TYPE_TEXT;
+use crate::Method;
+
+const DEFAULT_FAILURE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// `GatewayPool` spreads the pushes of a [`MetricsPusher`](crate::non_blocking::MetricsPusher)
+/// over several pushgateway instances. It wraps the push client and implements the `Push` and
+/// `Fetch` traits itself, so it is used in place of the wrapped client.
+///
+/// Pushes are distributed by smooth weighted round-robin, so a gateway with weight 3 receives
+/// three times the pushes of a gateway with weight 1. A gateway whose last push failed is
+/// skipped for the failure backoff; if all gateways failed recently the least recently failed
+/// one is used. Gateways with weight 0 are standbys that only receive pushes if all weighted
+/// gateways failed recently. Gateways that fail the health checks of the [`GatewaySet`] are
+/// evicted until they are ready again.
+///
+/// Only the origin of the urls is replaced, so all gateways have to serve the pushgateway api
+/// below the same path as the url the [`MetricsPusher`](crate::non_blocking::MetricsPusher)
+/// was created with. Gateways that only accept another format than the converter encodes to
+/// are configured with [`GatewayPool::with_format`].
+#[derive(Debug)]
+pub struct GatewayPool<P> {
+    push_client: P,
+    gateways: Arc<Mutex<Vec<Gateway>>>,
+    failure_backoff: Duration,
+}
+
+/// `GatewaySet` is a handle to the gateways of a [`GatewayPool`] that replaces them while the
+/// pool is in use, e.g. to keep them up to date with service discovery.
+#[derive(Clone, Debug)]
+pub struct GatewaySet {
+    gateways: Arc<Mutex<Vec<Gateway>>>,
+}
+
+/// `WireFormat` is the format in which the pushes are sent to a gateway of a [`GatewayPool`],
+/// e.g. protobuf to a local pushgateway but text to a hosted one that only accepts text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WireFormat {
+    /// Sends the payloads in the format the converter encoded them to.
+    #[default]
+    Encoded,
+    /// Sends the protobuf format. Payloads in other formats cannot be re-encoded as protobuf.
+    Protobuf,
+    /// Sends the Prometheus text format. Re-encoding protobuf requires the `prometheus_crate`
+    /// feature.
+    Text,
+    /// Sends the OpenMetrics text format. Re-encoding protobuf requires the `prometheus_crate`
+    /// feature.
+    OpenMetrics,
+}
+
+impl WireFormat {
+    fn of(content_type: &str) -> Option<Self> {
+        match content_type {
+            ct if ct.starts_with("application/vnd.google.protobuf") => Some(WireFormat::Protobuf),
+            ct if ct.starts_with("text/plain") => Some(WireFormat::Text),
+            ct if ct.starts_with("application/openmetrics-text") => Some(WireFormat::OpenMetrics),
+            _ => None,
+        }
+    }
+}
+
+/// `Transcode` is implemented by the payloads a [`GatewayPool`] re-encodes for gateways with
+/// another [`WireFormat`].
+pub trait Transcode: Sized {
+    /// Re-encodes the payload of the given content type in the given format and returns it
+    /// together with its new content type.
+    fn transcode<'a>(
+        self,
+        content_type: &'a str,
+        format: WireFormat,
+    ) -> Result<(Self, Cow<'a, str>)>;
+}
+
+impl Transcode for Vec<u8> {
+    fn transcode<'a>(
+        self,
+        content_type: &'a str,
+        format: WireFormat,
+    ) -> Result<(Self, Cow<'a, str>)> {
+        let source = WireFormat::of(content_type);
+        if format == WireFormat::Encoded || source == Some(format) {
+            return Ok((self, Cow::Borrowed(content_type)));
+        }
+
+        let text = match source {
+            Some(WireFormat::Protobuf) => protobuf_to_text(&self)?,
+            Some(WireFormat::Text) => utf8(self)?,
+            Some(WireFormat::OpenMetrics) => {
+                let families = text_format::parse(&utf8(self)?)?;
+                text_format::encode(&text_format::to_prometheus_text(families))
+            }
+            _ => return Err(unsupported(content_type, format)),
+        };
+
+        match format {
+            WireFormat::Text => Ok((text.into_bytes(), Cow::Borrowed(CONTENT_TYPE_TEXT))),
+            WireFormat::OpenMetrics => {
+                let openmetrics = text_format::to_openmetrics(text_format::parse(&text)?);
+                Ok((
+                    openmetrics.into_bytes(),
+                    Cow::Borrowed(CONTENT_TYPE_OPENMETRICS),
+                ))
+            }
+            _ => Err(unsupported(content_type, format)),
+        }
+    }
+}
+
+impl Transcode for String {
+    fn transcode<'a>(
+        self,
+        content_type: &'a str,
+        format: WireFormat,
+    ) -> Result<(Self, Cow<'a, str>)> {
+        let (payload, content_type) = self.into_bytes().transcode(content_type, format)?;
+        Ok((utf8(payload)?, content_type))
+    }
+}
+
+/// Re-encodes the body of a request, if there is one, in the given format.
+fn transcode<B: Transcode>(
+    body: Option<(B, &str)>,
+    format: WireFormat,
+) -> Result<Option<(B, Cow<'_, str>)>> {
+    body.map(|(body, content_type)| body.transcode(content_type, format))
+        .transpose()
+}
+
+fn utf8(payload: Vec<u8>) -> Result<String> {
+    String::from_utf8(payload).map_err(|error| PushMetricsError::Transcode(error.to_string()))
+}
+
+#[cfg(feature = "prometheus_crate")]
+fn protobuf_to_text(payload: &[u8]) -> Result<String> {
+    let (text, _) = crate::prometheus_crate::encode_text(payload)?;
+    utf8(text)
+}
+
+#[cfg(not(feature = "prometheus_crate"))]
+fn protobuf_to_text(_payload: &[u8]) -> Result<String> {
+    Err(PushMetricsError::Transcode(String::from(
+        "decoding protobuf requires the `prometheus_crate` feature",
+    )))
+}
+
+fn unsupported(content_type: &str, format: WireFormat) -> PushMetricsError {
+    PushMetricsError::Transcode(format!("'{content_type}' cannot be sent as {format:?}"))
+}
+
+#[derive(Debug)]
+struct Gateway {
+    url: Url,
+    weight: u32,
+    format: WireFormat,
+    current_weight: i64,
+    last_failure: Option<Instant>,
+    ready: bool,
+}
+
+impl<P> GatewayPool<P> {
+    /// Creates a new [`GatewayPool`] that pushes with the given client to the gateway at the
+    /// given url with weight 1.
+    pub fn new(push_client: P, url: &Url) -> Self {
+        Self {
+            push_client,
+            gateways: Arc::new(Mutex::new(Vec::new())),
+            failure_backoff: DEFAULT_FAILURE_BACKOFF,
+        }
+        .with_gateway(url, 1)
+    }
+
+    /// Adds the gateway at the given url with the given weight to the pool.
+    pub fn with_gateway(self, url: &Url, weight: u32) -> Self {
+        lock(&self.gateways).push(Gateway::new(url, weight));
+        self
+    }
+
+    /// Sets the [`WireFormat`] in which the pushes are sent to the gateway with the given url,
+    /// which has to be added to the pool already. Defaults to [`WireFormat::Encoded`].
+    pub fn with_format(self, url: &Url, format: WireFormat) -> Self {
+        let mut gateways = lock(&self.gateways);
+        if let Some(gateway) = gateways.iter_mut().find(|gateway| gateway.url == *url) {
+            gateway.format = format;
+        }
+        drop(gateways);
+
+        self
+    }
+
+    /// Returns a [`GatewaySet`] to replace the gateways of the pool at runtime.
+    pub fn gateway_set(&self) -> GatewaySet {
+        GatewaySet { gateways: Arc::clone(&self.gateways) }
+    }
+
+    /// Sets how long a gateway is skipped after a failed push. Defaults to 30 seconds.
+    pub fn with_failure_backoff(mut self, failure_backoff: Duration) -> Self {
+        self.failure_backoff = failure_backoff;
+        self
+    }
+
+    /// Selects the next gateway and returns its url and format together with the given url
+    /// moved to the gateway.
+    fn select(&self, url: &Url) -> (Url, Url, WireFormat) {
+        let mut gateways = lock(&self.gateways);
+        let now = Instant::now();
+        let healthy = |gateway: &Gateway| {
+            gateway.ready
+                && gateway
+                    .last_failure
+                    .is_none_or(|failure| now.duration_since(failure) >= self.failure_backoff)
+        };
+
+        let weighted: Vec<usize> = (0..gateways.len())
+            .filter(|&index| gateways[index].weight > 0 && healthy(&gateways[index]))
+            .collect();
+        let standbys: Vec<usize> = (0..gateways.len())
+            .filter(|&index| gateways[index].weight == 0 && healthy(&gateways[index]))
+            .collect();
+
+        let index = match (weighted.is_empty(), standbys.first()) {
+            (false, _) => round_robin(&mut gateways, &weighted),
+            (true, Some(&standby)) => standby,
+            (true, None) => (0..gateways.len())
+                .min_by_key(|&index| gateways[index].last_failure)
+                .unwrap_or_default(),
+        };
+
+        let gateway = gateways[index].url.clone();
+        let mut target = gateway.clone();
+        target.set_path(url.path());
+        target.set_query(url.query());
+
+        (gateway, target, gateways[index].format)
+    }
+
+    /// Records the result of a push to the gateway with the given url.
+    fn record<T>(&self, gateway: &Url, result: &Result<T>) {
+        let mut gateways = lock(&self.gateways);
+        if let Some(gateway) = gateways.iter_mut().find(|current| current.url == *gateway) {
+            gateway.last_failure = match result {
+                Ok(_) => None,
+                Err(_) => Some(Instant::now()),
+            };
+        }
+    }
+}
+
+impl GatewaySet {
+    /// Replaces the gateways of the pool with the given urls and weights. Gateways that were
+    /// already part of the pool keep their failure and health state and their format. An empty
+    /// list is ignored, so the pool keeps pushing to the last known gateways.
+    pub fn replace(&self, gateways: &[(Url, u32)]) {
+        if gateways.is_empty() {
+            return;
+        }
+
+        let mut current = lock(&self.gateways);
+        let replaced = gateways
+            .iter()
+            .map(|(url, weight)| {
+                let existing = current.iter().find(|gateway| gateway.url == *url);
+
+                Gateway {
+                    last_failure: existing.and_then(|gateway| gateway.last_failure),
+                    ready: existing.is_none_or(|gateway| gateway.ready),
+                    format: existing.map_or(WireFormat::Encoded, |gateway| gateway.format),
+                    ..Gateway::new(url, *weight)
+                }
+            })
+            .collect();
+
+        *current = replaced;
+    }
+
+    /// Returns the urls and weights of the gateways currently in the pool.
+    pub fn gateways(&self) -> Vec<(Url, u32)> {
+        lock(&self.gateways)
+            .iter()
+            .map(|gateway| (gateway.url.clone(), gateway.weight))
+            .collect()
+    }
+}
+
+#[cfg(feature = "non_blocking")]
+impl GatewaySet {
+    /// Checks the `/-/ready` endpoint of every gateway with the given [`Fetch`] client once.
+    /// Gateways that are not ready are evicted from the pool and re-admitted as soon as they
+    /// are ready again.
+    ///
+    /// [`Fetch`]: crate::non_blocking::Fetch
+    pub async fn check_health<F: crate::non_blocking::Fetch>(&self, fetch_client: &F) {
+        for (url, _) in self.gateways() {
+            let ready = match url.join("-/ready") {
+                Ok(ready_url) => fetch_client.fetch(&ready_url).await.is_ok(),
+                Err(_) => false,
+            };
+
+            let mut gateways = lock(&self.gateways);
+            if let Some(gateway) = gateways.iter_mut().find(|gateway| gateway.url == url) {
+                #[cfg(feature = "log")]
+                match (gateway.ready, ready) {
+                    (true, false) => log::warn!("Evicting pushgateway {url} as it is not ready"),
+                    (false, true) => log::info!("Re-admitting pushgateway {url} as it is ready"),
+                    _ => {}
+                }
+
+                gateway.ready = ready;
+            }
+        }
+    }
+
+    /// Checks the health of the gateways every `interval` until the returned future is dropped,
+    /// see [`GatewaySet::check_health`].
+    #[cfg(feature = "health_check")]
+    pub async fn run_health_checks<F: crate::non_blocking::Fetch>(
+        &self,
+        fetch_client: &F,
+        interval: Duration,
+    ) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            self.check_health(fetch_client).await;
+        }
+    }
+}
+
+impl Gateway {
+    fn new(url: &Url, weight: u32) -> Self {
+        Self {
+            url: url.clone(),
+            weight,
+            format: WireFormat::Encoded,
+            current_weight: 0,
+            last_failure: None,
+            ready: true,
+        }
+    }
+}
+
+fn lock(gateways: &Mutex<Vec<Gateway>>) -> std::sync::MutexGuard<'_, Vec<Gateway>> {
+    gateways
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Selects one of the given gateways by smooth weighted round-robin.
+fn round_robin(gateways: &mut [Gateway], candidates: &[usize]) -> usize {
+    let total: i64 = candidates
+        .iter()
+        .map(|&index| i64::from(gateways[index].weight))
+        .sum();
+
+    let mut selected = candidates[0];
+    for &index in candidates {
+        gateways[index].current_weight += i64::from(gateways[index].weight);
+        if gateways[index].current_weight > gateways[selected].current_weight {
+            selected = index;
+        }
+    }
+
+    gateways[selected].current_weight -= total;
+    selected
+}
+
+#[cfg(feature = "non_blocking")]
+impl<P, B> crate::non_blocking::Push<B> for GatewayPool<P>
+where
+    P: crate::non_blocking::Push<B> + Sync,
+    B: Transcode + Send,
+{
+    async fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(B, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        let (gateway, url, format) = self.select(url);
+        let (body, content_type) = transcode(body, format)?.unzip();
+        let body = body.zip(content_type.as_deref());
+        let result = self.push_client.send(method, &url, body, headers).await;
+        self.record(&gateway, &result);
+        result
+    }
+}
+
+#[cfg(feature = "non_blocking")]
+impl<P> crate::non_blocking::Fetch for GatewayPool<P>
+where
+    P: crate::non_blocking::Fetch + Sync,
+{
+    async fn fetch(&self, url: &Url) -> Result<String> {
+        let (gateway, url, _) = self.select(url);
+        let result = self.push_client.fetch(&url).await;
+        self.record(&gateway, &result);
+        result
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<P, B> crate::blocking::Push<B> for GatewayPool<P>
+where
+    P: crate::blocking::Push<B>,
+    B: Transcode,
+{
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(B, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        let (gateway, url, format) = self.select(url);
+        let (body, content_type) = transcode(body, format)?.unzip();
+        let body = body.zip(content_type.as_deref());
+        let result = self.push_client.send(method, &url, body, headers);
+        self.record(&gateway, &result);
+        result
+    }
+
+    fn push_all_with_timeout(
+        &self,
+        url: &Url,
+        body: B,
+        content_type: &str,
+        headers: &[(&str, &str)],
+        timeout: Duration,
+    ) -> Result<()> {
+        let (gateway, url, format) = self.select(url);
+        let (body, content_type) = body.transcode(content_type, format)?;
+        let result =
+            self.push_client
+                .push_all_with_timeout(&url, body, &content_type, headers, timeout);
+        self.record(&gateway, &result);
+        result
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<P> crate::blocking::Fetch for GatewayPool<P>
+where
+    P: crate::blocking::Fetch,
+{
+    fn fetch(&self, url: &Url) -> Result<String> {
+        let (gateway, url, _) = self.select(url);
+        let result = self.push_client.fetch(&url);
+        self.record(&gateway, &result);
+        result
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::collections::HashMap;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::non_blocking::MetricsPusher;
+    use crate::pool::GatewayPool;
+    use crate::pool::WireFormat;
+    use crate::raw::RawMetricsConverter;
+    use crate::with_reqwest::PushClient;
+
+    #[tokio::test]
+    async fn test_pushes_are_spread_by_weight() {
+        // Given I have a central and a standby push gateway
+        let mut central = Server::new_async().await;
+        let central_mock = central.mock("PUT", "/metrics/job/pooled").create();
+        let mut standby = Server::new_async().await;
+        let standby_mock = standby.mock("PUT", "/metrics/job/pooled").create();
+
+        // And a metrics pusher that pushes to both with a weight of 3 to 1
+        let central_url = Url::parse(&central.url()).unwrap();
+        let standby_url = Url::parse(&standby.url()).unwrap();
+        let push_client = GatewayPool::new(PushClient::new(reqwest::Client::new()), &standby_url)
+            .with_gateway(&central_url, 3);
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &central_url).unwrap();
+
+        // When I push eight times
+        for _ in 0..8 {
+            metrics_pusher
+                .push_all("pooled", &HashMap::new(), "up 1\n")
+                .await
+                .expect("Failed to push metrics");
+        }
+
+        // Then the central gateway receives three times the pushes of the standby
+        central_mock.expect(6).assert();
+        standby_mock.expect(2).assert();
+    }
+
+    #[tokio::test]
+    async fn test_pushes_are_sent_in_the_format_of_the_gateway() {
+        // Given I have a push gateway that accepts OpenMetrics and a hosted one that only
+        // accepts text
+        let mut local = Server::new_async().await;
+        let local_mock = local
+            .mock("PUT", "/metrics/job/pooled")
+            .match_header(
+                "content-type",
+                mockito::Matcher::Regex("^application/openmetrics".into()),
+            )
+            .match_body("# TYPE requests counter\nrequests_total 1\n# EOF\n")
+            .create();
+        let mut hosted = Server::new_async().await;
+        let hosted_mock = hosted
+            .mock("PUT", "/metrics/job/pooled")
+            .match_header("content-type", "text/plain; version=0.0.4")
+            .match_body("# TYPE requests_total counter\nrequests_total 1\n")
+            .create();
+
+        // And a metrics pusher that pushes to both with the hosted gateway configured for text
+        let local_url = Url::parse(&local.url()).unwrap();
+        let hosted_url = Url::parse(&hosted.url()).unwrap();
+        let push_client = GatewayPool::new(PushClient::new(reqwest::Client::new()), &local_url)
+            .with_gateway(&hosted_url, 1)
+            .with_format(&hosted_url, WireFormat::Text);
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::openmetrics(), &local_url)
+                .unwrap();
+
+        // When I push twice
+        for _ in 0..2 {
+            metrics_pusher
+                .push_all(
+                    "pooled",
+                    &HashMap::new(),
+                    "# TYPE requests counter\nrequests_total 1\n# EOF\n",
+                )
+                .await
+                .expect("Failed to push metrics");
+        }
+
+        // Then each gateway receives the metrics in its own format
+        local_mock.expect(1).assert();
+        hosted_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_failed_gateway_is_skipped() {
+        // Given I have a failing and a healthy push gateway
+        let mut failing = Server::new_async().await;
+        let failing_mock = failing
+            .mock("PUT", "/metrics/job/pooled")
+            .with_status(503)
+            .create();
+        let mut healthy = Server::new_async().await;
+        let healthy_mock = healthy.mock("PUT", "/metrics/job/pooled").create();
+
+        // And a metrics pusher that pushes to both with the same weight
+        let failing_url = Url::parse(&failing.url()).unwrap();
+        let push_client = GatewayPool::new(PushClient::new(reqwest::Client::new()), &failing_url)
+            .with_gateway(&Url::parse(&healthy.url()).unwrap(), 1);
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &failing_url).unwrap();
+
+        // When I push four times
+        let mut results = Vec::new();
+        for _ in 0..4 {
+            let result = metrics_pusher
+                .push_all("pooled", &HashMap::new(), "up 1\n")
+                .await;
+            results.push(result.is_ok());
+        }
+
+        // Then only the first push hits the failing gateway
+        assert_eq!(results, [false, true, true, true]);
+        failing_mock.expect(1).assert();
+        healthy_mock.expect(3).assert();
+    }
+
+    #[tokio::test]
+    async fn test_unready_gateway_is_evicted_until_it_recovers() {
+        // Given I have a push gateway that is not ready and one that is
+        let mut recovering = Server::new_async().await;
+        let not_ready_mock = recovering.mock("GET", "/-/ready").with_status(503).create();
+        let recovering_mock = recovering
+            .mock("PUT", "/metrics/job/pooled")
+            .expect(1)
+            .create();
+        let mut ready = Server::new_async().await;
+        ready.mock("GET", "/-/ready").create();
+        let ready_mock = ready.mock("PUT", "/metrics/job/pooled").expect(3).create();
+
+        // And a metrics pusher that pushes to both with the same weight
+        let recovering_url = Url::parse(&recovering.url()).unwrap();
+        let push_client =
+            GatewayPool::new(PushClient::new(reqwest::Client::new()), &recovering_url)
+                .with_gateway(&Url::parse(&ready.url()).unwrap(), 1);
+        let gateway_set = push_client.gateway_set();
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &recovering_url).unwrap();
+        let fetch_client = PushClient::new(reqwest::Client::new());
+
+        // When I check the health and push twice
+        gateway_set.check_health(&fetch_client).await;
+        for _ in 0..2 {
+            metrics_pusher
+                .push_all("pooled", &HashMap::new(), "up 1\n")
+                .await
+                .expect("Failed to push metrics");
+        }
+
+        // Then only the ready gateway receives the pushes
+        assert!(!recovering_mock.matched());
+
+        // When the evicted gateway is ready again and I check the health and push twice
+        not_ready_mock.remove();
+        recovering.mock("GET", "/-/ready").create();
+        gateway_set.check_health(&fetch_client).await;
+        for _ in 0..2 {
+            metrics_pusher
+                .push_all("pooled", &HashMap::new(), "up 1\n")
+                .await
+                .expect("Failed to push metrics");
+        }
+
+        // Then both gateways receive pushes again
+        recovering_mock.assert();
+        ready_mock.assert();
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 use prometheus_client::collector::Collector;
@@ -7,7 +8,6 @@ use url::Url;
 
 use crate::error::Result;
 use crate::utils::build_url;
-use crate::utils::validate;
 use crate::ConvertMetrics;
 
 #[cfg(feature = "with_reqwest")]
@@ -22,13 +22,104 @@ use reqwest::Client;
 #[cfg(feature = "with_reqwest_blocking")]
 use crate::blocking;
 
+const EOF: &str = "# EOF";
+const UNIT: &str = "# UNIT ";
+
 /// `PrometheusClientMetricsConverter` is a [`ConvertMetrics`] implementation that converts
 /// the given [`Collector`]s to a [`String`] of metrics that can be pushed to the pushgateway.
-pub struct PrometheusClientMetricsConverter;
+///
+/// By default the payload is pushed like [`encode`] writes it. `with_eof` and `with_unit_lines`
+/// adjust the encoding for pushgateways that do not expect the OpenMetrics extras. Timestamps
+/// are not supported, as `prometheus-client` does not encode them yet.
+#[derive(Clone, Debug, Default)]
+pub struct PrometheusClientMetricsConverter {
+    prefix: Option<String>,
+    labels: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    eof: Option<bool>,
+    unit_lines: Option<bool>,
+}
+
+impl PrometheusClientMetricsConverter {
+    /// Creates a new [`PrometheusClientMetricsConverter`] that encodes the collectors as they
+    /// are.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefixes the names of all metrics with the given prefix, like a registry created with
+    /// [`Registry::with_prefix`].
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_owned());
+        self
+    }
+
+    /// Adds the given constant labels to all metrics, like a registry created with
+    /// [`Registry::with_labels`]. The labels are encoded sorted by name.
+    pub fn with_labels(mut self, labels: &HashMap<&str, &str>) -> Self {
+        let mut labels: Vec<_> = labels
+            .iter()
+            .map(|(name, value)| (Cow::Owned(name.to_string()), Cow::Owned(value.to_string())))
+            .collect();
+        labels.sort();
+        self.labels = labels;
+        self
+    }
+
+    /// Ends every payload with the `# EOF` marker of OpenMetrics if `true`, or removes it if
+    /// `false`. Payloads are pushed with or without it as they are given by default.
+    pub fn with_eof(mut self, eof: bool) -> Self {
+        self.eof = Some(eof);
+        self
+    }
+
+    /// Keeps the `# UNIT` lines of metrics with a unit if `true`, or removes them if `false`,
+    /// e.g. for pushgateways that only understand the Prometheus text format. The unit stays a
+    /// suffix of the metric name either way.
+    pub fn with_unit_lines(mut self, unit_lines: bool) -> Self {
+        self.unit_lines = Some(unit_lines);
+        self
+    }
+
+    /// Applies the encoding options to the sorted payload.
+    fn apply_options(&self, payload: String) -> String {
+        if self.eof.is_none() && self.unit_lines.is_none() {
+            return payload;
+        }
+
+        let mut encoded = String::with_capacity(payload.len());
+        let mut eof = false;
+        for line in payload.lines() {
+            if line == EOF {
+                eof = true;
+                continue;
+            }
+            if self.unit_lines == Some(false) && line.starts_with(UNIT) {
+                continue;
+            }
+            encoded.push_str(line);
+            encoded.push('\n');
+        }
+
+        if self.eof.unwrap_or(eof) {
+            encoded.push_str(EOF);
+            encoded.push('\n');
+        }
+
+        encoded
+    }
+
+    fn registry(&self) -> Registry {
+        let labels = self.labels.iter().cloned();
+        match &self.prefix {
+            Some(prefix) => Registry::with_prefix_and_labels(prefix, labels),
+            None => Registry::with_labels(labels),
+        }
+    }
+}
 
 impl ConvertMetrics<String, Vec<Box<dyn Collector>>, Vec<u8>> for PrometheusClientMetricsConverter {
     fn metrics_from(&self, collectors: Vec<Box<dyn Collector>>) -> Result<String> {
-        let mut registry = Registry::default();
+        let mut registry = self.registry();
         for collector in collectors {
             registry.register_collector(collector);
         }
@@ -45,13 +136,139 @@ impl ConvertMetrics<String, Vec<Box<dyn Collector>>, Vec<u8>> for PrometheusClie
         grouping: &HashMap<&str, &str>,
         metric_families: String,
     ) -> Result<(Url, Vec<u8>, String)> {
-        let url = build_url(url, validate(job)?, grouping)?;
-        let encoded_metrics = metric_families.into_bytes();
+        let url = build_url(url, job, grouping)?;
+        let encoded_metrics = self
+            .apply_options(sort_families(&metric_families))
+            .into_bytes();
 
         Ok((url, encoded_metrics, String::from("text/plain")))
     }
 }
 
+/// Concatenates OpenMetrics text payloads, e.g. the encodings of several registries, into one
+/// payload that can be pushed at once. The `# EOF` markers of the payloads are removed and
+/// exactly one is appended at the end.
+pub fn concat_openmetrics<I>(payloads: I) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut concatenated = String::new();
+    for payload in payloads {
+        for line in payload.as_ref().lines().filter(|line| *line != EOF) {
+            concatenated.push_str(line);
+            concatenated.push('\n');
+        }
+    }
+
+    concatenated.push_str(EOF);
+    concatenated.push('\n');
+    concatenated
+}
+
+/// A metric family of a text payload with its comment lines and its series, where the lines of
+/// a series, e.g. the buckets, sum and count of a histogram, are kept together in their order.
+struct TextFamily<'a> {
+    name: &'a str,
+    comments: Vec<&'a str>,
+    series: Vec<(Vec<&'a str>, Vec<&'a str>)>,
+}
+
+/// Sorts the metric families of a text payload by name and their series by labels, so identical
+/// metrics are always encoded to identical payloads, no matter in which order they were
+/// registered or created. A trailing `# EOF` stays last.
+fn sort_families(text: &str) -> String {
+    let mut families: Vec<TextFamily> = Vec::new();
+    let mut eof = None;
+
+    for line in text.lines() {
+        if line == EOF {
+            eof = Some(line);
+            continue;
+        }
+
+        let is_comment = line.starts_with('#');
+        let name = match is_comment {
+            true => line.split(' ').nth(2).unwrap_or_default(),
+            false => line.split(['{', ' ']).next().unwrap_or_default(),
+        };
+
+        let starts_family = match families.last() {
+            None => true,
+            Some(family) if is_comment => !family.series.is_empty() || family.name != name,
+            Some(family) => !family.comments.is_empty() && !name.starts_with(family.name),
+        };
+        if starts_family {
+            families.push(TextFamily { name, comments: Vec::new(), series: Vec::new() });
+        }
+
+        let family = families.last_mut().expect("a family was pushed");
+        if is_comment {
+            family.comments.push(line);
+            continue;
+        }
+
+        let key = series_key(line);
+        match family.series.last_mut() {
+            Some((last_key, lines)) if *last_key == key => lines.push(line),
+            _ => family.series.push((key, vec![line])),
+        }
+    }
+
+    families.sort_by(|a, b| a.name.cmp(b.name));
+
+    let mut sorted = String::with_capacity(text.len());
+    for mut family in families {
+        family.series.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let lines = family.series.iter().flat_map(|(_, lines)| lines.iter());
+        for line in family.comments.iter().chain(lines) {
+            sorted.push_str(line);
+            sorted.push('\n');
+        }
+    }
+
+    if let Some(eof) = eof {
+        sorted.push_str(eof);
+        sorted.push('\n');
+    }
+
+    sorted
+}
+
+/// Returns the labels of a sample line that identify its series, i.e. without `le` and
+/// `quantile` that only tell the lines of a histogram or summary apart.
+fn series_key(line: &str) -> Vec<&str> {
+    let Some(start) = line.find('{') else {
+        return Vec::new();
+    };
+
+    let mut labels = Vec::new();
+    let mut label_start = start + 1;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices().skip_while(|(index, _)| *index <= start) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ',' | '}' if !in_quotes => {
+                let label = line[label_start..index].trim();
+                if !label.is_empty() && !label.starts_with("le=") && !label.starts_with("quantile=")
+                {
+                    labels.push(label);
+                }
+                label_start = index + 1;
+                if c == '}' {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    labels
+}
+
 #[cfg(feature = "with_reqwest")]
 pub type PrometheusClientMetricsPusher = MetricsPusher<
     PushClient,
@@ -72,7 +289,7 @@ where
     pub fn create(client: Client, url: &Url) -> Result<PrometheusClientMetricsPusher> {
         MetricsPusher::new(
             PushClient::new(client),
-            PrometheusClientMetricsConverter,
+            PrometheusClientMetricsConverter::new(),
             url,
         )
     }
@@ -101,7 +318,7 @@ where
     ) -> Result<PrometheusClientMetricsPusherBlocking> {
         blocking::MetricsPusher::new(
             blocking::with_reqwest::PushClient::new(client),
-            PrometheusClientMetricsConverter,
+            PrometheusClientMetricsConverter::new(),
             url,
         )
     }
@@ -110,13 +327,18 @@ where
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
+    use std::fmt::Error;
 
     use mockito::Mock;
     use mockito::Server;
     use mockito::ServerGuard;
+    use prometheus_client::collector::Collector;
     use prometheus_client::encoding::text::encode;
+    use prometheus_client::encoding::DescriptorEncoder;
     use prometheus_client::encoding::EncodeLabelSet;
     use prometheus_client::encoding::EncodeLabelValue;
+    use prometheus_client::encoding::EncodeMetric;
+    use prometheus_client::metrics::counter::ConstCounter;
     use prometheus_client::metrics::counter::Counter;
     use prometheus_client::metrics::family::Family;
     use prometheus_client::registry::Registry;
@@ -125,6 +347,10 @@ mod test {
     use url::Url;
 
     use crate::prometheus_client_crate;
+    use crate::prometheus_client_crate::concat_openmetrics;
+    use crate::prometheus_client_crate::sort_families;
+    use crate::prometheus_client_crate::PrometheusClientMetricsConverter;
+    use crate::ConvertMetrics;
 
     #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
     enum Method {
@@ -159,6 +385,123 @@ mod test {
         metrics
     }
 
+    #[derive(Debug)]
+    struct RequestsCollector;
+
+    impl Collector for RequestsCollector {
+        fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+            let counter = ConstCounter::new(1u64);
+            let metric_encoder = encoder.encode_descriptor(
+                "requests",
+                "Number of requests",
+                None,
+                counter.metric_type(),
+            )?;
+            counter.encode(metric_encoder)
+        }
+    }
+
+    #[test]
+    fn test_metrics_are_prefixed_and_labeled() {
+        // Given I have a converter with a prefix and constant labels
+        let converter = PrometheusClientMetricsConverter::new()
+            .with_prefix("app")
+            .with_labels(&HashMap::from([("env", "test"), ("app", "push")]));
+
+        // When I convert a collector
+        let metrics = converter
+            .metrics_from(vec![Box::new(RequestsCollector)])
+            .unwrap();
+
+        // Then the metric is prefixed and carries the labels sorted by name
+        assert!(metrics.contains("app_requests_total{app=\"push\",env=\"test\"} 1\n"));
+    }
+
+    #[test]
+    fn test_eof_and_unit_lines_are_configurable() {
+        // Given I have a payload with a unit and without an EOF marker
+        let payload = "# HELP latency_seconds Latency.\n".to_owned()
+            + "# TYPE latency_seconds gauge\n"
+            + "# UNIT latency_seconds seconds\n"
+            + "latency_seconds 1.5\n";
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let convert = |converter: PrometheusClientMetricsConverter| {
+            let (_, body, _) = converter
+                .create_push_details("job", &url, &HashMap::new(), payload.clone())
+                .unwrap();
+            String::from_utf8(body).unwrap()
+        };
+
+        // When I convert it with and without encoding options
+        let unchanged = convert(PrometheusClientMetricsConverter::new());
+        let adjusted = convert(
+            PrometheusClientMetricsConverter::new()
+                .with_eof(true)
+                .with_unit_lines(false),
+        );
+
+        // Then it is pushed as is by default and with EOF but without the unit line otherwise
+        assert_eq!(unchanged, payload);
+        assert_eq!(
+            adjusted,
+            "# HELP latency_seconds Latency.\n# TYPE latency_seconds gauge\nlatency_seconds 1.5\n# EOF\n"
+        );
+    }
+
+    #[test]
+    fn test_concat_openmetrics_keeps_a_single_eof() {
+        // Given I have the encodings of two registries
+        let first = "# TYPE a counter\na_total 1\n# EOF\n";
+        let second = "# TYPE b gauge\nb 2\n# EOF";
+
+        // When I concatenate them
+        let concatenated = concat_openmetrics([first, second]);
+
+        // Then there is exactly one EOF marker at the end
+        assert_eq!(
+            concatenated,
+            "# TYPE a counter\na_total 1\n# TYPE b gauge\nb 2\n# EOF\n"
+        );
+    }
+
+    #[test]
+    fn test_families_and_series_are_sorted() {
+        // Given I have a payload with unsorted families and series
+        let payload = "# HELP b B.\n".to_owned()
+            + "# TYPE b histogram\n"
+            + "b_bucket{path=\"/y\",le=\"1\"} 1\n"
+            + "b_bucket{path=\"/y\",le=\"+Inf\"} 1\n"
+            + "b_count{path=\"/y\"} 1\n"
+            + "b_bucket{path=\"/x\",le=\"1\"} 0\n"
+            + "b_bucket{path=\"/x\",le=\"+Inf\"} 2\n"
+            + "b_count{path=\"/x\"} 2\n"
+            + "# HELP a A.\n"
+            + "# TYPE a counter\n"
+            + "a_total{path=\"/y,}\"} 1\n"
+            + "a_total{path=\"/x\"} 2\n"
+            + "# EOF\n";
+
+        // When I sort them
+        let sorted = sort_families(&payload);
+
+        // Then the families are sorted by name and the series by labels with their lines intact
+        let expected = "# HELP a A.\n".to_owned()
+            + "# TYPE a counter\n"
+            + "a_total{path=\"/x\"} 2\n"
+            + "a_total{path=\"/y,}\"} 1\n"
+            + "# HELP b B.\n"
+            + "# TYPE b histogram\n"
+            + "b_bucket{path=\"/x\",le=\"1\"} 0\n"
+            + "b_bucket{path=\"/x\",le=\"+Inf\"} 2\n"
+            + "b_count{path=\"/x\"} 2\n"
+            + "b_bucket{path=\"/y\",le=\"1\"} 1\n"
+            + "b_bucket{path=\"/y\",le=\"+Inf\"} 1\n"
+            + "b_count{path=\"/y\"} 1\n"
+            + "# EOF\n";
+        assert_eq!(sorted, expected);
+        assert_eq!(sort_families(&sorted), sorted);
+    }
+
     fn create_push_gateway_mock(
         server: &mut ServerGuard,
     ) -> (Mock, Url, &'static str, HashMap<&'static str, &'static str>) {
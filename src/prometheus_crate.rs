@@ -1,18 +1,27 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 use prometheus::core::Collector;
+use prometheus::proto::Metric;
 use prometheus::proto::MetricFamily;
 use prometheus::Encoder;
+#[cfg(feature = "prometheus_crate")]
 use prometheus::ProtobufEncoder;
 use prometheus::Registry;
+use prometheus::TextEncoder;
+#[cfg(feature = "prometheus_crate")]
+use protobuf::CodedInputStream;
 use url::Url;
 
 use crate::error::LabelType;
 use crate::error::PushMetricsError;
 use crate::error::Result;
 use crate::utils::build_url;
-use crate::utils::validate;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::utils::PushType;
 use crate::ConvertMetrics;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::PushReceipt;
 
 #[cfg(feature = "with_reqwest")]
 use crate::non_blocking::MetricsPusher;
@@ -26,9 +35,18 @@ use reqwest::Client;
 #[cfg(feature = "with_reqwest_blocking")]
 use crate::blocking;
 
+/// The encoder of the pushed metrics: protobuf with the `prometheus_crate` feature, the text
+/// format with `prometheus_crate_text` alone, where the prometheus crate lacks its `protobuf`
+/// feature.
+#[cfg(feature = "prometheus_crate")]
+pub(crate) type PushEncoder = ProtobufEncoder;
+#[cfg(not(feature = "prometheus_crate"))]
+pub(crate) type PushEncoder = TextEncoder;
+
 /// `PrometheusMetricsConverter` is a [`ConvertMetrics`] implementation that converts
 /// the given [`Collector`]s to a [`Vec`] of [`MetricFamily`] that can be used to be
 /// pushed to the pushgateway.
+#[derive(Clone, Copy, Debug, Default)]
 pub struct PrometheusMetricsConverter;
 
 const LABEL_NAME_JOB: &str = "job";
@@ -52,48 +70,136 @@ impl ConvertMetrics<Vec<MetricFamily>, Vec<Box<dyn Collector>>, Vec<u8>>
         grouping: &HashMap<&str, &str>,
         metric_families: Vec<MetricFamily>,
     ) -> Result<(Url, Vec<u8>, String)> {
-        let url = build_url(url, validate(job)?, grouping)?;
-        let encoder = ProtobufEncoder::new();
-        let encoded_metrics = self.encode_metrics(&encoder, metric_families, grouping)?;
+        self.create_borrowed_push_details(job, url, grouping, &metric_families)
+    }
 
-        Ok((url, encoded_metrics, String::from(encoder.format_type())))
+    /// Falls back to the text format for pushgateway clones that only accept text. The
+    /// protobuf payload is decoded and encoded again, so the metrics are pushed unchanged.
+    #[cfg(feature = "prometheus_crate")]
+    fn fallback_push_details(
+        &self,
+        body: &Vec<u8>,
+        content_type: &str,
+    ) -> Option<Result<(Vec<u8>, String)>> {
+        if content_type != ProtobufEncoder::new().format_type() {
+            return None;
+        }
+
+        Some(encode_text(body))
+    }
+}
+
+/// Encodes the given length-delimited protobuf payload in the text format.
+#[cfg(feature = "prometheus_crate")]
+pub(crate) fn encode_text(protobuf: &[u8]) -> Result<(Vec<u8>, String)> {
+    let mut input = CodedInputStream::from_bytes(protobuf);
+    let mut metric_families = Vec::new();
+    while !input.eof().map_err(prometheus::Error::from)? {
+        let metric_family = input
+            .read_message::<MetricFamily>()
+            .map_err(prometheus::Error::from)?;
+        metric_families.push(metric_family);
     }
+
+    let encoder = TextEncoder::new();
+    let mut encoded_metrics = Vec::new();
+    encoder.encode(&metric_families, &mut encoded_metrics)?;
+
+    Ok((encoded_metrics, String::from(encoder.format_type())))
 }
 
 impl PrometheusMetricsConverter {
+    /// Creates the push details like [`ConvertMetrics::create_push_details`] but only borrows
+    /// the metric families, so they do not have to be cloned if they are used elsewhere, e.g.
+    /// to be exposed on a local `/metrics` endpoint as well.
+    pub fn create_borrowed_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        metric_families: &[MetricFamily],
+    ) -> Result<(Url, Vec<u8>, String)> {
+        let url = build_url(url, job, grouping)?;
+        let encoder = PushEncoder::new();
+        let encoded_metrics = self.encode_metrics(&encoder, metric_families, grouping)?;
+
+        Ok((url, encoded_metrics, String::from(encoder.format_type())))
+    }
+
     fn encode_metrics(
         &self,
-        encoder: &ProtobufEncoder,
-        metric_families: Vec<MetricFamily>,
+        encoder: &PushEncoder,
+        metric_families: &[MetricFamily],
         grouping: &HashMap<&str, &str>,
     ) -> Result<Vec<u8>> {
+        let mut sorted: Vec<&MetricFamily> = metric_families.iter().collect();
+        sorted.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+
         let mut encoded_metrics = Vec::new();
-        for metric_family in metric_families {
-            for metric in metric_family.get_metric() {
-                for label_pair in metric.get_label() {
-                    let label_name = label_pair.get_name();
-
-                    if LABEL_NAME_JOB == label_name {
-                        return Err(PushMetricsError::contains_label(
-                            metric_family.get_name(),
-                            LabelType::Job,
-                        ));
-                    }
-
-                    if grouping.contains_key(label_name) {
-                        return Err(PushMetricsError::contains_label(
-                            metric_family.get_name(),
-                            LabelType::Grouping(label_name),
-                        ));
-                    }
-                }
+        for metric_family in sorted {
+            check_labels(metric_family, grouping)?;
+            let metric_family = sort_metrics(metric_family);
+            encoder.encode(std::slice::from_ref(&metric_family), &mut encoded_metrics)?;
+        }
+
+        Ok(encoded_metrics)
+    }
+}
+
+/// Fails if a metric of the family carries the job label or one of the grouping labels, which
+/// are attached by the pushgateway.
+pub(crate) fn check_labels(
+    metric_family: &MetricFamily,
+    grouping: &HashMap<&str, &str>,
+) -> Result<()> {
+    for metric in metric_family.get_metric() {
+        for label_pair in metric.get_label() {
+            let label_name = label_pair.get_name();
+
+            if LABEL_NAME_JOB == label_name {
+                return Err(PushMetricsError::contains_label(
+                    metric_family.get_name(),
+                    LabelType::Job,
+                ));
             }
 
-            encoder.encode(&[metric_family], &mut encoded_metrics)?;
+            if grouping.contains_key(label_name) {
+                return Err(PushMetricsError::contains_label(
+                    metric_family.get_name(),
+                    LabelType::Grouping(label_name),
+                ));
+            }
         }
+    }
 
-        Ok(encoded_metrics)
+    Ok(())
+}
+
+/// Sorts the metrics of the family by their labels, so identical metrics are always encoded to
+/// identical payloads. The family is only cloned if it is not sorted yet.
+fn sort_metrics(metric_family: &MetricFamily) -> Cow<'_, MetricFamily> {
+    fn labels(metric: &Metric) -> Vec<(&str, &str)> {
+        metric
+            .get_label()
+            .iter()
+            .map(|label| (label.get_name(), label.get_value()))
+            .collect()
+    }
+
+    let metrics = metric_family.get_metric();
+    if metrics
+        .windows(2)
+        .all(|pair| labels(&pair[0]) <= labels(&pair[1]))
+    {
+        return Cow::Borrowed(metric_family);
     }
+
+    let mut metric_family = metric_family.clone();
+    metric_family
+        .mut_metric()
+        .sort_by(|a, b| labels(a).cmp(&labels(b)));
+
+    Cow::Owned(metric_family)
 }
 
 #[cfg(feature = "with_reqwest")]
@@ -147,6 +253,88 @@ where
     }
 }
 
+#[cfg(feature = "non_blocking")]
+impl<P>
+    crate::non_blocking::MetricsPusher<
+        P,
+        PrometheusMetricsConverter,
+        Vec<MetricFamily>,
+        Vec<Box<dyn Collector>>,
+        Vec<u8>,
+    >
+where
+    P: crate::non_blocking::Push<Vec<u8>>,
+{
+    /// Pushes all metrics like [`MetricsPusher::push_all`](crate::non_blocking::MetricsPusher::push_all)
+    /// but only borrows the metric families, so the output of `gather()` can be pushed and
+    /// still be used elsewhere without cloning it.
+    pub async fn push_all_borrowed(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: &[MetricFamily],
+    ) -> Result<PushReceipt> {
+        self.push_encoded(job, grouping, PushType::All, |converter, url, grouping| {
+            converter.create_borrowed_push_details(job, url, grouping, metric_families)
+        })
+        .await
+    }
+
+    /// Pushes metrics like [`MetricsPusher::push_add`](crate::non_blocking::MetricsPusher::push_add)
+    /// but only borrows the metric families.
+    pub async fn push_add_borrowed(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: &[MetricFamily],
+    ) -> Result<PushReceipt> {
+        self.push_encoded(job, grouping, PushType::Add, |converter, url, grouping| {
+            converter.create_borrowed_push_details(job, url, grouping, metric_families)
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<P>
+    crate::blocking::MetricsPusher<
+        P,
+        PrometheusMetricsConverter,
+        Vec<MetricFamily>,
+        Vec<Box<dyn Collector>>,
+        Vec<u8>,
+    >
+where
+    P: crate::blocking::Push<Vec<u8>>,
+{
+    /// Pushes all metrics like [`MetricsPusher::push_all`](crate::blocking::MetricsPusher::push_all)
+    /// but only borrows the metric families, so the output of `gather()` can be pushed and
+    /// still be used elsewhere without cloning it.
+    pub fn push_all_borrowed(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: &[MetricFamily],
+    ) -> Result<PushReceipt> {
+        self.push_encoded(job, grouping, PushType::All, |converter, url, grouping| {
+            converter.create_borrowed_push_details(job, url, grouping, metric_families)
+        })
+    }
+
+    /// Pushes metrics like [`MetricsPusher::push_add`](crate::blocking::MetricsPusher::push_add)
+    /// but only borrows the metric families.
+    pub fn push_add_borrowed(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: &[MetricFamily],
+    ) -> Result<PushReceipt> {
+        self.push_encoded(job, grouping, PushType::Add, |converter, url, grouping| {
+            converter.create_borrowed_push_details(job, url, grouping, metric_families)
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -159,12 +347,13 @@ mod test {
     use prometheus::Counter;
     use prometheus::Encoder;
     use prometheus::Opts;
-    use prometheus::ProtobufEncoder;
     use prometheus_crate::PrometheusMetricsPusher;
     use prometheus_crate::PrometheusMetricsPusherBlocking;
     use url::Url;
 
     use crate::prometheus_crate;
+    use crate::prometheus_crate::PrometheusMetricsConverter;
+    use crate::prometheus_crate::PushEncoder;
 
     fn create_metrics(name: &str) -> (Vec<u8>, Vec<MetricFamily>) {
         let counter_opts = Opts::new(name, "test counter help");
@@ -172,7 +361,7 @@ mod test {
         prometheus::register(Box::new(counter.clone())).unwrap();
         counter.inc();
 
-        let encoder = ProtobufEncoder::new();
+        let encoder = PushEncoder::new();
         let metric_families = prometheus::gather();
         let mut metrics = vec![];
         encoder.encode(&metric_families, &mut metrics).unwrap();
@@ -180,6 +369,40 @@ mod test {
         (metrics, metric_families)
     }
 
+    #[test]
+    fn test_families_and_metrics_are_encoded_in_a_deterministic_order() {
+        // Given I have the same metrics gathered in different orders
+        let registry = prometheus::Registry::new();
+        let requests =
+            prometheus::IntCounterVec::new(Opts::new("requests", "help"), &["path"]).unwrap();
+        let errors = prometheus::IntCounter::new("errors", "help").unwrap();
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(errors.clone())).unwrap();
+        requests.with_label_values(&["/b"]).inc();
+        requests.with_label_values(&["/a"]).inc();
+        errors.inc();
+
+        let sorted = registry.gather();
+        let mut shuffled = sorted.clone();
+        shuffled.reverse();
+        shuffled
+            .iter_mut()
+            .for_each(|family| family.mut_metric().reverse());
+
+        // When I create the push details for both
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let push_details = |metric_families: &[MetricFamily]| {
+            PrometheusMetricsConverter
+                .create_borrowed_push_details("job", &url, &HashMap::new(), metric_families)
+                .unwrap()
+                .1
+        };
+
+        // Then the payloads are byte-identical
+        assert_ne!(sorted, shuffled);
+        assert_eq!(push_details(&sorted), push_details(&shuffled));
+    }
+
     fn create_push_gateway_mock(
         server: &mut ServerGuard,
         metrics: Vec<u8>,
@@ -194,7 +417,7 @@ mod test {
         let pushgateway_mock = server
             .mock("PUT", &*path)
             .with_status(200)
-            .match_header("content-type", ProtobufEncoder::new().format_type())
+            .match_header("content-type", PushEncoder::new().format_type())
             .match_body(mockito::Matcher::from(metrics))
             .create();
 
@@ -252,4 +475,143 @@ mod test {
         // Then the metrics are received by the push_gateway
         pushgateway_mock.expect(1).assert();
     }
+
+    #[cfg(feature = "with_reqwest")]
+    #[tokio::test]
+    async fn test_push_all_borrowed_non_blocking_reqwest_prometheus_crate() {
+        // Given I have a counter metric in its own registry
+        let registry = prometheus::Registry::new();
+        let counter = Counter::with_opts(Opts::new("borrowed_counter", "help")).unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+        let metric_families = registry.gather();
+        let mut metrics = vec![];
+        PushEncoder::new()
+            .encode(&metric_families, &mut metrics)
+            .unwrap();
+
+        // And a push gateway and a job
+        let mut server = Server::new_async().await;
+        let (pushgateway_mock, push_gateway_address, job, grouping) =
+            create_push_gateway_mock(&mut server, metrics);
+
+        // And a nonblocking prometheus metrics pusher
+        let metrics_pusher =
+            PrometheusMetricsPusher::from(reqwest::Client::new(), &push_gateway_address).unwrap();
+
+        // When I push the borrowed metric families
+        metrics_pusher
+            .push_all_borrowed(job, &grouping, &metric_families)
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the metrics are received by the push_gateway and are still usable afterwards
+        pushgateway_mock.expect(1).assert();
+        assert_eq!(metric_families[0].get_name(), "borrowed_counter");
+    }
+
+    #[cfg(all(feature = "with_reqwest", feature = "prometheus_crate"))]
+    #[tokio::test]
+    async fn test_push_falls_back_to_text_if_protobuf_is_rejected() {
+        use mockito::Matcher;
+
+        // Given I have a counter metric in its own registry
+        let registry = prometheus::Registry::new();
+        let counter = Counter::with_opts(Opts::new("fallback_counter", "help")).unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+
+        // And a push gateway that only accepts the text format
+        let mut server = Server::new_async().await;
+        let protobuf_mock = server
+            .mock("PUT", "/metrics/job/fallback_job")
+            .match_header(
+                "content-type",
+                Matcher::Regex("^application/vnd.google".into()),
+            )
+            .with_status(415)
+            .expect(1)
+            .create();
+        let text_mock = server
+            .mock("PUT", "/metrics/job/fallback_job")
+            .match_header("content-type", Matcher::Regex("^text/plain".into()))
+            .match_body(Matcher::Regex("fallback_counter 1".into()))
+            .expect(2)
+            .create();
+
+        // And a nonblocking prometheus metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = PrometheusMetricsPusher::from(reqwest::Client::new(), &url).unwrap();
+
+        // When I push the metrics twice
+        for _ in 0..2 {
+            metrics_pusher
+                .push_all("fallback_job", &HashMap::new(), registry.gather())
+                .await
+                .expect("Failed to push metrics");
+        }
+
+        // Then protobuf is only tried once and the pushgateway is remembered to accept text
+        protobuf_mock.assert();
+        text_mock.assert();
+    }
+
+    fn create_delete_mock(server: &mut ServerGuard) -> (Mock, Url, &str, HashMap<&str, &str>) {
+        let push_gateway_address = Url::parse(&server.url()).unwrap();
+        let job = "prometheus_crate_job";
+        let grouping = labels! { "kind" => "test" };
+
+        let pushgateway_mock = server
+            .mock("DELETE", "/metrics/job/prometheus_crate_job/kind/test")
+            .with_status(202)
+            .create();
+
+        (pushgateway_mock, push_gateway_address, job, grouping)
+    }
+
+    #[cfg(feature = "with_reqwest_blocking")]
+    #[test]
+    fn test_delete_blocking_reqwest_prometheus_crate() {
+        // Given I have a push gateway and a job
+        let mut server = Server::new();
+        let (pushgateway_mock, push_gateway_address, job, grouping) =
+            create_delete_mock(&mut server);
+
+        // And a blocking prometheus metrics pusher
+        let metrics_pusher = PrometheusMetricsPusherBlocking::from(
+            reqwest::blocking::Client::new(),
+            &push_gateway_address,
+        )
+        .unwrap();
+
+        // When I delete the group from the push gateway
+        metrics_pusher
+            .delete(job, &grouping)
+            .expect("Failed to delete metrics");
+
+        // Then the delete request is received by the push_gateway
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[cfg(feature = "with_reqwest")]
+    #[tokio::test]
+    async fn test_delete_non_blocking_reqwest_prometheus_crate() {
+        // Given I have a push gateway and a job
+        let mut server = Server::new_async().await;
+        let (pushgateway_mock, push_gateway_address, job, grouping) =
+            create_delete_mock(&mut server);
+
+        // And a nonblocking prometheus metrics pusher
+        let metrics_pusher =
+            PrometheusMetricsPusher::from(reqwest::Client::new(), &push_gateway_address).unwrap();
+
+        // When I delete the group from the push gateway
+        metrics_pusher
+            .delete(job, &grouping)
+            .await
+            .expect("Failed to delete metrics");
+
+        // Then the delete request is received by the push_gateway
+        pushgateway_mock.expect(1).assert();
+    }
 }
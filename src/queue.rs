@@ -0,0 +1,536 @@
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::core::Collector;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::core::Desc;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::proto::MetricFamily;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::Gauge;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::IntCounter;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::IntGauge;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::grouping::Grouping;
+use crate::non_blocking::MetricsPusher;
+use crate::non_blocking::Push;
+use crate::text_format;
+use crate::utils::PushType;
+use crate::ConvertMetrics;
+
+/// `QueuedPusher` decouples producing metrics from pushing them: payloads are queued and pushed
+/// one after another by a background task with the given [`MetricsPusher`], so a slow network
+/// does not block the producer.
+///
+/// When the producer is faster than the network, consecutive queued [`QueuedPusher::push_add`]
+/// payloads of the same group are coalesced into a single push in which later metric families
+/// override earlier ones, just like the pushgateway would have applied them one by one.
+///
+/// The wrapped [`MetricsPusher`] has to accept the metrics as [`String`] in the text format,
+/// e.g. by using a [`RawMetricsConverter::text`](crate::raw::RawMetricsConverter::text).
+///
+/// As there is no async drop, call [`QueuedPusher::close`] before shutting down to push the
+/// payloads that are still queued within a deadline instead of losing them with the runtime.
+///
+/// The backlog can be monitored with [`QueuedPusher::stats`] or, with the `prometheus_crate`
+/// feature, by registering [`QueuedPusher::collector`] in the registry of your application.
+#[derive(Clone, Debug)]
+pub struct QueuedPusher {
+    sender: mpsc::Sender<Message>,
+    state: Arc<QueueState>,
+}
+
+/// Tracks the enqueue times of the pending payloads in the order they are queued and the
+/// number of payloads that were dropped because their push failed.
+#[derive(Debug, Default)]
+struct QueueState {
+    pending: Mutex<VecDeque<Instant>>,
+    dropped: AtomicU64,
+}
+
+impl QueueState {
+    fn pending(&self) -> std::sync::MutexGuard<'_, VecDeque<Instant>> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn complete(&self, count: usize, pushed: bool) {
+        let mut pending = self.pending();
+        let count = count.min(pending.len());
+        pending.drain(..count);
+
+        if !pushed {
+            self.dropped.fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn stats(&self) -> QueueStats {
+        let pending = self.pending();
+        QueueStats {
+            depth: pending.len(),
+            oldest_pending_age: pending.front().map(Instant::elapsed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// `QueueStats` is a snapshot of the backlog of a [`QueuedPusher`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    depth: usize,
+    oldest_pending_age: Option<Duration>,
+    dropped: u64,
+}
+
+impl QueueStats {
+    /// Returns the number of payloads that are queued or currently pushed.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns how long the oldest pending payload has been waiting, if there is one.
+    pub fn oldest_pending_age(&self) -> Option<Duration> {
+        self.oldest_pending_age
+    }
+
+    /// Returns the number of payloads that were dropped because their push failed.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+enum Message {
+    Push(Pending),
+    Close(oneshot::Sender<()>),
+}
+
+struct Pending {
+    job: String,
+    grouping: Grouping,
+    metrics: String,
+    push_type: PushType,
+    count: usize,
+}
+
+impl QueuedPusher {
+    /// Creates a new [`QueuedPusher`] that queues up to `capacity` payloads and spawns the task
+    /// pushing them on the current tokio runtime. Once all clones of the [`QueuedPusher`] are
+    /// dropped the task pushes the remaining payloads and the returned [`JoinHandle`] completes.
+    pub fn new<P, CM, C, B>(
+        metrics_pusher: MetricsPusher<P, CM, String, C, B>,
+        capacity: usize,
+    ) -> (Self, JoinHandle<()>)
+    where
+        P: Push<B> + Send + Sync + 'static,
+        CM: ConvertMetrics<String, C, B> + Send + Sync + 'static,
+        C: Send + Sync + 'static,
+        B: Send + Sync + 'static,
+    {
+        let (sender, mut receiver) = mpsc::channel(capacity);
+        let state = Arc::new(QueueState::default());
+        let worker_state = Arc::clone(&state);
+        let handle = tokio::spawn(async move {
+            let mut batch = Vec::new();
+            let mut closed = Vec::new();
+            while receiver.recv_many(&mut batch, capacity).await > 0 {
+                let mut pushes = Vec::with_capacity(batch.len());
+                for message in batch.drain(..) {
+                    match message {
+                        Message::Push(pending) => pushes.push(pending),
+                        Message::Close(ack) => {
+                            receiver.close();
+                            closed.push(ack);
+                        }
+                    }
+                }
+
+                for pending in coalesce(pushes.into_iter()) {
+                    let count = pending.count;
+                    let pushed = send(&metrics_pusher, pending).await;
+                    worker_state.complete(count, pushed);
+                }
+            }
+
+            for ack in closed {
+                let _ = ack.send(());
+            }
+        });
+
+        (Self { sender, state }, handle)
+    }
+
+    /// Queues the metrics to replace all metrics of the group, see
+    /// [`MetricsPusher::push_all`]. Waits for a free slot if the queue is full.
+    pub async fn push_all(&self, job: &str, grouping: &Grouping, metrics: String) -> Result<()> {
+        self.enqueue(job, grouping, metrics, PushType::All).await
+    }
+
+    /// Queues the metrics to replace the metrics with the same name within the group, see
+    /// [`MetricsPusher::push_add`]. Waits for a free slot if the queue is full.
+    pub async fn push_add(&self, job: &str, grouping: &Grouping, metrics: String) -> Result<()> {
+        self.enqueue(job, grouping, metrics, PushType::Add).await
+    }
+
+    /// Stops accepting payloads and waits until the payloads queued so far are pushed. Payloads
+    /// queued afterwards are rejected with [`PushMetricsError::QueueClosed`].
+    ///
+    /// Fails with [`PushMetricsError::Timeout`] if the queue is not drained within `deadline`;
+    /// the remaining payloads are still pushed in the background until the task is dropped.
+    /// Closing an already closed [`QueuedPusher`] returns immediately.
+    pub async fn close(&self, deadline: Duration) -> Result<()> {
+        let (ack, drained) = oneshot::channel();
+        if self.sender.send(Message::Close(ack)).await.is_err() {
+            return Ok(());
+        }
+
+        match tokio::time::timeout(deadline, drained).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err(PushMetricsError::Timeout(deadline)),
+        }
+    }
+
+    /// Returns a snapshot of the backlog.
+    pub fn stats(&self) -> QueueStats {
+        self.state.stats()
+    }
+
+    /// Returns a [`QueueCollector`] that exposes the backlog as metrics once it is registered
+    /// in a [`Registry`](prometheus::Registry).
+    #[cfg(feature = "prometheus_crate_text")]
+    pub fn collector(&self) -> QueueCollector {
+        QueueCollector::new(Arc::clone(&self.state))
+    }
+
+    async fn enqueue(
+        &self,
+        job: &str,
+        grouping: &Grouping,
+        metrics: String,
+        push_type: PushType,
+    ) -> Result<()> {
+        let pending = Pending {
+            job: job.to_owned(),
+            grouping: grouping.clone(),
+            metrics,
+            push_type,
+            count: 1,
+        };
+
+        let permit = self
+            .sender
+            .reserve()
+            .await
+            .map_err(|_| PushMetricsError::QueueClosed)?;
+
+        // the enqueue time is recorded under the lock, so the pending times stay in queue order
+        let mut pending_times = self.state.pending();
+        pending_times.push_back(Instant::now());
+        permit.send(Message::Push(pending));
+
+        Ok(())
+    }
+}
+
+/// Merges consecutive add payloads of the same group. Payloads that are not in the text format
+/// are left as they are, so the pushgateway can reject them on its own.
+fn coalesce(batch: impl Iterator<Item = Pending>) -> Vec<Pending> {
+    let mut coalesced: Vec<Pending> = Vec::new();
+
+    for pending in batch {
+        let last = coalesced.last_mut().filter(|last| {
+            matches!(
+                (&last.push_type, &pending.push_type),
+                (PushType::Add, PushType::Add)
+            ) && last.job == pending.job
+                && last.grouping == pending.grouping
+        });
+
+        match last.and_then(|last| merge(&last.metrics, &pending.metrics).map(|m| (last, m))) {
+            Some((last, merged)) => {
+                last.metrics = merged;
+                last.count += pending.count;
+            }
+            None => coalesced.push(pending),
+        }
+    }
+
+    coalesced
+}
+
+fn merge(metrics: &str, other: &str) -> Option<String> {
+    let mut families = text_format::parse(metrics).ok()?;
+    text_format::replace(&mut families, text_format::parse(other).ok()?);
+
+    Some(text_format::encode(&families))
+}
+
+/// Pushes the pending payload and returns whether it was accepted.
+async fn send<P, CM, C, B>(
+    metrics_pusher: &MetricsPusher<P, CM, String, C, B>,
+    pending: Pending,
+) -> bool
+where
+    P: Push<B>,
+    CM: ConvertMetrics<String, C, B>,
+{
+    let grouping = pending.grouping.labels();
+    let result = match pending.push_type {
+        PushType::All => {
+            metrics_pusher
+                .push_all(&pending.job, &grouping, pending.metrics)
+                .await
+        }
+        PushType::Add => {
+            metrics_pusher
+                .push_add(&pending.job, &grouping, pending.metrics)
+                .await
+        }
+    };
+
+    if let Err(error) = &result {
+        log::error!(
+            "Failed to push queued metrics of job {}: {error}",
+            pending.job
+        );
+    }
+
+    result.is_ok()
+}
+
+/// `QueueCollector` is a [`Collector`] that exposes the backlog of a [`QueuedPusher`] as
+/// `prometheus_push_queue_depth`, `prometheus_push_queue_oldest_pending_age_seconds` and
+/// `prometheus_push_queue_dropped_total`, so delivery backlogs can be alerted on.
+#[cfg(feature = "prometheus_crate_text")]
+#[derive(Clone, Debug)]
+pub struct QueueCollector {
+    state: Arc<QueueState>,
+    depth: IntGauge,
+    oldest_pending_age: Gauge,
+    dropped: IntCounter,
+}
+
+#[cfg(feature = "prometheus_crate_text")]
+impl QueueCollector {
+    fn new(state: Arc<QueueState>) -> Self {
+        Self {
+            state,
+            depth: IntGauge::new(
+                "prometheus_push_queue_depth",
+                "Number of payloads that are queued or currently pushed.",
+            )
+            .expect("valid metric"),
+            oldest_pending_age: Gauge::new(
+                "prometheus_push_queue_oldest_pending_age_seconds",
+                "Age of the oldest pending payload in seconds.",
+            )
+            .expect("valid metric"),
+            dropped: IntCounter::new(
+                "prometheus_push_queue_dropped_total",
+                "Number of queued payloads that were dropped because their push failed.",
+            )
+            .expect("valid metric"),
+        }
+    }
+}
+
+#[cfg(feature = "prometheus_crate_text")]
+impl Collector for QueueCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        [
+            self.depth.desc(),
+            self.oldest_pending_age.desc(),
+            self.dropped.desc(),
+        ]
+        .concat()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let stats = self.state.stats();
+        self.depth.set(stats.depth() as i64);
+        self.oldest_pending_age.set(
+            stats
+                .oldest_pending_age()
+                .map_or(0.0, |age| age.as_secs_f64()),
+        );
+        self.dropped
+            .inc_by(stats.dropped().saturating_sub(self.dropped.get()));
+
+        [
+            self.depth.collect(),
+            self.oldest_pending_age.collect(),
+            self.dropped.collect(),
+        ]
+        .concat()
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::time::Duration;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::error::PushMetricsError;
+    use crate::grouping::Grouping;
+    use crate::non_blocking::MetricsPusher;
+    use crate::queue::QueueStats;
+    use crate::queue::QueuedPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::with_reqwest::PushClient;
+
+    #[tokio::test]
+    async fn test_consecutive_push_adds_of_a_group_are_coalesced() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let coalesced_mock = server
+            .mock("POST", "/metrics/job/queued/env/a")
+            .match_body("jobs_done 2\njobs_failed 1\njobs_retried 1\n")
+            .expect(1)
+            .create();
+        let other_group_mock = server
+            .mock("POST", "/metrics/job/queued/env/b")
+            .match_body("jobs_done 3\n")
+            .expect(1)
+            .create();
+
+        // And a queued pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let (queued_pusher, handle) = QueuedPusher::new(metrics_pusher, 8);
+        let group_a = Grouping::new().with_label("env", "a");
+        let group_b = Grouping::new().with_label("env", "b");
+
+        // When I queue several adds faster than they are pushed
+        let adds = [
+            (&group_a, "jobs_done 1\njobs_failed 1\n"),
+            (&group_a, "jobs_done 2\njobs_retried 1\n"),
+            (&group_b, "jobs_done 3\n"),
+        ];
+        for (grouping, metrics) in adds {
+            queued_pusher
+                .push_add("queued", grouping, metrics.to_owned())
+                .await
+                .unwrap();
+        }
+        drop(queued_pusher);
+        handle.await.unwrap();
+
+        // Then the adds of the same group are pushed once with the later families winning
+        coalesced_mock.assert();
+        other_group_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_stats_track_the_backlog() {
+        // Given I have a push gateway that rejects the push
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/queued")
+            .with_status(400)
+            .expect(1)
+            .create();
+
+        // And a queued pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let (queued_pusher, handle) = QueuedPusher::new(metrics_pusher, 8);
+        assert_eq!(queued_pusher.stats(), QueueStats::default());
+
+        // When I queue a payload and wait for it to be pushed
+        queued_pusher
+            .push_all("queued", &Grouping::new(), String::from("jobs_done 1\n"))
+            .await
+            .unwrap();
+        queued_pusher.close(Duration::from_secs(5)).await.unwrap();
+        handle.await.unwrap();
+
+        // Then the queue is empty and the rejected payload is counted as dropped
+        pushgateway_mock.assert();
+        let stats = queued_pusher.stats();
+        assert_eq!(stats.depth(), 0);
+        assert_eq!(stats.oldest_pending_age(), None);
+        assert_eq!(stats.dropped(), 1);
+
+        // And the collector exposes the backlog in the registry of the application
+        #[cfg(feature = "prometheus_crate_text")]
+        {
+            let registry = prometheus::Registry::new();
+            registry
+                .register(Box::new(queued_pusher.collector()))
+                .unwrap();
+            let dropped = registry
+                .gather()
+                .into_iter()
+                .find(|family| family.get_name() == "prometheus_push_queue_dropped_total")
+                .unwrap();
+            assert_eq!(dropped.get_metric()[0].get_counter().get_value(), 1.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close_pushes_queued_payloads() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server.mock("PUT", "/metrics/job/queued").expect(2).create();
+
+        // And a queued pusher with two queued payloads
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let (queued_pusher, handle) = QueuedPusher::new(metrics_pusher, 8);
+        for metrics in ["jobs_done 1\n", "jobs_done 2\n"] {
+            queued_pusher
+                .push_all("queued", &Grouping::new(), metrics.to_owned())
+                .await
+                .unwrap();
+        }
+
+        // When I close it twice
+        let deadline = Duration::from_secs(5);
+        queued_pusher
+            .close(deadline)
+            .await
+            .expect("Failed to close");
+        queued_pusher
+            .close(deadline)
+            .await
+            .expect("Failed to close again");
+
+        // Then the queued payloads are pushed, new ones are rejected and the task completes
+        pushgateway_mock.assert();
+        let result = queued_pusher
+            .push_all("queued", &Grouping::new(), String::from("jobs_done 3\n"))
+            .await;
+        assert!(matches!(result, Err(PushMetricsError::QueueClosed)));
+        handle.await.unwrap();
+    }
+}
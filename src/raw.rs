@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use url::Url;
+
+use crate::error::Result;
+use crate::utils::build_url;
+use crate::utils::CONTENT_TYPE_OPENMETRICS;
+use crate::utils::CONTENT_TYPE_PROTOBUF;
+use crate::utils::CONTENT_TYPE_TEXT;
+use crate::ConvertMetrics;
+
+/// `RawMetricsConverter` is a [`ConvertMetrics`] implementation for payloads that are already
+/// encoded, e.g. by another library or a bridge to another language. The payload, given as
+/// [`String`] or [`Vec<u8>`], is pushed as is with the configured content type.
+#[derive(Clone, Debug)]
+pub struct RawMetricsConverter {
+    content_type: String,
+    comments: TextComments,
+}
+
+/// `TextComments` defines which `# HELP` and `# TYPE` lines of a payload in a text format are
+/// pushed, e.g. to shrink the payloads of edge devices that push every few seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextComments {
+    /// Pushes the payload as is.
+    #[default]
+    Keep,
+    /// Shortens the texts of `# HELP` lines to at most the given number of characters.
+    ShortenHelp(usize),
+    /// Omits all `# HELP` and `# TYPE` lines, so the pushgateway treats the metrics as untyped.
+    Strip,
+}
+
+impl RawMetricsConverter {
+    /// Creates a new [`RawMetricsConverter`] that pushes payloads with the given content type.
+    pub fn new(content_type: &str) -> Self {
+        Self {
+            content_type: content_type.to_owned(),
+            comments: TextComments::default(),
+        }
+    }
+
+    /// Sets which comment lines of text format payloads are pushed. Payloads in other formats,
+    /// like protobuf, are always pushed as is.
+    pub fn with_comments(mut self, comments: TextComments) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Creates a new [`RawMetricsConverter`] for payloads in the Prometheus text format.
+    pub fn text() -> Self {
+        Self::new(CONTENT_TYPE_TEXT)
+    }
+
+    /// Creates a new [`RawMetricsConverter`] for payloads in the OpenMetrics text format.
+    pub fn openmetrics() -> Self {
+        Self::new(CONTENT_TYPE_OPENMETRICS)
+    }
+
+    /// Creates a new [`RawMetricsConverter`] for length-delimited protobuf payloads.
+    pub fn protobuf() -> Self {
+        Self::new(CONTENT_TYPE_PROTOBUF)
+    }
+
+    /// Returns the content type the payloads are pushed with.
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+}
+
+impl Default for RawMetricsConverter {
+    fn default() -> Self {
+        Self::text()
+    }
+}
+
+impl<M: Into<Vec<u8>>> ConvertMetrics<M, M, Vec<u8>> for RawMetricsConverter {
+    fn metrics_from(&self, payload: M) -> Result<M> {
+        Ok(payload)
+    }
+
+    fn create_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        payload: M,
+    ) -> Result<(Url, Vec<u8>, String)> {
+        let url = build_url(url, job, grouping)?;
+        let payload = match self.comments {
+            TextComments::Keep => payload.into(),
+            comments
+                if self.content_type.starts_with("text/plain")
+                    || self
+                        .content_type
+                        .starts_with("application/openmetrics-text") =>
+            {
+                match String::from_utf8(payload.into()) {
+                    Ok(text) => strip_comments(&text, comments).into_bytes(),
+                    Err(error) => error.into_bytes(),
+                }
+            }
+            _ => payload.into(),
+        };
+
+        Ok((url, payload, self.content_type.clone()))
+    }
+}
+
+fn strip_comments(text: &str, comments: TextComments) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let is_help = line.starts_with("# HELP ");
+        match comments {
+            TextComments::Strip if is_help || line.starts_with("# TYPE ") => {}
+            TextComments::ShortenHelp(max) if is_help => {
+                stripped.push_str(&shorten_help(line.trim_end_matches('\n'), max));
+                stripped.push('\n');
+            }
+            _ => stripped.push_str(line),
+        }
+    }
+
+    stripped
+}
+
+/// Shortens the text of a `# HELP <name> <text>` line without cutting an escape sequence.
+fn shorten_help(line: &str, max: usize) -> String {
+    let mut parts = line.splitn(4, ' ');
+    let (Some(hash), Some(help), Some(name)) = (parts.next(), parts.next(), parts.next()) else {
+        return line.to_owned();
+    };
+
+    let mut text: String = parts.next().unwrap_or_default().chars().take(max).collect();
+    if text.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1 {
+        text.pop();
+    }
+
+    match text.is_empty() {
+        true => format!("{hash} {help} {name}"),
+        false => format!("{hash} {help} {name} {text}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use url::Url;
+
+    use crate::error::PushMetricsError;
+    use crate::raw::shorten_help;
+    use crate::raw::RawMetricsConverter;
+    use crate::raw::TextComments;
+    use crate::ConvertMetrics;
+
+    #[test]
+    fn test_invalid_label_name_is_rejected() {
+        // Given I have a grouping with a label name that starts with a digit
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let grouping = HashMap::from([("1kind", "test")]);
+
+        // When I create the push details
+        let result = RawMetricsConverter::text().create_push_details("job", &url, &grouping, "");
+
+        // Then the offending label name is reported unless UTF-8 label names are enabled
+        match result {
+            Ok(_) if cfg!(feature = "utf8_label_names") => {}
+            Err(PushMetricsError::InvalidLabelName(name)) => assert_eq!(name, "1kind"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_comments_of_text_payloads() {
+        // Given I have a text payload with comments
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let payload = "# HELP jobs_done Jobs done since the start.\n".to_owned()
+            + "# TYPE jobs_done counter\n"
+            + "jobs_done 3\n";
+
+        // When I create the push details with stripped and shortened comments
+        let push_details = |comments| {
+            let converter = RawMetricsConverter::text().with_comments(comments);
+            let (_, payload, _) = converter
+                .create_push_details("job", &url, &HashMap::new(), payload.clone())
+                .unwrap();
+            String::from_utf8(payload).unwrap()
+        };
+
+        // Then only the requested comments are pushed
+        assert_eq!(push_details(TextComments::Keep), payload);
+        assert_eq!(push_details(TextComments::Strip), "jobs_done 3\n");
+        assert_eq!(
+            push_details(TextComments::ShortenHelp(4)),
+            "# HELP jobs_done Jobs\n# TYPE jobs_done counter\njobs_done 3\n"
+        );
+    }
+
+    #[test]
+    fn test_shorten_help_keeps_escape_sequences_intact() {
+        assert_eq!(shorten_help("# HELP m a\\nb", 2), "# HELP m a");
+        assert_eq!(shorten_help("# HELP m a\\nb", 3), "# HELP m a\\n");
+        assert_eq!(shorten_help("# HELP m text", 0), "# HELP m");
+    }
+
+    #[test]
+    fn test_reserved_characters_are_percent_encoded() {
+        // Given I have a job and grouping values with reserved url characters
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let grouping = HashMap::from([("path", "a b?c#d%e")]);
+
+        // When I create the push details
+        let (url, _, _) = RawMetricsConverter::text()
+            .create_push_details("job?#", &url, &grouping, "")
+            .unwrap();
+
+        // Then every value is transmitted as a single percent-encoded path segment
+        assert_eq!(url.path(), "/metrics/job/job%3F%23/path/a%20b%3Fc%23d%25e");
+        assert_eq!(url.query(), None);
+        assert_eq!(url.fragment(), None);
+    }
+
+    #[cfg(feature = "with_reqwest")]
+    #[tokio::test]
+    async fn test_push_all_raw_payload() {
+        use mockito::Server;
+
+        use crate::non_blocking::MetricsPusher;
+        use crate::with_reqwest::PushClient;
+
+        // Given I have an already encoded payload
+        let payload = b"# TYPE up gauge\nup 1\n".to_vec();
+
+        // And a push gateway and a job
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/raw_job/kind/test")
+            .match_header(
+                "content-type",
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            )
+            .match_body(payload.clone())
+            .create();
+
+        // And a metrics pusher with a raw converter
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::openmetrics(),
+            &url,
+        )
+        .unwrap();
+
+        // When I push the payload to the push gateway
+        metrics_pusher
+            .push_all("raw_job", &HashMap::from([("kind", "test")]), payload)
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the payload is received unchanged by the push_gateway
+        pushgateway_mock.expect(1).assert();
+    }
+}
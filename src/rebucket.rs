@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::text_format;
+use crate::text_format::MetricFamily;
+use crate::text_format::Sample;
+use crate::utils::text_payload;
+use crate::ConvertMetrics;
+
+const BUCKET_LABEL: &str = "le";
+
+/// The bucket samples of one histogram series with their parsed upper bounds.
+type Buckets = Vec<(f64, Sample)>;
+
+/// `RebucketingConverter` wraps a [`ConvertMetrics`] implementation and reduces the buckets of
+/// every histogram to the configured upper bounds before the payload is pushed, for gateways
+/// and Prometheus servers that cannot handle the full resolution.
+///
+/// A bound that the histogram has as well keeps its count, any other bound gets the count of
+/// the next lower bucket, so the counts never claim more than was observed. The `+Inf` bucket,
+/// `_sum` and `_count` are always kept. The wrapped converter has to encode in the text format.
+#[derive(Debug)]
+pub struct RebucketingConverter<CM> {
+    converter: CM,
+    bounds: Vec<f64>,
+    family_bounds: HashMap<String, Vec<f64>>,
+}
+
+impl<CM> RebucketingConverter<CM> {
+    /// Creates a new [`RebucketingConverter`] that reduces the buckets of all histograms of the
+    /// given converter to the given upper bounds.
+    pub fn new(converter: CM, bounds: &[f64]) -> Self {
+        Self { converter, bounds: sorted(bounds), family_bounds: HashMap::new() }
+    }
+
+    /// Reduces the buckets of the histogram with the given name to the given upper bounds
+    /// instead of the ones given to [`RebucketingConverter::new`].
+    pub fn with_family_bounds(mut self, family: &str, bounds: &[f64]) -> Self {
+        self.family_bounds.insert(family.to_owned(), sorted(bounds));
+        self
+    }
+
+    /// Replaces the bucket samples of the given histogram with one per bound for each of its
+    /// series, where the series keep the position of their first bucket.
+    fn rebucket(&self, family: &mut MetricFamily) -> Result<()> {
+        let bounds = self.family_bounds.get(&family.name).unwrap_or(&self.bounds);
+        let bucket_name = format!("{}_bucket", family.name);
+
+        let mut series: Vec<(Vec<(String, String)>, Buckets)> = Vec::new();
+        let mut samples = Vec::new();
+        for sample in std::mem::take(&mut family.samples) {
+            if sample.name != bucket_name {
+                samples.push(Some(sample));
+                continue;
+            }
+
+            let (labels, bound) = split_bound(&sample)?;
+            match series.iter_mut().find(|(existing, _)| *existing == labels) {
+                Some((_, buckets)) => buckets.push((bound, sample)),
+                None => {
+                    series.push((labels, vec![(bound, sample)]));
+                    samples.push(None);
+                }
+            }
+        }
+
+        let mut series = series.into_iter();
+        for sample in samples {
+            match sample {
+                Some(sample) => family.samples.push(sample),
+                None => {
+                    let (_, buckets) = series.next().expect("one placeholder per series");
+                    family.samples.extend(reduce(buckets, bounds));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<CM, MF, C, B> ConvertMetrics<MF, C, B> for RebucketingConverter<CM>
+where
+    CM: ConvertMetrics<MF, C, B>,
+    B: AsRef<[u8]> + From<String>,
+{
+    fn metrics_from(&self, collectors: C) -> Result<MF> {
+        self.converter.metrics_from(collectors)
+    }
+
+    fn create_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<(Url, B, String)> {
+        let (url, body, content_type) =
+            self.converter
+                .create_push_details(job, url, grouping, metric_families)?;
+        let mut families = text_format::parse(&text_payload(body.as_ref(), &content_type)?)?;
+
+        for family in families
+            .iter_mut()
+            .filter(|family| family.kind.as_deref() == Some("histogram"))
+        {
+            self.rebucket(family)?;
+        }
+
+        let body = B::from(text_format::encode_as(&families, &content_type));
+        Ok((url, body, content_type))
+    }
+}
+
+/// Sorts the given bounds and drops the ones that are not finite, as `+Inf` is always kept.
+fn sorted(bounds: &[f64]) -> Vec<f64> {
+    let mut bounds: Vec<f64> = bounds
+        .iter()
+        .copied()
+        .filter(|bound| bound.is_finite())
+        .collect();
+    bounds.sort_by(f64::total_cmp);
+    bounds.dedup();
+    bounds
+}
+
+/// Splits the labels of a bucket sample into the labels of its series and its upper bound.
+fn split_bound(sample: &Sample) -> Result<(Vec<(String, String)>, f64)> {
+    let (bound, labels): (Vec<_>, Vec<_>) = sample
+        .labels
+        .iter()
+        .cloned()
+        .partition(|(name, _)| name == BUCKET_LABEL);
+
+    let bound = bound
+        .first()
+        .and_then(|(_, bound)| parse_bound(bound))
+        .ok_or_else(|| {
+            PushMetricsError::TextFormat(format!("bucket '{}' without valid le label", sample.name))
+        })?;
+
+    Ok((labels, bound))
+}
+
+fn parse_bound(bound: &str) -> Option<f64> {
+    match bound {
+        "+Inf" | "Inf" => Some(f64::INFINITY),
+        bound => bound.parse().ok(),
+    }
+}
+
+/// Reduces the buckets of one series to the given bounds and its `+Inf` bucket. A bound gets
+/// the count of the highest bucket that is not above it, or zero if there is none.
+fn reduce(mut buckets: Buckets, bounds: &[f64]) -> Vec<Sample> {
+    buckets.sort_by(|(left, _), (right, _)| left.total_cmp(right));
+    let Some((_, template)) = buckets.last() else {
+        return Vec::new();
+    };
+
+    let mut reduced: Vec<Sample> = bounds
+        .iter()
+        .map(|bound| {
+            let below = buckets.iter().rev().find(|(existing, _)| existing <= bound);
+            match below {
+                Some((existing, sample)) if existing == bound => sample.clone(),
+                below => with_bound(
+                    template,
+                    &bound.to_string(),
+                    below.map_or(0.0, |(_, sample)| sample.value),
+                ),
+            }
+        })
+        .collect();
+
+    if let Some((_, infinite)) = buckets.iter().find(|(bound, _)| *bound == f64::INFINITY) {
+        reduced.push(infinite.clone());
+    }
+
+    reduced
+}
+
+/// Copies the given bucket sample with another upper bound and count.
+fn with_bound(template: &Sample, bound: &str, value: f64) -> Sample {
+    let mut sample = template.clone();
+    sample.value = value;
+    for (name, label_value) in &mut sample.labels {
+        if name == BUCKET_LABEL {
+            *label_value = bound.to_owned();
+        }
+    }
+    sample
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use url::Url;
+
+    use crate::raw::RawMetricsConverter;
+    use crate::rebucket::RebucketingConverter;
+    use crate::ConvertMetrics;
+
+    #[test]
+    fn test_histogram_buckets_are_reduced() {
+        // Given I have a histogram with four buckets and a rebucketing converter
+        let payload = "# TYPE latency histogram\n".to_owned()
+            + "latency_bucket{path=\"/\",le=\"0.1\"} 1\n"
+            + "latency_bucket{path=\"/\",le=\"0.5\"} 3\n"
+            + "latency_bucket{path=\"/\",le=\"1\"} 4\n"
+            + "latency_bucket{path=\"/\",le=\"+Inf\"} 5\n"
+            + "latency_sum{path=\"/\"} 4.2\n"
+            + "latency_count{path=\"/\"} 5\n";
+        let converter = RebucketingConverter::new(RawMetricsConverter::text(), &[0.5, 2.0]);
+
+        // When I convert it
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let (_, body, _) = converter
+            .create_push_details("worker", &url, &HashMap::new(), payload)
+            .unwrap();
+
+        // Then only the configured bounds and +Inf are pushed with their cumulative counts
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "# TYPE latency histogram\n".to_owned()
+                + "latency_bucket{path=\"/\",le=\"0.5\"} 3\n"
+                + "latency_bucket{path=\"/\",le=\"2\"} 4\n"
+                + "latency_bucket{path=\"/\",le=\"+Inf\"} 5\n"
+                + "latency_sum{path=\"/\"} 4.2\n"
+                + "latency_count{path=\"/\"} 5\n"
+        );
+    }
+}
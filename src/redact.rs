@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use url::Url;
+
+use crate::error::Result;
+use crate::text_format;
+use crate::utils::text_payload;
+use crate::ConvertMetrics;
+
+type Rule = Box<dyn Fn(&str, &str) -> Option<String> + Send + Sync>;
+
+/// `RedactingConverter` wraps a [`ConvertMetrics`] implementation and masks label values with
+/// the given rules before the payload is pushed, so accidental personal data in labels, e.g.
+/// email addresses or user ids, never leaves the process.
+///
+/// Every rule gets the name and the current value of each label and returns the value that
+/// replaces it, or `None` to keep it. The rules are applied in the order they were added. The
+/// wrapped converter has to encode in the text format.
+pub struct RedactingConverter<CM> {
+    converter: CM,
+    rules: Vec<Rule>,
+}
+
+impl<CM> RedactingConverter<CM> {
+    /// Creates a new [`RedactingConverter`] without rules for the given converter.
+    pub fn new(converter: CM) -> Self {
+        Self { converter, rules: Vec::new() }
+    }
+
+    /// Adds a rule that gets the name and value of a label and returns its replacement, e.g.
+    /// `|name, _| (name == "user_id").then(|| "redacted".to_owned())`.
+    pub fn with_rule(
+        mut self,
+        rule: impl Fn(&str, &str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Adds a rule that replaces every match of the given regular expression in the values of
+    /// all labels with the replacement, which may refer to capture groups like `$1`.
+    #[cfg(feature = "redact_regex")]
+    pub fn with_regex(self, pattern: &str, replacement: &str) -> Result<Self> {
+        let regex = regex::Regex::new(pattern)?;
+        let replacement = replacement.to_owned();
+
+        Ok(self.with_rule(move |_, value| match regex.is_match(value) {
+            true => Some(regex.replace_all(value, &replacement).into_owned()),
+            false => None,
+        }))
+    }
+
+    fn redact(&self, name: &str, value: &mut String) {
+        for rule in &self.rules {
+            if let Some(redacted) = rule(name, value) {
+                *value = redacted;
+            }
+        }
+    }
+}
+
+impl<CM: fmt::Debug> fmt::Debug for RedactingConverter<CM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedactingConverter")
+            .field("converter", &self.converter)
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+impl<CM, MF, C, B> ConvertMetrics<MF, C, B> for RedactingConverter<CM>
+where
+    CM: ConvertMetrics<MF, C, B>,
+    B: AsRef<[u8]> + From<String>,
+{
+    fn metrics_from(&self, collectors: C) -> Result<MF> {
+        self.converter.metrics_from(collectors)
+    }
+
+    fn create_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<(Url, B, String)> {
+        let (url, body, content_type) =
+            self.converter
+                .create_push_details(job, url, grouping, metric_families)?;
+        let mut families = text_format::parse(&text_payload(body.as_ref(), &content_type)?)?;
+
+        for (name, value) in families
+            .iter_mut()
+            .flat_map(|family| family.samples.iter_mut())
+            .flat_map(|sample| sample.labels.iter_mut())
+        {
+            self.redact(name, value);
+        }
+
+        let body = B::from(text_format::encode_as(&families, &content_type));
+        Ok((url, body, content_type))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use url::Url;
+
+    use crate::raw::RawMetricsConverter;
+    use crate::redact::RedactingConverter;
+    use crate::ConvertMetrics;
+
+    fn convert(converter: &RedactingConverter<RawMetricsConverter>, payload: &str) -> String {
+        let url = Url::parse("http://localhost:9091/metrics/job/").unwrap();
+        let (_, body, _) = converter
+            .create_push_details("worker", &url, &HashMap::new(), payload.to_owned())
+            .unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn test_label_values_are_redacted_by_rules() {
+        // Given I have a converter that redacts the values of the user label
+        let converter = RedactingConverter::new(RawMetricsConverter::text())
+            .with_rule(|name, _| (name == "user").then(|| "redacted".to_owned()));
+
+        // When I convert a payload with a user label
+        let payload = convert(&converter, "logins{user=\"jane\",method=\"sso\"} 1\n");
+
+        // Then only the value of the user label is redacted
+        assert_eq!(payload, "logins{user=\"redacted\",method=\"sso\"} 1\n");
+    }
+
+    #[cfg(feature = "redact_regex")]
+    #[test]
+    fn test_label_values_are_redacted_by_regex() {
+        // Given I have a converter that masks email addresses
+        let converter = RedactingConverter::new(RawMetricsConverter::text())
+            .with_regex(r"[^@\s]+@([^@\s]+)", "***@$1")
+            .unwrap();
+
+        // When I convert a payload with an email address in a label
+        let payload = convert(
+            &converter,
+            "signups{source=\"mail from jane@example.com\"} 1\n",
+        );
+
+        // Then the local part of the address is masked
+        assert_eq!(payload, "signups{source=\"mail from ***@example.com\"} 1\n");
+    }
+}
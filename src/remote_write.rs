@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use prometheus::core::Collector;
+use prometheus::proto::Metric;
+use prometheus::proto::MetricFamily;
+use prometheus::proto::MetricType;
+use prometheus::Registry;
+use protobuf::CodedOutputStream;
+use protobuf::ProtobufResult;
+use url::Url;
+
+use crate::error::Result;
+use crate::prometheus_crate::check_labels;
+use crate::ConvertMetrics;
+
+const REMOTE_WRITE_CONTENT_TYPE: &str = "application/x-protobuf";
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+const REMOTE_WRITE_VERSION: &str = "0.1.0";
+const DEFAULT_PATH: &str = "api/v1/write";
+
+/// `RemoteWriteConverter` is a [`ConvertMetrics`] implementation that encodes the
+/// [`MetricFamily`]s of the given [`Collector`]s as a snappy compressed remote write request, so a
+/// [`MetricsPusher`](crate::non_blocking::MetricsPusher) pushes directly to Prometheus, Mimir,
+/// Thanos Receive or VictoriaMetrics instead of a pushgateway.
+///
+/// The job and grouping labels become labels of every series and samples without a timestamp
+/// are stamped with the time of the push. Send the requests with a [`RemoteWriteClient`], which
+/// adds the headers of the remote write protocol. Do not combine it with `with_compression` of
+/// the pusher, the payload is compressed already.
+#[derive(Clone, Debug)]
+pub struct RemoteWriteConverter {
+    path: String,
+}
+
+impl RemoteWriteConverter {
+    /// Creates a new [`RemoteWriteConverter`] that pushes to `api/v1/write` of the url of the
+    /// pusher, e.g. of Prometheus or VictoriaMetrics.
+    pub fn new() -> Self {
+        Self { path: DEFAULT_PATH.to_owned() }
+    }
+
+    /// Pushes to the given path instead, resolved against the url of the pusher, e.g.
+    /// `api/v1/push` for Mimir or `api/v1/receive` for Thanos Receive.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = path.to_owned();
+        self
+    }
+}
+
+impl Default for RemoteWriteConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConvertMetrics<Vec<MetricFamily>, Vec<Box<dyn Collector>>, Vec<u8>> for RemoteWriteConverter {
+    fn metrics_from(&self, collectors: Vec<Box<dyn Collector>>) -> Result<Vec<MetricFamily>> {
+        let registry = Registry::new();
+        for collector in collectors {
+            registry.register(collector)?;
+        }
+
+        Ok(registry.gather())
+    }
+
+    fn create_push_details(
+        &self,
+        job: &str,
+        url: &Url,
+        grouping: &HashMap<&str, &str>,
+        metric_families: Vec<MetricFamily>,
+    ) -> Result<(Url, Vec<u8>, String)> {
+        // the url of the pusher ends with the `metrics/job/` path of the pushgateway api
+        let url = url.join(&format!("../../{}", self.path))?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_millis() as i64);
+        let mut series = Vec::new();
+        for metric_family in &metric_families {
+            check_labels(metric_family, grouping)?;
+            for metric in metric_family.get_metric() {
+                append_series(&mut series, metric_family, metric, job, grouping, now);
+            }
+        }
+
+        let request = encode_write_request(&series).map_err(prometheus::Error::from)?;
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&request)
+            .map_err(|error| prometheus::Error::Msg(error.to_string()))?;
+
+        Ok((url, body, String::from(REMOTE_WRITE_CONTENT_TYPE)))
+    }
+}
+
+/// `Series` is a single sample of a time series of a remote write request.
+#[derive(Debug, PartialEq)]
+struct Series {
+    labels: Vec<(String, String)>,
+    value: f64,
+    timestamp_ms: i64,
+}
+
+/// Appends the samples of the given metric as series, one per sample of the text format, e.g.
+/// the buckets, sum and count of a histogram.
+fn append_series(
+    series: &mut Vec<Series>,
+    metric_family: &MetricFamily,
+    metric: &Metric,
+    job: &str,
+    grouping: &HashMap<&str, &str>,
+    now: i64,
+) {
+    let name = metric_family.get_name();
+    let timestamp_ms = match metric.get_timestamp_ms() {
+        0 => now,
+        timestamp_ms => timestamp_ms,
+    };
+    let mut sample = |suffix: &str, extra: Option<(&str, f64)>, value: f64| {
+        let mut labels: Vec<(String, String)> = metric
+            .get_label()
+            .iter()
+            .map(|label| (label.get_name().to_owned(), label.get_value().to_owned()))
+            .chain(grouping.iter().map(|(k, v)| (k.to_string(), v.to_string())))
+            .chain([
+                (String::from("__name__"), format!("{name}{suffix}")),
+                (String::from("job"), job.to_owned()),
+            ])
+            .chain(extra.map(|(k, bound)| (k.to_owned(), format_bound(bound))))
+            .collect();
+        labels.sort();
+        series.push(Series { labels, value, timestamp_ms });
+    };
+
+    match metric_family.get_field_type() {
+        MetricType::COUNTER => sample("", None, metric.get_counter().get_value()),
+        MetricType::GAUGE => sample("", None, metric.get_gauge().get_value()),
+        MetricType::UNTYPED => sample("", None, metric.get_untyped().get_value()),
+        MetricType::SUMMARY => {
+            let summary = metric.get_summary();
+            for quantile in summary.get_quantile() {
+                let extra = Some(("quantile", quantile.get_quantile()));
+                sample("", extra, quantile.get_value());
+            }
+            sample("_sum", None, summary.get_sample_sum());
+            sample("_count", None, summary.get_sample_count() as f64);
+        }
+        MetricType::HISTOGRAM => {
+            let histogram = metric.get_histogram();
+            let buckets = histogram.get_bucket();
+            for bucket in buckets {
+                let extra = Some(("le", bucket.get_upper_bound()));
+                sample("_bucket", extra, bucket.get_cumulative_count() as f64);
+            }
+            if buckets
+                .last()
+                .is_none_or(|bucket| bucket.get_upper_bound() != f64::INFINITY)
+            {
+                let extra = Some(("le", f64::INFINITY));
+                sample("_bucket", extra, histogram.get_sample_count() as f64);
+            }
+            sample("_sum", None, histogram.get_sample_sum());
+            sample("_count", None, histogram.get_sample_count() as f64);
+        }
+    }
+}
+
+/// Formats the bound of a bucket or quantile like the text format.
+fn format_bound(bound: f64) -> String {
+    if bound == f64::INFINITY {
+        String::from("+Inf")
+    } else if bound == f64::NEG_INFINITY {
+        String::from("-Inf")
+    } else {
+        bound.to_string()
+    }
+}
+
+/// Encodes the given series as a `prometheus.WriteRequest` protobuf message.
+fn encode_write_request(series: &[Series]) -> ProtobufResult<Vec<u8>> {
+    encode_message(|output| {
+        for series in series {
+            output.write_bytes(1, &encode_time_series(series)?)?;
+        }
+        Ok(())
+    })
+}
+
+fn encode_time_series(series: &Series) -> ProtobufResult<Vec<u8>> {
+    encode_message(|output| {
+        for (name, value) in &series.labels {
+            let label = encode_message(|label| {
+                label.write_string(1, name)?;
+                label.write_string(2, value)
+            })?;
+            output.write_bytes(1, &label)?;
+        }
+
+        let sample = encode_message(|sample| {
+            sample.write_double(1, series.value)?;
+            sample.write_int64(2, series.timestamp_ms)
+        })?;
+        output.write_bytes(2, &sample)
+    })
+}
+
+fn encode_message(
+    write: impl FnOnce(&mut CodedOutputStream) -> ProtobufResult<()>,
+) -> ProtobufResult<Vec<u8>> {
+    let mut message = Vec::new();
+    let mut output = CodedOutputStream::vec(&mut message);
+    write(&mut output)?;
+    output.flush()?;
+    drop(output);
+
+    Ok(message)
+}
+
+/// `RemoteWriteClient` wraps a push client and sends the requests of a [`RemoteWriteConverter`]
+/// the way remote write receivers expect them: every push as `POST` with the
+/// `Content-Encoding: snappy` and `X-Prometheus-Remote-Write-Version` headers. Remote write
+/// cannot delete series, so deletes fail with [`PushMetricsError::RemoteWriteDelete`].
+///
+/// [`PushMetricsError::RemoteWriteDelete`]: crate::error::PushMetricsError::RemoteWriteDelete
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Debug)]
+pub struct RemoteWriteClient<P> {
+    client: P,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<P> RemoteWriteClient<P> {
+    /// Creates a new [`RemoteWriteClient`] that sends the requests with the given client.
+    pub fn new(client: P) -> Self {
+        Self { client }
+    }
+}
+
+/// Appends the headers of the remote write protocol to the headers of a request.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+fn with_remote_write_headers<'a>(headers: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+    headers
+        .iter()
+        .copied()
+        .chain([
+            ("Content-Encoding", "snappy"),
+            ("X-Prometheus-Remote-Write-Version", REMOTE_WRITE_VERSION),
+        ])
+        .collect()
+}
+
+#[cfg(feature = "blocking")]
+mod blocking_impl {
+    use url::Url;
+
+    use super::with_remote_write_headers;
+    use super::RemoteWriteClient;
+    use crate::blocking::Push;
+    use crate::error::PushMetricsError;
+    use crate::error::Result;
+    use crate::Method;
+
+    impl<P, B> Push<B> for RemoteWriteClient<P>
+    where
+        P: Push<B>,
+    {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            if method == Method::Delete {
+                return Err(PushMetricsError::RemoteWriteDelete);
+            }
+
+            let headers = with_remote_write_headers(headers);
+            self.client.send(Method::Post, url, body, &headers)
+        }
+    }
+}
+
+#[cfg(feature = "non_blocking")]
+mod non_blocking_impl {
+    use url::Url;
+
+    use super::with_remote_write_headers;
+    use super::RemoteWriteClient;
+    use crate::error::PushMetricsError;
+    use crate::error::Result;
+    use crate::non_blocking::Push;
+    use crate::Method;
+
+    impl<P, B> Push<B> for RemoteWriteClient<P>
+    where
+        P: Push<B> + Sync,
+        B: Send,
+    {
+        async fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            if method == Method::Delete {
+                return Err(PushMetricsError::RemoteWriteDelete);
+            }
+
+            let headers = with_remote_write_headers(headers);
+            self.client.send(Method::Post, url, body, &headers).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use prometheus::HistogramOpts;
+    use prometheus::IntCounterVec;
+    use prometheus::Opts;
+    use protobuf::wire_format::WireType;
+    use protobuf::CodedInputStream;
+    use url::Url;
+
+    use crate::remote_write::RemoteWriteConverter;
+    use crate::remote_write::Series;
+    use crate::ConvertMetrics;
+
+    fn decode_write_request(body: &[u8]) -> Vec<Series> {
+        fn fields(message: &[u8]) -> Vec<(u32, Vec<u8>)> {
+            let mut input = CodedInputStream::from_bytes(message);
+            let mut fields = Vec::new();
+            while !input.eof().unwrap() {
+                let (field, wire_type) = input.read_tag_unpack().unwrap();
+                let value = match wire_type {
+                    WireType::WireTypeFixed64 => {
+                        input.read_double().unwrap().to_le_bytes().to_vec()
+                    }
+                    WireType::WireTypeVarint => input.read_int64().unwrap().to_le_bytes().to_vec(),
+                    _ => input.read_bytes().unwrap(),
+                };
+                fields.push((field, value));
+            }
+            fields
+        }
+
+        let request = snap::raw::Decoder::new().decompress_vec(body).unwrap();
+        fields(&request)
+            .into_iter()
+            .map(|(_, time_series)| {
+                let mut series = Series { labels: Vec::new(), value: 0.0, timestamp_ms: 0 };
+                for (field, value) in fields(&time_series) {
+                    let mut nested = fields(&value).into_iter().map(|(_, value)| value);
+                    if field == 1 {
+                        let name = String::from_utf8(nested.next().unwrap()).unwrap();
+                        let value = String::from_utf8(nested.next().unwrap()).unwrap();
+                        series.labels.push((name, value));
+                    } else {
+                        series.value =
+                            f64::from_le_bytes(nested.next().unwrap().try_into().unwrap());
+                        series.timestamp_ms =
+                            i64::from_le_bytes(nested.next().unwrap().try_into().unwrap());
+                    }
+                }
+                series
+            })
+            .collect()
+    }
+
+    fn labels(labels: &[(&str, &str)]) -> Vec<(String, String)> {
+        labels
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_metric_families_are_encoded_as_series() {
+        // Given I have a counter with a timestamp and a histogram
+        let registry = prometheus::Registry::new();
+        let requests = IntCounterVec::new(Opts::new("requests", "help"), &["path"]).unwrap();
+        let latency = prometheus::Histogram::with_opts(
+            HistogramOpts::new("latency", "help").buckets(vec![1.0]),
+        )
+        .unwrap();
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(latency.clone())).unwrap();
+        requests.with_label_values(&["/a"]).inc_by(3);
+        latency.observe(0.5);
+        let mut metric_families = registry.gather();
+        metric_families
+            .iter_mut()
+            .filter(|family| family.get_name() == "requests")
+            .for_each(|family| family.mut_metric()[0].set_timestamp_ms(1_700_000_000_000));
+
+        // When I create the push details for a job and group
+        let url = Url::parse("http://prometheus:9090/metrics/job/").unwrap();
+        let grouping = HashMap::from([("env", "prod")]);
+        let (url, body, content_type) = RemoteWriteConverter::new()
+            .create_push_details("batch", &url, &grouping, metric_families)
+            .unwrap();
+
+        // Then the samples are pushed to the remote write endpoint with the job and group labels
+        assert_eq!(url.as_str(), "http://prometheus:9090/api/v1/write");
+        assert_eq!(content_type, "application/x-protobuf");
+        let series = decode_write_request(&body);
+        let histogram = |name: &str, le: Option<&str>| {
+            let mut labels = labels(&[("__name__", name), ("env", "prod"), ("job", "batch")]);
+            labels.extend(le.map(|le| (String::from("le"), le.to_string())));
+            labels
+        };
+        assert_eq!(
+            series
+                .iter()
+                .map(|s| (&s.labels, s.value))
+                .collect::<Vec<_>>(),
+            [
+                (&histogram("latency_bucket", Some("1")), 1.0),
+                (&histogram("latency_bucket", Some("+Inf")), 1.0),
+                (&histogram("latency_sum", None), 0.5),
+                (&histogram("latency_count", None), 1.0),
+                (
+                    &labels(&[
+                        ("__name__", "requests"),
+                        ("env", "prod"),
+                        ("job", "batch"),
+                        ("path", "/a")
+                    ]),
+                    3.0
+                ),
+            ]
+        );
+        assert_eq!(series[4].timestamp_ms, 1_700_000_000_000);
+        assert!(series[0].timestamp_ms > 1_700_000_000_000);
+    }
+
+    #[cfg(feature = "with_reqwest")]
+    #[tokio::test]
+    async fn test_push_all_is_sent_as_remote_write_request() {
+        use mockito::Server;
+        use prometheus::proto::MetricFamily;
+
+        use crate::error::PushMetricsError;
+        use crate::non_blocking::MetricsPusher;
+        use crate::remote_write::RemoteWriteClient;
+        use crate::with_reqwest::PushClient;
+
+        // Given I have a remote write receiver
+        let mut server = Server::new_async().await;
+        let receiver_mock = server
+            .mock("POST", "/api/v1/write")
+            .match_header("content-type", "application/x-protobuf")
+            .match_header("content-encoding", "snappy")
+            .match_header("x-prometheus-remote-write-version", "0.1.0")
+            .with_status(204)
+            .expect(1)
+            .create();
+
+        // And a metrics pusher that pushes remote write requests
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher: MetricsPusher<_, _, Vec<MetricFamily>, _, Vec<u8>> =
+            MetricsPusher::new(
+                RemoteWriteClient::new(PushClient::new(reqwest::Client::new())),
+                RemoteWriteConverter::new(),
+                &url,
+            )
+            .unwrap();
+        let counter = prometheus::IntCounter::new("up", "help").unwrap();
+        let registry = prometheus::Registry::new();
+        registry.register(Box::new(counter)).unwrap();
+
+        // When I push all metrics and delete the group
+        let pushed = metrics_pusher
+            .push_all("batch", &HashMap::new(), registry.gather())
+            .await;
+        let deleted = metrics_pusher.delete("batch", &HashMap::new()).await;
+
+        // Then the push is accepted and the delete is refused
+        pushed.expect("Failed to push metrics");
+        assert!(matches!(
+            deleted.map_err(PushMetricsError::without_context),
+            Err(PushMetricsError::RemoteWriteDelete)
+        ));
+        receiver_mock.assert();
+    }
+}
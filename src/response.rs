@@ -0,0 +1,156 @@
+use std::fmt::Debug;
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+
+/// `Respond` gives access to the response of the pushgateway, so custom [`Push`] implementations
+/// can reuse the response handling of this crate with [`handle_response`].
+///
+/// [`Push`]: crate::non_blocking::Push
+pub trait Respond {
+    /// Returns the status code of the response.
+    fn get_status_code(&self) -> StatusCode;
+
+    /// Returns the final url of the request.
+    fn get_url(&self) -> &Url;
+
+    /// Returns the value of the given header if it is present and valid text.
+    fn get_header(&self, name: &str) -> Option<&str>;
+
+    /// Returns the body of the response if it has already been read. Implementations that
+    /// stream the body return `None` and attach it to the error themselves if they want to.
+    fn get_body(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Checks the response of a push and logs a successful one. A response with an unexpected status
+/// code fails with the variant of its failure class, e.g. [`PushMetricsError::BadRequest`] with
+/// the body of the response if it is available, or [`PushMetricsError::Response`] for every
+/// status code without a class of its own.
+pub fn handle_response<R: Respond + ?Sized>(response: &R) -> Result<()> {
+    check_status(response)?;
+    log::info!("Pushed metrics to the pushgateway.");
+    Ok(())
+}
+
+/// Checks the status code of the response without logging it.
+pub fn check_status<R: Respond + ?Sized>(response: &R) -> Result<()> {
+    match response.get_status_code() {
+        StatusCode::ACCEPTED | StatusCode::OK | StatusCode::NO_CONTENT => Ok(()),
+        status_code => Err(PushMetricsError::response(
+            &status_code,
+            response.get_url(),
+            response.get_body(),
+            response.get_header("retry-after"),
+        )),
+    }
+}
+
+/// `ResponseHandler` decides whether the pushgateway accepted a push. Override it on the
+/// `PushClient`s if your gateway-compatible backend signals failures differently, e.g. with a
+/// partial failure in the body of a `200 OK`, and return a [`PushMetricsError::Rejected`] or
+/// any other error.
+pub trait ResponseHandler: Debug + Send + Sync {
+    /// Returns `Ok` if the push was accepted. The body of the response is always available.
+    fn handle(&self, response: &dyn Respond) -> Result<()>;
+}
+
+/// `StatusCodeHandler` is the default [`ResponseHandler`] that accepts `200 OK` and
+/// `202 Accepted` with [`handle_response`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StatusCodeHandler;
+
+impl ResponseHandler for StatusCodeHandler {
+    fn handle(&self, response: &dyn Respond) -> Result<()> {
+        handle_response(response)
+    }
+}
+
+/// A response whose body has already been read, so a [`ResponseHandler`] can inspect it.
+pub(crate) struct BufferedResponse {
+    pub(crate) status: StatusCode,
+    pub(crate) url: Url,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: String,
+}
+
+impl Respond for BufferedResponse {
+    fn get_status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn get_url(&self) -> &Url {
+        &self.url
+    }
+
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    fn get_body(&self) -> Option<&str> {
+        Some(&self.body)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use reqwest::StatusCode;
+    use url::Url;
+
+    use crate::response::handle_response;
+    use crate::response::Respond;
+
+    struct BufferedResponse {
+        status: StatusCode,
+        url: Url,
+        headers: HashMap<&'static str, &'static str>,
+        body: String,
+    }
+
+    impl Respond for BufferedResponse {
+        fn get_status_code(&self) -> StatusCode {
+            self.status
+        }
+
+        fn get_url(&self) -> &Url {
+            &self.url
+        }
+
+        fn get_header(&self, name: &str) -> Option<&str> {
+            self.headers.get(name).copied()
+        }
+
+        fn get_body(&self) -> Option<&str> {
+            Some(&self.body)
+        }
+    }
+
+    #[test]
+    fn test_handle_response_of_custom_respond() {
+        // Given I have a buffered response of a custom client that rejected the push
+        let response = BufferedResponse {
+            status: StatusCode::BAD_REQUEST,
+            url: Url::parse("http://pushgateway/metrics/job/custom").unwrap(),
+            headers: HashMap::from([("content-type", "text/plain")]),
+            body: String::from("text format parsing error in line 1\n"),
+        };
+
+        // When I handle the response
+        let result = handle_response(&response);
+
+        // Then the error contains the status code and the body of the response
+        let error = result.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST));
+        assert!(error
+            .to_string()
+            .ends_with(": text format parsing error in line 1"));
+        assert_eq!(response.get_header("content-type"), Some("text/plain"));
+    }
+}
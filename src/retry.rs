@@ -0,0 +1,530 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::core::Collector;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::core::Desc;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::proto::MetricFamily;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::IntCounter;
+#[cfg(feature = "prometheus_crate_text")]
+use prometheus::IntGauge;
+
+use crate::error::PushMetricsError;
+use crate::Method;
+
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// `RetryOn` defines which failed requests [`Retry`] sends again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryOn {
+    /// Never sends the request again.
+    Never,
+    /// Sends the request again only if it failed to connect, so it never reached the
+    /// pushgateway.
+    ConnectErrors,
+    /// Sends the request again if it failed to connect, timed out or the pushgateway answered
+    /// with `429 Too Many Requests` or a server error, see [`PushMetricsError::is_retryable`].
+    TransientErrors,
+}
+
+/// `Retry` wraps a [`Push`](crate::non_blocking::Push) or
+/// [`blocking::Push`](crate::blocking::Push) implementation and sends failed requests again
+/// with an exponential backoff, but not earlier than a `Retry-After` of
+/// `429 Too Many Requests` asks for.
+///
+/// Which failures are retried depends on the method: `PUT` of `push_all` and `DELETE` are
+/// idempotent and retried on all [`RetryOn::TransientErrors`], whereas `POST` of `push_add` is
+/// only retried on [`RetryOn::ConnectErrors`], as a request that timed out may have been
+/// applied and a retry would add its metrics twice. Override this with
+/// [`Retry::with_retry_on`]. The body is sent more than once, so it has to be [`Clone`].
+#[derive(Clone, Debug)]
+pub struct Retry<P> {
+    client: P,
+    retries: u32,
+    backoff: Duration,
+    put: RetryOn,
+    post: RetryOn,
+    delete: RetryOn,
+    budget: Option<RetryBudget>,
+}
+
+impl<P> Retry<P> {
+    /// Creates a new [`Retry`] that sends a failed request at most `retries` times again.
+    pub fn new(client: P, retries: u32) -> Self {
+        Self {
+            client,
+            retries,
+            backoff: DEFAULT_BACKOFF,
+            put: RetryOn::TransientErrors,
+            post: RetryOn::ConnectErrors,
+            delete: RetryOn::TransientErrors,
+            budget: None,
+        }
+    }
+
+    /// Sets the backoff before the first retry, which doubles with every further retry.
+    /// Defaults to 100 milliseconds.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets which failed requests of the given method are retried.
+    pub fn with_retry_on(mut self, method: Method, retry_on: RetryOn) -> Self {
+        match method {
+            Method::Put => self.put = retry_on,
+            Method::Post => self.post = retry_on,
+            Method::Delete => self.delete = retry_on,
+        }
+        self
+    }
+
+    /// Takes a token from the given [`RetryBudget`] for every retry, so the clients sharing it
+    /// cannot multiply their retries during an outage of the pushgateway. Without a token the
+    /// request fails with [`PushMetricsError::RetryBudgetExhausted`].
+    pub fn with_budget(mut self, budget: RetryBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Returns the backoff before the given retry if the failed request is retried, the error
+    /// otherwise.
+    fn backoff(
+        &self,
+        method: Method,
+        retry: u32,
+        error: PushMetricsError,
+    ) -> Result<Duration, PushMetricsError> {
+        let retry_on = match method {
+            Method::Put => self.put,
+            Method::Post => self.post,
+            Method::Delete => self.delete,
+        };
+        let retried = match retry_on {
+            RetryOn::Never => false,
+            RetryOn::ConnectErrors => is_connect_error(&error),
+            RetryOn::TransientErrors => error.is_retryable(),
+        };
+
+        if !retried || retry >= self.retries {
+            return Err(error);
+        }
+        if self
+            .budget
+            .as_ref()
+            .is_some_and(|budget| !budget.withdraw())
+        {
+            return Err(PushMetricsError::RetryBudgetExhausted(Box::new(error)));
+        }
+
+        let backoff = self.backoff.saturating_mul(1 << retry.min(16));
+        Ok(retry_after(&error).map_or(backoff, |retry_after| retry_after.max(backoff)))
+    }
+}
+
+/// `RetryBudget` is a token bucket that limits the retries of all [`Retry`]s it is shared
+/// with, see [`Retry::with_budget`]. Every retry takes a token, the bucket refills at a fixed
+/// rate up to its capacity. When a pushgateway is down, hundreds of groups then do not retry
+/// all at once and overload it as soon as it recovers.
+///
+/// Clones share the same bucket.
+#[derive(Clone, Debug)]
+pub struct RetryBudget {
+    state: Arc<BudgetState>,
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Mutex<(f64, Instant)>,
+    retries: AtomicU64,
+    exhausted: AtomicU64,
+}
+
+impl RetryBudget {
+    /// Creates a new full [`RetryBudget`] with the given capacity of retries that refills with
+    /// the given number of retries per second.
+    pub fn new(capacity: u32, refill_per_second: f64) -> Self {
+        let capacity = f64::from(capacity);
+        Self {
+            state: Arc::new(BudgetState {
+                capacity,
+                refill_per_second: refill_per_second.max(0.0),
+                bucket: Mutex::new((capacity, Instant::now())),
+                retries: AtomicU64::new(0),
+                exhausted: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns a snapshot of the budget.
+    pub fn stats(&self) -> RetryBudgetStats {
+        RetryBudgetStats {
+            available: self.refill().floor() as u32,
+            retries: self.state.retries.load(Ordering::Relaxed),
+            exhausted: self.state.exhausted.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns a [`RetryBudgetCollector`] that exposes the budget as metrics once it is
+    /// registered in a [`Registry`](prometheus::Registry).
+    #[cfg(feature = "prometheus_crate_text")]
+    pub fn collector(&self) -> RetryBudgetCollector {
+        RetryBudgetCollector::new(self.clone())
+    }
+
+    /// Takes a token if there is one.
+    fn withdraw(&self) -> bool {
+        let mut bucket = self
+            .state
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        refill(&self.state, &mut bucket);
+
+        if bucket.0 < 1.0 {
+            self.state.exhausted.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        bucket.0 -= 1.0;
+        self.state.retries.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Refills the bucket and returns the available tokens.
+    fn refill(&self) -> f64 {
+        let mut bucket = self
+            .state
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        refill(&self.state, &mut bucket);
+        bucket.0
+    }
+}
+
+fn refill(state: &BudgetState, (tokens, refilled): &mut (f64, Instant)) {
+    let now = Instant::now();
+    let elapsed = now.duration_since(*refilled).as_secs_f64();
+    *tokens = (*tokens + elapsed * state.refill_per_second).min(state.capacity);
+    *refilled = now;
+}
+
+/// `RetryBudgetStats` is a snapshot of a [`RetryBudget`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RetryBudgetStats {
+    available: u32,
+    retries: u64,
+    exhausted: u64,
+}
+
+impl RetryBudgetStats {
+    /// Returns the number of retries that are currently available.
+    pub fn available(&self) -> u32 {
+        self.available
+    }
+
+    /// Returns the number of retries the budget granted.
+    pub fn retries(&self) -> u64 {
+        self.retries
+    }
+
+    /// Returns the number of retries the budget refused because it was exhausted.
+    pub fn exhausted(&self) -> u64 {
+        self.exhausted
+    }
+}
+
+/// `RetryBudgetCollector` is a [`Collector`] that exposes a [`RetryBudget`] as
+/// `prometheus_push_retry_budget_available`, `prometheus_push_retries_total` and
+/// `prometheus_push_retry_budget_exhausted_total`.
+#[cfg(feature = "prometheus_crate_text")]
+#[derive(Clone, Debug)]
+pub struct RetryBudgetCollector {
+    budget: RetryBudget,
+    available: IntGauge,
+    retries: IntCounter,
+    exhausted: IntCounter,
+}
+
+#[cfg(feature = "prometheus_crate_text")]
+impl RetryBudgetCollector {
+    fn new(budget: RetryBudget) -> Self {
+        Self {
+            budget,
+            available: IntGauge::new(
+                "prometheus_push_retry_budget_available",
+                "Number of retries that are currently available.",
+            )
+            .expect("valid metric"),
+            retries: IntCounter::new(
+                "prometheus_push_retries_total",
+                "Number of retries the retry budget granted.",
+            )
+            .expect("valid metric"),
+            exhausted: IntCounter::new(
+                "prometheus_push_retry_budget_exhausted_total",
+                "Number of retries the exhausted retry budget refused.",
+            )
+            .expect("valid metric"),
+        }
+    }
+}
+
+#[cfg(feature = "prometheus_crate_text")]
+impl Collector for RetryBudgetCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        [
+            self.available.desc(),
+            self.retries.desc(),
+            self.exhausted.desc(),
+        ]
+        .concat()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let stats = self.budget.stats();
+        self.available.set(i64::from(stats.available()));
+        self.retries
+            .inc_by(stats.retries().saturating_sub(self.retries.get()));
+        self.exhausted
+            .inc_by(stats.exhausted().saturating_sub(self.exhausted.get()));
+
+        [
+            self.available.collect(),
+            self.retries.collect(),
+            self.exhausted.collect(),
+        ]
+        .concat()
+    }
+}
+
+fn is_connect_error(error: &PushMetricsError) -> bool {
+    match error {
+        PushMetricsError::Io(error) => matches!(
+            error.kind(),
+            std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotConnected
+        ),
+        #[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
+        PushMetricsError::Reqwest(error) => error.is_connect(),
+        PushMetricsError::Context(context) => is_connect_error(context.error()),
+        _ => false,
+    }
+}
+
+/// Returns how long the pushgateway asked to wait with `429 Too Many Requests`.
+fn retry_after(error: &PushMetricsError) -> Option<Duration> {
+    match error {
+        #[cfg(feature = "http")]
+        PushMetricsError::TooManyRequests { retry_after, .. } => *retry_after,
+        PushMetricsError::Context(context) => retry_after(context.error()),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "blocking")]
+mod blocking_impl {
+    use url::Url;
+
+    use super::Retry;
+    use crate::blocking::Push;
+    use crate::error::Result;
+    use crate::Method;
+
+    impl<P, B> Push<B> for Retry<P>
+    where
+        P: Push<B>,
+        B: Clone,
+    {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            let mut retry = 0;
+            loop {
+                let error = match self.client.send(method, url, body.clone(), headers) {
+                    Ok(()) => return Ok(()),
+                    Err(error) => error,
+                };
+
+                std::thread::sleep(self.backoff(method, retry, error)?);
+                retry += 1;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "non_blocking")]
+mod non_blocking_impl {
+    use url::Url;
+
+    use super::Retry;
+    use crate::error::Result;
+    use crate::non_blocking::Push;
+    use crate::Method;
+
+    impl<P, B> Push<B> for Retry<P>
+    where
+        P: Push<B> + Sync,
+        B: Clone + Send + Sync,
+    {
+        async fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            headers: &[(&str, &str)],
+        ) -> Result<()> {
+            let mut retry = 0;
+            loop {
+                let error = match self.client.send(method, url, body.clone(), headers).await {
+                    Ok(()) => return Ok(()),
+                    Err(error) => error,
+                };
+
+                tokio::time::sleep(self.backoff(method, retry, error)?).await;
+                retry += 1;
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod test {
+    use std::io::ErrorKind;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use url::Url;
+
+    use crate::blocking::Push;
+    use crate::error::PushMetricsError;
+    use crate::error::Result;
+    use crate::retry::Retry;
+    use crate::retry::RetryBudget;
+    use crate::retry::RetryOn;
+    use crate::Method;
+
+    struct FlakyClient {
+        failures: Mutex<Vec<ErrorKind>>,
+        sent: Mutex<u32>,
+    }
+
+    impl FlakyClient {
+        fn new(failures: &[ErrorKind]) -> Self {
+            Self { failures: Mutex::new(failures.to_vec()), sent: Mutex::new(0) }
+        }
+    }
+
+    impl Push<Vec<u8>> for FlakyClient {
+        fn send(
+            &self,
+            _: Method,
+            _: &Url,
+            _: Option<(Vec<u8>, &str)>,
+            _: &[(&str, &str)],
+        ) -> Result<()> {
+            *self.sent.lock().unwrap() += 1;
+            match self.failures.lock().unwrap().pop() {
+                Some(kind) => Err(PushMetricsError::Io(kind.into())),
+                None => Ok(()),
+            }
+        }
+    }
+
+    fn send(retry: &Retry<FlakyClient>, method: Method) -> (Result<()>, u32) {
+        let url = Url::parse("http://pushgateway:9091/metrics/job/batch").unwrap();
+        let result = retry.send(method, &url, Some((b"up 1\n".to_vec(), "text/plain")), &[]);
+        (result, *retry.client.sent.lock().unwrap())
+    }
+
+    #[test]
+    fn test_put_is_retried_on_transient_errors() {
+        // Given I have a client that times out and then refuses the connection once
+        let client = FlakyClient::new(&[ErrorKind::ConnectionRefused, ErrorKind::TimedOut]);
+        let retry = Retry::new(client, 3).with_backoff(Duration::ZERO);
+
+        // When I send a put
+        let (result, sent) = send(&retry, Method::Put);
+
+        // Then it is sent until it succeeds
+        assert!(result.is_ok());
+        assert_eq!(sent, 3);
+    }
+
+    #[test]
+    fn test_post_is_only_retried_on_connect_errors() {
+        // Given I have a client that refuses the connection and then times out
+        let client = FlakyClient::new(&[ErrorKind::TimedOut, ErrorKind::ConnectionRefused]);
+        let retry = Retry::new(client, 3).with_backoff(Duration::ZERO);
+
+        // When I send a post
+        let (result, sent) = send(&retry, Method::Post);
+
+        // Then it is retried after the refused connection but not after the timeout
+        assert!(
+            matches!(result, Err(PushMetricsError::Io(error)) if error.kind() == ErrorKind::TimedOut)
+        );
+        assert_eq!(sent, 2);
+    }
+
+    #[test]
+    fn test_retries_are_limited_and_configurable_per_method() {
+        // Given I have a client that times out three times
+        let client = FlakyClient::new(&[ErrorKind::TimedOut; 3]);
+        let retry = Retry::new(client, 1)
+            .with_backoff(Duration::ZERO)
+            .with_retry_on(Method::Post, RetryOn::TransientErrors);
+
+        // When I send a post that is retried on timeouts as well
+        let (result, sent) = send(&retry, Method::Post);
+
+        // Then it is sent at most once again
+        assert!(result.is_err());
+        assert_eq!(sent, 2);
+    }
+
+    #[test]
+    fn test_retries_are_limited_by_a_shared_budget() {
+        // Given I have two clients that time out once and share a budget of one retry
+        let budget = RetryBudget::new(1, 0.0);
+        let retry = |client| {
+            Retry::new(client, 3)
+                .with_backoff(Duration::ZERO)
+                .with_budget(budget.clone())
+        };
+        let first = retry(FlakyClient::new(&[ErrorKind::TimedOut]));
+        let second = retry(FlakyClient::new(&[ErrorKind::TimedOut]));
+
+        // When I send a put with each of them
+        let (first_result, _) = send(&first, Method::Put);
+        let (second_result, sent) = send(&second, Method::Put);
+
+        // Then only the first one is retried and the second one fails with the exhausted budget
+        assert!(first_result.is_ok());
+        assert!(matches!(
+            second_result,
+            Err(PushMetricsError::RetryBudgetExhausted(error)) if matches!(*error, PushMetricsError::Io(_))
+        ));
+        assert_eq!(sent, 1);
+
+        // And the budget counts the granted and the refused retry
+        let stats = budget.stats();
+        assert_eq!(
+            (stats.available(), stats.retries(), stats.exhausted()),
+            (0, 1, 1)
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::error::Result;
+use crate::grouping::Grouping;
+use crate::non_blocking::MetricsPusher;
+use crate::non_blocking::Push;
+use crate::ConvertMetrics;
+use crate::PushReceipt;
+
+const DEFAULT_MAX_PUSHERS: usize = 128;
+
+/// A group on the pushgateway identified by its job and grouping labels.
+type Group = (String, BTreeMap<String, String>);
+
+/// `ScopedPusher` pushes to a single group, identified by its job and grouping labels, with a
+/// shared [`MetricsPusher`], so the code that produces the metrics of a job does not have to
+/// know its group.
+#[derive(Debug)]
+pub struct ScopedPusher<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    metrics_pusher: Arc<MetricsPusher<P, CM, MF, C, B>>,
+    job: String,
+    grouping: Grouping,
+}
+
+impl<P, CM, MF, C, B> ScopedPusher<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    /// Creates a new [`ScopedPusher`] that pushes to the group of the given job and grouping
+    /// labels with the given [`MetricsPusher`].
+    pub fn new(
+        metrics_pusher: Arc<MetricsPusher<P, CM, MF, C, B>>,
+        job: &str,
+        grouping: Grouping,
+    ) -> Self {
+        Self { metrics_pusher, job: job.to_owned(), grouping }
+    }
+
+    /// Pushes all metrics to the group, see [`MetricsPusher::push_all`].
+    pub async fn push_all(&self, metric_families: MF) -> Result<PushReceipt> {
+        self.metrics_pusher
+            .push_all(&self.job, &self.grouping.labels(), metric_families)
+            .await
+    }
+
+    /// Pushes all metrics with add logic to the group, see [`MetricsPusher::push_add`].
+    pub async fn push_add(&self, metric_families: MF) -> Result<PushReceipt> {
+        self.metrics_pusher
+            .push_add(&self.job, &self.grouping.labels(), metric_families)
+            .await
+    }
+
+    /// Deletes the group, see [`MetricsPusher::delete`].
+    pub async fn delete(&self) -> Result<PushReceipt> {
+        self.metrics_pusher
+            .delete(&self.job, &self.grouping.labels())
+            .await
+    }
+
+    /// Returns the job name of the group.
+    pub fn job(&self) -> &str {
+        &self.job
+    }
+
+    /// Returns the grouping labels of the group.
+    pub fn grouping(&self) -> &Grouping {
+        &self.grouping
+    }
+}
+
+/// `MetricsPusherPool` hands out a [`ScopedPusher`] per job and grouping labels that all share
+/// the push client and converter of one [`MetricsPusher`], for processes that push many
+/// distinct jobs. The scoped pushers are created on first use and cached, the least recently
+/// used one is evicted once the pool holds more than its maximum, 128 by default.
+#[derive(Debug)]
+pub struct MetricsPusherPool<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    metrics_pusher: Arc<MetricsPusher<P, CM, MF, C, B>>,
+    max_pushers: usize,
+    pushers: Mutex<Pushers<P, CM, MF, C, B>>,
+}
+
+/// The cached scoped pushers and the tick of the latest use.
+#[derive(Debug)]
+struct Pushers<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    tick: u64,
+    by_group: HashMap<Group, Cached<P, CM, MF, C, B>>,
+}
+
+/// A cached [`ScopedPusher`] with the tick of its last use.
+#[derive(Debug)]
+struct Cached<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    scoped_pusher: Arc<ScopedPusher<P, CM, MF, C, B>>,
+    last_used: u64,
+}
+
+impl<P, CM, MF, C, B> MetricsPusherPool<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    /// Creates a new [`MetricsPusherPool`] whose scoped pushers push with the given
+    /// [`MetricsPusher`].
+    pub fn new(metrics_pusher: MetricsPusher<P, CM, MF, C, B>) -> Self {
+        Self {
+            metrics_pusher: Arc::new(metrics_pusher),
+            max_pushers: DEFAULT_MAX_PUSHERS,
+            pushers: Mutex::new(Pushers { tick: 0, by_group: HashMap::new() }),
+        }
+    }
+
+    /// Sets the maximum number of cached scoped pushers, at least one is kept.
+    pub fn with_max_pushers(mut self, max_pushers: usize) -> Self {
+        self.max_pushers = max_pushers.max(1);
+        self
+    }
+
+    /// Returns the [`ScopedPusher`] of the given job and grouping labels and creates it if it
+    /// is not cached.
+    pub fn get(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+    ) -> Arc<ScopedPusher<P, CM, MF, C, B>> {
+        let group: Group = (
+            job.to_owned(),
+            grouping
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        );
+
+        let mut pushers = self
+            .pushers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        pushers.tick += 1;
+        let tick = pushers.tick;
+
+        if let Some(cached) = pushers.by_group.get_mut(&group) {
+            cached.last_used = tick;
+            return Arc::clone(&cached.scoped_pusher);
+        }
+
+        if pushers.by_group.len() >= self.max_pushers {
+            let least_recently_used = pushers
+                .by_group
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(group, _)| group.clone());
+            if let Some(group) = least_recently_used {
+                pushers.by_group.remove(&group);
+            }
+        }
+
+        let grouping = group
+            .1
+            .iter()
+            .fold(Grouping::new(), |grouping, (name, value)| {
+                grouping.with_label(name, value)
+            });
+        let scoped_pusher = Arc::new(ScopedPusher::new(
+            Arc::clone(&self.metrics_pusher),
+            job,
+            grouping,
+        ));
+        pushers.by_group.insert(
+            group,
+            Cached { scoped_pusher: Arc::clone(&scoped_pusher), last_used: tick },
+        );
+
+        scoped_pusher
+    }
+
+    /// Returns the number of cached scoped pushers.
+    pub fn len(&self) -> usize {
+        self.pushers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .by_group
+            .len()
+    }
+
+    /// Returns whether no scoped pusher is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the shared [`MetricsPusher`].
+    pub fn metrics_pusher(&self) -> &MetricsPusher<P, CM, MF, C, B> {
+        &self.metrics_pusher
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::scoped::MetricsPusherPool;
+    use crate::with_reqwest::PushClient;
+
+    #[tokio::test]
+    async fn test_pool_caches_and_evicts_scoped_pushers() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/job_a/instance/x")
+            .match_body("up 1\n")
+            .create();
+
+        // And a pool that caches two scoped pushers
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let pool = MetricsPusherPool::new(metrics_pusher).with_max_pushers(2);
+
+        // When I get the pushers of job a twice, of job b and then of job c
+        let grouping = HashMap::from([("instance", "x")]);
+        let job_a = pool.get("job_a", &grouping);
+        let job_a_again = pool.get("job_a", &grouping);
+        let job_b = pool.get("job_b", &grouping);
+        pool.get("job_a", &grouping);
+        pool.get("job_c", &grouping);
+
+        // Then job a is cached and job b was evicted as least recently used one
+        assert!(Arc::ptr_eq(&job_a, &job_a_again));
+        assert_eq!(pool.len(), 2);
+        assert!(!Arc::ptr_eq(&job_b, &pool.get("job_b", &grouping)));
+
+        // And the scoped pusher pushes to its group
+        job_a
+            .push_all("up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        pushgateway_mock.expect(1).assert();
+    }
+}
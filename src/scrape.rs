@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use url::Url;
+
+use crate::error::Result;
+use crate::non_blocking::Fetch;
+use crate::non_blocking::MetricsPusher;
+use crate::non_blocking::Push;
+use crate::text_format;
+use crate::ConvertMetrics;
+use crate::PushReceipt;
+
+type MetricFilter = Box<dyn Fn(&str) -> bool + Send + Sync>;
+type Relabel = Box<dyn Fn(&mut Vec<(String, String)>) + Send + Sync>;
+
+/// `ScrapePusher` bridges any exporter to the pushgateway: it scrapes a `/metrics` endpoint
+/// in the text format with a [`Fetch`] client and forwards the payload with the given
+/// [`MetricsPusher`]. This gets metrics out of environments the Prometheus server cannot
+/// scrape, e.g. behind a NAT.
+///
+/// The wrapped [`MetricsPusher`] has to accept the metrics as [`String`] in the text format,
+/// e.g. by using a [`RawMetricsConverter::text`](crate::raw::RawMetricsConverter::text).
+///
+/// Optionally the scraped metric families can be filtered by name and the labels of every
+/// sample can be rewritten before they are pushed.
+pub struct ScrapePusher<F, P, CM, C, B>
+where
+    F: Fetch,
+    P: Push<B>,
+    CM: ConvertMetrics<String, C, B>,
+{
+    fetch_client: F,
+    target: Url,
+    metrics_pusher: MetricsPusher<P, CM, String, C, B>,
+    job: String,
+    grouping: HashMap<String, String>,
+    filter: Option<MetricFilter>,
+    relabel: Option<Relabel>,
+}
+
+impl<F, P, CM, C, B> ScrapePusher<F, P, CM, C, B>
+where
+    F: Fetch,
+    P: Push<B>,
+    CM: ConvertMetrics<String, C, B>,
+{
+    /// Creates a new [`ScrapePusher`] that scrapes `target` with the given [`Fetch`] client
+    /// and pushes the result as `job` with the given [`MetricsPusher`].
+    pub fn new(
+        fetch_client: F,
+        target: Url,
+        metrics_pusher: MetricsPusher<P, CM, String, C, B>,
+        job: &str,
+    ) -> Self {
+        Self {
+            fetch_client,
+            target,
+            metrics_pusher,
+            job: job.to_owned(),
+            grouping: HashMap::new(),
+            filter: None,
+            relabel: None,
+        }
+    }
+
+    /// Sets the grouping labels that are used for every push.
+    pub fn with_grouping(mut self, grouping: &HashMap<&str, &str>) -> Self {
+        self.grouping = grouping
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self
+    }
+
+    /// Only forwards the metric families whose name the given filter returns `true` for.
+    pub fn with_filter(mut self, filter: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Rewrites the labels of every forwarded sample with the given function, e.g. to drop
+    /// labels that conflict with the grouping labels.
+    pub fn with_relabel(
+        mut self,
+        relabel: impl Fn(&mut Vec<(String, String)>) + Send + Sync + 'static,
+    ) -> Self {
+        self.relabel = Some(Box::new(relabel));
+        self
+    }
+
+    /// Scrapes the target once and pushes the scraped metrics to the pushgateway.
+    pub async fn scrape_and_push(&self) -> Result<PushReceipt> {
+        let scraped = self.fetch_client.fetch(&self.target).await?;
+        let metrics = self.transform(scraped)?;
+        let grouping = self
+            .grouping
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+
+        self.metrics_pusher
+            .push_all(&self.job, &grouping, metrics)
+            .await
+    }
+
+    /// Scrapes and pushes every `interval` until the returned future is dropped. Failed
+    /// scrapes or pushes are logged and retried with the next tick.
+    pub async fn run(&self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.scrape_and_push().await {
+                log::error!(
+                    "Failed to forward metrics scraped from {}: {error}",
+                    self.target
+                );
+            }
+        }
+    }
+
+    fn transform(&self, scraped: String) -> Result<String> {
+        if self.filter.is_none() && self.relabel.is_none() {
+            return Ok(scraped);
+        }
+
+        let mut families = text_format::parse(&scraped)?;
+        if let Some(filter) = &self.filter {
+            families.retain(|family| filter(&family.name));
+        }
+
+        if let Some(relabel) = &self.relabel {
+            for sample in families
+                .iter_mut()
+                .flat_map(|family| family.samples.iter_mut())
+            {
+                relabel(&mut sample.labels);
+            }
+        }
+
+        Ok(text_format::encode(&families))
+    }
+}
+
+impl<F, P, CM, C, B> fmt::Debug for ScrapePusher<F, P, CM, C, B>
+where
+    F: Fetch + fmt::Debug,
+    P: Push<B>,
+    CM: ConvertMetrics<String, C, B>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScrapePusher")
+            .field("fetch_client", &self.fetch_client)
+            .field("target", &self.target)
+            .field("job", &self.job)
+            .field("grouping", &self.grouping)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::collections::HashMap;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::scrape::ScrapePusher;
+    use crate::with_reqwest::PushClient;
+
+    #[tokio::test]
+    async fn test_scrape_filter_relabel_and_push() {
+        // Given I have an exporter
+        let mut exporter = Server::new_async().await;
+        let scraped = "# TYPE up gauge\n".to_owned()
+            + "up{instance=\"local\"} 1\n"
+            + "# TYPE go_goroutines gauge\n"
+            + "go_goroutines 8\n";
+        let exporter_mock = exporter.mock("GET", "/metrics").with_body(scraped).create();
+
+        // And a push gateway that expects the filtered and relabeled metrics
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/bridge/site/edge")
+            .match_body("# TYPE up gauge\nup 1\n")
+            .create();
+
+        // And a scrape pusher that drops go metrics and the instance label
+        let target = Url::parse(&exporter.url())
+            .unwrap()
+            .join("metrics")
+            .unwrap();
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let scrape_pusher = ScrapePusher::new(
+            PushClient::new(reqwest::Client::new()),
+            target,
+            metrics_pusher,
+            "bridge",
+        )
+        .with_grouping(&HashMap::from([("site", "edge")]))
+        .with_filter(|name| !name.starts_with("go_"))
+        .with_relabel(|labels| labels.retain(|(name, _)| name != "instance"));
+
+        // When I scrape and push
+        scrape_pusher
+            .scrape_and_push()
+            .await
+            .expect("Failed to forward metrics");
+
+        // Then the exporter is scraped and the transformed metrics are pushed
+        exporter_mock.expect(1).assert();
+        pushgateway_mock.expect(1).assert();
+    }
+}
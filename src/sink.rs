@@ -0,0 +1,235 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use url::Url;
+
+use crate::error::Result;
+use crate::Method;
+
+/// `FilePushClient` is a [`Push`](crate::non_blocking::Push) and
+/// [`blocking::Push`](crate::blocking::Push) implementation that writes the body of every
+/// request to its own file in the given directory instead of sending it, e.g. to debug pushes
+/// in air-gapped environments or to diff what would have been pushed.
+///
+/// The files are named after the time of the request in milliseconds since the unix epoch, a
+/// sequence number, the method and the path of the url, e.g.
+/// `1700000000000-0-PUT-metrics_job_batch.txt`. The directory has to exist.
+#[derive(Debug)]
+pub struct FilePushClient {
+    directory: PathBuf,
+    sequence: AtomicU64,
+}
+
+impl FilePushClient {
+    /// Creates a new [`FilePushClient`] that writes to the given directory.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self { directory: directory.into(), sequence: AtomicU64::new(0) }
+    }
+
+    fn write(&self, method: Method, url: &Url, body: Option<(&[u8], &str)>) -> Result<()> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let path = url.path().trim_matches('/').replace('/', "_");
+        let extension = match body {
+            Some((_, content_type)) if content_type.contains("protobuf") => "bin",
+            _ => "txt",
+        };
+
+        let file = format!("{millis}-{sequence}-{}-{path}.{extension}", method.as_str());
+        fs::write(
+            self.directory.join(file),
+            body.map_or(&[][..], |(body, _)| body),
+        )?;
+        Ok(())
+    }
+}
+
+/// `StdoutPushClient` is a [`Push`](crate::non_blocking::Push) and
+/// [`blocking::Push`](crate::blocking::Push) implementation that prints every request to
+/// stdout instead of sending it: the method and url, the content type and the body, or its
+/// size if it is not text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdoutPushClient;
+
+impl StdoutPushClient {
+    /// Creates a new [`StdoutPushClient`].
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn write(&self, method: Method, url: &Url, body: Option<(&[u8], &str)>) -> Result<()> {
+        let request = render(method, url, body);
+        std::io::stdout().lock().write_all(request.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Renders a request like an http request: the request line, the content type and the body.
+fn render(method: Method, url: &Url, body: Option<(&[u8], &str)>) -> String {
+    let mut request = format!("{} {url}\n", method.as_str());
+
+    if let Some((body, content_type)) = body {
+        request.push_str(&format!("Content-Type: {content_type}\n\n"));
+        match std::str::from_utf8(body) {
+            Ok(text) if !content_type.contains("protobuf") => request.push_str(text),
+            _ => request.push_str(&format!("<{} bytes>\n", body.len())),
+        }
+    }
+
+    request.push('\n');
+    request
+}
+
+#[cfg(feature = "blocking")]
+mod blocking_impl {
+    use url::Url;
+
+    use super::FilePushClient;
+    use super::StdoutPushClient;
+    use crate::blocking::Push;
+    use crate::error::Result;
+    use crate::Method;
+
+    impl<B: AsRef<[u8]>> Push<B> for FilePushClient {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            _: &[(&str, &str)],
+        ) -> Result<()> {
+            let body = body.as_ref().map(|(body, ct)| (body.as_ref(), *ct));
+            self.write(method, url, body)
+        }
+    }
+
+    impl<B: AsRef<[u8]>> Push<B> for StdoutPushClient {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            _: &[(&str, &str)],
+        ) -> Result<()> {
+            let body = body.as_ref().map(|(body, ct)| (body.as_ref(), *ct));
+            self.write(method, url, body)
+        }
+    }
+}
+
+#[cfg(feature = "non_blocking")]
+mod non_blocking_impl {
+    use std::future::Future;
+
+    use url::Url;
+
+    use super::FilePushClient;
+    use super::StdoutPushClient;
+    use crate::error::Result;
+    use crate::non_blocking::Push;
+    use crate::Method;
+
+    impl<B: AsRef<[u8]>> Push<B> for FilePushClient {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            _: &[(&str, &str)],
+        ) -> impl Future<Output = Result<()>> + Send {
+            let body = body.as_ref().map(|(body, ct)| (body.as_ref(), *ct));
+            std::future::ready(self.write(method, url, body))
+        }
+    }
+
+    impl<B: AsRef<[u8]>> Push<B> for StdoutPushClient {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            body: Option<(B, &str)>,
+            _: &[(&str, &str)],
+        ) -> impl Future<Output = Result<()>> + Send {
+            let body = body.as_ref().map(|(body, ct)| (body.as_ref(), *ct));
+            std::future::ready(self.write(method, url, body))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "non_blocking"))]
+mod test {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use url::Url;
+
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::sink::render;
+    use crate::sink::FilePushClient;
+    use crate::Method;
+
+    #[tokio::test]
+    async fn test_file_push_client_writes_every_payload() {
+        // Given I have a metrics pusher that writes to a directory
+        let directory = tempfile::tempdir().unwrap();
+        let url = Url::parse("http://pushgateway:9091").unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            FilePushClient::new(directory.path()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+
+        // When I push twice
+        let grouping = HashMap::from([("env", "test")]);
+        for value in ["up 1\n", "up 2\n"] {
+            metrics_pusher
+                .push_all("batch", &grouping, value)
+                .await
+                .expect("Failed to push metrics");
+        }
+
+        // Then every payload is written to its own file named after the request
+        let mut files: Vec<_> = fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort_by_key(|file| fs::read_to_string(file).unwrap());
+        assert_eq!(files.len(), 2);
+        assert!(files[0]
+            .to_string_lossy()
+            .ends_with("-PUT-metrics_job_batch_env_test.txt"));
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), "up 1\n");
+        assert_eq!(fs::read_to_string(&files[1]).unwrap(), "up 2\n");
+    }
+
+    #[test]
+    fn test_requests_are_rendered_for_stdout() {
+        // Given I have a text and a protobuf request
+        let url = Url::parse("http://pushgateway:9091/metrics/job/batch").unwrap();
+
+        // When I render them
+        let text = render(Method::Put, &url, Some((b"up 1\n", "text/plain")));
+        let protobuf = render(
+            Method::Post,
+            &url,
+            Some((b"\x0a\x02up", "application/vnd.google.protobuf")),
+        );
+
+        // Then text bodies are printed and binary bodies are summarized
+        assert_eq!(
+            text,
+            "PUT http://pushgateway:9091/metrics/job/batch\nContent-Type: text/plain\n\nup 1\n\n"
+        );
+        assert!(protobuf.ends_with("\n\n<4 bytes>\n\n"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use hickory_resolver::proto::rr::rdata::SRV;
+use hickory_resolver::proto::rr::RData;
+use hickory_resolver::TokioResolver;
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::pool::GatewaySet;
+
+const MIN_REFRESH: Duration = Duration::from_secs(5);
+const RETRY_AFTER_ERROR: Duration = Duration::from_secs(30);
+
+/// `SrvDiscovery` discovers the pushgateway instances of a service via DNS SRV records and keeps
+/// the gateways of a [`GatewayPool`](crate::pool::GatewayPool) up to date, e.g. for a gateway
+/// fleet that scales behind service discovery. The records are looked up again once their TTL
+/// expired.
+///
+/// The records with the lowest priority take the pushes according to their weight, all others
+/// become standbys that are only pushed to if the preferred gateways failed.
+#[derive(Debug)]
+pub struct SrvDiscovery {
+    resolver: TokioResolver,
+    service: String,
+    scheme: String,
+    path: String,
+}
+
+impl SrvDiscovery {
+    /// Creates a new [`SrvDiscovery`] for the given service name, e.g.
+    /// `_pushgateway._tcp.monitoring.svc.cluster.local`, with the system's resolver
+    /// configuration. The gateways are pushed to via `http`.
+    pub fn new(service: &str) -> Result<Self> {
+        let resolver = TokioResolver::builder_tokio()
+            .map_err(|error| PushMetricsError::Discovery(error.to_string()))?
+            .build()
+            .map_err(|error| PushMetricsError::Discovery(error.to_string()))?;
+
+        Ok(Self::with_resolver(resolver, service))
+    }
+
+    /// Creates a new [`SrvDiscovery`] for the given service name with a configured resolver.
+    pub fn with_resolver(resolver: TokioResolver, service: &str) -> Self {
+        Self {
+            resolver,
+            service: service.to_owned(),
+            scheme: String::from("http"),
+            path: String::from("/"),
+        }
+    }
+
+    /// Sets the scheme the discovered gateways are pushed to with. Defaults to `http`.
+    pub fn with_scheme(mut self, scheme: &str) -> Self {
+        self.scheme = scheme.to_owned();
+        self
+    }
+
+    /// Sets the path the discovered gateways serve the pushgateway api below, which has to
+    /// match the url the `MetricsPusher` was created with. Defaults to `/`.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = path.to_owned();
+        self
+    }
+
+    /// Looks up the SRV records once and returns the discovered gateways with their weights
+    /// together with the instant the records expire.
+    pub async fn discover(&self) -> Result<(Vec<(Url, u32)>, Instant)> {
+        let lookup = self
+            .resolver
+            .srv_lookup(self.service.as_str())
+            .await
+            .map_err(|error| PushMetricsError::Discovery(error.to_string()))?;
+
+        let records: Vec<&SRV> = lookup
+            .answers()
+            .iter()
+            .filter_map(|record| match &record.data {
+                RData::SRV(srv) => Some(srv),
+                _ => None,
+            })
+            .collect();
+
+        Ok((self.gateways(&records)?, lookup.valid_until()))
+    }
+
+    /// Keeps the given [`GatewaySet`] up to date until the returned future is dropped. Failed
+    /// lookups are logged and retried, the pool keeps the last discovered gateways meanwhile.
+    pub async fn run(&self, gateway_set: GatewaySet) {
+        loop {
+            let refresh = match self.discover().await {
+                Ok((gateways, valid_until)) if !gateways.is_empty() => {
+                    gateway_set.replace(&gateways);
+                    valid_until
+                        .saturating_duration_since(Instant::now())
+                        .max(MIN_REFRESH)
+                }
+                Ok(_) => {
+                    log::warn!("No pushgateways found for {}", self.service);
+                    RETRY_AFTER_ERROR
+                }
+                Err(error) => {
+                    log::error!(
+                        "Failed to discover pushgateways of {}: {error}",
+                        self.service
+                    );
+                    RETRY_AFTER_ERROR
+                }
+            };
+
+            tokio::time::sleep(refresh).await;
+        }
+    }
+
+    fn gateways(&self, records: &[&SRV]) -> Result<Vec<(Url, u32)>> {
+        let Some(preferred) = records.iter().map(|srv| srv.priority).min() else {
+            return Ok(Vec::new());
+        };
+        let unweighted = records
+            .iter()
+            .filter(|srv| srv.priority == preferred)
+            .all(|srv| srv.weight == 0);
+
+        records
+            .iter()
+            .map(|srv| {
+                let host = srv.target.to_utf8();
+                let host = host.trim_end_matches('.');
+                let url = Url::parse(&format!("{}://{host}:{}", self.scheme, srv.port))?
+                    .join(&self.path)?;
+                let weight = match srv.priority == preferred {
+                    true if unweighted => 1,
+                    true => u32::from(srv.weight),
+                    false => 0,
+                };
+
+                Ok((url, weight))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hickory_resolver::config::ResolverConfig;
+    use hickory_resolver::net::runtime::TokioRuntimeProvider;
+    use hickory_resolver::proto::rr::rdata::SRV;
+    use hickory_resolver::proto::rr::Name;
+    use hickory_resolver::TokioResolver;
+    use url::Url;
+
+    use crate::srv::SrvDiscovery;
+
+    #[test]
+    fn test_gateways_from_srv_records() {
+        // Given I have a discovery for gateways below a path
+        let resolver = TokioResolver::builder_with_config(
+            ResolverConfig::default(),
+            TokioRuntimeProvider::default(),
+        )
+        .build()
+        .unwrap();
+        let discovery = SrvDiscovery::with_resolver(resolver, "_pushgateway._tcp.example.com")
+            .with_path("/pushgateway/");
+
+        // And SRV records of two preferred gateways and a standby
+        let target = |name: &str| Name::from_ascii(name).unwrap();
+        let central = SRV::new(10, 3, 9091, target("central.example.com."));
+        let small = SRV::new(10, 1, 9091, target("small.example.com."));
+        let standby = SRV::new(20, 5, 9092, target("standby.example.com."));
+
+        // When I map them to gateways
+        let gateways = discovery.gateways(&[&central, &small, &standby]).unwrap();
+
+        // Then the preferred gateways keep their weights and the standby gets none
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert_eq!(
+            gateways,
+            vec![
+                (url("http://central.example.com:9091/pushgateway/"), 3),
+                (url("http://small.example.com:9091/pushgateway/"), 1),
+                (url("http://standby.example.com:9092/pushgateway/"), 0),
+            ]
+        );
+    }
+}
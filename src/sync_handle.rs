@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use tokio::runtime::Handle;
+
+use crate::error::Result;
+use crate::non_blocking::MetricsPusher;
+use crate::non_blocking::Push;
+use crate::ConvertMetrics;
+use crate::PushReceipt;
+
+/// `SyncHandle` exposes a non-blocking [`MetricsPusher`] to synchronous code by driving its
+/// pushes on the tokio runtime of the given [`Handle`], so a mostly synchronous application
+/// with a runtime at hand does not need the blocking reqwest client as well.
+///
+/// Like [`Handle::block_on`] its methods panic if they are called from within an asynchronous
+/// execution context, call them from synchronous code or [`tokio::task::spawn_blocking`].
+#[derive(Debug)]
+pub struct SyncHandle<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    metrics_pusher: MetricsPusher<P, CM, MF, C, B>,
+    handle: Handle,
+}
+
+impl<P, CM, MF, C, B> SyncHandle<P, CM, MF, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<MF, C, B>,
+{
+    /// Creates a new [`SyncHandle`] that pushes with the given [`MetricsPusher`] on the runtime
+    /// of the given [`Handle`].
+    pub fn new(metrics_pusher: MetricsPusher<P, CM, MF, C, B>, handle: Handle) -> Self {
+        Self { metrics_pusher, handle }
+    }
+
+    /// Pushes all metrics and blocks until the push finished, see [`MetricsPusher::push_all`].
+    pub fn push_all(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        self.handle
+            .block_on(self.metrics_pusher.push_all(job, grouping, metric_families))
+    }
+
+    /// Pushes all metrics with add logic and blocks until the push finished, see
+    /// [`MetricsPusher::push_add`].
+    pub fn push_add(
+        &self,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metric_families: MF,
+    ) -> Result<PushReceipt> {
+        self.handle
+            .block_on(self.metrics_pusher.push_add(job, grouping, metric_families))
+    }
+
+    /// Deletes the group and blocks until the delete finished, see [`MetricsPusher::delete`].
+    pub fn delete(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<PushReceipt> {
+        self.handle
+            .block_on(self.metrics_pusher.delete(job, grouping))
+    }
+
+    /// Returns the wrapped [`MetricsPusher`], e.g. to push from asynchronous code as well.
+    pub fn metrics_pusher(&self) -> &MetricsPusher<P, CM, MF, C, B> {
+        &self.metrics_pusher
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::collections::HashMap;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::sync_handle::SyncHandle;
+    use crate::with_reqwest::PushClient;
+
+    #[test]
+    fn test_push_from_synchronous_code() {
+        // Given I have a tokio runtime and a push gateway
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut server = Server::new();
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/sync_job")
+            .match_body("up 1\n")
+            .create();
+
+        // And a sync handle of a non-blocking metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let sync_handle = SyncHandle::new(metrics_pusher, runtime.handle().clone());
+
+        // When I push from synchronous code
+        sync_handle
+            .push_all("sync_job", &HashMap::new(), "up 1\n")
+            .expect("Failed to push metrics");
+
+        // Then the metrics are received by the push gateway
+        pushgateway_mock.expect(1).assert();
+    }
+}
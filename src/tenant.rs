@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use url::Url;
+
+use crate::error::Result;
+use crate::utils::create_metrics_job_url;
+use crate::UrlJoin;
+
+/// `Tenant` is the routing decision of a [`TenantResolver`] for a push: the tenant it belongs
+/// to, the header that isolates it at the pushgateway or a proxy in front of it and the
+/// endpoint it is pushed to instead of the url of the pusher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tenant {
+    id: String,
+    header: Option<(String, String)>,
+    endpoint: Option<Url>,
+}
+
+impl Tenant {
+    /// Creates a new [`Tenant`] with the given identifier that is pushed like every other push
+    /// of the pusher.
+    pub fn new(id: &str) -> Self {
+        Self { id: id.to_owned(), header: None, endpoint: None }
+    }
+
+    /// Sends the given header with the pushes of the tenant, e.g. `X-Scope-OrgID: <id>`.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.header = Some((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Pushes the metrics of the tenant to the pushgateway at the given url instead. The path
+    /// of the url is joined with the api path like the url of the pusher.
+    pub fn with_endpoint(mut self, url: &Url) -> Self {
+        self.endpoint = Some(url.clone());
+        self
+    }
+
+    /// Returns the identifier of the tenant.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the header that is sent with the pushes of the tenant.
+    pub fn header(&self) -> Option<(&str, &str)> {
+        self.header
+            .as_ref()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the url of the pushgateway the tenant is pushed to if it differs from the url of
+    /// the pusher.
+    pub fn endpoint(&self) -> Option<&Url> {
+        self.endpoint.as_ref()
+    }
+}
+
+/// `TenantResolver` maps the job and grouping labels of every push and delete to the
+/// [`Tenant`] they belong to when they are sent, see `with_tenant_resolver` of
+/// [`non_blocking::MetricsPusher`](crate::non_blocking::MetricsPusher) and
+/// [`blocking::MetricsPusher`](crate::blocking::MetricsPusher). An error fails the push. It is
+/// implemented for closures.
+pub trait TenantResolver: Send + Sync {
+    fn resolve(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<Tenant>;
+}
+
+impl<F> TenantResolver for F
+where
+    F: Fn(&str, &HashMap<&str, &str>) -> Result<Tenant> + Send + Sync,
+{
+    fn resolve(&self, job: &str, grouping: &HashMap<&str, &str>) -> Result<Tenant> {
+        self(job, grouping)
+    }
+}
+
+/// `TenantRouter` routes the requests of a pusher to the [`Tenant`] of its [`TenantResolver`].
+pub(crate) struct TenantRouter {
+    resolver: Box<dyn TenantResolver>,
+}
+
+impl TenantRouter {
+    pub(crate) fn new(resolver: impl TenantResolver + 'static) -> Self {
+        Self { resolver: Box::new(resolver) }
+    }
+
+    /// Resolves the tenant of a request if there is a router and moves the url of the group,
+    /// which starts with the given metrics job url of the pusher, to the endpoint of the tenant.
+    pub(crate) fn route(
+        router: Option<&Self>,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        metrics_job_url: &Url,
+        url_join: UrlJoin,
+        url: Url,
+    ) -> Result<(Url, Option<Tenant>)> {
+        let Some(router) = router else {
+            return Ok((url, None));
+        };
+
+        let tenant = router.resolver.resolve(job, grouping)?;
+        let Some(endpoint) = tenant.endpoint() else {
+            return Ok((url, Some(tenant)));
+        };
+
+        let group = url
+            .as_str()
+            .strip_prefix(metrics_job_url.as_str())
+            .unwrap_or_default();
+        let endpoint = create_metrics_job_url(endpoint, url_join)?;
+        let url = Url::parse(&format!("{endpoint}{group}"))?;
+        Ok((url, Some(tenant)))
+    }
+}
+
+impl fmt::Debug for TenantRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TenantRouter").finish_non_exhaustive()
+    }
+}
+
+/// Appends the header of the given tenant to the headers of a request.
+pub(crate) fn with_tenant_header<'a>(
+    headers: &[(&'a str, &'a str)],
+    tenant: Option<&'a Tenant>,
+) -> Vec<(&'a str, &'a str)> {
+    headers
+        .iter()
+        .copied()
+        .chain(tenant.and_then(Tenant::header))
+        .collect()
+}
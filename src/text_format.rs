@@ -0,0 +1,792 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::error::Mismatch;
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::utils::is_label_name;
+use crate::NonFinitePolicy;
+use crate::PushTimes;
+
+const SAMPLE_SUFFIXES: [&str; 8] = [
+    "_bucket", "_sum", "_count", "_total", "_created", "_info", "_gcount", "_gsum",
+];
+
+/// `MetricFamily` is a parsed metric family of the Prometheus text exposition format.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MetricFamily {
+    pub(crate) name: String,
+    pub(crate) help: Option<String>,
+    pub(crate) kind: Option<String>,
+    pub(crate) unit: Option<String>,
+    pub(crate) samples: Vec<Sample>,
+}
+
+/// `Sample` is a single parsed sample line of the Prometheus text exposition format.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Sample {
+    pub(crate) name: String,
+    pub(crate) labels: Vec<(String, String)>,
+    pub(crate) value: f64,
+    pub(crate) timestamp: Option<i64>,
+}
+
+impl MetricFamily {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            help: None,
+            kind: None,
+            unit: None,
+            samples: Vec::new(),
+        }
+    }
+
+    fn owns_sample(&self, sample_name: &str) -> bool {
+        match sample_name.strip_prefix(self.name.as_str()) {
+            Some("") => true,
+            Some(suffix) => SAMPLE_SUFFIXES.contains(&suffix),
+            None => false,
+        }
+    }
+}
+
+/// Parses the given payload in the Prometheus text (or OpenMetrics text) exposition format
+/// into its metric families.
+pub(crate) fn parse(input: &str) -> Result<Vec<MetricFamily>> {
+    let mut families: Vec<MetricFamily> = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        parse_line(line, &mut families).map_err(|message| {
+            PushMetricsError::TextFormat(format!("line {}: {message}", index + 1))
+        })?;
+    }
+
+    Ok(families)
+}
+
+fn parse_line(line: &str, families: &mut Vec<MetricFamily>) -> std::result::Result<(), String> {
+    if let Some(comment) = line.strip_prefix('#') {
+        let mut parts = comment.trim_start().splitn(3, ' ');
+        let (keyword, name, rest) = (parts.next(), parts.next(), parts.next());
+
+        match (keyword, name) {
+            (Some("HELP"), Some(name)) => {
+                family_for_descriptor(families, name)?.help = Some(rest.unwrap_or("").to_owned())
+            }
+            (Some("TYPE"), Some(name)) => {
+                let kind = rest.map(str::trim).unwrap_or("");
+                if !is_metric_type(kind) {
+                    return Err(format!("unknown metric type '{kind}'"));
+                }
+                family_for_descriptor(families, name)?.kind = Some(kind.to_owned())
+            }
+            (Some("UNIT"), Some(name)) => {
+                family_for_descriptor(families, name)?.unit = Some(rest.unwrap_or("").to_owned())
+            }
+            _ => {}
+        }
+
+        return Ok(());
+    }
+
+    let sample = parse_sample(line)?;
+    match families.last_mut() {
+        Some(family) if family.owns_sample(&sample.name) => family.samples.push(sample),
+        _ => {
+            let mut family = MetricFamily::new(&sample.name);
+            family.samples.push(sample);
+            families.push(family);
+        }
+    }
+
+    Ok(())
+}
+
+fn family_for_descriptor<'a>(
+    families: &'a mut Vec<MetricFamily>,
+    name: &str,
+) -> std::result::Result<&'a mut MetricFamily, String> {
+    if !is_metric_name(name) {
+        return Err(format!("invalid metric name '{name}'"));
+    }
+
+    let is_current = families
+        .last()
+        .is_some_and(|family| family.name == name && family.samples.is_empty());
+
+    if !is_current {
+        if families.iter().any(|family| family.name == name) {
+            return Err(format!("duplicate metric family '{name}'"));
+        }
+        families.push(MetricFamily::new(name));
+    }
+
+    Ok(families.last_mut().expect("metric family was just pushed"))
+}
+
+fn parse_sample(line: &str) -> std::result::Result<Sample, String> {
+    let name_end = line.find(['{', ' ', '\t']).unwrap_or(line.len());
+    let name = &line[..name_end];
+    if !is_metric_name(name) {
+        return Err(format!("invalid metric name '{name}'"));
+    }
+
+    let mut rest = &line[name_end..];
+    let mut labels = Vec::new();
+    if let Some(label_set) = rest.strip_prefix('{') {
+        rest = parse_labels(label_set, &mut labels)?;
+    }
+
+    let mut fields = rest.split_whitespace();
+    let value = fields
+        .next()
+        .ok_or_else(|| format!("missing value for sample '{name}'"))
+        .and_then(parse_value)?;
+    let timestamp = fields
+        .next()
+        .map(|timestamp| {
+            timestamp
+                .parse::<i64>()
+                .map_err(|_| format!("invalid timestamp '{timestamp}'"))
+        })
+        .transpose()?;
+
+    if let Some(unexpected) = fields.next() {
+        return Err(format!("unexpected token '{unexpected}'"));
+    }
+
+    Ok(Sample { name: name.to_owned(), labels, value, timestamp })
+}
+
+fn parse_labels<'a>(
+    mut input: &'a str,
+    labels: &mut Vec<(String, String)>,
+) -> std::result::Result<&'a str, String> {
+    loop {
+        input = input.trim_start();
+        if let Some(rest) = input.strip_prefix('}') {
+            return Ok(rest);
+        }
+
+        let (name, rest) = input
+            .split_once('=')
+            .ok_or_else(|| String::from("label without value"))?;
+        let name = name.trim();
+        if !is_label_name(name) {
+            return Err(format!("invalid label name '{name}'"));
+        }
+
+        let rest = rest
+            .trim_start()
+            .strip_prefix('"')
+            .ok_or_else(|| format!("label value of '{name}' is not quoted"))?;
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, escaped)) => value.push(escaped),
+                    None => return Err(String::from("unterminated label value")),
+                },
+                Some((end, '"')) => break end,
+                Some((_, c)) => value.push(c),
+                None => return Err(String::from("unterminated label value")),
+            }
+        };
+
+        labels.push((name.to_owned(), value));
+        input = rest[end + 1..].trim_start();
+        input = input.strip_prefix(',').unwrap_or(input);
+    }
+}
+
+fn parse_value(value: &str) -> std::result::Result<f64, String> {
+    match value {
+        "+Inf" | "Inf" => Ok(f64::INFINITY),
+        "-Inf" => Ok(f64::NEG_INFINITY),
+        "NaN" => Ok(f64::NAN),
+        _ => value
+            .parse()
+            .map_err(|_| format!("invalid sample value '{value}'")),
+    }
+}
+
+fn is_metric_type(kind: &str) -> bool {
+    matches!(
+        kind,
+        "counter"
+            | "gauge"
+            | "histogram"
+            | "gaugehistogram"
+            | "summary"
+            | "info"
+            | "stateset"
+            | "unknown"
+            | "untyped"
+    )
+}
+
+pub(crate) fn is_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Encodes the given metric families in the Prometheus text exposition format.
+pub(crate) fn encode(families: &[MetricFamily]) -> String {
+    let mut output = String::new();
+
+    for family in families {
+        if let Some(help) = &family.help {
+            let _ = writeln!(output, "# HELP {} {help}", family.name);
+        }
+        if let Some(kind) = &family.kind {
+            let _ = writeln!(output, "# TYPE {} {kind}", family.name);
+        }
+        if let Some(unit) = &family.unit {
+            let _ = writeln!(output, "# UNIT {} {unit}", family.name);
+        }
+
+        for sample in &family.samples {
+            encode_sample(&mut output, sample);
+        }
+    }
+
+    output
+}
+
+fn encode_sample(output: &mut String, sample: &Sample) {
+    output.push_str(&sample.name);
+
+    if !sample.labels.is_empty() {
+        output.push('{');
+        for (index, (name, value)) in sample.labels.iter().enumerate() {
+            if index > 0 {
+                output.push(',');
+            }
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            let _ = write!(output, "{name}=\"{value}\"");
+        }
+        output.push('}');
+    }
+
+    let _ = match sample.value {
+        value if value.is_nan() => write!(output, " NaN"),
+        value if value == f64::INFINITY => write!(output, " +Inf"),
+        value if value == f64::NEG_INFINITY => write!(output, " -Inf"),
+        value => write!(output, " {value}"),
+    };
+
+    if let Some(timestamp) = sample.timestamp {
+        let _ = write!(output, " {timestamp}");
+    }
+
+    output.push('\n');
+}
+
+/// Converts metric families parsed from the OpenMetrics text format to the Prometheus text
+/// format: counters and infos are named after their samples, `_created` samples and units are
+/// dropped and types without a Prometheus equivalent are mapped to the closest one.
+pub(crate) fn to_prometheus_text(families: Vec<MetricFamily>) -> Vec<MetricFamily> {
+    families
+        .into_iter()
+        .map(|mut family| {
+            let created = format!("{}_created", family.name);
+            family.samples.retain(|sample| sample.name != created);
+            family.unit = None;
+
+            match family.kind.as_deref() {
+                Some("counter") | Some("info") => {
+                    if let Some(sample) = family.samples.first() {
+                        family.name = sample.name.clone();
+                    }
+                    if family.kind.as_deref() == Some("info") {
+                        family.kind = Some(String::from("gauge"));
+                    }
+                }
+                Some("stateset") => family.kind = Some(String::from("gauge")),
+                Some("unknown") => family.kind = Some(String::from("untyped")),
+                Some("gaugehistogram") => family.kind = None,
+                _ => {}
+            }
+
+            family
+        })
+        .collect()
+}
+
+/// Converts metric families parsed from the Prometheus text format to the OpenMetrics text
+/// format: counters are named without and their samples with the `_total` suffix, `untyped`
+/// becomes `unknown` and the payload is terminated by `# EOF`.
+pub(crate) fn to_openmetrics(families: Vec<MetricFamily>) -> String {
+    let families: Vec<MetricFamily> = families
+        .into_iter()
+        .map(|mut family| {
+            match family.kind.as_deref() {
+                Some("counter") => {
+                    if let Some(name) = family.name.strip_suffix("_total") {
+                        family.name = name.to_owned();
+                    }
+                    let total = format!("{}_total", family.name);
+                    for sample in &mut family.samples {
+                        if sample.name == family.name {
+                            sample.name = total.clone();
+                        }
+                    }
+                }
+                Some("untyped") => family.kind = Some(String::from("unknown")),
+                _ => {}
+            }
+
+            family
+        })
+        .collect();
+
+    let mut output = encode(&families);
+    output.push_str("# EOF\n");
+    output
+}
+
+/// Encodes the given metric families parsed from a payload of the given content type in the
+/// same format again.
+pub(crate) fn encode_as(families: &[MetricFamily], content_type: &str) -> String {
+    let mut output = encode(families);
+    if content_type.starts_with("application/openmetrics-text") {
+        output.push_str("# EOF\n");
+    }
+
+    output
+}
+
+/// Partitions the samples of the given families by the value of the given label and removes
+/// the label from them. Samples without the label end up in the `None` partition, families
+/// without samples in a partition are left out of it.
+pub(crate) fn split_by_label(
+    families: Vec<MetricFamily>,
+    label: &str,
+) -> BTreeMap<Option<String>, Vec<MetricFamily>> {
+    let mut partitions: BTreeMap<Option<String>, Vec<MetricFamily>> = BTreeMap::new();
+
+    for mut family in families {
+        let mut samples: BTreeMap<Option<String>, Vec<Sample>> = BTreeMap::new();
+        for mut sample in std::mem::take(&mut family.samples) {
+            let value = sample
+                .labels
+                .iter()
+                .position(|(name, _)| name == label)
+                .map(|index| sample.labels.remove(index).1);
+            samples.entry(value).or_default().push(sample);
+        }
+
+        for (value, samples) in samples {
+            let partition = MetricFamily { samples, ..family.clone() };
+            partitions.entry(value).or_default().push(partition);
+        }
+    }
+
+    partitions
+}
+
+/// Applies the given [`NonFinitePolicy`] to the samples with a `NaN` or infinite value and
+/// returns their number.
+pub(crate) fn handle_non_finite(
+    families: &mut Vec<MetricFamily>,
+    policy: NonFinitePolicy,
+) -> Result<usize> {
+    let mut affected = 0;
+
+    families.retain_mut(|family| {
+        let non_finite = family
+            .samples
+            .iter()
+            .filter(|sample| !sample.value.is_finite())
+            .count();
+        affected += non_finite;
+
+        match policy {
+            NonFinitePolicy::DropFamily => non_finite == 0,
+            NonFinitePolicy::DropSample => {
+                family.samples.retain(|sample| sample.value.is_finite());
+                true
+            }
+            NonFinitePolicy::ReplaceWithZero => {
+                for sample in &mut family.samples {
+                    if !sample.value.is_finite() {
+                        sample.value = 0.0;
+                    }
+                }
+                true
+            }
+            NonFinitePolicy::Error => true,
+        }
+    });
+
+    if policy == NonFinitePolicy::Error {
+        if let Some(sample) = families
+            .iter()
+            .flat_map(|family| &family.samples)
+            .find(|sample| !sample.value.is_finite())
+        {
+            return Err(PushMetricsError::NonFiniteValue(sample.name.clone()));
+        }
+    }
+
+    Ok(affected)
+}
+
+/// Encodes a single sample without labels as metric family of the given type, e.g. `gauge` or
+/// `counter`, in the Prometheus text exposition format.
+pub(crate) fn single_sample(name: &str, kind: &str, value: f64, help: &str) -> Result<String> {
+    if !is_metric_name(name) {
+        return Err(PushMetricsError::TextFormat(format!(
+            "invalid metric name '{name}'"
+        )));
+    }
+
+    let family = MetricFamily {
+        help: Some(help.replace('\\', "\\\\").replace('\n', "\\n")),
+        kind: Some(kind.to_owned()),
+        samples: vec![Sample { name: name.to_owned(), labels: Vec::new(), value, timestamp: None }],
+        ..MetricFamily::new(name)
+    };
+
+    Ok(encode(&[family]))
+}
+
+/// Finds the [`PushTimes`] of the group identified by the given job and grouping labels in
+/// the metrics exposed by a pushgateway. Returns `None` if the pushgateway does not know the
+/// group.
+pub(crate) fn push_times(
+    metrics: &str,
+    job: &str,
+    grouping: &HashMap<&str, &str>,
+) -> Result<Option<PushTimes>> {
+    let families = parse(metrics)?;
+    let time_of = |name: &str| {
+        families
+            .iter()
+            .filter(|family| family.name == name)
+            .flat_map(|family| family.samples.iter())
+            .find(|sample| is_group(&sample.labels, job, grouping))
+            .map(|sample| sample.value)
+    };
+
+    let (last_push, last_failure) = (
+        time_of("push_time_seconds"),
+        time_of("push_failure_time_seconds"),
+    );
+    if last_push.is_none() && last_failure.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(PushTimes {
+        last_push: last_push.and_then(system_time),
+        last_failure: last_failure.and_then(system_time),
+    }))
+}
+
+/// Verifies that the pushgateway exposes every sample of the pushed metrics with the pushed
+/// value under the group identified by the given job and grouping labels.
+pub(crate) fn verify(
+    pushed: &str,
+    exposed: &str,
+    job: &str,
+    grouping: &HashMap<&str, &str>,
+) -> Result<()> {
+    let exposed = parse(exposed)?;
+    let mut mismatches = Vec::new();
+
+    for sample in parse(pushed)?
+        .iter()
+        .flat_map(|family| family.samples.iter())
+    {
+        let mut labels: HashMap<&str, &str> = sample
+            .labels
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        labels.extend(grouping.iter().map(|(name, value)| (*name, *value)));
+
+        let found = exposed
+            .iter()
+            .flat_map(|family| family.samples.iter())
+            .find(|candidate| {
+                candidate.name == sample.name && is_group(&candidate.labels, job, &labels)
+            })
+            .map(|candidate| candidate.value);
+
+        let matches = found.is_some_and(|found| {
+            found == sample.value || (found.is_nan() && sample.value.is_nan())
+        });
+        if !matches {
+            mismatches.push(Mismatch {
+                sample: display_sample(sample),
+                expected: sample.value,
+                found,
+            });
+        }
+    }
+
+    match mismatches.is_empty() {
+        true => Ok(()),
+        false => Err(PushMetricsError::Verification(mismatches)),
+    }
+}
+
+fn display_sample(sample: &Sample) -> String {
+    let labels = sample
+        .labels
+        .iter()
+        .map(|(name, value)| format!("{name}=\"{value}\""))
+        .collect::<Vec<_>>();
+
+    match labels.is_empty() {
+        true => sample.name.clone(),
+        false => format!("{}{{{}}}", sample.name, labels.join(",")),
+    }
+}
+
+/// Checks whether the labels of a sample exposed by the pushgateway identify the group. The
+/// pushgateway adds an empty `instance` label to every group without one.
+fn is_group(labels: &[(String, String)], job: &str, grouping: &HashMap<&str, &str>) -> bool {
+    let has_instance = grouping.contains_key("instance");
+    let expected = grouping.len() + if has_instance { 1 } else { 2 };
+
+    labels.len() == expected
+        && labels.iter().all(|(name, value)| match name.as_str() {
+            "job" => value == job,
+            "instance" if !has_instance => value.is_empty(),
+            name => grouping.get(name) == Some(&value.as_str()),
+        })
+}
+
+/// Converts seconds since the unix epoch to a [`SystemTime`], where `0` means never.
+fn system_time(seconds: f64) -> Option<SystemTime> {
+    (seconds.is_finite() && seconds > 0.0).then(|| UNIX_EPOCH + Duration::from_secs_f64(seconds))
+}
+
+/// Merges the metric families of `other` into `families`. Samples of families with the
+/// same name are appended, which fails if both families declare different types.
+#[cfg(feature = "textfile")]
+pub(crate) fn merge(families: &mut Vec<MetricFamily>, other: Vec<MetricFamily>) -> Result<()> {
+    for family in other {
+        match families
+            .iter_mut()
+            .find(|existing| existing.name == family.name)
+        {
+            Some(existing) => {
+                if existing.kind.is_some() && family.kind.is_some() && existing.kind != family.kind
+                {
+                    return Err(PushMetricsError::TextFormat(format!(
+                        "metric family '{}' declared with different types",
+                        family.name
+                    )));
+                }
+
+                existing.help = existing.help.take().or(family.help);
+                existing.kind = existing.kind.take().or(family.kind);
+                existing.unit = existing.unit.take().or(family.unit);
+                existing.samples.extend(family.samples);
+            }
+            None => families.push(family),
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the metric families in `families` with the families of `other` that have the same
+/// name and appends the others, like the pushgateway does for a push with add logic.
+pub(crate) fn replace(families: &mut Vec<MetricFamily>, other: Vec<MetricFamily>) {
+    for family in other {
+        match families
+            .iter_mut()
+            .find(|existing| existing.name == family.name)
+        {
+            Some(existing) => *existing = family,
+            None => families.push(family),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_encode_round_trip() {
+        // Given a payload in the text format
+        let payload = "# HELP http_requests Number of requests.\n".to_owned()
+            + "# TYPE http_requests counter\n"
+            + "http_requests_total{method=\"GET\",path=\"/a \\\"b\\\"\"} 3\n"
+            + "http_requests_total{method=\"POST\",path=\"/\"} 1 1700000000000\n"
+            + "# TYPE temperature gauge\n"
+            + "temperature -Inf\n"
+            + "untyped_metric 0.5\n";
+
+        // When I parse it
+        let families = parse(&payload).unwrap();
+
+        // Then the families are recognized
+        assert_eq!(families.len(), 3);
+        assert_eq!(families[0].samples.len(), 2);
+        assert_eq!(families[0].samples[1].timestamp, Some(1700000000000));
+        assert_eq!(families[0].samples[0].labels[1].1, "/a \"b\"");
+        assert_eq!(families[2].kind, None);
+
+        // And encoding them yields the same payload
+        assert_eq!(encode(&families), payload);
+    }
+
+    #[test]
+    fn test_convert_between_openmetrics_and_prometheus_text() {
+        // Given a payload in the OpenMetrics text format
+        let openmetrics = "# TYPE requests counter\n".to_owned()
+            + "# UNIT requests requests\n"
+            + "requests_total 3\n"
+            + "requests_created 1700000000\n"
+            + "# TYPE build info\n"
+            + "build_info{version=\"1.0\"} 1\n"
+            + "# EOF\n";
+
+        // When I convert it to the Prometheus text format
+        let text = encode(&to_prometheus_text(parse(&openmetrics).unwrap()));
+
+        // Then counters and infos are named after their samples
+        let expected = "# TYPE requests_total counter\n".to_owned()
+            + "requests_total 3\n"
+            + "# TYPE build_info gauge\n"
+            + "build_info{version=\"1.0\"} 1\n";
+        assert_eq!(text, expected);
+
+        // And converting it back yields the OpenMetrics counter
+        let converted = to_openmetrics(parse("# TYPE requests counter\nrequests 3\n").unwrap());
+        assert_eq!(
+            converted,
+            "# TYPE requests counter\nrequests_total 3\n# EOF\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_lines() {
+        assert!(parse("1metric 1\n").is_err());
+        assert!(parse("metric{label=value} 1\n").is_err());
+        assert!(parse("metric one\n").is_err());
+        assert!(parse("# TYPE metric nonsense\n").is_err());
+    }
+
+    #[test]
+    fn test_push_times_of_group() {
+        // Given the metrics of a pushgateway with two groups of the same job
+        let metrics = "# TYPE push_time_seconds gauge\n".to_owned()
+            + "push_time_seconds{env=\"prod\",instance=\"\",job=\"backup\"} 1.7e+09\n"
+            + "push_time_seconds{env=\"test\",instance=\"\",job=\"backup\"} 1.6e+09\n"
+            + "# TYPE push_failure_time_seconds gauge\n"
+            + "push_failure_time_seconds{env=\"prod\",instance=\"\",job=\"backup\"} 0\n"
+            + "push_failure_time_seconds{env=\"test\",instance=\"\",job=\"backup\"} 1.65e+09\n";
+
+        // When I read the push times of both groups and an unknown one
+        let prod = push_times(&metrics, "backup", &HashMap::from([("env", "prod")])).unwrap();
+        let test = push_times(&metrics, "backup", &HashMap::from([("env", "test")])).unwrap();
+        let unknown = push_times(&metrics, "backup", &HashMap::new()).unwrap();
+
+        // Then the times of the matching group are returned
+        let seconds = |seconds| Some(UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!(prod.unwrap().last_push(), seconds(1_700_000_000));
+        assert_eq!(prod.unwrap().last_failure(), None);
+        assert_eq!(test.unwrap().last_failure(), seconds(1_650_000_000));
+        assert_eq!(unknown, None);
+    }
+
+    #[test]
+    fn test_verify_reports_missing_and_changed_samples() {
+        // Given I have pushed three samples
+        let pushed = "jobs_total{kind=\"a\"} 1\njobs_total{kind=\"b\"} 2\nup 1\n";
+
+        // And the pushgateway exposes one of them, one with another value and drops one
+        let exposed = "jobs_total{env=\"prod\",instance=\"\",job=\"batch\",kind=\"a\"} 1\n"
+            .to_owned()
+            + "jobs_total{env=\"prod\",instance=\"\",job=\"batch\",kind=\"b\"} 3\n"
+            + "up{env=\"test\",instance=\"\",job=\"batch\"} 1\n";
+
+        // When I verify the push
+        let result = verify(pushed, &exposed, "batch", &HashMap::from([("env", "prod")]));
+
+        // Then the changed and the missing sample are reported
+        match result {
+            Err(PushMetricsError::Verification(mismatches)) => {
+                assert_eq!(mismatches.len(), 2);
+                assert_eq!(mismatches[0].sample(), "jobs_total{kind=\"b\"}");
+                assert_eq!(mismatches[0].found(), Some(3.0));
+                assert_eq!(mismatches[1].sample(), "up");
+                assert_eq!(mismatches[1].found(), None);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[cfg(feature = "textfile")]
+    #[test]
+    fn test_merge_appends_samples_of_same_family() {
+        let mut families = parse("# TYPE jobs counter\njobs_total{job=\"a\"} 1\n").unwrap();
+        let other = parse("# TYPE jobs counter\njobs_total{job=\"b\"} 2\n").unwrap();
+
+        merge(&mut families, other).unwrap();
+
+        assert_eq!(families.len(), 1);
+        assert_eq!(families[0].samples.len(), 2);
+        assert!(merge(&mut families, parse("# TYPE jobs gauge\njobs 1\n").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_non_finite_policies() {
+        // Given a payload with a NaN gauge next to a finite one and a finite family
+        let payload = "# TYPE a gauge\na{x=\"1\"} NaN\na{x=\"2\"} 1\n# TYPE b gauge\nb 2\n";
+        let handle = |policy| {
+            let mut families = parse(payload).unwrap();
+            handle_non_finite(&mut families, policy).map(|affected| (encode(&families), affected))
+        };
+
+        // When I apply each policy
+        // Then the NaN sample, its family or its value are dropped or the payload is rejected
+        assert_eq!(
+            handle(NonFinitePolicy::DropSample).unwrap(),
+            (
+                "# TYPE a gauge\na{x=\"2\"} 1\n# TYPE b gauge\nb 2\n".to_owned(),
+                1
+            )
+        );
+        assert_eq!(
+            handle(NonFinitePolicy::DropFamily).unwrap(),
+            ("# TYPE b gauge\nb 2\n".to_owned(), 1)
+        );
+        assert_eq!(
+            handle(NonFinitePolicy::ReplaceWithZero).unwrap(),
+            (
+                "# TYPE a gauge\na{x=\"1\"} 0\na{x=\"2\"} 1\n# TYPE b gauge\nb 2\n".to_owned(),
+                1
+            )
+        );
+        assert!(matches!(
+            handle(NonFinitePolicy::Error),
+            Err(PushMetricsError::NonFiniteValue(name)) if name == "a"
+        ));
+    }
+}
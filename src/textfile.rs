@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tokio::fs;
+
+use crate::error::Result;
+use crate::non_blocking::MetricsPusher;
+use crate::non_blocking::Push;
+use crate::text_format;
+use crate::ConvertMetrics;
+
+const TEXTFILE_EXTENSION: &str = "prom";
+
+/// `TextfileMode` defines how the files of a textfile-collector directory are pushed.
+#[derive(Clone, Debug)]
+pub enum TextfileMode {
+    /// Every `*.prom` file is pushed as its own job named after the file stem, e.g.
+    /// `backup.prom` is pushed as job `backup`.
+    JobPerFile,
+    /// All `*.prom` files are merged into a single payload that is pushed as the given job.
+    Merged(String),
+}
+
+/// `TextfilePusher` pushes the `*.prom` files of a directory following the node_exporter
+/// textfile-collector convention to the pushgateway. Every file is parsed and validated
+/// before it is pushed, so a malformed file never reaches the pushgateway.
+///
+/// The wrapped [`MetricsPusher`] has to accept the metrics as [`String`] in the text format,
+/// e.g. by using a [`RawMetricsConverter::text`](crate::raw::RawMetricsConverter::text).
+#[derive(Debug)]
+pub struct TextfilePusher<P, CM, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<String, C, B>,
+{
+    metrics_pusher: MetricsPusher<P, CM, String, C, B>,
+    directory: PathBuf,
+    grouping: HashMap<String, String>,
+    mode: TextfileMode,
+}
+
+impl<P, CM, C, B> TextfilePusher<P, CM, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<String, C, B>,
+{
+    /// Creates a new [`TextfilePusher`] that pushes the files in `directory` with the given
+    /// [`MetricsPusher`] according to the [`TextfileMode`].
+    pub fn new(
+        metrics_pusher: MetricsPusher<P, CM, String, C, B>,
+        directory: impl Into<PathBuf>,
+        mode: TextfileMode,
+    ) -> Self {
+        Self {
+            metrics_pusher,
+            directory: directory.into(),
+            grouping: HashMap::new(),
+            mode,
+        }
+    }
+
+    /// Sets the grouping labels that are used for every push.
+    pub fn with_grouping(mut self, grouping: &HashMap<&str, &str>) -> Self {
+        self.grouping = grouping
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self
+    }
+
+    /// Reads all `*.prom` files of the directory once and pushes them.
+    ///
+    /// In [`TextfileMode::JobPerFile`] a file that fails to be read, validated or pushed does
+    /// not prevent the other files from being pushed; the first error is returned afterwards.
+    pub async fn push_once(&self) -> Result<()> {
+        let grouping = self.grouping();
+        let files = self.prom_files().await?;
+
+        match &self.mode {
+            TextfileMode::Merged(job) => {
+                let mut families = Vec::new();
+                for file in &files {
+                    text_format::merge(
+                        &mut families,
+                        text_format::parse(&fs::read_to_string(file).await?)?,
+                    )?;
+                }
+
+                self.metrics_pusher
+                    .push_all(job, &grouping, text_format::encode(&families))
+                    .await?;
+
+                Ok(())
+            }
+
+            TextfileMode::JobPerFile => {
+                let mut first_error = None;
+                for file in &files {
+                    if let Err(error) = self.push_file(file, &grouping).await {
+                        log::warn!("Failed to push textfile {}: {error}", file.display());
+                        first_error.get_or_insert(error);
+                    }
+                }
+
+                first_error.map_or(Ok(()), Err)
+            }
+        }
+    }
+
+    /// Pushes the directory every `interval` until the returned future is dropped. Failed
+    /// pushes are logged and retried with the next tick.
+    pub async fn run(&self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.push_once().await {
+                log::error!(
+                    "Failed to push textfiles of {}: {error}",
+                    self.directory.display()
+                );
+            }
+        }
+    }
+
+    async fn push_file(&self, file: &Path, grouping: &HashMap<&str, &str>) -> Result<()> {
+        let metrics = fs::read_to_string(file).await?;
+        text_format::parse(&metrics)?;
+
+        let job = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+
+        self.metrics_pusher
+            .push_all(&job, grouping, metrics)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn prom_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut entries = fs::read_dir(&self.directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == TEXTFILE_EXTENSION)
+            {
+                files.push(path);
+            }
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
+    fn grouping(&self) -> HashMap<&str, &str> {
+        self.grouping
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use mockito::Server;
+    use tempfile::TempDir;
+    use url::Url;
+
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::textfile::TextfileMode;
+    use crate::textfile::TextfilePusher;
+    use crate::with_reqwest::PushClient;
+
+    /// Creates a textfile directory that is removed when it is dropped.
+    fn create_textfile_directory(files: &[(&str, &str)]) -> TempDir {
+        let directory = tempfile::tempdir().unwrap();
+        for (file, content) in files {
+            fs::write(directory.path().join(file), content).unwrap();
+        }
+
+        directory
+    }
+
+    #[tokio::test]
+    async fn test_push_textfiles_job_per_file() {
+        // Given I have a textfile directory with a valid, an invalid and an ignored file
+        let directory = create_textfile_directory(&[
+            ("backup.prom", "backup_size_bytes 42\n"),
+            ("broken.prom", "1broken\n"),
+            ("notes.txt", "x"),
+        ]);
+
+        // And a push gateway
+        let mut server = Server::new_async().await;
+        let backup_mock = server
+            .mock("PUT", "/metrics/job/backup/env/test")
+            .match_body("backup_size_bytes 42\n")
+            .create();
+        let broken_mock = server.mock("PUT", "/metrics/job/broken/env/test").create();
+
+        // And a textfile pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let pusher =
+            TextfilePusher::new(metrics_pusher, directory.path(), TextfileMode::JobPerFile)
+                .with_grouping(&HashMap::from([("env", "test")]));
+
+        // When I push the directory
+        let result = pusher.push_once().await;
+
+        // Then the valid file is pushed as its own job and the invalid one is reported
+        assert!(result.is_err());
+        backup_mock.expect(1).assert();
+        broken_mock.expect(0).assert();
+    }
+
+    #[tokio::test]
+    async fn test_push_textfiles_merged() {
+        // Given I have a textfile directory with two files of the same metric family
+        let directory = create_textfile_directory(&[
+            (
+                "a.prom",
+                "# TYPE jobs_done counter\njobs_done_total{kind=\"a\"} 1\n",
+            ),
+            (
+                "b.prom",
+                "# TYPE jobs_done counter\njobs_done_total{kind=\"b\"} 2\n",
+            ),
+        ]);
+
+        // And a push gateway
+        let mut server = Server::new_async().await;
+        let expected = "# TYPE jobs_done counter\n".to_owned()
+            + "jobs_done_total{kind=\"a\"} 1\n"
+            + "jobs_done_total{kind=\"b\"} 2\n";
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/textfiles")
+            .match_body(&*expected)
+            .create();
+
+        // And a textfile pusher in merged mode
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let mode = TextfileMode::Merged(String::from("textfiles"));
+        let pusher = TextfilePusher::new(metrics_pusher, directory.path(), mode);
+
+        // When I push the directory
+        pusher.push_once().await.expect("Failed to push textfiles");
+
+        // Then a single merged payload is received by the push gateway
+        pushgateway_mock.expect(1).assert();
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use ring::digest;
+use rustls::client::danger::HandshakeSignatureValid;
+use rustls::client::danger::ServerCertVerified;
+use rustls::client::danger::ServerCertVerifier;
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use rustls::pki_types::ServerName;
+use rustls::pki_types::UnixTime;
+use rustls::ClientConfig;
+use rustls::DigitallySignedStruct;
+use rustls::RootCertStore;
+use rustls::SignatureScheme;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+
+/// `TlsConfig` holds the TLS options of the `PushClient` builders: additional root
+/// certificates, e.g. of a private CA, and SHA-256 hashes the server certificate is pinned to.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TlsConfig {
+    root_certificates: Vec<CertificateDer<'static>>,
+    pinned_hashes: Vec<[u8; 32]>,
+}
+
+impl TlsConfig {
+    pub(crate) fn add_root_certificates_pem(&mut self, pem: &[u8]) -> Result<()> {
+        let certificates = CertificateDer::pem_slice_iter(pem)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|error| PushMetricsError::Tls(error.to_string()))?;
+
+        if certificates.is_empty() {
+            return Err(PushMetricsError::Tls(String::from(
+                "no certificate found in PEM",
+            )));
+        }
+
+        self.root_certificates.extend(certificates);
+        Ok(())
+    }
+
+    pub(crate) fn pin_certificate_sha256(&mut self, hash: [u8; 32]) {
+        self.pinned_hashes.push(hash);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.root_certificates.is_empty() && self.pinned_hashes.is_empty()
+    }
+
+    /// Creates a rustls [`ClientConfig`] that trusts the webpki roots and the additional root
+    /// certificates and, if hashes are pinned, only accepts a server certificate whose SHA-256
+    /// hash matches one of them.
+    pub(crate) fn client_config(&self) -> Result<ClientConfig> {
+        let provider = Arc::new(default_provider());
+
+        let mut roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+        for certificate in &self.root_certificates {
+            roots.add(certificate.clone()).map_err(tls_error)?;
+        }
+
+        let verifier =
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .map_err(tls_error)?;
+
+        let verifier: Arc<dyn ServerCertVerifier> = match self.pinned_hashes.is_empty() {
+            true => verifier,
+            false => {
+                Arc::new(PinningVerifier { verifier, pinned_hashes: self.pinned_hashes.clone() })
+            }
+        };
+
+        Ok(ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .dangerous()
+            .with_custom_certificate_verifier(verifier)
+            .with_no_client_auth())
+    }
+}
+
+fn tls_error(error: impl std::fmt::Display) -> PushMetricsError {
+    PushMetricsError::Tls(error.to_string())
+}
+
+/// `PinningVerifier` verifies the server certificate as usual and additionally requires its
+/// SHA-256 hash to match one of the pinned hashes.
+#[derive(Debug)]
+struct PinningVerifier {
+    verifier: Arc<WebPkiServerVerifier>,
+    pinned_hashes: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        self.verifier.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        let hash = digest::digest(&digest::SHA256, end_entity);
+        match self
+            .pinned_hashes
+            .iter()
+            .any(|pinned| pinned == hash.as_ref())
+        {
+            true => Ok(ServerCertVerified::assertion()),
+            false => Err(rustls::Error::General(String::from(
+                "server certificate does not match any pinned hash",
+            ))),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.verifier.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.verifier.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.verifier.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::PushMetricsError;
+    use crate::tls::TlsConfig;
+
+    const CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBljCCATugAwIBAgIUeWccYQNk0IsBeQLmKG6bsH8qIX8wCgYIKoZIzj0EAwIw
+HzEdMBsGA1UEAwwUcHVzaGdhdGV3YXkuaW50ZXJuYWwwIBcNMjYxMDE3MDQwOTIz
+WhgPMjEyNjA5MjMwNDA5MjNaMB8xHTAbBgNVBAMMFHB1c2hnYXRld2F5LmludGVy
+bmFsMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEDLUYn2EbIK2Q92UR1Re59Ao9
+Gt9aXFB0jysSm8AFnqD6NMeuJ93XrB8olp4+5LGAgvu3CsKxLK/jcFz6BmXLoKNT
+MFEwHQYDVR0OBBYEFDtuxndqqGV9P22b9h8Sq8St9ApPMB8GA1UdIwQYMBaAFDtu
+xndqqGV9P22b9h8Sq8St9ApPMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwID
+SQAwRgIhAIvu/sBxA6YchmJWy6rsfBaClfw0Y5qZv8zUzK6KRcHFAiEAzRK6Jdn5
+Wqi6/tZ9jK5uiaeJEwiKaBVoRDUKGtxKcU8=
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_client_config_with_private_root_and_pin() {
+        // Given I have a TLS config with the certificate of a private CA and a pinned hash
+        let mut tls_config = TlsConfig::default();
+        tls_config
+            .add_root_certificates_pem(CERTIFICATE.as_bytes())
+            .unwrap();
+        tls_config.pin_certificate_sha256([0; 32]);
+
+        // When I create the rustls client config
+        let client_config = tls_config.client_config();
+
+        // Then it is created successfully
+        assert!(client_config.is_ok());
+    }
+
+    #[test]
+    fn test_pem_without_certificate_is_rejected() {
+        // Given I have a TLS config
+        let mut tls_config = TlsConfig::default();
+
+        // When I add a PEM that contains no certificate
+        let result = tls_config.add_root_certificates_pem(b"not a certificate");
+
+        // Then an error is returned and nothing is added
+        assert!(matches!(result, Err(PushMetricsError::Tls(_))));
+        assert!(tls_config.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use std::task::Context;
+use std::task::Poll;
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::non_blocking::Push;
+use crate::periodic::PeriodicPusher;
+use crate::ConvertMetrics;
+
+/// `PushLayer` wires a [`PeriodicPusher`] into a tower based server like axum or hyper: the
+/// metrics are pushed periodically while the server is serving and a final time as soon as the
+/// layer and all services it wrapped are dropped, i.e. when the server has shut down.
+///
+/// The final push happens in the background, so await the [`JoinHandle`] returned by
+/// [`PushLayer::new`] after the server stopped to make sure it is not cut off by the process
+/// exiting.
+#[derive(Clone, Debug)]
+pub struct PushLayer {
+    serving: mpsc::Sender<()>,
+}
+
+impl PushLayer {
+    /// Creates a new [`PushLayer`] and spawns the given [`PeriodicPusher`] on the current tokio
+    /// runtime. The returned [`JoinHandle`] completes after the final push.
+    pub fn new<P, CM, MF, C, B, G>(
+        periodic_pusher: PeriodicPusher<P, CM, MF, C, B, G>,
+    ) -> (Self, JoinHandle<()>)
+    where
+        P: Push<B> + Send + Sync + 'static,
+        CM: ConvertMetrics<MF, C, B> + Send + Sync + 'static,
+        MF: Send + Sync + 'static,
+        C: Send + Sync + 'static,
+        B: Send + Sync + 'static,
+        G: Fn() -> MF + Send + Sync + 'static,
+    {
+        let (serving, mut stopped) = mpsc::channel(1);
+        let handle = tokio::spawn(async move {
+            // `recv` only returns once every sender, i.e. the layer and all services, is dropped
+            let shutdown = async move { while stopped.recv().await.is_some() {} };
+            periodic_pusher.run_until(shutdown).await
+        });
+
+        (Self { serving }, handle)
+    }
+}
+
+impl<S> Layer<S> for PushLayer {
+    type Service = PushService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PushService { inner, _serving: self.serving.clone() }
+    }
+}
+
+/// `PushService` is the [`Service`] created by the [`PushLayer`]. It passes all requests to the
+/// wrapped service and keeps the periodic pushes running as long as it is alive.
+#[derive(Clone, Debug)]
+pub struct PushService<S> {
+    inner: S,
+    _serving: mpsc::Sender<()>,
+}
+
+impl<S, Request> Service<Request> for PushService<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use std::convert::Infallible;
+    use std::future::Ready;
+    use std::task::Context;
+    use std::task::Poll;
+    use std::time::Duration;
+
+    use mockito::Server;
+    use tower_layer::Layer;
+    use tower_service::Service;
+    use url::Url;
+
+    use crate::non_blocking::MetricsPusher;
+    use crate::periodic::PeriodicPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::tower::PushLayer;
+    use crate::with_reqwest::PushClient;
+
+    struct Echo;
+
+    impl Service<&'static str> for Echo {
+        type Response = &'static str;
+        type Error = Infallible;
+        type Future = Ready<Result<&'static str, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: &'static str) -> Self::Future {
+            std::future::ready(Ok(request))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_layer_pushes_on_shutdown() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/server")
+            .match_body("up 1\n")
+            .expect(2)
+            .create();
+
+        // And a service wrapped by a push layer with an interval longer than the test
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let periodic_pusher = PeriodicPusher::new(
+            metrics_pusher,
+            "server",
+            || String::from("up 1\n"),
+            Duration::from_secs(3600),
+        );
+        let (layer, handle) = PushLayer::new(periodic_pusher);
+        let mut service = layer.layer(Echo);
+
+        // When the service has served a request and the server shuts down
+        let response = service.call("ping").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(service);
+        drop(layer);
+        handle.await.unwrap();
+
+        // Then the metrics are pushed on the first tick and on shutdown
+        assert_eq!(response, "ping");
+        pushgateway_mock.assert();
+    }
+}
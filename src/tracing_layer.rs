@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tracing_core::span;
+use tracing_core::Event;
+use tracing_core::Level;
+use tracing_core::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+use crate::error::Result;
+use crate::non_blocking::MetricsPusher;
+use crate::non_blocking::Push;
+use crate::text_format;
+use crate::text_format::MetricFamily;
+use crate::text_format::Sample;
+use crate::ConvertMetrics;
+use crate::PushReceipt;
+
+const EVENTS: &str = "tracing_events_total";
+const SPANS: &str = "tracing_spans_total";
+
+/// The counts of events or spans by their family, level and target.
+type Counts = BTreeMap<(&'static str, Level, &'static str), u64>;
+
+/// `EventCountLayer` is a `tracing` [`Layer`] that counts the events and the created spans by
+/// level and target, see [`TracingPusher::layer`].
+#[derive(Clone, Debug, Default)]
+pub struct EventCountLayer {
+    counts: Arc<Mutex<Counts>>,
+}
+
+impl EventCountLayer {
+    fn count(&self, family: &'static str, metadata: &'static tracing_core::Metadata<'static>) {
+        let mut counts = self
+            .counts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *counts
+            .entry((family, *metadata.level(), metadata.target()))
+            .or_default() += 1;
+    }
+
+    /// Encodes the counts in the Prometheus text format.
+    fn encode(&self) -> String {
+        let counts = self
+            .counts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let families = [
+            (EVENTS, "Number of tracing events by level and target."),
+            (
+                SPANS,
+                "Number of created tracing spans by level and target.",
+            ),
+        ]
+        .into_iter()
+        .map(|(name, help)| MetricFamily {
+            name: name.to_owned(),
+            help: Some(help.to_owned()),
+            kind: Some(String::from("counter")),
+            unit: None,
+            samples: counts
+                .iter()
+                .filter(|((family, ..), _)| *family == name)
+                .map(|((_, level, target), count)| Sample {
+                    name: name.to_owned(),
+                    labels: vec![
+                        (String::from("level"), level.as_str().to_lowercase()),
+                        (String::from("target"), target.to_string()),
+                    ],
+                    value: *count as f64,
+                    timestamp: None,
+                })
+                .collect(),
+        })
+        .filter(|family| !family.samples.is_empty())
+        .collect::<Vec<_>>();
+
+        text_format::encode(&families)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for EventCountLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        self.count(EVENTS, event.metadata());
+    }
+
+    fn on_new_span(&self, attributes: &span::Attributes<'_>, _: &span::Id, _: Context<'_, S>) {
+        self.count(SPANS, attributes.metadata());
+    }
+}
+
+/// `TracingPusher` pushes the number of `tracing` events and spans by level and target with
+/// the given [`MetricsPusher`], e.g. to get error rates of an application that is only
+/// instrumented with `tracing`. Register its [`TracingPusher::layer`] with your subscriber and
+/// push with [`TracingPusher::run`].
+///
+/// The counts are pushed as the counters `tracing_events_total` and `tracing_spans_total` with
+/// the labels `level` and `target`. The pusher has to take the metrics in the text format,
+/// e.g. with the `RawMetricsConverter`.
+#[derive(Debug)]
+pub struct TracingPusher<P, CM, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<String, C, B>,
+{
+    metrics_pusher: MetricsPusher<P, CM, String, C, B>,
+    job: String,
+    grouping: HashMap<String, String>,
+    layer: EventCountLayer,
+}
+
+impl<P, CM, C, B> TracingPusher<P, CM, C, B>
+where
+    P: Push<B>,
+    CM: ConvertMetrics<String, C, B>,
+{
+    /// Creates a new [`TracingPusher`] that pushes the counts for the given job.
+    pub fn new(metrics_pusher: MetricsPusher<P, CM, String, C, B>, job: &str) -> Self {
+        Self {
+            metrics_pusher,
+            job: job.to_owned(),
+            grouping: HashMap::new(),
+            layer: EventCountLayer::default(),
+        }
+    }
+
+    /// Pushes the counts with the given grouping labels.
+    pub fn with_grouping(mut self, grouping: &HashMap<&str, &str>) -> Self {
+        self.grouping = grouping
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self
+    }
+
+    /// Returns the [`Layer`] that counts the events and spans for this pusher.
+    pub fn layer(&self) -> EventCountLayer {
+        self.layer.clone()
+    }
+
+    /// Pushes the current counts, replacing the ones pushed before.
+    pub async fn push(&self) -> Result<PushReceipt> {
+        let grouping = self
+            .grouping
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+
+        self.metrics_pusher
+            .push_all(&self.job, &grouping, self.layer.encode())
+            .await
+    }
+
+    /// Pushes every interval until the returned future is dropped. Failed pushes are logged.
+    pub async fn run(&self, interval: Duration) {
+        self.run_until(interval, std::future::pending()).await
+    }
+
+    /// Pushes every interval until `shutdown` completes and once more afterwards, so the final
+    /// counts are not lost. Failed pushes are logged.
+    pub async fn run_until(&self, interval: Duration, shutdown: impl Future<Output = ()>) {
+        let mut interval = tokio::time::interval(interval);
+        tokio::pin!(shutdown);
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = interval.tick() => self.push_logged().await,
+            }
+        }
+
+        self.push_logged().await;
+    }
+
+    async fn push_logged(&self) {
+        if let Err(error) = self.push().await {
+            log::error!("Failed to push tracing event counts: {error}");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "with_reqwest"))]
+mod test {
+    use mockito::Matcher;
+    use mockito::Server;
+    use tracing_subscriber::layer::SubscriberExt;
+    use url::Url;
+
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::tracing_layer::TracingPusher;
+    use crate::with_reqwest::PushClient;
+
+    #[tokio::test]
+    async fn test_events_and_spans_are_counted_and_pushed() {
+        // Given I have a push gateway that expects the counts
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/tracing_job")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(r#"tracing_events_total\{level="error",target="db"\} 2"#.into()),
+                Matcher::Regex(r#"tracing_events_total\{level="info",target="db"\} 1"#.into()),
+                Matcher::Regex(r#"tracing_spans_total\{level="info",target="db"\} 1"#.into()),
+            ]))
+            .create();
+
+        // And a tracing pusher whose layer is registered with a subscriber
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let tracing_pusher = TracingPusher::new(metrics_pusher, "tracing_job");
+        let subscriber = tracing_subscriber::registry().with(tracing_pusher.layer());
+
+        // When events are emitted within a span and the counts are pushed
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!(target: "db", "query").entered();
+            tracing::info!(target: "db", "connected");
+            tracing::error!(target: "db", "failed");
+            tracing::error!(target: "db", "failed again");
+        });
+        tracing_pusher.push().await.expect("Failed to push counts");
+
+        // Then the counts by level and target are received by the push gateway
+        pushgateway_mock.expect(1).assert();
+    }
+}
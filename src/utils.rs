@@ -1,38 +1,826 @@
-#[cfg(any(feature = "prometheus_crate", feature = "prometheus_client_crate"))]
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use std::borrow::Cow;
+#[cfg(any(
+    feature = "blocking",
+    feature = "non_blocking",
+    feature = "prometheus_crate_text",
+    feature = "prometheus_client_crate"
+))]
 use std::collections::HashMap;
 
-#[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
-use reqwest::StatusCode;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use std::sync::Arc;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use std::sync::Mutex;
+#[cfg(any(
+    feature = "blocking",
+    feature = "non_blocking",
+    feature = "with_reqwest",
+    feature = "with_reqwest_blocking"
+))]
+use std::time::Duration;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
 use url::Url;
 
 #[cfg(any(
-    feature = "with_reqwest",
-    feature = "with_reqwest_blocking",
-    feature = "prometheus_crate",
+    feature = "blocking",
+    feature = "non_blocking",
+    feature = "prometheus_crate_text",
     feature = "prometheus_client_crate"
 ))]
 use crate::error::PushMetricsError;
 use crate::error::Result;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::text_format;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::text_format::MetricFamily;
+#[cfg(all(
+    feature = "body_digest",
+    any(feature = "blocking", feature = "non_blocking")
+))]
+use crate::BodyDigest;
+#[cfg(all(
+    any(feature = "compress_gzip", feature = "compress_zstd"),
+    any(feature = "blocking", feature = "non_blocking")
+))]
+use crate::Compression;
+#[cfg(all(
+    any(feature = "compress_gzip", feature = "compress_zstd"),
+    any(feature = "blocking", feature = "non_blocking")
+))]
+use crate::ContentEncoding;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::ConvertMetrics;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::Method;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::NonFinitePolicy;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::PushEvent;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::PushReceipt;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::UrlJoin;
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+use crate::ValidationMode;
+
+static REQUEST_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Generates a request id from the current time, the process id and a counter, which is
+/// unique enough to correlate a push with the logs of the pushgateway.
+pub(crate) fn generate_request_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+    let counter = REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    format!("{nanos:016x}{:08x}{counter:08x}", std::process::id())
+}
+
+/// Turns the result of a `Push` call into a [`PushReceipt`] and
+/// logs its outcome together with the request id if there is one.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) fn into_receipt(
+    result: Result<()>,
+    url: Url,
+    request_id: Option<(&str, String)>,
+) -> Result<PushReceipt> {
+    let request_id = request_id.map(|(_, id)| id);
+
+    #[cfg(feature = "log")]
+    if let Some(request_id) = &request_id {
+        match &result {
+            Ok(()) => log::debug!("Push to {url} with request id {request_id} succeeded"),
+            Err(error) => log::warn!("Push to {url} with request id {request_id} failed: {error}"),
+        }
+    }
+
+    result.map(|()| PushReceipt::new(url, request_id))
+}
+
+/// Notifies the observer of a pusher with a [`PushEvent`] for every request it sent.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) struct PushObserver<B> {
+    observe: Arc<dyn Fn(&PushEvent) + Send + Sync>,
+    measure: fn(&B) -> usize,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> PushObserver<B> {
+    pub(crate) fn new(observe: impl Fn(&PushEvent) + Send + Sync + 'static) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        Self { observe: Arc::new(observe), measure: |body| body.as_ref().len() }
+    }
+
+    /// Starts the observation of a request, which does nothing without an observer.
+    pub(crate) fn start<'a>(
+        observer: Option<&'a Self>,
+        method: Method,
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: &Url,
+    ) -> Observation<'a, B> {
+        let started = observer.map(|observer| {
+            let event = PushEvent {
+                job: job.to_owned(),
+                grouping: grouping
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                endpoint: url.to_string(),
+                method,
+                success: false,
+                status: None,
+                error: None,
+                duration: Default::default(),
+                bytes: None,
+                retries: 0,
+            };
+            (observer, event, Instant::now())
+        });
+
+        Observation { started }
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> std::fmt::Debug for PushObserver<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PushObserver").finish_non_exhaustive()
+    }
+}
+
+/// The [`PushEvent`] of a request that is in flight, see [`PushObserver::start`].
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) struct Observation<'a, B> {
+    started: Option<(&'a PushObserver<B>, PushEvent, Instant)>,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> Observation<'_, B> {
+    /// Records the size of the body that is sent, replacing the size of a previous attempt.
+    pub(crate) fn measure(&mut self, body: &B) {
+        if let Some((observer, event, _)) = &mut self.started {
+            event.bytes = Some((observer.measure)(body));
+        }
+    }
+
+    /// Records that the request is sent again.
+    pub(crate) fn retry(&mut self) {
+        if let Some((_, event, _)) = &mut self.started {
+            event.retries += 1;
+        }
+    }
+
+    /// Completes the event with the result of the request and passes it to the observer.
+    pub(crate) fn finish(self, result: &Result<()>) {
+        let Some((observer, mut event, started)) = self.started else {
+            return;
+        };
+
+        event.duration = started.elapsed();
+        event.success = result.is_ok();
+        if let Err(error) = result {
+            event.status = status(error);
+            event.error = Some(error.to_string());
+        }
+
+        (observer.observe)(&event)
+    }
+}
+
+/// Returns the status code of a rejected request if the client reports it.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+fn status(error: &PushMetricsError) -> Option<u16> {
+    #[cfg(feature = "http")]
+    return error.status().map(|status| status.as_u16());
+    #[cfg(not(feature = "http"))]
+    {
+        let _ = error;
+        None
+    }
+}
+
+/// Serializes a [`Duration`] as fractional milliseconds, which log pipelines handle better
+/// than seconds and nanoseconds apart.
+#[cfg(all(
+    feature = "json_events",
+    any(feature = "blocking", feature = "non_blocking")
+))]
+pub(crate) fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+/// Measures the encoded payloads of a pusher and warns about payloads that exceed a threshold,
+/// which usually hints at a cardinality explosion.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Debug)]
+pub(crate) struct PayloadSizeWarning<B> {
+    threshold: usize,
+    measure: fn(&B) -> usize,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> PayloadSizeWarning<B> {
+    pub(crate) fn new(threshold: usize) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        Self { threshold, measure: |payload| payload.as_ref().len() }
+    }
+
+    /// Returns the size of the payload in bytes and logs a warning if it exceeds the threshold.
+    pub(crate) fn check(&self, payload: &B, url: &Url) -> usize {
+        let size = (self.measure)(payload);
+
+        if size > self.threshold {
+            #[cfg(feature = "log")]
+            log::warn!(
+                "Payload of {size} bytes pushed to {url} exceeds the threshold of {} bytes",
+                self.threshold
+            );
+            #[cfg(not(feature = "log"))]
+            let _ = url;
+        }
+
+        size
+    }
+}
+
+/// `PayloadSizeLimit` rejects encoded payloads above a maximum size in bytes before they are
+/// sent.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Debug)]
+pub(crate) struct PayloadSizeLimit<B> {
+    limit: usize,
+    measure: fn(&B) -> usize,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> PayloadSizeLimit<B> {
+    pub(crate) fn new(limit: usize) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        Self { limit, measure: |payload| payload.as_ref().len() }
+    }
+
+    /// Fails with [`PushMetricsError::PayloadTooLarge`] if there is a limit and the payload
+    /// exceeds it.
+    pub(crate) fn check(limit: Option<&Self>, payload: &B) -> Result<()> {
+        let Some(limit) = limit else {
+            return Ok(());
+        };
+
+        match (limit.measure)(payload) {
+            size if size > limit.limit => {
+                Err(PushMetricsError::PayloadTooLarge { size, limit: limit.limit })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `NonFiniteFilter` applies a [`NonFinitePolicy`] to the encoded payloads of a pusher, which
+/// have to be in the text format.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Debug)]
+pub(crate) struct NonFiniteFilter<B> {
+    policy: NonFinitePolicy,
+    handle: fn(NonFinitePolicy, B, &str) -> Result<(B, usize)>,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> NonFiniteFilter<B> {
+    pub(crate) fn new(policy: NonFinitePolicy) -> Self
+    where
+        B: AsRef<[u8]> + From<String>,
+    {
+        let handle = |policy, body: B, content_type: &str| {
+            let mut families = text_format::parse(&text_payload(body.as_ref(), content_type)?)?;
+            match text_format::handle_non_finite(&mut families, policy)? {
+                0 => Ok((body, 0)),
+                affected => Ok((
+                    B::from(text_format::encode_as(&families, content_type)),
+                    affected,
+                )),
+            }
+        };
+
+        Self { policy, handle }
+    }
+
+    /// Applies the policy to the given payload and returns it with the number of affected
+    /// samples, which does nothing without a filter.
+    pub(crate) fn apply(filter: Option<&Self>, body: B, content_type: &str) -> Result<(B, usize)> {
+        match filter {
+            Some(filter) => (filter.handle)(filter.policy, body, content_type),
+            None => Ok((body, 0)),
+        }
+    }
+}
+
+/// `BodyDigester` computes the [`BodyDigest`] header of the encoded payloads of a pusher.
+#[cfg(all(
+    feature = "body_digest",
+    any(feature = "blocking", feature = "non_blocking")
+))]
+#[derive(Debug)]
+pub(crate) struct BodyDigester<B> {
+    digest: BodyDigest,
+    bytes: fn(&B) -> &[u8],
+}
+
+#[cfg(all(
+    feature = "body_digest",
+    any(feature = "blocking", feature = "non_blocking")
+))]
+impl<B> BodyDigester<B> {
+    pub(crate) fn new(digest: BodyDigest) -> Self
+    where
+        B: AsRef<[u8]>,
+    {
+        Self { digest, bytes: as_bytes::<B> }
+    }
+
+    /// Returns the digest header of the given body.
+    pub(crate) fn header(&self, body: &B) -> (&'static str, String) {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::Engine;
+        use md5::Digest;
+
+        let bytes = (self.bytes)(body);
+        match self.digest {
+            BodyDigest::ContentMd5 => ("Content-MD5", STANDARD.encode(md5::Md5::digest(bytes))),
+            BodyDigest::Sha256 => (
+                "Digest",
+                format!("sha-256={}", STANDARD.encode(sha2::Sha256::digest(bytes))),
+            ),
+        }
+    }
+}
+
+/// `Compressor` compresses the encoded payloads of a pusher according to its [`Compression`].
+#[cfg(all(
+    any(feature = "compress_gzip", feature = "compress_zstd"),
+    any(feature = "blocking", feature = "non_blocking")
+))]
+#[derive(Debug)]
+pub(crate) struct Compressor<B> {
+    compression: Compression,
+    bytes: fn(&B) -> &[u8],
+    body: fn(Vec<u8>) -> B,
+}
+
+#[cfg(all(
+    any(feature = "compress_gzip", feature = "compress_zstd"),
+    any(feature = "blocking", feature = "non_blocking")
+))]
+impl<B> Compressor<B> {
+    pub(crate) fn new(compression: Compression) -> Self
+    where
+        B: AsRef<[u8]> + From<Vec<u8>>,
+    {
+        Self { compression, bytes: as_bytes::<B>, body: B::from }
+    }
+
+    /// Compresses the body if there is a compressor and the body exceeds one of its thresholds
+    /// and returns it with the [`ContentEncoding`] it is compressed with.
+    pub(crate) fn compress(compressor: Option<&Self>, body: B) -> Result<(B, ContentEncoding)> {
+        let Some(compressor) = compressor else {
+            return Ok((body, ContentEncoding::Identity));
+        };
+
+        let bytes = (compressor.bytes)(&body);
+        let encoding = compressor.compression.select(bytes.len());
+        let compressed = match encoding {
+            #[cfg(feature = "compress_gzip")]
+            ContentEncoding::Gzip => {
+                use std::io::Write;
+
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()?
+            }
+            #[cfg(feature = "compress_zstd")]
+            ContentEncoding::Zstd => zstd::encode_all(bytes, 0)?,
+            _ => return Ok((body, ContentEncoding::Identity)),
+        };
+
+        if compressed.len() < bytes.len() {
+            Ok(((compressor.body)(compressed), encoding))
+        } else {
+            Ok((body, ContentEncoding::Identity))
+        }
+    }
+}
+
+/// Appends the given header to the headers of a request.
+#[cfg(all(
+    any(
+        feature = "body_digest",
+        feature = "compress_gzip",
+        feature = "compress_zstd"
+    ),
+    any(feature = "blocking", feature = "non_blocking")
+))]
+pub(crate) fn with_header<'a>(
+    headers: &[(&'a str, &'a str)],
+    header: Option<&'a (&'static str, String)>,
+) -> Vec<(&'a str, &'a str)> {
+    headers
+        .iter()
+        .copied()
+        .chain(header.map(|(name, value)| (*name, value.as_str())))
+        .collect()
+}
+
+#[cfg(all(
+    any(
+        feature = "body_digest",
+        feature = "compress_gzip",
+        feature = "compress_zstd"
+    ),
+    any(feature = "blocking", feature = "non_blocking")
+))]
+fn as_bytes<B: AsRef<[u8]>>(body: &B) -> &[u8] {
+    body.as_ref()
+}
+
+/// `Format` is the format a pushgateway accepted, see [`FormatCache`].
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// The format the converter encodes to.
+    Native,
+    /// The fallback format of the converter, see
+    /// [`ConvertMetrics::fallback_push_details`](crate::ConvertMetrics::fallback_push_details).
+    Fallback,
+}
+
+/// `FormatCache` remembers per pushgateway which format it accepted, so a pushgateway that
+/// rejected the native format is not asked again on every push.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Debug, Default)]
+pub(crate) struct FormatCache(Mutex<HashMap<Url, Format>>);
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl FormatCache {
+    pub(crate) fn get(&self, gateway: &Url) -> Option<Format> {
+        self.formats().get(gateway).copied()
+    }
+
+    pub(crate) fn set(&self, gateway: &Url, format: Format) {
+        self.formats().insert(gateway.clone(), format);
+    }
+
+    fn formats(&self) -> std::sync::MutexGuard<'_, HashMap<Url, Format>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// `PushedFamilies` remembers per group the metric families of its last push of only the
+/// changed families, so unchanged families are not pushed again.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Debug, Default)]
+pub(crate) struct PushedFamilies(Mutex<HashMap<Url, Vec<MetricFamily>>>);
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl PushedFamilies {
+    /// Returns the given families that differ from the ones last pushed to the group.
+    pub(crate) fn changed(&self, group: &Url, families: &[MetricFamily]) -> Vec<MetricFamily> {
+        let pushed = self.families();
+        let pushed = pushed.get(group).map(Vec::as_slice).unwrap_or_default();
+
+        families
+            .iter()
+            .filter(|family| !pushed.contains(family))
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn remember(&self, group: &Url, families: Vec<MetricFamily>) {
+        self.families().insert(group.clone(), families);
+    }
+
+    /// Forgets the families of the group, e.g. after it was deleted, so they are all pushed
+    /// with the next push.
+    pub(crate) fn forget(&self, group: &Url) {
+        self.families().remove(group);
+    }
+
+    fn families(&self) -> std::sync::MutexGuard<'_, HashMap<Url, Vec<MetricFamily>>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// `Throttle` limits the pushes of a pusher to one per interval and group. Payloads that come
+/// in too early are deferred, where the newest payload of a group replaces an older one.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) struct Throttle<B> {
+    interval: Duration,
+    groups: Mutex<HashMap<Url, ThrottledGroup<B>>>,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+struct ThrottledGroup<B> {
+    last_push: Instant,
+    deferred: Option<Deferred<B>>,
+}
+
+/// `Deferred` is a payload that is pushed later, either as it is admitted or by a flush.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) struct Deferred<B> {
+    pub(crate) job: String,
+    pub(crate) grouping: HashMap<String, String>,
+    pub(crate) url: Url,
+    pub(crate) body: B,
+    pub(crate) content_type: String,
+    pub(crate) push_type: PushType,
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> Deferred<B> {
+    pub(crate) fn new(
+        job: &str,
+        grouping: &HashMap<&str, &str>,
+        url: Url,
+        body: B,
+        content_type: &str,
+        push_type: PushType,
+    ) -> Self {
+        Self {
+            job: job.to_owned(),
+            grouping: grouping
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            url,
+            body,
+            content_type: content_type.to_owned(),
+            push_type,
+        }
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> Throttle<B> {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self { interval, groups: Mutex::new(HashMap::new()) }
+    }
+
+    /// Returns the payload if its group was not pushed within the interval, otherwise defers
+    /// it in place of an older deferred payload of the group.
+    pub(crate) fn admit(&self, payload: Deferred<B>) -> Option<Deferred<B>> {
+        let mut groups = self.groups();
+        let now = Instant::now();
+
+        match groups.get_mut(&payload.url) {
+            Some(group) if now.duration_since(group.last_push) < self.interval => {
+                group.deferred = Some(payload);
+                None
+            }
+            _ => {
+                let group = ThrottledGroup { last_push: now, deferred: None };
+                groups.insert(payload.url.clone(), group);
+                Some(payload)
+            }
+        }
+    }
+
+    /// Takes the deferred payloads of all groups to push them right away.
+    pub(crate) fn take_deferred(&self) -> Vec<Deferred<B>> {
+        let now = Instant::now();
+
+        self.groups()
+            .values_mut()
+            .filter_map(|group| {
+                let deferred = group.deferred.take()?;
+                group.last_push = now;
+                Some(deferred)
+            })
+            .collect()
+    }
+
+    /// Drops the deferred payload of the group, e.g. after it was deleted, so a flush does not
+    /// push it again.
+    pub(crate) fn forget(&self, group: &Url) {
+        if let Some(group) = self.groups().get_mut(group) {
+            group.deferred = None;
+        }
+    }
+
+    fn groups(&self) -> std::sync::MutexGuard<'_, HashMap<Url, ThrottledGroup<B>>> {
+        self.groups
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl<B> std::fmt::Debug for Throttle<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Throttle")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
 
+/// `Negotiated` is the payload to push in the format the pushgateway is known to accept and,
+/// as long as its format is not known, the fallback payload to retry with.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) struct Negotiated<'a, B> {
+    pub(crate) body: B,
+    pub(crate) content_type: Cow<'a, str>,
+    pub(crate) fallback: Option<(B, String)>,
+}
+
+/// Prepares the given payload for a pushgateway that accepted the given format before. A
+/// payload for a pushgateway of unknown format carries its fallback if the converter has one.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) fn negotiate<'a, CM, MF, C, B>(
+    converter: &CM,
+    format: Option<Format>,
+    body: B,
+    content_type: &'a str,
+) -> Result<Negotiated<'a, B>>
+where
+    CM: ConvertMetrics<MF, C, B>,
+{
+    let content_type = Cow::Borrowed(content_type);
+    match format {
+        Some(Format::Native) => Ok(Negotiated { body, content_type, fallback: None }),
+        Some(Format::Fallback) => match converter.fallback_push_details(&body, &content_type) {
+            Some(fallback) => {
+                let (body, content_type) = fallback?;
+                let content_type = Cow::Owned(content_type);
+                Ok(Negotiated { body, content_type, fallback: None })
+            }
+            None => Ok(Negotiated { body, content_type, fallback: None }),
+        },
+        None => {
+            let fallback = converter
+                .fallback_push_details(&body, &content_type)
+                .and_then(Result::ok);
+            Ok(Negotiated { body, content_type, fallback })
+        }
+    }
+}
+
+/// Returns whether the pushgateway rejected a push with `400 Bad Request` or
+/// `415 Unsupported Media Type`, which is how gateway-compatible backends without protobuf
+/// support answer a protobuf payload.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) fn rejects_format(error: &PushMetricsError) -> bool {
+    #[cfg(feature = "http")]
+    return matches!(
+        error.status(),
+        Some(http::StatusCode::BAD_REQUEST | http::StatusCode::UNSUPPORTED_MEDIA_TYPE)
+    );
+    #[cfg(not(feature = "http"))]
+    {
+        let _ = error;
+        false
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4";
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) const CONTENT_TYPE_OPENMETRICS: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) const CONTENT_TYPE_PROTOBUF: &str =
+    "application/vnd.google.protobuf; proto=io.prometheus.client.MetricFamily; encoding=delimited";
+
+/// Returns the given payload as text if it is encoded in the Prometheus or OpenMetrics text
+/// format.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) fn text_payload(payload: &[u8], content_type: &str) -> Result<String> {
+    if !content_type.starts_with("text/plain")
+        && !content_type.starts_with("application/openmetrics-text")
+    {
+        return Err(PushMetricsError::TextFormat(format!(
+            "expected metrics in the text format, got '{content_type}'"
+        )));
+    }
+
+    String::from_utf8(payload.to_vec())
+        .map_err(|error| PushMetricsError::TextFormat(error.to_string()))
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
 const METRICS_JOB_PATH: &str = "metrics/job/";
 
-pub(crate) fn create_metrics_job_url(url: &Url) -> Result<Url> {
-    Ok(url.join(METRICS_JOB_PATH)?)
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) fn create_metrics_job_url(url: &Url, url_join: UrlJoin) -> Result<Url> {
+    match url_join {
+        UrlJoin::Relative => Ok(url.join(METRICS_JOB_PATH)?),
+        UrlJoin::Directory => {
+            let mut url = url.clone();
+            url.set_query(None);
+            url.set_fragment(None);
+            url.path_segments_mut()
+                .map_err(|_| url::ParseError::RelativeUrlWithCannotBeABaseBase)?
+                .pop_if_empty()
+                .extend(METRICS_JOB_PATH.split('/'));
+
+            Ok(url)
+        }
+    }
 }
 
-#[cfg(any(feature = "prometheus_crate", feature = "prometheus_client_crate"))]
+#[cfg(any(
+    feature = "blocking",
+    feature = "non_blocking",
+    feature = "prometheus_crate_text",
+    feature = "prometheus_client_crate"
+))]
 pub(crate) fn build_url(url: &Url, job: &str, grouping: &HashMap<&str, &str>) -> Result<Url> {
-    let mut url_params = vec![job];
+    let mut url = url.clone();
 
-    for (label_name, label_value) in grouping {
-        url_params.push(validate(label_name)?);
-        url_params.push(validate(label_value)?);
+    {
+        let mut segments = url
+            .path_segments_mut()
+            .map_err(|_| url::ParseError::RelativeUrlWithCannotBeABaseBase)?;
+
+        // every pushed segment is percent-encoded, so reserved characters like '?' or '#'
+        // end up in the path instead of corrupting the url. Values with a '/' and empty label
+        // values cannot be expressed that way and are base64 encoded as the pushgateway allows
+        segments.pop_if_empty();
+        if job.contains('/') {
+            segments.pop().push("job@base64").push(&encode_base64(job));
+        } else {
+            segments.push(job);
+        }
+        for (label_name, label_value) in grouping {
+            let label_name = validate_label_name(label_name)?;
+            if label_value.is_empty() || label_value.contains('/') {
+                segments
+                    .push(&format!("{label_name}@base64"))
+                    .push(&encode_base64(label_value));
+            } else {
+                segments.push(label_name).push(label_value);
+            }
+        }
+    }
+
+    Ok(url)
+}
+
+/// Encodes a job name or label value for a `@base64` path segment with the url safe alphabet
+/// of RFC 4648 without padding, an empty value becomes the single `=` the pushgateway expects.
+#[cfg(any(
+    feature = "blocking",
+    feature = "non_blocking",
+    feature = "prometheus_crate_text",
+    feature = "prometheus_client_crate"
+))]
+fn encode_base64(value: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    if value.is_empty() {
+        return "=".to_owned();
+    }
+
+    let mut encoded = String::with_capacity(value.len().div_ceil(3) * 4);
+    for chunk in value.as_bytes().chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            encoded.push(char::from(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize]));
+        }
     }
 
-    Ok(url.join(&url_params.join("/"))?)
+    encoded
 }
 
-#[cfg(any(feature = "prometheus_crate", feature = "prometheus_client_crate"))]
+#[cfg(any(
+    feature = "blocking",
+    feature = "non_blocking",
+    feature = "prometheus_crate_text",
+    feature = "prometheus_client_crate"
+))]
 pub(crate) fn validate(value: &str) -> Result<&str> {
     if value.contains('/') {
         return Err(PushMetricsError::slash_in_name(value));
@@ -41,26 +829,391 @@ pub(crate) fn validate(value: &str) -> Result<&str> {
     Ok(value)
 }
 
+/// Validates a grouping label name against the Prometheus naming rules `[a-zA-Z_][a-zA-Z0-9_]*`.
+/// With the `utf8_label_names` feature any name without '/' is accepted.
+#[cfg(any(
+    feature = "blocking",
+    feature = "non_blocking",
+    feature = "prometheus_crate_text",
+    feature = "prometheus_client_crate"
+))]
+pub(crate) fn validate_label_name(name: &str) -> Result<&str> {
+    let name = validate(name)?;
+    if cfg!(feature = "utf8_label_names") || is_label_name(name) {
+        return Ok(name);
+    }
+
+    Err(PushMetricsError::InvalidLabelName(name.to_owned()))
+}
+
+#[cfg(any(
+    feature = "blocking",
+    feature = "non_blocking",
+    feature = "prometheus_crate_text",
+    feature = "prometheus_client_crate"
+))]
+pub(crate) fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Sanitizes the grouping label names according to the given [`ValidationMode`]. Returns `None`
+/// if the grouping is used as is.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) fn sanitize_grouping<'a>(
+    mode: ValidationMode,
+    grouping: &HashMap<&'a str, &'a str>,
+) -> Result<Option<HashMap<String, &'a str>>> {
+    if mode == ValidationMode::Strict {
+        return Ok(None);
+    }
+
+    let mut sanitized = HashMap::with_capacity(grouping.len());
+    for (label_name, label_value) in grouping {
+        let label_name = sanitize_label_name(label_name);
+        if sanitized.contains_key(&label_name) {
+            return Err(PushMetricsError::InvalidLabelName(label_name));
+        }
+        sanitized.insert(label_name, *label_value);
+    }
+
+    Ok(Some(sanitized))
+}
+
+/// Merges the default grouping labels of a pusher into the grouping labels of a push, the
+/// latter win if both contain the same label name.
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) fn merge_grouping<'a>(
+    defaults: &'a HashMap<String, String>,
+    grouping: &'a HashMap<&'a str, &'a str>,
+) -> Cow<'a, HashMap<&'a str, &'a str>> {
+    if defaults.is_empty() {
+        return Cow::Borrowed(grouping);
+    }
+
+    let mut merged: HashMap<&str, &str> = defaults
+        .iter()
+        .map(|(label_name, label_value)| (label_name.as_str(), label_value.as_str()))
+        .collect();
+    merged.extend(grouping);
+
+    Cow::Owned(merged)
+}
+
+/// Borrows a grouping with owned label names as it is accepted by the [`ConvertMetrics`]
+/// implementations.
+///
+/// [`ConvertMetrics`]: crate::ConvertMetrics
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+pub(crate) fn borrow_grouping<'a>(
+    grouping: &'a HashMap<String, &'a str>,
+) -> HashMap<&'a str, &'a str> {
+    grouping
+        .iter()
+        .map(|(label_name, label_value)| (label_name.as_str(), *label_value))
+        .collect()
+}
+
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+fn sanitize_label_name(label_name: &str) -> String {
+    if cfg!(feature = "utf8_label_names") {
+        return label_name.replace('/', "_");
+    }
+
+    let mut sanitized: String = label_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if !sanitized.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
 /// PushType defines the two types of push requests to the pushgateway.
 #[cfg(any(feature = "blocking", feature = "non_blocking"))]
+#[derive(Clone, Copy)]
 pub enum PushType {
     Add,
     All,
 }
 
+#[cfg(any(feature = "blocking", feature = "non_blocking"))]
+impl PushType {
+    pub(crate) fn method(self) -> Method {
+        match self {
+            PushType::Add => Method::Post,
+            PushType::All => Method::Put,
+        }
+    }
+}
+
+/// Default timeout for establishing the connection to the pushgateway.
+#[cfg(any(
+    feature = "with_reqwest",
+    feature = "with_reqwest_blocking",
+    feature = "with_hyper",
+    feature = "with_ureq"
+))]
+pub(crate) const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default timeout for a whole push including the response of the pushgateway.
+#[cfg(any(
+    feature = "with_reqwest",
+    feature = "with_reqwest_blocking",
+    feature = "with_hyper",
+    feature = "with_ureq"
+))]
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default `User-Agent` that identifies pushes of this crate at the pushgateway.
+#[cfg(any(
+    feature = "with_reqwest",
+    feature = "with_reqwest_blocking",
+    all(
+        feature = "named_pipe",
+        any(feature = "blocking", feature = "non_blocking")
+    ),
+    feature = "with_hyper",
+    feature = "with_ureq"
+))]
+pub(crate) const DEFAULT_USER_AGENT: &str = concat!("prometheus-push/", env!("CARGO_PKG_VERSION"));
+
+/// Default number of idle connections kept open to the pushgateway.
+#[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
+pub(crate) const DEFAULT_POOL_MAX_IDLE: usize = 2;
+
+/// Generates the `MetricsPusherBuilder` of a reqwest push client module. The module has to bring
+/// its `PushClient`, `PushClientBuilder`, `MetricsPusher` and `Push` into scope, which are the
+/// only items that differ between the blocking and the non-blocking builder.
 #[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
-pub(crate) trait Respond {
-    fn get_status_code(&self) -> StatusCode;
-    fn get_url(&self) -> &Url;
+macro_rules! metrics_pusher_builder {
+    () => {
+        /// `MetricsPusherBuilder` creates a [`MetricsPusher`] together with its [`PushClient`], so
+        /// the timeout, user agent, credentials, extra headers, default grouping labels and retries
+        /// of a pusher are configured in one place instead of building the client, wrapping it and
+        /// configuring the pusher one after another.
+        pub struct MetricsPusherBuilder<CM, P = PushClient> {
+            client_builder: PushClientBuilder,
+            wrap: Box<dyn FnOnce(PushClient) -> P + Send>,
+            metrics_converter: CM,
+            url: Url,
+            default_grouping: Vec<(String, String)>,
+        }
+
+        impl<CM> MetricsPusherBuilder<CM> {
+            /// Creates a new [`MetricsPusherBuilder`] for a pusher that converts metrics with the
+            /// given converter and pushes them to the pushgateway at the given url. The client
+            /// starts with the defaults of [`PushClient::builder`].
+            pub fn new(metrics_converter: CM, url: &Url) -> Self {
+                Self {
+                    client_builder: PushClient::builder(),
+                    wrap: Box::new(|client| client),
+                    metrics_converter,
+                    url: url.clone(),
+                    default_grouping: Vec::new(),
+                }
+            }
+        }
+
+        impl<CM, P> MetricsPusherBuilder<CM, P> {
+            /// Sets the timeout for a whole push, see [`PushClientBuilder::timeout`].
+            pub fn timeout(mut self, timeout: Duration) -> Self {
+                self.client_builder = self.client_builder.timeout(timeout);
+                self
+            }
+
+            /// Sets the `User-Agent` pushes are sent with, see [`PushClientBuilder::user_agent`].
+            pub fn user_agent(mut self, user_agent: &str) -> Self {
+                self.client_builder = self.client_builder.user_agent(user_agent);
+                self
+            }
+
+            /// Sends the given [`Auth`] credentials with every request.
+            pub fn auth(mut self, auth: Auth) -> Self {
+                self.client_builder = self.client_builder.auth(auth);
+                self
+            }
+
+            /// Sends the given header with every request, see [`PushClientBuilder::header`].
+            pub fn header(mut self, name: &str, value: &str) -> Self {
+                self.client_builder = self.client_builder.header(name, value);
+                self
+            }
+
+            /// Sets the grouping labels that are merged into the grouping labels of every push and
+            /// delete, see [`MetricsPusher::with_default_grouping`].
+            pub fn default_grouping(mut self, grouping: &HashMap<&str, &str>) -> Self {
+                self.default_grouping = grouping
+                    .iter()
+                    .map(|(label_name, label_value)| {
+                        (label_name.to_string(), label_value.to_string())
+                    })
+                    .collect();
+                self
+            }
+
+            /// Configures everything else of the [`PushClientBuilder`], e.g. the redirect policy or
+            /// trusted certificates.
+            pub fn client_builder(
+                mut self,
+                configure: impl FnOnce(PushClientBuilder) -> PushClientBuilder,
+            ) -> Self {
+                self.client_builder = configure(self.client_builder);
+                self
+            }
+
+            /// Retries failed requests up to `retries` times with an exponential backoff starting
+            /// at `backoff`, see [`Retry`]. Pass a [`Retry`] to [`MetricsPusherBuilder::wrap`] to
+            /// configure it further.
+            #[cfg(feature = "retry")]
+            pub fn retry(
+                self,
+                retries: u32,
+                backoff: Duration,
+            ) -> MetricsPusherBuilder<CM, Retry<P>>
+            where
+                P: 'static,
+            {
+                self.wrap(move |client| Retry::new(client, retries).with_backoff(backoff))
+            }
+
+            /// Wraps the push client with the given function, e.g. in a [`Retry`] with a budget or
+            /// in [`DeadLetters`](crate::dead_letter::DeadLetters). Wrappers are applied in the
+            /// order they are added, so the last one is the outermost.
+            pub fn wrap<W>(
+                self,
+                wrap: impl FnOnce(P) -> W + Send + 'static,
+            ) -> MetricsPusherBuilder<CM, W>
+            where
+                P: 'static,
+            {
+                let inner = self.wrap;
+                MetricsPusherBuilder {
+                    client_builder: self.client_builder,
+                    wrap: Box::new(move |client| wrap(inner(client))),
+                    metrics_converter: self.metrics_converter,
+                    url: self.url,
+                    default_grouping: self.default_grouping,
+                }
+            }
+
+            /// Builds the [`PushClient`], wraps it and creates the [`MetricsPusher`] with it.
+            pub fn build<MF, C, B>(self) -> Result<MetricsPusher<P, CM, MF, C, B>>
+            where
+                P: Push<B>,
+                CM: ConvertMetrics<MF, C, B>,
+            {
+                let push_client = (self.wrap)(self.client_builder.build()?);
+                let default_grouping = self
+                    .default_grouping
+                    .iter()
+                    .map(|(label_name, label_value)| (label_name.as_str(), label_value.as_str()))
+                    .collect();
+
+                Ok(
+                    MetricsPusher::new(push_client, self.metrics_converter, &self.url)?
+                        .with_default_grouping(&default_grouping),
+                )
+            }
+        }
+
+        impl<CM: fmt::Debug, P> fmt::Debug for MetricsPusherBuilder<CM, P> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("MetricsPusherBuilder")
+                    .field("client_builder", &self.client_builder)
+                    .field("metrics_converter", &self.metrics_converter)
+                    .field("url", &self.url)
+                    .field("default_grouping", &self.default_grouping)
+                    .finish_non_exhaustive()
+            }
+        }
+    };
 }
 
 #[cfg(any(feature = "with_reqwest", feature = "with_reqwest_blocking"))]
-pub(crate) fn handle_response<R: Respond>(response: &R) -> Result<()> {
-    match response.get_status_code() {
-        StatusCode::ACCEPTED | StatusCode::OK => {
-            log::info!("Pushed metrics to the pushgateway.");
-            Ok(())
+pub(crate) use metrics_pusher_builder;
+
+#[cfg(all(test, any(feature = "blocking", feature = "non_blocking")))]
+mod test {
+    use std::collections::HashMap;
+
+    use url::Url;
+
+    use crate::utils::build_url;
+    use crate::utils::create_metrics_job_url;
+    use crate::UrlJoin;
+
+    #[test]
+    fn test_values_with_slashes_and_empty_values_are_base64_encoded() {
+        // Given I have a job name and label values with slashes and an empty label value
+        let url = Url::parse("https://host/metrics/job/").unwrap();
+
+        // When I build the urls of their groups
+        let job = build_url(&url, "backup/nightly", &HashMap::new()).unwrap();
+        let path = build_url(&url, "backup", &HashMap::from([("path", "/var/tmp")])).unwrap();
+        let empty = build_url(&url, "backup", &HashMap::from([("env", "")])).unwrap();
+        let plain = build_url(&url, "backup", &HashMap::from([("env", "prod")])).unwrap();
+
+        // Then only those are encoded as base64 segments
+        assert_eq!(
+            job.as_str(),
+            "https://host/metrics/job@base64/YmFja3VwL25pZ2h0bHk"
+        );
+        assert_eq!(
+            path.as_str(),
+            "https://host/metrics/job/backup/path@base64/L3Zhci90bXA"
+        );
+        assert_eq!(
+            empty.as_str(),
+            "https://host/metrics/job/backup/env@base64/="
+        );
+        assert_eq!(plain.as_str(), "https://host/metrics/job/backup/env/prod");
+    }
+
+    #[test]
+    fn test_create_metrics_job_url() {
+        let cases = [
+            (
+                "https://host",
+                UrlJoin::Directory,
+                "https://host/metrics/job/",
+            ),
+            (
+                "https://host/push",
+                UrlJoin::Directory,
+                "https://host/push/metrics/job/",
+            ),
+            (
+                "https://host/push/",
+                UrlJoin::Directory,
+                "https://host/push/metrics/job/",
+            ),
+            (
+                "https://host/push?x=1",
+                UrlJoin::Directory,
+                "https://host/push/metrics/job/",
+            ),
+            (
+                "https://host/push",
+                UrlJoin::Relative,
+                "https://host/metrics/job/",
+            ),
+            (
+                "https://host/push/",
+                UrlJoin::Relative,
+                "https://host/push/metrics/job/",
+            ),
+        ];
+
+        for (url, url_join, expected) in cases {
+            let url = create_metrics_job_url(&Url::parse(url).unwrap(), url_join).unwrap();
+            assert_eq!(url.as_str(), expected);
         }
-        status_code => Err(PushMetricsError::response(&status_code, response.get_url())),
     }
 }
@@ -0,0 +1,289 @@
+use std::io;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use http_body_util::Full;
+use hyper::header::CONTENT_TYPE;
+use hyper::header::RETRY_AFTER;
+use hyper::header::USER_AGENT;
+use hyper::Request;
+use hyper_util::client::legacy::connect::Connect;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use url::Url;
+
+use crate::error::PushMetricsError;
+use crate::error::Result;
+use crate::non_blocking::Fetch;
+use crate::non_blocking::Push;
+use crate::utils::DEFAULT_CONNECT_TIMEOUT;
+use crate::utils::DEFAULT_TIMEOUT;
+use crate::utils::DEFAULT_USER_AGENT;
+use crate::Method;
+
+/// `HyperPushClient` is a [`Push`] and [`Fetch`] implementation on top of the legacy [`Client`]
+/// of `hyper-util`, for applications that already depend on hyper 1.x and do not want
+/// `reqwest` in their tree.
+///
+/// The connector of the client decides how the pushgateway is reached, e.g. pass a client
+/// with the connector of `hyper-rustls` to push via HTTPS. Pushes are identified by the
+/// `User-Agent` `prometheus-push/<version>` and fail with the variant of their failure class if
+/// the pushgateway does not answer with a success status code, e.g.
+/// [`PushMetricsError::ServerError`].
+#[derive(Clone, Debug)]
+pub struct HyperPushClient<C = HttpConnector> {
+    client: Client<C, Full<Bytes>>,
+    timeout: Option<Duration>,
+}
+
+impl HyperPushClient {
+    /// Creates a new [`HyperPushClient`] that pushes via plain HTTP on the tokio runtime with
+    /// defaults suited for pushing: a connect timeout of 5 seconds and a timeout of 30 seconds
+    /// per push.
+    pub fn http() -> Self {
+        let mut connector = HttpConnector::new();
+        connector.set_connect_timeout(Some(DEFAULT_CONNECT_TIMEOUT));
+
+        Self::new(Client::builder(TokioExecutor::new()).build(connector))
+            .with_timeout(DEFAULT_TIMEOUT)
+    }
+}
+
+impl<C> HyperPushClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    /// Creates a new [`HyperPushClient`] that sends with the given client and without a
+    /// timeout per push.
+    pub fn new(client: Client<C, Full<Bytes>>) -> Self {
+        Self { client, timeout: None }
+    }
+
+    /// Fails every push with [`PushMetricsError::Timeout`] that does not finish within the
+    /// given timeout including the response of the pushgateway.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the request and returns the body of a successful response.
+    async fn request(
+        &self,
+        method: hyper::Method,
+        url: &Url,
+        body: Option<(Vec<u8>, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<Bytes> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(url.as_str())
+            .header(USER_AGENT, DEFAULT_USER_AGENT);
+        let body = match body {
+            Some((body, content_type)) => {
+                request = request.header(CONTENT_TYPE, content_type);
+                Full::new(Bytes::from(body))
+            }
+            None => Full::default(),
+        };
+        let request = headers
+            .iter()
+            .fold(request, |request, (name, value)| {
+                request.header(*name, *value)
+            })
+            .body(body)
+            .map_err(io::Error::other)?;
+
+        let exchange = async {
+            let response = self.client.request(request).await.map_err(io_error)?;
+            let status = response.status();
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned);
+            let body = response.into_body().collect().await.map_err(io_error)?;
+
+            Ok::<_, PushMetricsError>((status, retry_after, body.to_bytes()))
+        };
+        let (status, retry_after, body) = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, exchange)
+                .await
+                .map_err(|_| PushMetricsError::Timeout(timeout))??,
+            None => exchange.await?,
+        };
+
+        if !status.is_success() {
+            return Err(PushMetricsError::response(
+                &status,
+                url,
+                Some(&String::from_utf8_lossy(&body)),
+                retry_after.as_deref(),
+            ));
+        }
+
+        Ok(body)
+    }
+}
+
+/// Keeps the kind of the io error that made the request fail, e.g. a refused connection, so
+/// [`PushMetricsError::is_retryable`] can tell transient failures apart.
+fn io_error<E>(error: E) -> PushMetricsError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&error);
+    let mut kind = io::ErrorKind::Other;
+    while let Some(cause) = source {
+        if let Some(cause) = cause.downcast_ref::<io::Error>() {
+            kind = cause.kind();
+            break;
+        }
+        source = cause.source();
+    }
+
+    io::Error::new(kind, error).into()
+}
+
+impl<C> Push<Vec<u8>> for HyperPushClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    async fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(Vec<u8>, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        let method = match method {
+            Method::Put => hyper::Method::PUT,
+            Method::Post => hyper::Method::POST,
+            Method::Delete => hyper::Method::DELETE,
+        };
+
+        self.request(method, url, body, headers).await.map(drop)
+    }
+}
+
+impl<C> Fetch for HyperPushClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    async fn fetch(&self, url: &Url) -> Result<String> {
+        let body = self.request(hyper::Method::GET, url, None, &[]).await?;
+
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::error::PushMetricsError;
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::with_hyper::HyperPushClient;
+
+    #[tokio::test]
+    async fn test_push_with_hyper() {
+        // Given I have a push gateway that accepts one group and fails for another
+        let mut server = Server::new_async().await;
+        let accepted_mock = server
+            .mock("PUT", "/metrics/job/hyper/env/test")
+            .match_header("content-type", "text/plain; version=0.0.4")
+            .match_header(
+                "user-agent",
+                concat!("prometheus-push/", env!("CARGO_PKG_VERSION")),
+            )
+            .match_body("up 1\n")
+            .expect(1)
+            .create();
+        let failing_mock = server
+            .mock("DELETE", "/metrics/job/failing")
+            .with_status(500)
+            .with_body("storage full\n")
+            .expect(1)
+            .create();
+
+        // And a metrics pusher with a hyper client
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher: MetricsPusher<_, _, Vec<u8>, Vec<u8>, _> =
+            MetricsPusher::new(HyperPushClient::http(), RawMetricsConverter::text(), &url).unwrap();
+
+        // When I push to the one and delete the other
+        let pushed = metrics_pusher
+            .push_all(
+                "hyper",
+                &HashMap::from([("env", "test")]),
+                b"up 1\n".to_vec(),
+            )
+            .await;
+        let deleted = metrics_pusher.delete("failing", &HashMap::new()).await;
+
+        // Then the push succeeds and the delete fails with the status and body of the response
+        pushed.expect("Failed to push metrics");
+        let error = deleted.unwrap_err();
+        assert!(error.is_retryable());
+        assert!(matches!(
+            error.without_context(),
+            PushMetricsError::ServerError(response)
+                if response.body() == Some("storage full\n") && response.url().path() == "/metrics/job/failing"
+        ));
+        accepted_mock.assert();
+        failing_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_and_slow_pushgateways_are_retryable() {
+        // Given I have an address nobody listens on and a push gateway that answers too late
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable =
+            Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+        let mut server = Server::new_async().await;
+        server
+            .mock("PUT", "/metrics/job/hyper")
+            .with_chunked_body(|_| {
+                thread::sleep(Duration::from_millis(500));
+                Ok(())
+            })
+            .create();
+
+        // And metrics pushers with a hyper client with a short timeout
+        let metrics_pusher = |url: &Url| {
+            let push_client = HyperPushClient::http().with_timeout(Duration::from_millis(100));
+            MetricsPusher::<_, _, Vec<u8>, Vec<u8>, _>::new(
+                push_client,
+                RawMetricsConverter::text(),
+                url,
+            )
+            .unwrap()
+        };
+
+        // When I push to both
+        let refused = metrics_pusher(&unreachable)
+            .push_all("hyper", &HashMap::new(), b"up 1\n".to_vec())
+            .await
+            .unwrap_err();
+        let timed_out = metrics_pusher(&Url::parse(&server.url()).unwrap())
+            .push_all("hyper", &HashMap::new(), b"up 1\n".to_vec())
+            .await
+            .unwrap_err();
+
+        // Then both fail with an error that is worth retrying
+        assert!(refused.is_retryable());
+        assert!(matches!(
+            timed_out.without_context(),
+            PushMetricsError::Timeout(timeout) if timeout == Duration::from_millis(100)
+        ));
+    }
+}
@@ -1,51 +1,357 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::dns::Resolve;
 use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
 use reqwest::Body;
 use reqwest::Client;
+use reqwest::ClientBuilder;
+#[cfg(feature = "socks")]
+use reqwest::Proxy;
+use reqwest::RequestBuilder;
 use reqwest::Response;
 use reqwest::StatusCode;
 use url::Url;
 
 use crate::error::Result;
+#[cfg(feature = "stream")]
+use crate::non_blocking::BodyStream;
+use crate::non_blocking::Fetch;
+use crate::non_blocking::MetricsPusher;
 use crate::non_blocking::Push;
-use crate::utils::handle_response;
-use crate::utils::Respond;
+#[cfg(feature = "stream")]
+use crate::non_blocking::PushStream;
+use crate::response::check_status;
+use crate::response::BufferedResponse;
+use crate::response::Respond;
+use crate::response::ResponseHandler;
+use crate::response::StatusCodeHandler;
+#[cfg(feature = "retry")]
+use crate::retry::Retry;
+#[cfg(feature = "rustls_tls")]
+use crate::tls::TlsConfig;
+use crate::utils::DEFAULT_CONNECT_TIMEOUT;
+use crate::utils::DEFAULT_POOL_MAX_IDLE;
+use crate::utils::DEFAULT_TIMEOUT;
+use crate::utils::DEFAULT_USER_AGENT;
+use crate::Auth;
+use crate::ConvertMetrics;
+use crate::Method;
 
 /// `PushClient` is a wrapper for an async `reqwest` http [`Client`] that implements
-/// the [`Push`] trait.
+/// the [`Push`] and [`Fetch`] traits.
 #[derive(Debug)]
 pub struct PushClient {
     client: Client,
+    auth: Option<Auth>,
+    headers: Vec<(String, String)>,
+    response_handler: Arc<dyn ResponseHandler>,
 }
 
 impl PushClient {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            auth: None,
+            headers: Vec::new(),
+            response_handler: Arc::new(StatusCodeHandler),
+        }
+    }
+
+    /// Creates a [`PushClientBuilder`] to configure the underlying [`Client`].
+    pub fn builder() -> PushClientBuilder {
+        PushClientBuilder::default()
+    }
+}
+
+/// `PushClientBuilder` creates a [`PushClient`] with a configured [`Client`], so you do not have
+/// to rebuild the whole client with the right features yourself.
+///
+/// Unlike a plain [`Client`] it comes with defaults suited for pushing: a connect timeout of
+/// 5 seconds, a timeout of 30 seconds per push, no redirects and at most 2 idle connections.
+/// Pushes are identified by the `User-Agent` `prometheus-push/<version>`.
+#[derive(Debug)]
+pub struct PushClientBuilder {
+    client_builder: ClientBuilder,
+    auth: Option<Auth>,
+    headers: Vec<(String, String)>,
+    response_handler: Arc<dyn ResponseHandler>,
+    #[cfg(feature = "rustls_tls")]
+    tls_config: TlsConfig,
+}
+
+impl Default for PushClientBuilder {
+    fn default() -> Self {
+        Self {
+            client_builder: ClientBuilder::new()
+                .connect_timeout(DEFAULT_CONNECT_TIMEOUT)
+                .timeout(DEFAULT_TIMEOUT)
+                .redirect(Policy::none())
+                .pool_max_idle_per_host(DEFAULT_POOL_MAX_IDLE)
+                .user_agent(DEFAULT_USER_AGENT),
+            auth: None,
+            headers: Vec::new(),
+            response_handler: Arc::new(StatusCodeHandler),
+            #[cfg(feature = "rustls_tls")]
+            tls_config: TlsConfig::default(),
+        }
+    }
+}
+
+impl PushClientBuilder {
+    /// Sets the timeout for establishing the connection to the pushgateway.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.client_builder = self.client_builder.connect_timeout(timeout);
+        self
+    }
+
+    /// Sets the timeout for a whole push, from connecting until the response is received.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client_builder = self.client_builder.timeout(timeout);
+        self
+    }
+
+    /// Sets the redirect [`Policy`]. Redirects are not followed by default, as a redirected
+    /// push usually points to a misconfigured pushgateway url.
+    pub fn redirect(mut self, policy: Policy) -> Self {
+        self.client_builder = self.client_builder.redirect(policy);
+        self
+    }
+
+    /// Sets the `User-Agent` pushes are sent with, so the pushgateway's operators can identify
+    /// your application.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.client_builder = self.client_builder.user_agent(user_agent);
+        self
+    }
+
+    /// Sets the maximum number of idle connections that are kept open to the pushgateway.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.client_builder = self.client_builder.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// Sends the given [`Auth`] credentials with every request.
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sends the given header with every request, e.g. a header a proxy in front of the
+    /// pushgateway routes by. Invalid headers fail the requests when they are sent.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Decides with the given [`ResponseHandler`] whether a push was accepted, instead of only
+    /// checking the status code of the response.
+    pub fn response_handler(mut self, handler: impl ResponseHandler + 'static) -> Self {
+        self.response_handler = Arc::new(handler);
+        self
+    }
+
+    /// Resolves `host` to the given address instead of using DNS. The url of the pushgateway
+    /// is kept as is, so the `Host` header and TLS server name still match the hostname.
+    pub fn resolve(mut self, host: &str, addr: SocketAddr) -> Self {
+        self.client_builder = self.client_builder.resolve(host, addr);
+        self
+    }
+
+    /// Resolves `host` to the given addresses instead of using DNS, see
+    /// [`PushClientBuilder::resolve`].
+    pub fn resolve_to_addrs(mut self, host: &str, addrs: &[SocketAddr]) -> Self {
+        self.client_builder = self.client_builder.resolve_to_addrs(host, addrs);
+        self
+    }
+
+    /// Uses the given [`Resolve`] implementation for all hosts that are not overridden by
+    /// [`PushClientBuilder::resolve`], e.g. to resolve the pushgateway in a split-horizon DNS.
+    pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> Self {
+        self.client_builder = self.client_builder.dns_resolver(resolver);
+        self
+    }
+
+    /// Adds all certificates of the given PEM, e.g. of your private CA, to the trusted root
+    /// certificates.
+    #[cfg(feature = "rustls_tls")]
+    pub fn add_root_certificates_pem(mut self, pem: &[u8]) -> Result<Self> {
+        self.tls_config.add_root_certificates_pem(pem)?;
+        Ok(self)
+    }
+
+    /// Pins the certificate of the pushgateway: the connection is only established if the
+    /// SHA-256 hash of the DER encoded server certificate matches one of the pinned hashes.
+    /// Call it more than once to pin several certificates, e.g. during a rotation.
+    #[cfg(feature = "rustls_tls")]
+    pub fn pin_certificate_sha256(mut self, hash: [u8; 32]) -> Self {
+        self.tls_config.pin_certificate_sha256(hash);
+        self
+    }
+
+    /// Pushes through the SOCKS5 proxy at the given url, e.g. `socks5://jump-host:1080`. With
+    /// the `socks5h` scheme the proxy resolves the hostname of the pushgateway. Optional
+    /// credentials are used for username/password authentication with the proxy.
+    #[cfg(feature = "socks")]
+    pub fn socks5_proxy(mut self, proxy: &Url, credentials: Option<(&str, &str)>) -> Result<Self> {
+        let proxy = Proxy::all(proxy.as_str())?;
+        let proxy = match credentials {
+            Some((username, password)) => proxy.basic_auth(username, password),
+            None => proxy,
+        };
+
+        self.client_builder = self.client_builder.proxy(proxy);
+        Ok(self)
+    }
+
+    /// Builds the [`PushClient`].
+    pub fn build(self) -> Result<PushClient> {
+        let client_builder = self.client_builder;
+
+        // the selected backend wins over the one other crates enable on the shared reqwest
+        #[cfg(any(
+            feature = "with_reqwest_rustls",
+            feature = "with_reqwest_rustls_native_roots"
+        ))]
+        let client_builder = client_builder.use_rustls_tls();
+        #[cfg(all(
+            feature = "with_reqwest_native_tls",
+            not(any(
+                feature = "with_reqwest_rustls",
+                feature = "with_reqwest_rustls_native_roots"
+            ))
+        ))]
+        let client_builder = client_builder.use_native_tls();
+
+        #[cfg(feature = "rustls_tls")]
+        let client_builder = match self.tls_config.is_empty() {
+            true => client_builder,
+            false => client_builder.use_preconfigured_tls(self.tls_config.client_config()?),
+        };
+
+        Ok(PushClient {
+            client: client_builder.build()?,
+            auth: self.auth,
+            headers: self.headers,
+            response_handler: self.response_handler,
+        })
     }
 }
 
+crate::utils::metrics_pusher_builder!();
+
 impl<B: Into<Body> + Send + Sync + 'static> Push<B> for PushClient {
-    async fn push_all(&self, url: &Url, body: B, content_type: &str) -> Result<()> {
-        let response = &self
+    async fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<(B, &str)>,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        let request = self.client.request(method.into(), url.as_str());
+        let request = match body {
+            Some((body, content_type)) => request.header(CONTENT_TYPE, content_type).body(body),
+            None => request,
+        };
+        let response = request
+            .with_headers(headers)
+            .with_auth(self.auth.as_ref())
+            .with_default_headers(&self.headers)
+            .send()
+            .await?;
+
+        self.handle(response).await
+    }
+}
+
+#[cfg(feature = "stream")]
+impl PushStream for PushClient {
+    async fn send_stream(
+        &self,
+        method: Method,
+        url: &Url,
+        body: BodyStream,
+        content_type: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        let response = self
             .client
-            .put(url.as_str())
+            .request(method.into(), url.as_str())
             .header(CONTENT_TYPE, content_type)
-            .body(body)
+            .body(Body::wrap_stream(body))
+            .with_headers(headers)
+            .with_auth(self.auth.as_ref())
+            .with_default_headers(&self.headers)
             .send()
             .await?;
 
-        handle_response(response)
+        self.handle(response).await
     }
+}
+
+impl PushClient {
+    /// Reads the whole response, so the [`ResponseHandler`] can inspect its body as well.
+    async fn handle(&self, response: Response) -> Result<()> {
+        let status = response.status();
+        let url = response.url().clone();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
 
-    async fn push_add(&self, url: &Url, body: B, content_type: &str) -> Result<()> {
-        let response = &self
+        self.response_handler
+            .handle(&BufferedResponse { status, url, headers, body })
+    }
+}
+
+impl Fetch for PushClient {
+    async fn fetch(&self, url: &Url) -> Result<String> {
+        let response = self
             .client
-            .post(url.as_str())
-            .header(CONTENT_TYPE, content_type)
-            .body(body)
+            .get(url.as_str())
+            .with_auth(self.auth.as_ref())
+            .with_default_headers(&self.headers)
             .send()
             .await?;
+        check_status(&response)?;
 
-        handle_response(response)
+        Ok(response.text().await?)
+    }
+}
+
+trait WithHeaders {
+    fn with_headers(self, headers: &[(&str, &str)]) -> Self;
+    fn with_auth(self, auth: Option<&Auth>) -> Self;
+    fn with_default_headers(self, headers: &[(String, String)]) -> Self;
+}
+
+impl WithHeaders for RequestBuilder {
+    /// Adds the given headers to the request. Invalid headers fail the request when it is sent.
+    fn with_headers(self, headers: &[(&str, &str)]) -> Self {
+        headers
+            .iter()
+            .fold(self, |request, (name, value)| request.header(*name, *value))
+    }
+
+    /// Adds the `Authorization` or custom header for the given credentials to the request.
+    fn with_auth(self, auth: Option<&Auth>) -> Self {
+        match auth {
+            Some(Auth::Basic { username, password }) => {
+                self.basic_auth(username, password.as_ref())
+            }
+            Some(Auth::Bearer(token)) => self.bearer_auth(token),
+            Some(Auth::Header { name, value }) => self.header(name.as_str(), value.as_str()),
+            None => self,
+        }
+    }
+
+    /// Adds the headers the client sends with every request to the request.
+    fn with_default_headers(self, headers: &[(String, String)]) -> Self {
+        headers
+            .iter()
+            .fold(self, |request, (name, value)| request.header(name, value))
     }
 }
 
@@ -57,4 +363,488 @@ impl Respond for Response {
     fn get_url(&self) -> &Url {
         self.url()
     }
+
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use mockito::Server;
+    use url::Url;
+
+    use crate::error::GatewayRejected;
+    use crate::error::PushMetricsError;
+    use crate::error::Result;
+    use crate::non_blocking::MetricsPusher;
+    use crate::raw::RawMetricsConverter;
+    use crate::response::check_status;
+    use crate::response::Respond;
+    use crate::response::ResponseHandler;
+    use crate::with_reqwest::MetricsPusherBuilder;
+    use crate::with_reqwest::PushClient;
+    use crate::Auth;
+
+    #[tokio::test]
+    async fn test_push_to_resolved_address() {
+        // Given I have a push gateway that is not resolvable via DNS
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/resolved")
+            .match_header(
+                "host",
+                mockito::Matcher::Regex("^pushgateway.invalid".into()),
+            )
+            .create();
+
+        // And a push client that resolves its hostname to the address of the push gateway
+        let push_client = PushClient::builder()
+            .resolve("pushgateway.invalid", server.socket_address())
+            .build()
+            .unwrap();
+        let port = server.socket_address().port();
+        let url = Url::parse(&format!("http://pushgateway.invalid:{port}")).unwrap();
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &url).unwrap();
+
+        // When I push to the hostname
+        metrics_pusher
+            .push_all("resolved", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the push gateway receives the push with the original host header
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_builder_defaults_do_not_follow_redirects() {
+        // Given I have a push gateway that redirects pushes
+        let mut server = Server::new_async().await;
+        let redirect_mock = server
+            .mock("PUT", "/metrics/job/redirected")
+            .with_status(307)
+            .with_header("location", "/elsewhere")
+            .create();
+        let elsewhere_mock = server.mock("PUT", "/elsewhere").create();
+
+        // And a metrics pusher with a push client with the default settings
+        let push_client = PushClient::builder().build().unwrap();
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &url).unwrap();
+
+        // When I push
+        let result = metrics_pusher
+            .push_all("redirected", &HashMap::new(), "up 1\n")
+            .await;
+
+        // Then the redirect is reported instead of followed
+        assert_eq!(
+            result.unwrap_err().status().map(|status| status.as_u16()),
+            Some(307)
+        );
+        redirect_mock.expect(1).assert();
+        elsewhere_mock.expect(0).assert();
+    }
+
+    #[tokio::test]
+    async fn test_builder_user_agent() {
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let default_mock = server
+            .mock("PUT", "/metrics/job/default")
+            .match_header(
+                "user-agent",
+                concat!("prometheus-push/", env!("CARGO_PKG_VERSION")),
+            )
+            .create();
+        let custom_mock = server
+            .mock("PUT", "/metrics/job/custom")
+            .match_header("user-agent", "billing-batch/1.2")
+            .create();
+
+        // And push clients with the default and a custom user agent
+        let url = Url::parse(&server.url()).unwrap();
+        let default_pusher = MetricsPusher::new(
+            PushClient::builder().build().unwrap(),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+        let custom_pusher = MetricsPusher::new(
+            PushClient::builder()
+                .user_agent("billing-batch/1.2")
+                .build()
+                .unwrap(),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+
+        // When I push with both
+        default_pusher
+            .push_all("default", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        custom_pusher
+            .push_all("custom", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the pushes are identified by their user agents
+        default_mock.expect(1).assert();
+        custom_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_auth_is_sent_with_every_request() {
+        // Given I have a push gateway behind a reverse proxy that checks credentials
+        let mut server = Server::new_async().await;
+        let basic_mock = server
+            .mock("PUT", "/metrics/job/basic")
+            .match_header("authorization", "Basic dXNlcjpwYXNz")
+            .create();
+        let bearer_mock = server
+            .mock("POST", "/metrics/job/bearer")
+            .match_header("authorization", "Bearer token")
+            .create();
+        let header_mock = server
+            .mock("DELETE", "/metrics/job/header")
+            .match_header("x-api-key", "key")
+            .create();
+
+        // And push clients with basic, bearer and custom header credentials
+        let url = Url::parse(&server.url()).unwrap();
+        let pusher = |auth| {
+            MetricsPusher::new(
+                PushClient::builder().auth(auth).build().unwrap(),
+                RawMetricsConverter::text(),
+                &url,
+            )
+            .unwrap()
+        };
+        let basic_pusher = pusher(Auth::Basic {
+            username: String::from("user"),
+            password: Some(String::from("pass")),
+        });
+        let bearer_pusher = pusher(Auth::Bearer(String::from("token")));
+        let header_pusher =
+            pusher(Auth::Header { name: String::from("X-Api-Key"), value: String::from("key") });
+
+        // When I push, push add and delete with them
+        basic_pusher
+            .push_all("basic", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        bearer_pusher
+            .push_add("bearer", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        header_pusher
+            .delete("header", &HashMap::new())
+            .await
+            .expect("Failed to delete metrics");
+
+        // Then every request carries its credentials
+        basic_mock.expect(1).assert();
+        bearer_mock.expect(1).assert();
+        header_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_build_configured_metrics_pusher() {
+        // Given I have a push gateway behind a reverse proxy that checks credentials and headers
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/built/instance/host")
+            .match_header("authorization", "Bearer token")
+            .match_header("x-tenant", "team")
+            .match_header("user-agent", "batch/1.0")
+            .expect(1)
+            .create();
+
+        // When I build a pusher with the builder and push with it
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher: MetricsPusher<_, _, String, String, _> =
+            MetricsPusherBuilder::new(RawMetricsConverter::text(), &url)
+                .timeout(Duration::from_secs(5))
+                .user_agent("batch/1.0")
+                .auth(Auth::Bearer(String::from("token")))
+                .header("X-Tenant", "team")
+                .default_grouping(&HashMap::from([("instance", "host")]))
+                .build()
+                .unwrap();
+        metrics_pusher
+            .push_all("built", &HashMap::new(), String::from("up 1\n"))
+            .await
+            .expect("Failed to push metrics");
+
+        // Then the push carries the configured headers and default grouping labels
+        pushgateway_mock.assert();
+    }
+
+    #[cfg(feature = "retry")]
+    #[tokio::test]
+    async fn test_build_retrying_metrics_pusher() {
+        // Given I have a push gateway that is unavailable for the first push
+        let mut server = Server::new_async().await;
+        let unavailable_mock = server
+            .mock("PUT", "/metrics/job/retried")
+            .with_status(503)
+            .expect(1)
+            .create();
+        let available_mock = server
+            .mock("PUT", "/metrics/job/retried")
+            .expect(1)
+            .create();
+
+        // When I push with a pusher built to retry
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher: MetricsPusher<_, _, String, String, _> =
+            MetricsPusherBuilder::new(RawMetricsConverter::text(), &url)
+                .retry(1, Duration::from_millis(1))
+                .build()
+                .unwrap();
+        let pushed = metrics_pusher
+            .push_all("retried", &HashMap::new(), String::from("up 1\n"))
+            .await;
+
+        // Then the push is retried and succeeds
+        pushed.expect("Failed to push metrics");
+        unavailable_mock.assert();
+        available_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_push_reports_status_and_body() {
+        // Given I have a push gateway that rejects the payload
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/raw_job")
+            .with_status(400)
+            .with_body("text format parsing error in line 1\n")
+            .create();
+
+        // And a metrics pusher
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+
+        // When I push the payload
+        let result = metrics_pusher
+            .push_all("raw_job", &HashMap::new(), "up one\n")
+            .await;
+
+        // Then the job, status code and body of the response are reported
+        pushgateway_mock.expect(1).assert();
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.context().map(|context| context.job()),
+            Some("raw_job")
+        );
+        assert!(error
+            .to_string()
+            .starts_with("push of job 'raw_job' to http://"));
+        assert_eq!(error.status(), Some(reqwest::StatusCode::BAD_REQUEST));
+        assert!(!error.is_retryable());
+        assert!(matches!(
+            error.rejection(),
+            Some(GatewayRejected::InvalidPayload(_))
+        ));
+        match error.without_context() {
+            PushMetricsError::BadRequest(response) => {
+                assert_eq!(
+                    response.body(),
+                    Some("text format parsing error in line 1\n")
+                );
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[derive(Debug)]
+    struct PartialFailureHandler;
+
+    impl ResponseHandler for PartialFailureHandler {
+        fn handle(&self, response: &dyn Respond) -> Result<()> {
+            check_status(response)?;
+            match response.get_body() {
+                Some(body) if body.starts_with("partial failure") => {
+                    Err(PushMetricsError::Rejected(body.trim_end().to_owned()))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_response_handler() {
+        // Given I have a gateway-compatible backend that signals failures in a 200 body
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server
+            .mock("PUT", "/metrics/job/partial")
+            .with_status(200)
+            .with_body("partial failure: 1 of 2 series dropped\n")
+            .create();
+
+        // And a metrics pusher with a push client with a custom response handler
+        let push_client = PushClient::builder()
+            .response_handler(PartialFailureHandler)
+            .build()
+            .unwrap();
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &url).unwrap();
+
+        // When I push
+        let result = metrics_pusher
+            .push_all("partial", &HashMap::new(), "up 1\nother 2\n")
+            .await;
+
+        // Then the push fails with the error of the response handler
+        match result.map_err(PushMetricsError::without_context) {
+            Err(PushMetricsError::Rejected(reason)) => {
+                assert_eq!(reason, "partial failure: 1 of 2 series dropped")
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        pushgateway_mock.expect(1).assert();
+    }
+
+    #[tokio::test]
+    async fn test_builder_timeout() {
+        // Given I have a push gateway that accepts connections but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+        // And a metrics pusher with a push client with a short timeout
+        let push_client = PushClient::builder()
+            .timeout(std::time::Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &url).unwrap();
+
+        // When I push
+        let result = metrics_pusher
+            .push_all("timeout", &HashMap::new(), "up 1\n")
+            .await;
+
+        // Then the push times out
+        match result.map_err(PushMetricsError::without_context) {
+            Err(PushMetricsError::Reqwest(error)) => assert!(error.is_timeout()),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_broken_off_response_body_fails_the_push() {
+        use tokio::io::AsyncReadExt;
+        use tokio::io::AsyncWriteExt;
+
+        // Given I have a push gateway that breaks off its response in the middle of the body
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let (mut connection, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = connection.read(&mut request).await.unwrap();
+            connection
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\npartial")
+                .await
+                .unwrap();
+        });
+
+        // And a metrics pusher
+        let metrics_pusher = MetricsPusher::new(
+            PushClient::new(reqwest::Client::new()),
+            RawMetricsConverter::text(),
+            &url,
+        )
+        .unwrap();
+
+        // When I push
+        let result = metrics_pusher
+            .push_all("broken", &HashMap::new(), "up 1\n")
+            .await;
+
+        // Then the push fails with the error of reading the body
+        match result.map_err(PushMetricsError::without_context) {
+            Err(PushMetricsError::Reqwest(error)) => assert!(error.is_decode()),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[cfg(feature = "socks")]
+    #[tokio::test]
+    async fn test_push_through_socks5_proxy_with_credentials() {
+        use tokio::io::AsyncReadExt;
+        use tokio::io::AsyncWriteExt;
+        use tokio::net::TcpListener;
+        use tokio::net::TcpStream;
+
+        // Given I have a push gateway
+        let mut server = Server::new_async().await;
+        let pushgateway_mock = server.mock("PUT", "/metrics/job/socks").create();
+
+        // And a SOCKS5 proxy that requires username/password authentication
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = Url::parse(&format!("socks5://{}", listener.local_addr().unwrap())).unwrap();
+        let proxy_task = tokio::spawn(async move {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 512];
+
+            let read = client.read(&mut buffer).await.unwrap();
+            assert!(buffer[2..read].contains(&2));
+            client.write_all(&[5, 2]).await.unwrap();
+
+            let read = client.read(&mut buffer).await.unwrap();
+            let credentials = buffer[..read].to_vec();
+            client.write_all(&[1, 0]).await.unwrap();
+
+            let read = client.read(&mut buffer).await.unwrap();
+            let port = u16::from_be_bytes([buffer[read - 2], buffer[read - 1]]);
+            let mut target = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            client
+                .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+
+            let _ = tokio::io::copy_bidirectional(&mut client, &mut target).await;
+            credentials
+        });
+
+        // And a push client that pushes through the proxy
+        let push_client = PushClient::builder()
+            .socks5_proxy(&proxy, Some(("agent", "secret")))
+            .unwrap()
+            .build()
+            .unwrap();
+        let url = Url::parse(&server.url()).unwrap();
+        let metrics_pusher =
+            MetricsPusher::new(push_client, RawMetricsConverter::text(), &url).unwrap();
+
+        // When I push
+        metrics_pusher
+            .push_all("socks", &HashMap::new(), "up 1\n")
+            .await
+            .expect("Failed to push metrics");
+        drop(metrics_pusher);
+
+        // Then the push passed the proxy with the credentials
+        pushgateway_mock.expect(1).assert();
+        let credentials = proxy_task.await.unwrap();
+        assert_eq!(credentials, b"\x01\x05agent\x06secret");
+    }
 }